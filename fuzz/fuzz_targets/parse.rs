@@ -0,0 +1,14 @@
+#![no_main]
+
+// External dependencies
+use jlox::diagnostic::CollectingReporter;
+use libfuzzer_sys::fuzz_target;
+
+// Feeds arbitrary strings through the scanner and, if scanning succeeds,
+// into the parser. Parse errors are expected, panics are not.
+fuzz_target!(|source: &str| {
+    let mut reporter = CollectingReporter::default();
+    if let Ok(tokens) = jlox::scanner::scan_tokens(source.to_string(), &mut reporter) {
+        let _ = jlox::parser::parse(tokens, &mut reporter);
+    }
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+// External dependencies
+use jlox::diagnostic::CollectingReporter;
+use libfuzzer_sys::fuzz_target;
+
+// Feeds arbitrary strings into the scanner. Scan errors are expected,
+// panics are not.
+fuzz_target!(|source: &str| {
+    let _ = jlox::scanner::scan_tokens(source.to_string(), &mut CollectingReporter::default());
+});
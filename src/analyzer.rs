@@ -0,0 +1,390 @@
+// Internal dependencies
+use crate::diagnostic::{Diagnostic, Reporter};
+use crate::obj::expression::Expression;
+use crate::obj::function::FunctionDeclaration;
+use crate::obj::statement::Statement;
+use crate::obj::token::Token;
+use crate::obj::token_type::TokenType;
+use crate::obj::value::Value;
+
+/// Only public function of the analyzer module. It walks the parsed
+/// statements without running them and hands a warning to the `reporter`
+/// for every condition that is always true or always false, for code
+/// that can never run and for functions that only return a value on some
+/// paths. Analysis cannot fail, the statements stay as they are.
+pub fn analyze(statements: &[Statement], reporter: &mut dyn Reporter) {
+    let mut analyzer = Analyzer { reporter, returns_value: false };
+    analyzer.statements(statements);
+}
+
+/// Contraption that walks the syntax tree and reports what it finds
+struct Analyzer<'a> {
+    reporter: &'a mut dyn Reporter,
+    /// Whether the function being analyzed has a `return` with a value
+    returns_value: bool,
+}
+
+impl Analyzer<'_> {
+    /// Analyzes a sequence of statements, like the body of a block. Returns
+    /// the keyword of the statement control never comes back from (a
+    /// `return`, a `throw`, a `break`, a `continue` or an endless loop), if there is one. Only
+    /// the first statement after it is reported, the ones following it are
+    /// unreachable for the same reason.
+    fn statements<'s>(&mut self, statements: &'s [Statement]) -> Option<&'s Token> {
+        let mut diverged: Option<&Token> = None;
+        for stmt in statements {
+            if let Some(keyword) = diverged {
+                let after = match keyword.token_type() {
+                    TokenType::Return => "return",
+                    TokenType::Break => "break",
+                    TokenType::Continue => "continue",
+                    TokenType::Throw => "throw",
+                    _ => "endless loop",
+                };
+                self.warn(
+                    format!("Unreachable code after the {} on line {}", after, keyword.line()),
+                    first_token(stmt).unwrap_or(keyword),
+                );
+                return diverged;
+            }
+            diverged = self.statement(stmt);
+        }
+        diverged
+    }
+
+    /// Analyzes a statement, see [Analyzer::statements] for the result
+    fn statement<'s>(&mut self, stmt: &'s Statement) -> Option<&'s Token> {
+        match stmt {
+            Statement::Block(stmts) => self.statements(stmts),
+            Statement::Class(_, _, _, methods, setters) => {
+                for method in methods.iter().chain(setters) {
+                    self.function(method);
+                }
+                None
+            }
+            Statement::Function(declaration) => {
+                self.function(declaration);
+                None // Declaring the function does not run the body
+            }
+            Statement::If(keyword, cond, then, els) => {
+                self.expression(cond);
+                let constant = truthiness(cond);
+                if let Some(value) = constant {
+                    self.warn(format!("Condition is always {} on line {}", value, keyword.line()), keyword);
+                }
+                let then = self.statement(then);
+                let els = els.as_ref().and_then(|els| self.statement(els));
+                match constant {
+                    Some(true) => then,
+                    Some(false) => els,
+                    None => then.and(els),
+                }
+            }
+            Statement::While(keyword, cond, body, increment, label) => {
+                self.expression(cond);
+                increment.iter().for_each(|increment| self.expression(increment));
+                // `while (true)` is how endless loops are written, only the opposite is suspicious
+                let constant = truthiness(cond);
+                if constant == Some(false) && keyword.token_type() == TokenType::While {
+                    self.warn(format!("Condition is always false on line {}, the loop never runs", keyword.line()), keyword);
+                }
+                self.statement(body);
+                // A loop that always continues only ends through a `break`
+                (constant == Some(true) && !breaks_out(body, label.as_ref(), false)).then_some(keyword)
+            }
+            Statement::Return(keyword, value) => {
+                value.iter().for_each(|value| self.expression(value));
+                self.returns_value |= value.is_some();
+                Some(keyword)
+            }
+            Statement::Break(keyword, _) | Statement::Continue(keyword, _) => Some(keyword),
+            Statement::Throw(keyword, value) => {
+                self.expression(value);
+                Some(keyword)
+            }
+            Statement::Try(_, body, catches, finally) => {
+                let mut handled = self.statements(body);
+                let mut catch_all: Option<&Token> = None;
+                for (name, class, handler) in catches {
+                    if let Some(earlier) = catch_all {
+                        self.warn(
+                            format!("Unreachable catch block on line {}, the one on line {} catches everything", name.line(), earlier.line()),
+                            name,
+                        );
+                    } else if class.is_none() {
+                        catch_all = Some(name);
+                    }
+                    // Whatever leaves the body early might be caught, then the handler decides
+                    let diverged = self.statements(handler);
+                    handled = handled.and(diverged);
+                }
+                finally.as_ref().and_then(|finally| self.statements(finally)).or(handled)
+            }
+            Statement::ForIn(_, _, iterable, body, _) => {
+                self.expression(iterable);
+                self.statement(body);
+                None // Collections are finite
+            }
+            Statement::Switch(_, subject, cases, default) => {
+                self.expression(subject);
+                let mut diverged = true;
+                for (values, body) in cases {
+                    values.iter().for_each(|value| self.expression(value));
+                    diverged &= self.statements(body).is_some();
+                }
+                // Without a default branch no case might match, and control goes on after the switch
+                let default = default.as_ref().and_then(|body| self.statements(body));
+                default.filter(|_| diverged)
+            }
+            Statement::Expression(expr)
+            | Statement::Var(_, _, Some(expr))
+            | Statement::Const(_, _, expr)
+            | Statement::Destructure(_, expr) => {
+                self.expression(expr);
+                None
+            }
+            Statement::Print(_, exprs) => {
+                exprs.iter().for_each(|expr| self.expression(expr));
+                None
+            }
+            Statement::Var(_, _, None) => None,
+        }
+    }
+
+    /// Looks for anonymous functions and `if` expressions in an expression
+    /// and analyzes their bodies and conditions
+    fn expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Lambda(declaration) => self.function(declaration),
+            Expression::If(keyword, cond, then, els) => {
+                self.expression(cond);
+                if let Some(value) = truthiness(cond) {
+                    self.warn(format!("Condition is always {} on line {}", value, keyword.line()), keyword);
+                }
+                self.expression(then);
+                els.iter().for_each(|els| self.expression(els));
+            }
+            Expression::Block(_, stmts, value) => {
+                self.statements(stmts);
+                value.iter().for_each(|value| self.expression(value));
+            }
+            Expression::Assign(_, inner)
+            | Expression::Destructure(_, inner)
+            | Expression::Get(inner, _)
+            | Expression::OptionalGet(inner, _)
+            | Expression::Grouping(inner)
+            | Expression::Unary(_, inner) => {
+                self.expression(inner)
+            }
+            Expression::Binary(left, _, right)
+            | Expression::Logical(left, _, right)
+            | Expression::Index(left, _, right)
+            | Expression::Set(left, _, right) => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expression::IndexSet(object, _, index, value) => {
+                [object, index, value].into_iter().for_each(|expr| self.expression(expr));
+            }
+            Expression::Slice(object, _, start, end) => {
+                self.expression(object);
+                [start, end].into_iter().flatten().for_each(|bound| self.expression(bound));
+            }
+            Expression::Call(callee, _, args) => {
+                self.expression(callee);
+                args.iter().for_each(|arg| self.expression(arg));
+            }
+            Expression::List(_, items) => items.iter().for_each(|item| self.expression(item)),
+            Expression::Map(_, entries) => {
+                for (key, value) in entries {
+                    self.expression(key);
+                    self.expression(value);
+                }
+            }
+            Expression::Literal(_) | Expression::Super(..) | Expression::This(_) | Expression::Variable(_) => {}
+        }
+    }
+
+    /// Analyzes the body of a function or method on its own
+    fn function(&mut self, declaration: &FunctionDeclaration) {
+        let outer = std::mem::replace(&mut self.returns_value, false);
+        let falls_through = self.statements(&declaration.body).is_none();
+        if self.returns_value && falls_through {
+            let name = &declaration.name;
+            let function = if declaration.is_lambda() { "the anonymous function".to_string() } else { name.lexeme() };
+            self.warn(format!("Not every path of {} returns a value on line {}", function, name.line()), name);
+        }
+        self.returns_value = outer;
+    }
+
+    fn warn(&mut self, message: String, token: &Token) {
+        self.reporter.report(Diagnostic::warning(message, Some(token.line())).with_span(token.span()));
+    }
+}
+
+/// Whether the expression is always truthy or always falsy, `None` if
+/// that depends on the values of variables or calls
+fn truthiness(expr: &Expression) -> Option<bool> {
+    match expr {
+        Expression::Literal(value) => Some(!matches!(value, Value::Nil | Value::Bool(false))),
+        Expression::Grouping(inner) => truthiness(inner),
+        Expression::Unary(op, right) if op.token_type() == TokenType::Bang => truthiness(right).map(|b| !b),
+        // `??` only falls back to the right side for nil, which only a literal is known to be
+        Expression::Logical(left, op, right) if op.token_type() == TokenType::QuestionQuestion => match left.as_ref() {
+            Expression::Literal(Value::Nil) => truthiness(right),
+            Expression::Literal(_) => truthiness(left),
+            _ => None,
+        },
+        // `and` and `or` evaluate to one of their operands
+        Expression::Logical(left, op, right) => match (truthiness(left)?, op.token_type()) {
+            (false, TokenType::And) => Some(false),
+            (true, TokenType::Or) => Some(true),
+            _ => truthiness(right),
+        },
+        _ => None,
+    }
+}
+
+/// Whether a `break` in the body of the loop with `label` leaves that loop.
+/// A `break` without a label in a `nested` loop only leaves the nested one.
+fn breaks_out(stmt: &Statement, label: Option<&Token>, nested: bool) -> bool {
+    let any = |stmts: &[Statement]| stmts.iter().any(|stmt| breaks_out(stmt, label, nested));
+    match stmt {
+        Statement::Break(_, None) => !nested,
+        Statement::Break(_, Some(target)) => label.is_some_and(|label| label.lexeme() == target.lexeme()),
+        Statement::Block(stmts) => any(stmts),
+        Statement::If(_, _, then, els) => {
+            breaks_out(then, label, nested) || els.as_ref().is_some_and(|els| breaks_out(els, label, nested))
+        }
+        Statement::While(_, _, body, ..) | Statement::ForIn(_, _, _, body, _) => breaks_out(body, label, true),
+        Statement::Switch(_, _, cases, default) => {
+            cases.iter().any(|(_, body)| any(body)) || default.as_ref().is_some_and(|body| any(body))
+        }
+        Statement::Try(_, body, catches, finally) => {
+            any(body) || catches.iter().any(|(_, _, body)| any(body)) || finally.as_ref().is_some_and(|body| any(body))
+        }
+        _ => false, // Functions declared in the loop cannot leave it
+    }
+}
+
+/// The first token of a statement in source order, if it has one
+pub fn first_token(stmt: &Statement) -> Option<&Token> {
+    match stmt {
+        Statement::Block(stmts) => stmts.iter().find_map(first_token),
+        Statement::If(keyword, ..) | Statement::Return(keyword, _) | Statement::While(keyword, ..) => Some(keyword),
+        Statement::ForIn(keyword, ..) | Statement::Switch(keyword, ..) => Some(keyword),
+        Statement::Break(keyword, _) | Statement::Continue(keyword, _) => Some(keyword),
+        Statement::Throw(keyword, _) | Statement::Try(keyword, ..) | Statement::Print(keyword, _) => Some(keyword),
+        Statement::Function(declaration) => Some(&declaration.name),
+        Statement::Class(name, ..) | Statement::Var(name, ..) | Statement::Const(name, ..) => Some(name),
+        Statement::Destructure(pattern, _) => pattern.names().first(),
+        Statement::Expression(expr) => first_expression_token(expr),
+    }
+}
+
+/// The leftmost token of an expression as it is written, `None` if the
+/// expression is just a literal
+pub fn first_expression_token(expr: &Expression) -> Option<&Token> {
+    match expr {
+        Expression::Assign(name, _) | Expression::This(name) | Expression::Variable(name) => Some(name),
+        Expression::Super(keyword, _) => Some(keyword),
+        Expression::Unary(op, _) => Some(op),
+        Expression::Binary(left, op, _) | Expression::Logical(left, op, _) => first_expression_token(left).or(Some(op)),
+        Expression::Call(callee, paren, _) => first_expression_token(callee).or(Some(paren)),
+        Expression::Get(object, name) | Expression::OptionalGet(object, name) | Expression::Set(object, name, _) => {
+            first_expression_token(object).or(Some(name))
+        }
+        Expression::Index(list, bracket, _) | Expression::IndexSet(list, bracket, ..) | Expression::Slice(list, bracket, ..) => {
+            first_expression_token(list).or(Some(bracket))
+        }
+        Expression::List(bracket, _) | Expression::Map(bracket, _) | Expression::Block(bracket, ..) => Some(bracket),
+        Expression::If(keyword, ..) => Some(keyword),
+        Expression::Destructure(pattern, _) => Some(pattern.token()),
+        Expression::Lambda(declaration) => Some(&declaration.name),
+        Expression::Grouping(inner) => first_expression_token(inner),
+        Expression::Literal(_) => None,
+    }
+}
+
+// ---------- Tests for the Analyzer module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::CollectingReporter;
+    use crate::parser::parse;
+    use crate::scanner::scan_tokens;
+
+    fn analyze_source(source: &str) -> Vec<String> {
+        let mut reporter = CollectingReporter::default();
+        let tokens = scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+        let statements = parse(tokens, &mut reporter).expect("Parsing failed!");
+        analyze(&statements, &mut reporter);
+        reporter.into_diagnostics().into_iter().map(|d| d.message).collect()
+    }
+
+    #[test]
+    fn reports_constant_conditions() {
+        let source = "var a = 1;\nif (!nil) print a;\nif (a and false) print a;\nwhile ((false)) a = 2;\nif (a) print a;";
+        assert_eq!(
+            analyze_source(source),
+            [
+                "Condition is always true on line 2",
+                "Condition is always false on line 4, the loop never runs",
+            ]
+        );
+    }
+
+    #[test]
+    fn knows_that_only_nil_falls_back() {
+        let source = "var a;\nif (false ?? true) print 1;\nif (nil ?? 0) print 2;\nif (a ?? true) print 3;";
+        assert_eq!(analyze_source(source), ["Condition is always false on line 2", "Condition is always true on line 3"]);
+    }
+
+    #[test]
+    fn reports_code_after_endless_loops() {
+        let source = "while (true) print 1;\nprint 2;\nprint 3;";
+        assert_eq!(analyze_source(source), ["Unreachable code after the endless loop on line 1"]);
+
+        // Only if every branch loops forever
+        let source = "var a;\nif (a) { for (;;) {} } else { while (true) {} }\na = 1;";
+        assert_eq!(analyze_source(source), ["Unreachable code after the endless loop on line 2"]);
+        assert!(analyze_source("var a;\nif (a) while (true) {}\na = 1;").is_empty());
+
+        // A `break` ends them, unless it belongs to a loop inside
+        assert!(analyze_source("var a;\nwhile (true) if (a) break;\na = 1;").is_empty());
+        let source = "outer: while (true) {\n  for (;;) break outer;\n}\nprint 1;";
+        assert!(analyze_source(source).is_empty());
+        let source = "while (true) {\n  for (;;) break;\n}\nprint 1;";
+        assert_eq!(analyze_source(source), ["Unreachable code after the endless loop on line 1"]);
+    }
+
+    #[test]
+    fn reports_code_after_return_and_missing_returns() {
+        let source = "fun f(a) {\n  if (a) return 1;\n}\nfun g() {\n  return;\n  print 1;\n}\nfun h(a) { if (a) return 1; else return 2; }";
+        assert_eq!(
+            analyze_source(source),
+            ["Not every path of f returns a value on line 1", "Unreachable code after the return on line 5"]
+        );
+        let source = "var a;\nwhile (a) {\n  continue;\n  a = 1;\n}";
+        assert_eq!(analyze_source(source), ["Unreachable code after the continue on line 3"]);
+    }
+
+    #[test]
+    fn looks_into_anonymous_functions() {
+        let source = "var f = fun (a) {\n  if (a) return 1;\n};\nprint fun () { return; print 1; };";
+        assert_eq!(
+            analyze_source(source),
+            ["Not every path of the anonymous function returns a value on line 1", "Unreachable code after the return on line 4"]
+        );
+    }
+
+    #[test]
+    fn warnings_point_at_their_token() {
+        let mut reporter = CollectingReporter::default();
+        let tokens = scan_tokens("while (true) {}\n  var a;".to_string(), &mut reporter).expect("Token Scanning failed!");
+        analyze(&parse(tokens, &mut reporter).expect("Parsing failed!"), &mut reporter);
+        let span = reporter.diagnostics()[0].span.expect("The warning has no span");
+        assert_eq!((span.line, span.column, span.length), (2, 7, 1));
+    }
+}
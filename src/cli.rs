@@ -0,0 +1,325 @@
+// External dependencies
+use anyhow::Result;
+use tracing::Level;
+
+// Internal dependencies
+use jlox::errors::ArgumentError;
+use jlox::interpreter::Capabilities;
+use crate::commands::bench::{BenchOptions, DEFAULT_BENCH_DIR, DEFAULT_RUNS, DEFAULT_THRESHOLD};
+use crate::reporter::DEFAULT_MAX_ERRORS;
+use crate::style::ColorChoice;
+
+/// Directory that `jlox test` uses when no directory is given
+const DEFAULT_TEST_DIR: &str = "tests/cases";
+
+/// Everything that can be configured through the command line.
+/// Built by [parse_args] from the raw argument vector.
+pub struct Options {
+    /// Maximum level of log messages that get written to stderr
+    pub log_level: Level,
+    /// Number of errors the front end prints before it stops reporting, `0` for no limit
+    pub max_errors: usize,
+    /// Whether warnings make the run fail like errors
+    pub deny_warnings: bool,
+    /// Whether diagnostics and command output get colored
+    pub color: ColorChoice,
+    /// Whether scripts get type checked before they run
+    pub typed: bool,
+    /// Whether the standard prelude runs before the script or prompt session
+    pub prelude: bool,
+    /// Groups of natives that scripts may call, all of them unless
+    /// restricted with `--allow` or `--deny`
+    pub capabilities: Capabilities,
+    /// What jlox should actually do
+    pub command: Command,
+}
+
+/// The different modes jlox can run in
+pub enum Command {
+    /// Run the script (or compiled `.loxc` chunk) at the given path,
+    /// optionally recording a trace of the execution
+    RunFile { script: String, record: Option<String> },
+    /// Start the interactive prompt
+    Prompt,
+    /// Run every script in `dir` and compare its output against the
+    /// committed `.expected` files. With `bless`, the files get rewritten.
+    Test { dir: String, bless: bool },
+    /// Time every script in a directory, optionally against a baseline
+    Bench(BenchOptions),
+    /// Print the semantic differences between two scripts
+    Diff { old: String, new: String },
+    /// Print the script with minimal whitespace, optionally with
+    /// local variables renamed to short names
+    Minify { script: String, rename_locals: bool },
+    /// Compile the script into a `.loxc` chunk. Without an explicit
+    /// output path, the chunk is written next to the script.
+    Compile { script: String, output: Option<String> },
+    /// Print the long description of an error code
+    Explain(String),
+    /// Step through a trace recorded with `--record`
+    Replay(String),
+    /// Print the script in the style of the closest `.loxfmt.toml`, with
+    /// `key=value` settings from `--config` on top. With `write`, the
+    /// script gets rewritten instead.
+    Fmt { script: String, write: bool, settings: Vec<String> },
+    /// Check the script against its type annotations without running it
+    Typecheck(String),
+    /// Render the doc comments of the scripts in `dir` as Markdown, or as
+    /// HTML with `html`. Without an output path, the result is printed.
+    Doc { dir: String, html: bool, output: Option<String> },
+    /// Install the dependencies of the `lox.toml` at or above `dir`
+    Install(String),
+    /// Render a text template with islands of Lox code to stdout
+    Template(String),
+    /// Serve Jupyter clients as a kernel, with the given connection file,
+    /// or install the kernel spec if it is `install`
+    #[cfg(feature = "kernel")]
+    Kernel(String),
+}
+
+/// Takes in the command line arguments (including the program name
+/// at index 0) and turns them into [Options].
+///
+/// Verbosity is controlled by `-v` (debug), `-vv` (trace) and `-q`
+/// (errors only). By default, warnings and errors are logged.
+pub fn parse_args(argv: &[String]) -> Result<Options> {
+    let mut verbosity: u8 = 0;
+    let mut quiet = false;
+    let mut max_errors = DEFAULT_MAX_ERRORS;
+    let mut deny_warnings = false;
+    let mut typed = false;
+    let mut prelude = true;
+    let mut allowed: Option<Capabilities> = None;
+    let mut denied: Vec<String> = Vec::new();
+    let mut html = false;
+    let mut color = ColorChoice::Auto;
+    let mut bless = false;
+    let mut rename_locals = false;
+    let mut output: Option<String> = None;
+    let mut explain: Option<String> = None;
+    let mut record: Option<String> = None;
+    let mut runs: Option<usize> = None;
+    let mut baseline: Option<String> = None;
+    let mut threshold: Option<f64> = None;
+    let mut write = false;
+    let mut settings: Vec<String> = Vec::new();
+    let mut positional: Vec<String> = Vec::new();
+
+    let mut args = argv.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-q" | "--quiet" => quiet = true,
+            "-v" | "--verbose" => verbosity += 1,
+            "-vv" => verbosity += 2,
+            "--bless" => bless = true,
+            "--rename-locals" => rename_locals = true,
+            "--deny-warnings" => deny_warnings = true,
+            "--write" => write = true,
+            "--typed" => typed = true,
+            "--no-prelude" => prelude = false,
+            "--html" => html = true,
+            "-o" | "--output" => {
+                let value = args.next().ok_or(ArgumentError::MissingValue(arg.clone()))?;
+                output = Some(value.clone());
+            }
+            "--record" => {
+                let value = args.next().ok_or(ArgumentError::MissingValue(arg.clone()))?;
+                record = Some(value.clone());
+            }
+            "--runs" => {
+                let value = args.next().ok_or(ArgumentError::MissingValue(arg.clone()))?;
+                runs = Some(value.parse().map_err(|_| ArgumentError::InvalidValue(arg.clone(), value.clone()))?);
+            }
+            "--baseline" => {
+                let value = args.next().ok_or(ArgumentError::MissingValue(arg.clone()))?;
+                baseline = Some(value.clone());
+            }
+            "--threshold" => {
+                let value = args.next().ok_or(ArgumentError::MissingValue(arg.clone()))?;
+                threshold = Some(value.parse().map_err(|_| ArgumentError::InvalidValue(arg.clone(), value.clone()))?);
+            }
+            "--allow" => {
+                let value = args.next().ok_or(ArgumentError::MissingValue(arg.clone()))?;
+                let allowed = allowed.get_or_insert_with(Capabilities::none);
+                for group in capability_groups(arg, value)? {
+                    allowed.set(group, true);
+                }
+            }
+            "--deny" => {
+                let value = args.next().ok_or(ArgumentError::MissingValue(arg.clone()))?;
+                denied.extend(capability_groups(arg, value)?.map(str::to_string));
+            }
+            "--config" => {
+                let value = args.next().ok_or(ArgumentError::MissingValue(arg.clone()))?;
+                settings.push(value.clone());
+            }
+            "--explain" => {
+                let value = args.next().ok_or(ArgumentError::MissingValue(arg.clone()))?;
+                explain = Some(value.clone());
+            }
+            "--max-errors" => {
+                let value = args.next().ok_or(ArgumentError::MissingValue(arg.clone()))?;
+                max_errors = value.parse().map_err(|_| ArgumentError::InvalidValue(arg.clone(), value.clone()))?;
+            }
+            "--color" => {
+                let value = args.next().ok_or(ArgumentError::MissingValue(arg.clone()))?;
+                color = parse_color(arg, value)?;
+            }
+            _ if arg.starts_with("--color=") => color = parse_color("--color", &arg["--color=".len()..])?,
+            _ if arg.starts_with('-') => return Err(ArgumentError::UnknownFlag(arg.clone()).into()),
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    if quiet && verbosity > 0 {
+        return Err(ArgumentError::ConflictingVerbosity.into());
+    }
+
+    let log_level = match (quiet, verbosity) {
+        (true, _) => Level::ERROR,
+        (false, 0) => Level::WARN,
+        (false, 1) => Level::DEBUG,
+        (false, _) => Level::TRACE,
+    };
+
+    // The first positional argument is either a subcommand or the script path
+    let command = match positional.as_slice() {
+        [] if explain.is_some() => Command::Explain(explain.take().unwrap_or_default()),
+        [] => Command::Prompt,
+        [cmd, rest @ ..] if cmd == "test" && rest.len() <= 1 => Command::Test {
+            dir: rest.first().cloned().unwrap_or(DEFAULT_TEST_DIR.to_string()),
+            bless,
+        },
+        [cmd, rest @ ..] if cmd == "bench" && rest.len() <= 1 => Command::Bench(BenchOptions {
+            dir: rest.first().cloned().unwrap_or(DEFAULT_BENCH_DIR.to_string()),
+            runs: runs.take().unwrap_or(DEFAULT_RUNS),
+            output: output.take(),
+            baseline: baseline.take(),
+            threshold: threshold.take().unwrap_or(DEFAULT_THRESHOLD),
+        }),
+        [cmd, old, new] if cmd == "diff" => Command::Diff {
+            old: old.clone(),
+            new: new.clone(),
+        },
+        [cmd, script] if cmd == "minify" => Command::Minify {
+            script: script.clone(),
+            rename_locals,
+        },
+        [cmd, script] if cmd == "compile" => Command::Compile {
+            script: script.clone(),
+            output: output.take(),
+        },
+        [cmd, script] if cmd == "fmt" => Command::Fmt {
+            script: script.clone(),
+            write,
+            settings: std::mem::take(&mut settings),
+        },
+        [cmd, dir] if cmd == "doc" => Command::Doc {
+            dir: dir.clone(),
+            html,
+            output: output.take(),
+        },
+        [cmd, rest @ ..] if cmd == "install" && rest.len() <= 1 => {
+            Command::Install(rest.first().cloned().unwrap_or(".".to_string()))
+        }
+        [cmd, script] if cmd == "typecheck" => Command::Typecheck(script.clone()),
+        [cmd, template] if cmd == "template" => Command::Template(template.clone()),
+        #[cfg(feature = "kernel")]
+        [cmd, connection_file] if cmd == "kernel" => Command::Kernel(connection_file.clone()),
+        [cmd, trace] if cmd == "replay" => Command::Replay(trace.clone()),
+        [cmd, script] if cmd == "run" => Command::RunFile {
+            script: script.clone(),
+            record: record.take(),
+        },
+        [script] => Command::RunFile {
+            script: script.clone(),
+            record: record.take(),
+        },
+        _ => return Err(ArgumentError::InvalidArgs.into()),
+    };
+
+    if bless && !matches!(command, Command::Test { .. }) {
+        return Err(ArgumentError::FlagWithoutCommand("--bless".to_string(), "test").into());
+    }
+    if html && !matches!(command, Command::Doc { .. }) {
+        return Err(ArgumentError::FlagWithoutCommand("--html".to_string(), "doc").into());
+    }
+    if rename_locals && !matches!(command, Command::Minify { .. }) {
+        return Err(ArgumentError::FlagWithoutCommand("--rename-locals".to_string(), "minify").into());
+    }
+    if write && !matches!(command, Command::Fmt { .. }) {
+        return Err(ArgumentError::FlagWithoutCommand("--write".to_string(), "fmt").into());
+    }
+    if !settings.is_empty() {
+        return Err(ArgumentError::FlagWithoutCommand("--config".to_string(), "fmt").into());
+    }
+    if runs.is_some() || baseline.is_some() || threshold.is_some() {
+        return Err(ArgumentError::FlagWithoutCommand("--runs, --baseline and --threshold".to_string(), "bench").into());
+    }
+    if record.is_some() {
+        return Err(ArgumentError::FlagWithoutCommand("--record".to_string(), "run").into());
+    }
+    if explain.is_some() {
+        return Err(ArgumentError::InvalidArgs.into());
+    }
+    if output.is_some() {
+        return Err(ArgumentError::FlagWithoutCommand("-o".to_string(), "compile, doc or bench").into());
+    }
+
+    // Without --allow every group is granted, --deny takes groups away again
+    let mut capabilities = allowed.unwrap_or_else(Capabilities::all);
+    for group in &denied {
+        capabilities.set(group, false);
+    }
+
+    Ok(Options { log_level, max_errors, deny_warnings, color, typed, prelude, capabilities, command })
+}
+
+/// Splits the comma separated value of `--allow` or `--deny` into group
+/// names, failing on names that are no capability group
+fn capability_groups<'a>(flag: &str, value: &'a str) -> Result<impl Iterator<Item = &'a str>> {
+    let groups = value.split(',').map(str::trim);
+    match groups.clone().find(|group| !Capabilities::GROUPS.contains(group)) {
+        Some(_) => Err(ArgumentError::InvalidValue(flag.to_string(), value.to_string()).into()),
+        None => Ok(groups),
+    }
+}
+
+/// Parses the value of `--color`
+fn parse_color(flag: &str, value: &str) -> Result<ColorChoice> {
+    match value {
+        "always" => Ok(ColorChoice::Always),
+        "auto" => Ok(ColorChoice::Auto),
+        "never" => Ok(ColorChoice::Never),
+        _ => Err(ArgumentError::InvalidValue(flag.to_string(), value.to_string()).into()),
+    }
+}
+
+// ---------- Tests for the cli module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_level(flags: &[&str]) -> Result<Level> {
+        let argv: Vec<String> = ["jlox"].iter().chain(flags).map(|arg| arg.to_string()).collect();
+        parse_args(&argv).map(|options| options.log_level)
+    }
+
+    #[test]
+    fn verbosity_flags_set_the_log_level() {
+        assert_eq!(log_level(&[]).unwrap(), Level::WARN);
+        assert_eq!(log_level(&["-v"]).unwrap(), Level::DEBUG);
+        assert_eq!(log_level(&["--verbose", "script.lox"]).unwrap(), Level::DEBUG);
+        assert_eq!(log_level(&["-vv"]).unwrap(), Level::TRACE);
+        assert_eq!(log_level(&["-v", "-v"]).unwrap(), Level::TRACE);
+        assert_eq!(log_level(&["-q"]).unwrap(), Level::ERROR);
+        assert_eq!(log_level(&["--quiet", "script.lox"]).unwrap(), Level::ERROR);
+    }
+
+    #[test]
+    fn quiet_and_verbose_conflict() {
+        assert!(log_level(&["-q", "-v"]).is_err());
+        assert!(log_level(&["-vv", "--quiet"]).is_err());
+    }
+}
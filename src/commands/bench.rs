@@ -0,0 +1,409 @@
+// External dependencies
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+// Internal dependencies
+use super::common::collect_scripts;
+use jlox::diagnostic::CollectingReporter;
+use jlox::errors::BenchError;
+use jlox::interpreter::{Capabilities, Interpreter};
+use crate::style;
+
+/// Directory that `jlox bench` uses when no directory is given
+pub const DEFAULT_BENCH_DIR: &str = "benches";
+
+/// Number of timed runs per script when `--runs` is not given
+pub const DEFAULT_RUNS: usize = 10;
+
+/// Relative change (in percent) below which a difference to the baseline
+/// is never reported, when `--threshold` is not given
+pub const DEFAULT_THRESHOLD: f64 = 5.0;
+
+/// Version of the results file format, bumped on incompatible changes
+const FORMAT_VERSION: u32 = 1;
+
+/// Settings of a benchmark run, taken from the command line
+pub struct BenchOptions {
+    pub dir: String,
+    pub runs: usize,
+    pub output: Option<String>,
+    pub baseline: Option<String>,
+    pub threshold: f64,
+}
+
+/// Timings of one script, in milliseconds
+#[derive(Debug, Clone, PartialEq)]
+struct Measurement {
+    script: String,
+    runs: usize,
+    mean: f64,
+    stddev: f64,
+    min: f64,
+}
+
+/// How a script compares to the baseline
+#[derive(Debug, PartialEq)]
+enum Verdict {
+    Faster,
+    Slower,
+    /// Within the threshold or the noise of the measurements
+    Unchanged,
+}
+
+/// Runs every `.lox` script below the directory a number of times in a
+/// fresh interpreter and prints how long a run takes. With `output`, the
+/// results are written as JSON. With `baseline`, every script is compared
+/// to the results of an earlier run, and the command fails if one got
+/// significantly slower.
+pub fn run(options: BenchOptions) -> Result<()> {
+    let mut scripts: Vec<PathBuf> = Vec::new();
+    collect_scripts(Path::new(&options.dir), &mut scripts)?;
+    scripts.sort();
+    if scripts.is_empty() {
+        return Err(BenchError::NoScripts(options.dir).into());
+    }
+
+    let baseline = match &options.baseline {
+        Some(path) => Some(parse_results(&std::fs::read_to_string(path)?, path)?),
+        None => None,
+    };
+
+    let mut results = Vec::with_capacity(scripts.len());
+    let mut regressions = 0;
+    for script in &scripts {
+        let measurement = measure(script, options.runs.max(1))?;
+        let old = baseline.as_ref().and_then(|old| old.iter().find(|m| m.script == measurement.script));
+        match old {
+            Some(old) => {
+                let verdict = compare(old, &measurement, options.threshold);
+                let label = match verdict {
+                    Verdict::Faster => style::paint("faster", style::theme().success),
+                    Verdict::Slower => style::paint("slower", style::theme().error),
+                    Verdict::Unchanged => "~".to_string(),
+                };
+                if verdict == Verdict::Slower {
+                    regressions += 1;
+                }
+                println!(
+                    "{:<40} {:>10.3} ms -> {:>10.3} ms  {:>+7.1}%  {}",
+                    measurement.script,
+                    old.mean,
+                    measurement.mean,
+                    change(old, &measurement),
+                    label
+                );
+            }
+            None => println!(
+                "{:<40} {:>10.3} ms  ± {:.3} ms  (min {:.3} ms)",
+                measurement.script, measurement.mean, measurement.stddev, measurement.min
+            ),
+        }
+        results.push(measurement);
+    }
+
+    if let Some(path) = &options.output {
+        std::fs::write(path, render_results(&results))?;
+    }
+    if regressions > 0 {
+        return Err(BenchError::Regressed(regressions, results.len()).into());
+    }
+    Ok(())
+}
+
+/// Runs the script once to warm up and then `runs` times while timing it.
+/// Output of the script is discarded, errors fail the benchmark.
+fn measure(script: &Path, runs: usize) -> Result<Measurement> {
+    let source = std::fs::read_to_string(script)?;
+    let mut timings = Vec::with_capacity(runs);
+    for run in 0..=runs {
+        let builder = Interpreter::builder().capabilities(Capabilities::all()).prelude(true).output(std::io::sink());
+        #[cfg(feature = "jit")]
+        let builder = builder.jit_threshold(jlox::interpreter::JIT_THRESHOLD);
+        let mut interpreter = builder.build();
+        let start = Instant::now();
+        interpreter.run(source.clone(), &mut CollectingReporter::default())?;
+        if run > 0 {
+            timings.push(start.elapsed().as_secs_f64() * 1000.0);
+        }
+    }
+
+    let mean = timings.iter().sum::<f64>() / runs as f64;
+    let variance = timings.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / (runs.max(2) - 1) as f64;
+    Ok(Measurement {
+        script: script.display().to_string(),
+        runs,
+        mean,
+        stddev: variance.sqrt(),
+        min: timings.iter().copied().fold(f64::INFINITY, f64::min),
+    })
+}
+
+/// Relative change of the mean, in percent
+fn change(old: &Measurement, new: &Measurement) -> f64 {
+    (new.mean - old.mean) / old.mean * 100.0
+}
+
+/// A difference counts if it is larger than the threshold and larger than
+/// twice its standard error, so noisy scripts don't raise false alarms
+fn compare(old: &Measurement, new: &Measurement, threshold: f64) -> Verdict {
+    let standard_error = (old.stddev.powi(2) / old.runs as f64 + new.stddev.powi(2) / new.runs as f64).sqrt();
+    let difference = new.mean - old.mean;
+    if change(old, new).abs() < threshold || difference.abs() <= 2.0 * standard_error {
+        Verdict::Unchanged
+    } else if difference > 0.0 {
+        Verdict::Slower
+    } else {
+        Verdict::Faster
+    }
+}
+
+fn render_results(results: &[Measurement]) -> String {
+    let entries: Vec<String> = results
+        .iter()
+        .map(|m| {
+            format!(
+                "    {{ \"script\": {}, \"runs\": {}, \"mean_ms\": {}, \"stddev_ms\": {}, \"min_ms\": {} }}",
+                json_string(&m.script),
+                m.runs,
+                m.mean,
+                m.stddev,
+                m.min
+            )
+        })
+        .collect();
+    format!("{{\n  \"version\": {},\n  \"results\": [\n{}\n  ]\n}}\n", FORMAT_VERSION, entries.join(",\n"))
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Reads a results file written by [render_results]. `path` names the file in errors.
+fn parse_results(text: &str, path: &str) -> Result<Vec<Measurement>> {
+    let invalid = |what: &str| BenchError::InvalidResults(path.to_string(), what.to_string());
+    let root = Json::parse(text).ok_or_else(|| invalid("not valid JSON"))?;
+    if root.get("version").and_then(Json::number) != Some(FORMAT_VERSION as f64) {
+        return Err(invalid("unsupported version").into());
+    }
+
+    let entries = match root.get("results") {
+        Some(Json::Array(entries)) => entries,
+        _ => return Err(invalid("no results").into()),
+    };
+    entries
+        .iter()
+        .map(|entry| {
+            let number = |key: &str| entry.get(key).and_then(Json::number).ok_or_else(|| invalid(key));
+            let script = match entry.get("script") {
+                Some(Json::String(script)) => script.clone(),
+                _ => return Err(invalid("script").into()),
+            };
+            Ok(Measurement {
+                script,
+                runs: number("runs")? as usize,
+                mean: number("mean_ms")?,
+                stddev: number("stddev_ms")?,
+                min: number("min_ms")?,
+            })
+        })
+        .collect()
+}
+
+/// The parts of JSON results files use. Enough to read files that other
+/// tools rewrote, as long as they kept the structure.
+#[derive(Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn parse(text: &str) -> Option<Json> {
+        let mut parser = JsonParser { chars: text.chars().collect(), current: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        (parser.current == parser.chars.len()).then_some(value)
+    }
+
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn number(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// Recursive descent over the characters of a JSON document
+struct JsonParser {
+    chars: Vec<char>,
+    current: usize,
+}
+
+impl JsonParser {
+    fn value(&mut self) -> Option<Json> {
+        self.skip_whitespace();
+        match self.peek()? {
+            '{' => self.object(),
+            '[' => self.array(),
+            '"' => self.string().map(Json::String),
+            't' => self.keyword("true", Json::Bool(true)),
+            'f' => self.keyword("false", Json::Bool(false)),
+            'n' => self.keyword("null", Json::Null),
+            _ => self.number(),
+        }
+    }
+
+    fn object(&mut self) -> Option<Json> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.current += 1;
+            return Some(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.advance()? {
+                ',' => continue,
+                '}' => return Some(Json::Object(fields)),
+                _ => return None,
+            }
+        }
+    }
+
+    fn array(&mut self) -> Option<Json> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.current += 1;
+            return Some(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.advance()? {
+                ',' => continue,
+                ']' => return Some(Json::Array(items)),
+                _ => return None,
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.advance()? {
+                '"' => return Some(out),
+                '\\' => out.push(match self.advance()? {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    'u' => {
+                        let hex: String = (0..4).map(|_| self.advance()).collect::<Option<_>>()?;
+                        char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+                    }
+                    c => c, // \" \\ \/
+                }),
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Option<Json> {
+        let start = self.current;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(c)) {
+            self.current += 1;
+        }
+        let text: String = self.chars[start..self.current].iter().collect();
+        text.parse().ok().map(Json::Number)
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Option<Json> {
+        for expected in word.chars() {
+            self.expect(expected)?;
+        }
+        Some(value)
+    }
+
+    fn expect(&mut self, expected: char) -> Option<()> {
+        (self.advance()? == expected).then_some(())
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.current += 1;
+        Some(c)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.current).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.current += 1;
+        }
+    }
+}
+
+// ---------- Tests for the bench module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(script: &str, mean: f64, stddev: f64) -> Measurement {
+        Measurement { script: script.to_string(), runs: 10, mean, stddev, min: mean - stddev }
+    }
+
+    #[test]
+    fn results_round_trip() {
+        let results = vec![measurement("benches/a \"b\".lox", 12.5, 0.25), measurement("benches/c.lox", 0.001, 0.0)];
+        let text = render_results(&results);
+        assert_eq!(parse_results(&text, "results.json").expect("Parsing failed!"), results);
+
+        assert!(parse_results("{ \"version\": 0, \"results\": [] }", "old.json").is_err());
+        assert!(parse_results("{ \"version\": 1, \"results\": [ { \"script\": \"a\" } ] }", "old.json").is_err());
+        assert!(parse_results("[1, 2", "old.json").is_err());
+    }
+
+    #[test]
+    fn only_significant_changes_count() {
+        let old = measurement("a", 100.0, 1.0);
+        assert_eq!(compare(&old, &measurement("a", 120.0, 1.0), 5.0), Verdict::Slower);
+        assert_eq!(compare(&old, &measurement("a", 80.0, 1.0), 5.0), Verdict::Faster);
+        // Below the threshold
+        assert_eq!(compare(&old, &measurement("a", 103.0, 0.1), 5.0), Verdict::Unchanged);
+        // Above the threshold, but within the noise
+        assert_eq!(compare(&old, &measurement("a", 120.0, 60.0), 5.0), Verdict::Unchanged);
+    }
+}
@@ -0,0 +1,79 @@
+// External dependencies
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+// Internal dependencies
+use crate::reporter::StderrReporter;
+use jlox::obj::number::Number;
+use jlox::scanner::Comment;
+use jlox::obj::statement::Statement;
+use jlox::obj::token_type::TokenType;
+use jlox::{parser, scanner};
+
+/// Reads and parses a Lox file into its top-level statements.
+/// Used by the tooling commands that work on the AST.
+pub fn parse_file(path: &str) -> Result<Vec<Statement>> {
+    parse_source(std::fs::read_to_string(path)?)
+}
+
+/// Parses Lox source code into its top-level statements
+pub fn parse_source(source: String) -> Result<Vec<Statement>> {
+    parse_with_comments(source).map(|(statements, _)| statements)
+}
+
+/// Parses Lox source code into its top-level statements and the comments
+/// in between them, for the commands that print the code again
+pub fn parse_with_comments(source: String) -> Result<(Vec<Statement>, Vec<Comment>)> {
+    let mut reporter = StderrReporter::new();
+    let parsed = scanner::scan_with_comments(source, &mut reporter)
+        .and_then(|(tokens, comments)| Ok((parser::parse(tokens, &mut reporter)?, comments)));
+    let denied = reporter.finish();
+    let parsed = parsed?;
+    denied?;
+    Ok(parsed)
+}
+
+/// Writes a string value as a Lox literal. Strings with quotes in them
+/// go into triple quotes.
+pub fn string_literal(text: &str) -> String {
+    if text.contains('"') {
+        format!("\"\"\"{}\"\"\"", text)
+    } else {
+        format!("\"{}\"", text)
+    }
+}
+
+/// Writes a number value as a Lox literal. Whole numbers get a fractional
+/// part, so they don't read back as ints.
+pub fn number_literal(number: &Number) -> String {
+    match number.to_string() {
+        text if text.contains('.') => text,
+        text => text + ".0",
+    }
+}
+
+/// The initializer and the loop of a block the parser created for a `for`
+/// loop with an initializer. The printers turn it back into a `for`.
+pub fn desugared_for(stmts: &[Statement]) -> Option<(&Statement, &Statement)> {
+    match stmts {
+        [init @ (Statement::Var(..) | Statement::Expression(_)), stmt @ Statement::While(keyword, ..)]
+            if keyword.token_type() == TokenType::For =>
+        {
+            Some((init, stmt))
+        }
+        _ => None,
+    }
+}
+
+/// Recursively collects all `.lox` files inside a directory
+pub fn collect_scripts(dir: &Path, scripts: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_scripts(&path, scripts)?;
+        } else if path.extension().is_some_and(|ext| ext == "lox") {
+            scripts.push(path);
+        }
+    }
+    Ok(())
+}
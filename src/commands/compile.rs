@@ -0,0 +1,24 @@
+// External dependencies
+use anyhow::Result;
+use std::path::Path;
+
+// Internal dependencies
+use super::common::parse_file;
+use jlox::loxc;
+
+/// Parses the script and writes it as a `.loxc` chunk, either to the
+/// given output path or next to the script with the extension replaced.
+pub fn run(script: &str, output: Option<String>) -> Result<()> {
+    let statements = parse_file(script)?;
+    let chunk = loxc::compile(&statements);
+
+    let output = output.unwrap_or_else(|| {
+        Path::new(script)
+            .with_extension("loxc")
+            .to_string_lossy()
+            .into_owned()
+    });
+    std::fs::write(&output, chunk)?;
+    println!("compiled {} to {}", script, output);
+    Ok(())
+}
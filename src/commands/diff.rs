@@ -0,0 +1,226 @@
+// External dependencies
+use anyhow::Result;
+
+// Internal dependencies
+use super::common::parse_file;
+use crate::style;
+use jlox::obj::statement::Statement;
+
+/// A single semantic difference between two programs
+enum Change {
+    /// A statement only present in the new program
+    Added(String),
+    /// A statement only present in the old program
+    Removed(String),
+    /// A declaration present in both programs, but with a different body
+    Changed { name: String, old: String, new: String },
+}
+
+/// One step of the edit script that turns the old statements into the new ones
+enum Edit {
+    Keep,
+    Remove(usize),
+    Add(usize),
+}
+
+/// Parses both files and prints the differences between their top-level
+/// statements. Statements are compared by their serialized AST, so pure
+/// formatting changes (whitespace, comments, line breaks) are ignored.
+pub fn run(old_path: &str, new_path: &str) -> Result<()> {
+    let old = serialize(parse_file(old_path)?);
+    let new = serialize(parse_file(new_path)?);
+
+    let changes = diff(&old, &new);
+    if changes.is_empty() {
+        println!("No semantic differences");
+        return Ok(());
+    }
+
+    for change in &changes {
+        match change {
+            Change::Added(stmt) => println!("{}", style::paint(&format!("+ {}", stmt), style::theme().success)),
+            Change::Removed(stmt) => println!("{}", style::paint(&format!("- {}", stmt), style::theme().error)),
+            Change::Changed { name, old, new } => {
+                println!("~ {}", name);
+                println!("    {}", style::paint(&format!("- {}", old), style::theme().error));
+                println!("    {}", style::paint(&format!("+ {}", new), style::theme().success));
+            }
+        }
+    }
+    println!("\n{} difference(s)", changes.len());
+    Ok(())
+}
+
+/// A top-level statement in serialized form, together with the name
+/// of what it declares (if it is a declaration)
+struct Entry {
+    declares: Option<String>,
+    serialized: String,
+}
+
+fn serialize(statements: Vec<Statement>) -> Vec<Entry> {
+    statements
+        .iter()
+        .map(|stmt| Entry {
+            declares: declaration_name(stmt),
+            serialized: stmt.to_string(),
+        })
+        .collect()
+}
+
+/// Returns a readable name for statements that declare something, so
+/// a removed and an added declaration of the same name can be reported
+/// as a single change.
+fn declaration_name(stmt: &Statement) -> Option<String> {
+    match stmt {
+        Statement::Var(name, ..) => Some(format!("var {}", name.lexeme())),
+        Statement::Const(name, ..) => Some(format!("const {}", name.lexeme())),
+        Statement::Destructure(pattern, _) => Some(format!("var {}", pattern)),
+        Statement::Function(declaration) => Some(format!("fun {}", declaration.name.lexeme())),
+        Statement::Class(name, ..) => Some(format!("class {}", name.lexeme())),
+        _ => None,
+    }
+}
+
+/// Computes the semantic changes between two lists of serialized statements
+fn diff(old: &[Entry], new: &[Entry]) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut removed: Vec<usize> = Vec::new();
+    let mut added: Vec<usize> = Vec::new();
+
+    // Every run of removals and additions between two kept statements is
+    // one hunk. Declarations inside a hunk are paired up by their name.
+    for edit in edit_script(old, new).into_iter().chain([Edit::Keep]) {
+        match edit {
+            Edit::Remove(i) => removed.push(i),
+            Edit::Add(j) => added.push(j),
+            Edit::Keep => {
+                flush_hunk(old, new, &mut removed, &mut added, &mut changes);
+            }
+        }
+    }
+    changes
+}
+
+/// Turns a hunk of removed and added statements into changes
+fn flush_hunk(
+    old: &[Entry],
+    new: &[Entry],
+    removed: &mut Vec<usize>,
+    added: &mut Vec<usize>,
+    changes: &mut Vec<Change>,
+) {
+    for i in removed.drain(..) {
+        let paired = old[i].declares.as_ref().and_then(|name| {
+            added
+                .iter()
+                .position(|&j| new[j].declares.as_ref() == Some(name))
+                .map(|pos| (name.clone(), added.remove(pos)))
+        });
+
+        match paired {
+            Some((name, j)) => changes.push(Change::Changed {
+                name,
+                old: old[i].serialized.clone(),
+                new: new[j].serialized.clone(),
+            }),
+            None => changes.push(Change::Removed(old[i].serialized.clone())),
+        }
+    }
+    for j in added.drain(..) {
+        changes.push(Change::Added(new[j].serialized.clone()));
+    }
+}
+
+/// Computes the shortest edit script between both statement lists using
+/// the longest common subsequence of their serialized forms.
+fn edit_script(old: &[Entry], new: &[Entry]) -> Vec<Edit> {
+    // lcs[i][j] is the LCS length of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i].serialized == new[j].serialized {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i].serialized == new[j].serialized {
+            edits.push(Edit::Keep);
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            edits.push(Edit::Add(j));
+            j += 1;
+        } else {
+            edits.push(Edit::Remove(i));
+            i += 1;
+        }
+    }
+    edits
+}
+
+// ---------- Tests for the diff module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jlox::diagnostic::CollectingReporter;
+    use jlox::{parser, scanner};
+
+    /// The changes between both sources, one line each like `jlox diff` starts them
+    fn changes(old: &str, new: &str) -> Vec<String> {
+        let parse = |source: &str| {
+            let mut reporter = CollectingReporter::default();
+            let tokens = scanner::scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+            serialize(parser::parse(tokens, &mut reporter).expect("Parsing failed!"))
+        };
+        diff(&parse(old), &parse(new))
+            .into_iter()
+            .map(|change| match change {
+                Change::Added(stmt) => format!("+ {}", stmt),
+                Change::Removed(stmt) => format!("- {}", stmt),
+                Change::Changed { name, .. } => format!("~ {}", name),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn formatting_is_no_difference() {
+        assert!(changes("var a = 1; print a;", "// Same\nvar   a=1;\n\nprint\n    a;").is_empty());
+    }
+
+    #[test]
+    fn reports_added_statements() {
+        assert_eq!(changes("var a = 1;", "var a = 1; print a;"), ["+ (print (var a))"]);
+    }
+
+    #[test]
+    fn reports_removed_statements() {
+        assert_eq!(changes("var a = 1; print a;", "print a;"), ["- (var a 1)"]);
+    }
+
+    #[test]
+    fn reports_changed_declarations() {
+        assert_eq!(changes("var a = 1; const b = 2; print a;", "var a = 2; const b = 3; print a;"), ["~ var a", "~ const b"]);
+    }
+
+    #[test]
+    fn reports_changed_functions() {
+        let old = "fun greet(name) { print \"Hi \" + name; } greet(\"Ada\");";
+        let new = "fun greet(name) { print \"Hello \" + name; } greet(\"Ada\");";
+        assert_eq!(changes(old, new), ["~ fun greet"]);
+    }
+
+    #[test]
+    fn reports_changed_classes() {
+        let old = "class Point { init(x) { this.x = x; } } print Point;";
+        let new = "class Point { init(x, y) { this.x = x; this.y = y; } } print Point;";
+        assert_eq!(changes(old, new), ["~ class Point"]);
+    }
+}
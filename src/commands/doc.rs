@@ -0,0 +1,204 @@
+// External dependencies
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// Internal dependencies
+use super::common::{collect_scripts, parse_source};
+use jlox::errors::DocError;
+use jlox::obj::statement::Statement;
+
+/// A top-level declaration together with the doc comment above it
+#[derive(Debug, PartialEq)]
+struct Item {
+    /// The declaration as it would be written, without initializer or body
+    signature: String,
+    /// Lines of the doc comment without the leading `///`
+    docs: Vec<String>,
+}
+
+/// The documented declarations of one script
+struct Page {
+    path: String,
+    items: Vec<Item>,
+}
+
+/// Collects the `///` doc comments of the top-level declarations of every
+/// script below `dir` (or of the script `dir` itself) and renders them as
+/// Markdown, or HTML with `html`. Written to `output` or printed.
+pub fn run(dir: &str, html: bool, output: Option<String>) -> Result<()> {
+    let root = Path::new(dir);
+    let mut scripts: Vec<PathBuf> = Vec::new();
+    if root.is_file() {
+        scripts.push(root.to_path_buf());
+    } else {
+        collect_scripts(root, &mut scripts)?;
+    }
+    scripts.sort();
+    if scripts.is_empty() {
+        return Err(DocError::NoScripts(dir.to_string()).into());
+    }
+
+    let mut pages = Vec::with_capacity(scripts.len());
+    for script in &scripts {
+        let source = std::fs::read_to_string(script)?;
+        let docs = doc_comments(&source);
+        let statements = parse_source(source)?;
+        let path = script.strip_prefix(root).ok().filter(|path| !path.as_os_str().is_empty()).unwrap_or(script);
+        pages.push(Page {
+            path: path.display().to_string(),
+            items: items(&statements, docs),
+        });
+    }
+
+    let rendered = if html { render_html(&pages) } else { render_markdown(&pages) };
+    match output {
+        Some(path) => std::fs::write(path, rendered)?,
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+/// The doc comments of a source, keyed by the line right after them. A
+/// comment block belongs to the declaration on that line.
+fn doc_comments(source: &str) -> HashMap<u32, Vec<String>> {
+    let mut comments = HashMap::new();
+    let mut block: Vec<String> = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        match line.trim_start().strip_prefix("///") {
+            Some(text) => block.push(text.strip_prefix(' ').unwrap_or(text).trim_end().to_string()),
+            None if !block.is_empty() => {
+                comments.insert(index as u32 + 1, std::mem::take(&mut block));
+            }
+            None => {}
+        }
+    }
+    comments
+}
+
+/// The top-level declarations, in source order, with their doc comments
+fn items(statements: &[Statement], mut docs: HashMap<u32, Vec<String>>) -> Vec<Item> {
+    statements
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Statement::Var(name, annotation, _) | Statement::Const(name, annotation, _) => {
+                let keyword = if matches!(stmt, Statement::Const(..)) { "const" } else { "var" };
+                let mut signature = format!("{} {}", keyword, name.lexeme());
+                if let Some(annotation) = annotation {
+                    signature.push_str(&format!(": {}", annotation));
+                }
+                Some(Item {
+                    signature,
+                    docs: docs.remove(&name.line()).unwrap_or_default(),
+                })
+            }
+            Statement::Destructure(pattern, _) => Some(Item {
+                signature: format!("var {}", pattern),
+                docs: docs.remove(&pattern.token().line()).unwrap_or_default(),
+            }),
+            Statement::Function(declaration) => Some(Item {
+                signature: format!("{} {}", declaration.keyword(), declaration.signature()),
+                docs: docs.remove(&declaration.name.line()).unwrap_or_default(),
+            }),
+            Statement::Class(name, superclass, mixins, ..) => Some(Item {
+                signature: {
+                    let mut signature = format!("class {}", name.lexeme());
+                    if let Some(superclass) = superclass {
+                        signature.push_str(&format!(" < {}", superclass.lexeme()));
+                    }
+                    if !mixins.is_empty() {
+                        let mixins: Vec<String> = mixins.iter().map(|mixin| mixin.lexeme()).collect();
+                        signature.push_str(&format!(" with {}", mixins.join(", ")));
+                    }
+                    signature
+                },
+                docs: docs.remove(&name.line()).unwrap_or_default(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn render_markdown(pages: &[Page]) -> String {
+    let mut out = String::from("# Documentation\n");
+    for page in pages {
+        out.push_str(&format!("\n## {}\n", page.path));
+        for item in &page.items {
+            out.push_str(&format!("\n### `{}`\n", item.signature));
+            if !item.docs.is_empty() {
+                out.push_str(&format!("\n{}\n", item.docs.join("\n")));
+            }
+        }
+    }
+    out
+}
+
+fn render_html(pages: &[Page]) -> String {
+    let mut out = String::from("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Documentation</title></head>\n<body>\n<h1>Documentation</h1>\n");
+    for page in pages {
+        out.push_str(&format!("<h2>{}</h2>\n", escape_html(&page.path)));
+        for item in &page.items {
+            out.push_str(&format!("<h3><code>{}</code></h3>\n", escape_html(&item.signature)));
+            // Blank lines separate paragraphs, like in Markdown
+            for paragraph in item.docs.split(|line| line.is_empty()).filter(|lines| !lines.is_empty()) {
+                out.push_str(&format!("<p>{}</p>\n", escape_html(&paragraph.join("\n"))));
+            }
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// ---------- Tests for the doc module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+/// The greeting
+/// printed on start.
+var greeting: String = \"hi\";
+var undocumented = 1;
+
+/// Detached, a blank line follows
+
+/// Number of <retries>
+///
+/// Zero disables them.
+var retries = 3;
+{ /// Not top-level
+  var local; }
+
+/// Waits before the next retry
+fun backoff(attempt: Number): Number { return attempt * 2; }
+";
+
+    fn pages() -> Vec<Page> {
+        let statements = parse_source(SOURCE.to_string()).expect("Parsing failed!");
+        vec![Page { path: "a.lox".to_string(), items: items(&statements, doc_comments(SOURCE)) }]
+    }
+
+    #[test]
+    fn attaches_comments_to_declarations() {
+        let pages = pages();
+        let signatures: Vec<(&str, usize)> = pages[0].items.iter().map(|i| (i.signature.as_str(), i.docs.len())).collect();
+        assert_eq!(
+            signatures,
+            [("var greeting: String", 2), ("var undocumented", 0), ("var retries", 3), ("fun backoff(attempt: Number): Number", 1)]
+        );
+    }
+
+    #[test]
+    fn renders_markdown_and_html() {
+        let markdown = render_markdown(&pages());
+        assert!(markdown.contains("## a.lox\n\n### `var greeting: String`\n\nThe greeting\nprinted on start.\n"));
+
+        let html = render_html(&pages());
+        assert!(html.contains("<p>Number of &lt;retries&gt;</p>\n<p>Zero disables them.</p>\n"));
+    }
+}
@@ -0,0 +1,883 @@
+// External dependencies
+use anyhow::Result;
+
+// Internal dependencies
+use jlox::errors::ArgumentError;
+
+/// The long description of every error code, with examples where they help
+const EXPLANATIONS: &[(&str, &str)] = &[
+    ("E0100", "\
+At least one error occurred while scanning, so the script was not run.
+The errors themselves are printed above this one."),
+    ("E0101", "\
+A string literal is missing its closing quote. Strings may span several
+lines, so the error points at the line where the string starts.
+
+    print \"hello;    // the quote after hello is missing
+
+Lox strings have no escape sequences, a string cannot contain a quote."),
+    ("E0102", "\
+The scanner found a character that is not part of the Lox syntax.
+
+    var a = 1 @ 2;    // @ is not an operator
+
+Outside of strings and comments, only ASCII letters, digits, `_`,
+whitespace and the operators of the language are allowed."),
+    ("E0103", "\
+A number literal could not be represented, for example because it is too
+large for the number type jlox was built with."),
+    ("E0104", "\
+The scanner tried to read past the end of the source. This is a bug in
+jlox, please report it together with the script."),
+    ("E0105", "\
+A number literal is written wrong. Besides decimals like `4.2`, numbers
+can be written in hexadecimal (`0xFF`), binary (`0b1010`) or with an
+exponent (`1.5e-3`). Underscores may separate digits (`1_000_000`).
+
+    print 0xFG;          // G is no hexadecimal digit
+    print 1e;            // the exponent is missing
+    print 1__000;        // underscores go between digits"),
+    ("E0200", "\
+At least one error occurred while parsing, so the script was not run.
+The errors themselves are printed above this one."),
+    ("E0201", "\
+An expression was expected, but the next token cannot start one.
+
+    var a = ;         // the value is missing
+    print 1 + ;       // the right operand is missing"),
+    ("E0202", "\
+A statement is missing its terminating semicolon.
+
+    var a = 1         // needs a ; at the end"),
+    ("E0203", "\
+A closing parenthesis `)` is missing, for example after the arguments of a
+call or the condition of an `if` or `while`.
+
+    print len(\"abc\";    // should be len(\"abc\")
+    if (a < 2 print a;   // should be if (a < 2)"),
+    ("E0204", "\
+An opening parenthesis `(` is missing, for example after `if` or `while`.
+
+    while a < 2 a = a + 1;    // should be while (a < 2)"),
+    ("E0205", "\
+A parenthesized expression is missing its closing parenthesis.
+
+    print (1 + 2;     // should be (1 + 2)"),
+    ("E0206", "\
+A print statement is missing its terminating semicolon.
+
+    print 1           // should be print 1;"),
+    ("E0207", "\
+An expression statement is missing its terminating semicolon.
+
+    a = 2             // should be a = 2;
+
+In the REPL, expressions need a semicolon as well."),
+    ("E0208", "\
+A variable declaration is missing its terminating semicolon.
+
+    var a = 1         // should be var a = 1;"),
+    ("E0209", "\
+A block is missing its closing brace `}`.
+
+    { var a = 1; print a;    // the } is missing"),
+    ("E0210", "\
+A name was expected, for example after `var`.
+
+    var 1 = 2;        // 1 is not a valid variable name"),
+    ("E0211", "\
+A property name is expected after a dot.
+
+    print \"abc\".;     // should be \"abc\".length"),
+    ("E0212", "\
+A literal token carries no value. This is a bug in jlox, please report it
+together with the script."),
+    ("E0213", "\
+The left side of an assignment is not something that can be assigned to.
+Only variables can be assigned.
+
+    1 = 2;            // a number cannot be assigned
+    a + b = 3;        // neither can the result of an operation"),
+    ("E0214", "\
+A single expression was expected (for example by `:type` in the REPL), but
+more tokens follow it.
+
+    :type 1 2         // only one expression is allowed"),
+    ("E0215", "\
+The parser tried to read past the last token. This is a bug in jlox, please
+report it together with the script."),
+    ("E0216", "\
+A type name is expected after the colon of a type annotation.
+
+    var a: = 1;       // should be var a: Number = 1;
+
+Annotations are optional, `var a = 1;` is fine as well."),
+    ("E0217", "\
+Expressions, blocks and statements can be nested at most 128 levels deep,
+so that unusual input cannot overflow the stack of the parser.
+
+    print ((((((((1))))))));   // fine, but 128 parentheses are not
+
+Move parts of deeply nested code into variables."),
+    ("E0218", "\
+`return` can only be used inside the body of a function. Top-level code
+ends when the script ends.
+
+    return 1;                      // error
+    fun one() { return 1; }        // fine"),
+    ("E0219", "\
+The parameter list of a function declaration has to be followed by its
+body in braces, optionally after a return type annotation.
+
+    fun add(a, b) return a + b;    // should be fun add(a, b) { return a + b; }"),
+    ("E0220", "\
+The name of a class declaration has to be followed by its body in braces.
+
+    class Point;                   // should be class Point {}"),
+    ("E0221", "\
+The body of a class was not closed with a \"}\" before the end of the
+script. Methods in the body are declared like functions, but without `fun`.
+
+    class Point {
+        norm() { return 0; }
+                                   // the closing brace is missing"),
+    ("E0222", "\
+`this` refers to the instance a method was called on, so it can only be
+used inside the methods of a class, including functions declared in them.
+
+    fun show() { print this; }     // should be a method of a class"),
+    ("E0223", "\
+A \"<\" after the name of a class has to be followed by the name of the
+class it inherits from.
+
+    class Square < {}              // should be class Square < Shape {}"),
+    ("E0224", "\
+A class cannot be its own superclass.
+
+    class Shape < Shape {}         // error"),
+    ("E0225", "\
+`super` refers to the methods of the superclass, so it can only be used
+inside the methods of a class that inherits from another one.
+
+    class Shape {
+        area() { return super.area(); }    // Shape has no superclass
+    }"),
+    ("E0226", "\
+`super` cannot be used on its own, only to look up a method of the
+superclass.
+
+    super();                       // should be super.init()"),
+    ("E0227", "\
+The `init` method of a class always returns the new instance, so it can
+only use `return` without a value to stop early.
+
+    class Point {
+        init(x) {
+            if (x == nil) return;      // fine
+            return x;                  // error
+        }
+    }"),
+    ("E0228", "\
+A setter receives the value assigned to the property, so it takes exactly
+one parameter.
+
+    class Temperature {
+        set celsius(value) { this.kelvin = value + 273.15; }   // fine
+        set fahrenheit() {}                                     // error
+    }"),
+    ("E0229", "\
+`continue` skips to the next iteration of the loop around it, so it can
+only be used inside the body of a `while` or `for` loop. A function
+declared inside a loop body does not count as being inside the loop.
+
+    if (done) continue;            // error without a loop around it"),
+    ("E0230", "\
+A list literal starts with \"[\" and ends with \"]\", its elements are
+separated by commas.
+
+    var a = [1, 2, 3];             // fine
+    var b = [1, 2 3];              // error, a comma is missing"),
+    ("E0231", "\
+An index or slice is written in square brackets right after the list or
+string and has to be closed with \"]\".
+
+    print list[0];                 // fine
+    print list[1:];                // fine
+    print list[0;                  // error"),
+    ("E0232", "\
+A map literal starts with \"{\" and ends with \"}\", its entries are
+separated by commas.
+
+    var a = {\"x\": 1, \"y\": 2};      // fine
+    var b = {\"x\": 1 \"y\": 2};       // error, a comma is missing"),
+    ("E0233", "\
+Every entry of a map literal is a key and a value, separated by \":\".
+
+    var a = {\"x\": 1};              // fine
+    var b = {\"x\" 1};               // error"),
+    ("E0234", "\
+The cases of a switch are written in braces after the value in parentheses.
+
+    switch (a) { case 1: print a; } // fine
+    switch (a) case 1: print a;    // error"),
+    ("E0235", "\
+The values of a case, and the default keyword, are followed by \":\" and
+then the statements of the branch.
+
+    switch (a) {
+        case 1, 2: print \"small\";   // fine
+        case 3 print \"three\";       // error
+    }"),
+    ("E0236", "\
+Every statement in a switch belongs to a branch, which starts with \"case\"
+and its values or with \"default\".
+
+    switch (a) {
+        print a;                   // error, no branch yet
+        case 1: print 1;
+    }"),
+    ("E0237", "\
+The default branch runs when no case matches, no matter where it is
+written. To keep the order of the branches the order in which values are
+compared, it has to come last.
+
+    switch (a) {
+        default: print \"other\";
+        case 1: print 1;           // error
+    }"),
+    ("E0238", "\
+The bodies of try, catch and finally and the branches of an if expression
+are always blocks in braces, even when they hold a single statement.
+
+    try print f();                 // error
+    try { print f(); }             // fine"),
+    ("E0239", "\
+A try block on its own does nothing special. It needs a catch block that
+handles what was thrown in it, a finally block that runs after it in any
+case, or both.
+
+    try { f(); }                   // error
+    try { f(); } catch (e) {}      // fine"),
+    ("E0240", "\
+The catch block gets the thrown value in a variable, whose name goes in
+parentheses after catch.
+
+    try { f(); } catch { }         // error
+    try { f(); } catch (e) { }     // fine"),
+    ("E0241", "\
+A catch block can be restricted to instances of a class, which is named
+after a colon behind the variable.
+
+    try { f(); } catch (e: 42) { }        // error
+    try { f(); } catch (e: TypeError) { } // fine"),
+    ("E0242", "\
+A constant gets its value where it is declared and keeps it, so the
+declaration has to give it one.
+
+    const limit;                   // error
+    const limit = 10;              // fine"),
+    ("E0243", "\
+A constant cannot be assigned after its declaration. Declare it with var
+if it has to change. Constants declared in the same script are checked
+before it runs, the others when the assignment happens (see R0333).
+
+    const limit = 10;
+    limit = 20;                    // error"),
+    ("E0244", "\
+A class can include the methods of other classes by naming them after
+`with`, separated by commas.
+
+    class Point with { }           // error
+    class Point with Printable { } // fine"),
+    ("E0245", "\
+A destructuring pattern lists the variables it assigns, separated by
+commas, between brackets for lists or braces for maps and instances.
+
+    var [first, 2] = pair;         // error
+    var [first, second] = pair;    // fine"),
+    ("E0246", "\
+A destructuring declaration takes its variables from a value, so it
+cannot be declared without one.
+
+    var [first, second];           // error
+    var [first, second] = pair;    // fine"),
+    ("E0247", "\
+`break` leaves the loop around it, so it can only be used inside the body
+of a `while` or `for` loop. A function declared inside a loop body does
+not count as being inside the loop.
+
+    if (done) break;               // error without a loop around it"),
+    ("E0248", "\
+`break` and `continue` followed by a label leave or continue the loop
+with that label, which has to be one of the loops around them. Loops are
+labeled by writing the label and a colon before them.
+
+    outer: while (true) {
+      while (true) break inner;    // error, no loop is labeled inner
+      while (true) break outer;    // fine
+    }"),
+    ("E0249", "\
+A loop inside of another loop cannot have the same label, `break` and
+`continue` would not know which of them is meant.
+
+    rows: for (;;) {
+      rows: for (;;) {}            // error
+    }"),
+    ("E0250", "\
+Only loops can be labeled. The label is written before the `while` or
+`for` keyword and followed by a colon.
+
+    done: print 1;                 // error
+    done: while (true) break done; // fine"),
+    ("E0251", "\
+An if expression has the value of the block that runs, so its blocks have
+to run to their end. A return, break or continue cannot leave them, a
+function or loop inside of them can still use these.
+
+    var x = if (a) { return; } else { 1 };    // error
+    var y = if (a) { 0 } else { -1 };         // fine"),
+    ("E0252", "\
+`async` only marks a function as asynchronous, so it has to be followed by
+`fun`, either in a declaration or in an anonymous function.
+
+    async greet() {}                // error
+    async fun greet() {}            // fine
+    var f = async fun () {};        // fine"),
+    ("E0400", "\
+At least one type error was found, so the script was not run. The errors
+themselves are printed above this one. Type checking only happens with
+`jlox typecheck` and `--typed`."),
+    ("E0401", "\
+A value does not match the annotated type of the variable it is stored in.
+
+    var a: Number = \"one\";    // a string is not a number
+
+Variables without an annotation accept values of any type."),
+    ("E0402", "\
+Only functions can be called, but the type checker knows the callee is
+something else.
+
+    var a: Number = 1;
+    a();              // a is a number"),
+    ("E0403", "\
+The operands of the operator have types it cannot combine. `+` adds two
+numbers or concatenates strings (also a string with a number), `*` also
+repeats a string, the other arithmetic and comparison operators need two
+numbers.
+
+    print \"a\" + true;    // a string and a bool cannot be added"),
+    ("E0404", "\
+A function is called with the wrong number of arguments.
+
+    len(\"a\", \"b\");    // len takes a single argument"),
+    ("E0405", "\
+An argument does not have the type the function expects.
+
+    len(42);          // len expects a string"),
+    ("E0406", "\
+A type annotation names a type that does not exist. The known types are
+Any, Nil, Bool, Number, String and Function.
+
+    var a: Int = 1;   // should be var a: Number = 1;"),
+    ("E0407", "\
+The operand of a unary operator has a type the operator does not work on.
+
+    print -\"abc\";    // strings cannot be negated"),
+    ("E0408", "\
+The value does not have the accessed property. Strings and lists have
+`length`, functions have `arity`.
+
+    print true.size;  // booleans have no properties"),
+    ("E0409", "\
+Only lists, strings and maps can be indexed with square brackets, but the
+type checker knows the value is something else.
+
+    var n: Number = 3;
+    print n[0];       // numbers have no elements"),
+    ("E0410", "\
+Only lists and strings can be sliced with [start:end], but the type checker
+knows the value is something else.
+
+    var m: Map = {\"a\": 1};
+    print m[0:1];     // maps have no order to slice by"),
+    ("E0411", "\
+A for-in loop goes over the elements of a list, the keys of a map or the
+characters of a string, but the type checker knows the value is something
+else.
+
+    var n: Number = 3;
+    for (var x in n) print x;      // error"),
+    ("R0300", "\
+An unknown error occurred while running the script. This is a bug in jlox,
+please report it together with the script."),
+    ("R0301", "\
+A variable is used before it was declared, or outside of the block it was
+declared in.
+
+    print a;          // a was never declared
+    { var b = 1; }
+    print b;          // b only exists inside the block"),
+    ("R0302", "\
+The value does not have the accessed property. Strings and lists have
+`length`, functions have `arity`.
+
+    print \"abc\".size;    // should be \"abc\".length"),
+    ("R0303", "\
+The operator only works on numbers, but got another type.
+
+    print -\"abc\";    // strings cannot be negated
+    print 1 < \"2\";   // comparisons need two numbers"),
+    ("R0304", "\
+The operands of the operator have types it cannot combine. `+` adds two
+numbers or concatenates two strings (a number next to a string is turned
+into text), but does not work on other values.
+
+    print \"a\" + 1;     // a1
+    print \"a\" + nil;   // error"),
+    ("R0305", "\
+The result of the arithmetic cannot be represented. Ints fail when the
+result is too large for 64 bits and on floor division by zero. With the
+decimal number type numbers fail on overflow and on division by zero,
+where floating point numbers would give an infinity.
+
+    print 9223372036854775807 + 1; // error
+    print 7 ~/ 0;                  // error"),
+    ("R0306", "\
+Only functions can be called.
+
+    var a = 1;
+    a();              // a is a number"),
+    ("R0307", "\
+A function was called with the wrong number of arguments.
+
+    len(\"a\", \"b\");    // len takes a single argument"),
+    ("R0308", "\
+A native function got an argument of the wrong type.
+
+    len(42);          // len expects a string"),
+    ("R0309", "\
+A native function got an argument of the right type, but with a value it
+cannot use.
+
+    sleep(-1);        // a duration cannot be negative"),
+    ("R0310", "\
+In strict mode, a variable cannot be declared twice in the same scope.
+
+    var a = 1;
+    var a = 2;        // use a = 2; to assign instead"),
+    ("R0311", "\
+A native function needs a capability that was not granted, for example
+`sleep` needs the time capability. The groups are fs, net, process, env,
+time and ffi. The jlox executable grants every capability unless it is
+started with `--allow` or `--deny`, embedders choose them with
+`InterpreterBuilder::capabilities`."),
+    ("R0312", "\
+The script used more memory than the configured limit allows. The limit is
+set by the embedder with `InterpreterBuilder::memory_limit`."),
+    ("R0313", "\
+The script ran more statements than the configured limit allows, usually
+because of an endless loop. The limit is set by the embedder with
+`InterpreterBuilder::step_limit`."),
+    ("R0314", "\
+The script ran longer than the configured limit allows. The limit is set
+by the embedder with `InterpreterBuilder::time_limit`."),
+    ("R0315", "\
+The embedder cancelled the script through its cancellation token."),
+    ("R0316", "\
+Output of the script could not be written, for example because stdout was
+closed."),
+    ("R0317", "\
+A value grew larger than the configured size limit allows, for example a
+string with too many characters. Embedders set the limits with
+`InterpreterBuilder::size_limits`, the sandbox configuration
+(`InterpreterBuilder::sandbox`) sets conservative ones."),
+    ("R0318", "\
+A call through `loadLibrary` or `callNative` failed: the library could not
+be loaded, it has no such symbol, or the signature is invalid. Signatures
+look like `double(double, double)`, see the documentation of `callNative`."),
+    ("R0319", "\
+Function calls were nested deeper than the interpreter allows, usually
+because of a recursion that never reaches its base case.
+
+    fun forever(n) { return forever(n + 1); }
+
+The limit keeps such scripts from crashing the interpreter itself."),
+    ("R0320", "\
+Only instances of classes can have fields, other values cannot be
+assigned properties.
+
+    var s = \"text\";
+    s.size = 4;                    // error
+    class Box {}
+    var b = Box();
+    b.size = 4;                    // fine"),
+    ("R0321", "\
+A class can only inherit from another class. The name after \"<\" was
+bound to a value that is not a class.
+
+    var Shape = \"shape\";
+    class Square < Shape {}        // error"),
+    ("R0322", "\
+Multiplying a string by a number repeats it, which only works for whole
+numbers that are not negative.
+
+    print \"ab\" * 3;                // ababab
+    print \"ab\" * 1.5;              // error"),
+    ("R0323", "\
+An index has to refer to an existing element of a list or character of a
+string. Indices start at 0, negative ones count from the end, so -1 is the
+last element. The bounds of a slice may also be the length itself.
+
+    var a = [1, 2, 3];
+    print a[2];                    // 3
+    print a[-1];                   // 3
+    print a[3];                    // error
+    print a[-4];                   // error
+    print a[1:3];                  // [2, 3]
+    print a[1:4];                  // error"),
+    ("R0324", "\
+Lists and strings are indexed by the position of an element, which is a
+whole number.
+
+    var a = [1, 2, 3];
+    print a[1.5];                  // error
+    print a[\"1\"];                  // error"),
+    ("R0325", "\
+Square brackets after a value look up an element of a list, a character
+of a string or the value of a key in a map, other values have no elements.
+
+    var s = 42;
+    print s[0];                    // error"),
+    ("R0326", "\
+Only values that never change and are compared by their content can be
+keys of a map: strings, numbers, booleans and nil. Lists, maps, instances
+and functions cannot, and neither can NaN.
+
+    var m = {};
+    m[\"name\"] = 1;                 // fine
+    m[[1, 2]] = 1;                 // error"),
+    ("R0327", "\
+Looking up a key that the map has no entry for is an error. Use has()
+to check whether the key is there first.
+
+    var m = {\"a\": 1};
+    print m[\"b\"];                  // error
+    if (has(m, \"b\")) print m[\"b\"]; // fine"),
+    ("R0328", "\
+Strings never change, so a character of a string cannot be assigned to.
+Build a new string from slices instead.
+
+    var s = \"cat\";
+    s[0] = \"b\";                    // error
+    s = \"b\" + s[1:];               // fine"),
+    ("R0329", "\
+Slices take a run of elements out of a list or characters out of a
+string. Maps have no order to slice by, and other values have no elements.
+
+    var m = {\"a\": 1};
+    print m[0:1];                  // error"),
+    ("R0330", "\
+A for-in loop goes over the elements of a list, the keys of a map in the
+order they were added, or the characters of a string. Other values have
+nothing to go over.
+
+    for (var c in \"abc\") print c; // fine
+    for (var x in 42) print x;     // error"),
+    ("R0331", "\
+A value was thrown with throw and no catch block around it took it, so
+the script stopped. The value is printed as part of the error, instances
+of error classes as their class and message.
+
+    throw \"disk full\";             // error
+    try { throw \"disk full\"; } catch (e) { print e; } // fine"),
+    ("R0332", "\
+The name after the colon in a catch block has to refer to a class, the
+block then takes instances of that class and its subclasses.
+
+    var NotFound = \"not found\";
+    try { f(); } catch (e: NotFound) { } // error"),
+    ("R0333", "\
+A constant cannot be assigned after its declaration. This is the runtime
+check for constants the parser could not see, like the ones declared in
+an earlier prompt of the REPL (see E0243).
+
+    > const limit = 10;
+    > limit = 20;                  // error"),
+    ("R0334", "\
+The names after `with` in a class declaration have to refer to classes,
+their methods are copied into the declared class.
+
+    var Printable = \"printable\";
+    class Point with Printable { } // error"),
+    ("R0335", "\
+A list pattern takes the elements of a list, a map pattern the entries of
+a map or the properties of an instance. Other values cannot be destructured.
+
+    var [a, b] = \"ab\";             // error
+    var {x, y} = {\"x\": 1, \"y\": 2}; // fine"),
+    ("R0336", "\
+A list pattern needs a list with exactly as many elements as it has
+names, extra or missing elements are an error.
+
+    var [a, b] = [1, 2, 3];        // error
+    var [a, b] = [1, 2];           // fine"),
+    ("R0337", "\
+`value is Class` tests whether the value is an instance of the class or
+of one of its subclasses, `value is \"type\"` whether the value has the
+type `type()` would name. Other values cannot be on the right side.
+
+    print p is 3;                  // error
+    print p is Point;              // fine"),
+    ("R0338", "\
+The name of a type on the right side of `is` has to be one of the names
+`type()` gives: string, number, int, bool, nil, function, class, instance,
+list, map, task, channel, coroutine or future. Ints are numbers too.
+
+    print 1 is \"integer\";        // error
+    print 1 is \"int\";            // fine"),
+    ("R0339", "\
+A list, map or instance that went through `freeze()` can't be changed
+anymore: no assigning elements, entries or fields, and no adding or
+removing elements or entries. Freezing is shallow, the values inside can still change unless
+they are frozen too. `clone()` gives a copy that isn't frozen.
+
+    var point = freeze({\"x\": 1});
+    point[\"x\"] = 2;               // error
+    var moved = clone(point);
+    moved[\"x\"] = 2;               // fine"),
+    ("R0340", "\
+The code given to `eval()` or `evalIn()` is scanned and parsed when the
+call runs. If it doesn't scan or parse, the call fails with the first
+error in the code. Its lines count from the line of the call.
+
+    eval(\"print (1 + 2;\");        // error
+    eval(\"print (1 + 2);\");       // fine"),
+    ("R0341", "\
+The script called `exit(code)`. This isn't a failure: jlox ends with that
+exit code and doesn't print the error, embedders get it from
+`LoxError::exit_code`. On the way out, `finally` blocks still run, but no
+`catch` block can stop it. Exiting needs the `process` capability."),
+    ("R0342", "\
+`readLine()` or `input()` could not read from the input, for example
+because it isn't valid UTF-8 text. At the end of the input they give nil
+instead of failing."),
+    ("R0343", "\
+The computer couldn't provide the memory for a string, like one repeated
+with `*` a huge number of times. Without a memory limit, this is how
+jlox finds out that a string is too large. With a limit set by the
+embedder with `InterpreterBuilder::memory_limit`, R0312 stops it earlier.
+
+    print \"a\" * 100000000000000;   // error"),
+    ("R0344", "\
+An `await` waits for a future that nothing can complete anymore: every
+async call that is still running waits for another future itself, and no
+`sleepAsync` timer is left that could wake one of them up. Without the
+error the script would hang forever. This happens when async calls wait
+for each other in a circle.
+
+    var task;
+    async fun wait() { await task; }
+    task = wait();
+    await task;                     // error"),
+    ("R0345", "\
+Every coroutine and every pending async call runs on a thread of its own
+while it is alive. A script can keep at most 10000 of them alive at the
+same time, and the system may run out of threads even earlier. Resume
+coroutines until they are done or drop them, and await async calls, so
+their threads end.
+
+    var all = [];
+    for (var i = 0; i < 20000; i = i + 1) {
+        var c = coroutine(fun () { yield(1); });
+        resume(c, nil);             // suspended, its thread stays
+        push(all, c);               // error, eventually
+    }"),
+    ("R0346", "\
+A task started with `spawn` crashed inside the interpreter instead of
+finishing its function. Runtime errors of the function are raised by
+`join` as they are, this only happens on a bug in jlox or a native
+library. The message says what went wrong; please report it.
+
+    var task = spawn(work);
+    print join(task);               // error, if the task crashed"),
+    ("R0347", "\
+The operating system refused what a native asked for: `readFile` or
+`writeFile` on a file that doesn't exist or can't be accessed, or
+`lookupHost` on a host name that doesn't resolve. The message gives
+the reason the system reported. The error can be caught.
+
+    print readFile(\"missing.txt\");   // error"),
+];
+
+/// Prints the long description of an error code like `E0203`
+pub fn run(code: &str) -> Result<()> {
+    let code = code.to_uppercase();
+    match explanation(&code) {
+        Some(text) => {
+            println!("{}\n\n{}", code, text);
+            Ok(())
+        }
+        None => Err(ArgumentError::UnknownErrorCode(code).into()),
+    }
+}
+
+/// The long description of an error code
+fn explanation(code: &str) -> Option<&'static str> {
+    EXPLANATIONS.iter().find(|(c, _)| *c == code).map(|(_, text)| *text)
+}
+
+// ---------- Tests for the explain module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jlox::errors::{ParseError, RuntimeError, ScanError, TypeError};
+    use std::time::Duration;
+
+    #[test]
+    fn every_code_is_explained() {
+        let scan = [
+            ScanError::HadError,
+            ScanError::CharacterAccessError(1),
+            ScanError::UnexpectedCharacter('@', 1),
+            ScanError::UnterminatedString(1),
+            ScanError::InvalidNumber(String::new(), 1),
+            ScanError::MalformedNumber(String::new(), "", 1),
+        ];
+        let parse = [
+            ParseError::HadError,
+            ParseError::TokenAccessError(1),
+            ParseError::UnterminatedGrouping(1),
+            ParseError::UnterminatedPrintStatement(1),
+            ParseError::UnterminatedExpressionStatement(1),
+            ParseError::UnterminatedVarDeclaration(1),
+            ParseError::UnterminatedBlock(1),
+            ParseError::ExpectedIdentifier(1),
+            ParseError::ExpectedPropertyName(1),
+            ParseError::ExprectedLeftParen(1),
+            ParseError::ExpectedRightParen(1),
+            ParseError::ExpectedExpression(1),
+            ParseError::ExpectedSemicolon(1),
+            ParseError::NoLiteralOnToken(1),
+            ParseError::InvalidAssignmentTarget(1),
+            ParseError::TrailingTokens(1),
+            ParseError::ExpectedTypeName(1),
+            ParseError::TooDeeplyNested(1),
+            ParseError::ReturnOutsideFunction(1),
+            ParseError::ExpectedFunctionBody(1),
+            ParseError::ExpectedClassBody(1),
+            ParseError::UnterminatedClassBody(1),
+            ParseError::ThisOutsideClass(1),
+            ParseError::ExpectedSuperclassName(1),
+            ParseError::InheritsFromItself(String::new(), 1),
+            ParseError::SuperWithoutSuperclass(1),
+            ParseError::ExpectedSuperMethod(1),
+            ParseError::ReturnValueFromInitializer(1),
+            ParseError::SetterArity(String::new(), 1),
+            ParseError::ContinueOutsideLoop(1),
+            ParseError::UnterminatedList(1),
+            ParseError::UnterminatedIndex(1),
+            ParseError::UnterminatedMap(1),
+            ParseError::ExpectedColonAfterKey(1),
+            ParseError::ExpectedSwitchBody(1),
+            ParseError::ExpectedColonAfterCase(1),
+            ParseError::ExpectedCase(1),
+            ParseError::DefaultNotLast(1),
+            ParseError::ExpectedBlock("try", 1),
+            ParseError::TryWithoutHandler(1),
+            ParseError::ExpectedCatchVariable(1),
+            ParseError::ExpectedCatchClass(1),
+            ParseError::ConstWithoutInitializer(String::new(), 1),
+            ParseError::AssignToConst(String::new(), 1),
+            ParseError::ExpectedMixinName(1),
+            ParseError::ExpectedPatternName(1),
+            ParseError::PatternWithoutInitializer(1),
+            ParseError::BreakOutsideLoop(1),
+            ParseError::UndefinedLabel("break", String::new(), 1),
+            ParseError::DuplicateLabel(String::new(), 1),
+            ParseError::LabelWithoutLoop(String::new(), 1),
+            ParseError::JumpOutOfValueBlock("return", 1),
+            ParseError::ExpectedFunAfterAsync(1),
+        ];
+        let types = [
+            TypeError::HadError,
+            TypeError::Mismatch(String::new(), String::new(), 1),
+            TypeError::NotCallable(String::new(), 1),
+            TypeError::OperandTypes(String::new(), String::new(), String::new(), 1),
+            TypeError::WrongArgumentCount(1, 2, 1),
+            TypeError::ArgumentType(1, String::new(), String::new(), 1),
+            TypeError::UnknownType(String::new(), 1),
+            TypeError::OperandType(String::new(), String::new(), 1),
+            TypeError::UndefinedProperty(String::new(), String::new(), 1),
+            TypeError::NotIndexable(String::new(), 1),
+            TypeError::NotSliceable(String::new(), 1),
+            TypeError::NotIterable(String::new(), 1),
+        ];
+        let runtime = [
+            RuntimeError::NumberOperand(1),
+            RuntimeError::InvalidArithmetic(1),
+            RuntimeError::IncompatibleTypes(1),
+            RuntimeError::UndefinedVariable(String::new(), None, 1),
+            RuntimeError::NotCallable(1),
+            RuntimeError::WrongArgumentCount(1, 2, 1),
+            RuntimeError::ArgumentType("f", "a", "b", 1),
+            RuntimeError::UndefinedProperty(String::new(), 1),
+            RuntimeError::MemoryLimitExceeded(1, 1),
+            RuntimeError::StepLimitExceeded(1),
+            RuntimeError::TimeLimitExceeded(Duration::ZERO),
+            RuntimeError::AlreadyDefined(String::new(), 1),
+            RuntimeError::Cancelled,
+            RuntimeError::CapabilityDisabled("f", "fs", 1),
+            RuntimeError::InvalidArgument("f", "x", 1),
+            RuntimeError::OutputFailed(String::new()),
+            RuntimeError::SizeLimitExceeded("string length", 1, 1),
+            RuntimeError::ForeignCall(String::new(), 1),
+            RuntimeError::StackOverflow(1, 1),
+            RuntimeError::OnlyInstancesHaveFields(String::new(), 1),
+            RuntimeError::SuperclassMustBeClass(String::new(), 1),
+            RuntimeError::InvalidRepeatCount(String::new(), 1),
+            RuntimeError::IndexOutOfBounds(String::new(), "list", 0, 1),
+            RuntimeError::InvalidIndex(String::new(), 1),
+            RuntimeError::NotIndexable("nil", 1),
+            RuntimeError::InvalidKey(String::new(), 1),
+            RuntimeError::MissingKey(String::new(), 1),
+            RuntimeError::ImmutableString(1),
+            RuntimeError::NotSliceable("map", 1),
+            RuntimeError::NotIterable("nil", 1),
+            RuntimeError::Uncaught(String::new(), 1),
+            RuntimeError::CatchNotClass(String::new(), 1),
+            RuntimeError::AssignToConst(String::new(), 1),
+            RuntimeError::MixinMustBeClass(String::new(), String::new(), 1),
+            RuntimeError::NotDestructurable("list", "string", 1),
+            RuntimeError::PatternLength(2, 3, 1),
+            RuntimeError::InvalidTypeTest("number", 1),
+            RuntimeError::UnknownTypeName(String::new(), 1),
+            RuntimeError::Frozen("list", 1),
+            RuntimeError::InvalidEvalCode(String::new(), 1),
+            RuntimeError::Exit(0),
+            RuntimeError::InputFailed(String::new(), 1),
+            RuntimeError::OutOfMemory(1, 1),
+            RuntimeError::NeverCompletes(1),
+            RuntimeError::ThreadUnavailable("coroutine", String::new(), 1),
+            RuntimeError::TaskPanicked(String::new(), 1),
+            RuntimeError::SystemFailed("readFile", String::new(), 1),
+            RuntimeError::Unknown,
+        ];
+
+        let codes: Vec<&str> = scan.iter().map(|e| e.code())
+            .chain(parse.iter().map(|e| e.code()))
+            .chain(types.iter().map(|e| e.code()))
+            .chain(runtime.iter().map(|e| e.code()))
+            .collect();
+        assert_eq!(codes.len(), EXPLANATIONS.len());
+        for code in codes {
+            assert!(explanation(code).is_some(), "{} has no explanation", code);
+        }
+    }
+
+    #[test]
+    fn messages_contain_their_code() {
+        let error = ParseError::ExpectedRightParen(3);
+        assert_eq!(error.to_string(), "Parse Error [E0203]: Exprected closing parentheses \")\" on line 3");
+    }
+}
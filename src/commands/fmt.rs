@@ -0,0 +1,941 @@
+// External dependencies
+use anyhow::Result;
+use std::path::Path;
+
+// Internal dependencies
+use super::common::{desugared_for, number_literal, parse_with_comments, string_literal};
+use jlox::analyzer::first_token;
+use jlox::errors::FmtError;
+use jlox::obj::annotation::TypeAnnotation;
+use jlox::obj::expression::Expression;
+use jlox::obj::statement::Statement;
+use jlox::obj::token::Token;
+use jlox::obj::token_type::TokenType;
+use jlox::obj::value::Value;
+use jlox::scanner::Comment;
+
+/// Name of the config file, looked up in the directory of the formatted
+/// file and every directory above it
+const CONFIG_FILE: &str = ".loxfmt.toml";
+
+/// How braces of blocks that belong to an `if` or `while` are placed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BraceStyle {
+    /// `if (a) {`
+    SameLine,
+    /// `if (a)`, then `{` on its own line
+    NextLine,
+}
+
+/// The style the formatter prints with. Read from `.loxfmt.toml`, which
+/// holds one `key = value` per line in TOML syntax:
+///
+/// ```toml
+/// indent_width = 2
+/// use_tabs = false
+/// max_line_length = 80
+/// brace_style = "next_line"
+/// trailing_newline = true
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FmtConfig {
+    /// Columns per indentation level, also the width of a tab
+    pub indent_width: usize,
+    /// Indent with tabs instead of spaces
+    pub use_tabs: bool,
+    /// Calls, lists and maps that would make a line longer than this get one argument, element or entry per line
+    pub max_line_length: usize,
+    pub brace_style: BraceStyle,
+    /// Whether the output ends with a line break
+    pub trailing_newline: bool,
+}
+
+impl Default for FmtConfig {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            use_tabs: false,
+            max_line_length: 100,
+            brace_style: BraceStyle::SameLine,
+            trailing_newline: true,
+        }
+    }
+}
+
+impl FmtConfig {
+    /// The config for formatting `script`: the defaults, then the closest
+    /// `.loxfmt.toml` above the script, then the `key=value` overrides
+    pub fn discover(script: &str, overrides: &[String]) -> Result<FmtConfig> {
+        let mut config = FmtConfig::default();
+
+        let script = std::fs::canonicalize(script)?;
+        if let Some(path) = script.ancestors().skip(1).map(|dir| dir.join(CONFIG_FILE)).find(|path| path.is_file()) {
+            config.apply_file(&path)?;
+        }
+        for setting in overrides {
+            config.apply(setting).ok_or_else(|| FmtError::InvalidSetting(setting.clone()))?;
+        }
+        Ok(config)
+    }
+
+    fn apply_file(&mut self, path: &Path) -> Result<()> {
+        let text = std::fs::read_to_string(path)?;
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim(); // No setting contains a #
+            if !line.is_empty() && self.apply(line).is_none() {
+                return Err(FmtError::InvalidConfigLine(path.display().to_string(), index + 1, line.to_string()).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies a single `key = value` setting, `None` if it is invalid
+    fn apply(&mut self, setting: &str) -> Option<()> {
+        let (key, value) = setting.split_once('=')?;
+        let value = value.trim();
+        let string = || value.strip_prefix('"')?.strip_suffix('"');
+        match key.trim() {
+            "indent_width" => self.indent_width = value.parse().ok().filter(|width| *width > 0)?,
+            "use_tabs" => self.use_tabs = value.parse().ok()?,
+            "max_line_length" => self.max_line_length = value.parse().ok()?,
+            "brace_style" => {
+                self.brace_style = match string()? {
+                    "same_line" => BraceStyle::SameLine,
+                    "next_line" => BraceStyle::NextLine,
+                    _ => return None,
+                }
+            }
+            "trailing_newline" => self.trailing_newline = value.parse().ok()?,
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+/// Formats the script with the config found for it and prints the result,
+/// or writes it back into the script with `write`
+pub fn run(script: &str, write: bool, overrides: &[String]) -> Result<()> {
+    let config = FmtConfig::discover(script, overrides)?;
+    let (statements, comments) = parse_with_comments(std::fs::read_to_string(script)?)?;
+    let formatted = format(&statements, &comments, &config);
+    if write {
+        std::fs::write(script, formatted)?;
+    } else {
+        print!("{}", formatted);
+    }
+    Ok(())
+}
+
+/// Turns the statements back into Lox source code in the configured style.
+/// Comments on lines of their own go above the statement that follows
+/// them, those after code stay at the end of its line if the statement
+/// takes a single line.
+pub fn format(statements: &[Statement], comments: &[Comment], config: &FmtConfig) -> String {
+    let mut formatter = Formatter { config, out: String::new(), comments };
+    for stmt in statements {
+        formatter.statement(stmt, 0);
+    }
+    formatter.comments_before(u32::MAX, 0);
+    if !config.trailing_newline {
+        formatter.out.truncate(formatter.out.trim_end().len());
+    }
+    formatter.out
+}
+
+/// Contraption that prints statements line by line into a String
+struct Formatter<'a> {
+    config: &'a FmtConfig,
+    out: String,
+    /// The comments that are not printed yet, in source order
+    comments: &'a [Comment],
+}
+
+impl Formatter<'_> {
+    /// Prints a statement on its own line(s), ending with a line break,
+    /// with the comments that come before it
+    fn statement(&mut self, stmt: &Statement, level: usize) {
+        let line = first_token(stmt).map(Token::line);
+        if let Some(line) = line {
+            self.comments_before(line, level);
+        }
+        let start = self.out.len();
+        self.indent(level);
+        self.statement_code(stmt, level);
+        if let Some(line) = line {
+            self.trailing_comment(line, start);
+        }
+    }
+
+    /// Prints the comments on the lines before `line`, each on its own line
+    fn comments_before(&mut self, line: u32, level: usize) {
+        while let Some((comment, rest)) = self.comments.split_first() {
+            if comment.line >= line {
+                break;
+            }
+            self.indent(level);
+            self.out.push_str(&comment.text);
+            self.out.push('\n');
+            self.comments = rest;
+        }
+    }
+
+    /// Moves a comment that follows code on `line` to the end of the
+    /// statement printed from `start`, if that took a single line. Other
+    /// comments wait for the next statement.
+    fn trailing_comment(&mut self, line: u32, start: usize) {
+        let Some((comment, rest)) = self.comments.split_first() else {
+            return;
+        };
+        let printed = &self.out[start..];
+        if comment.trailing && comment.line == line && printed.find('\n') == Some(printed.len() - 1) {
+            self.out.pop();
+            self.out.push_str(&format!(" {}\n", comment.text));
+            self.comments = rest;
+        }
+    }
+
+    /// Prints the code of a statement after its indentation
+    fn statement_code(&mut self, stmt: &Statement, level: usize) {
+        match stmt {
+            Statement::Block(stmts) => match desugared_for(stmts) {
+                Some((init, stmt)) => self.for_statement(Some(init), stmt, level),
+                None => {
+                    self.block(stmts, level);
+                    self.out.push('\n');
+                }
+            },
+            Statement::Break(_, label) => self.line(|_| jump("break", label.as_ref()), ";"),
+            Statement::Continue(_, label) => self.line(|_| jump("continue", label.as_ref()), ";"),
+            Statement::If(..) => self.if_statement(stmt, level),
+            Statement::While(keyword, ..) if keyword.token_type() == TokenType::For => self.for_statement(None, stmt, level),
+            Statement::While(_, cond, body, _, label) => {
+                self.label(label.as_ref());
+                self.header("while", cond, level);
+                if self.body(body, level) {
+                    self.out.push('\n');
+                }
+            }
+            Statement::Class(name, superclass, mixins, methods, setters) => {
+                self.out.push_str(&format!("class {}", name.lexeme()));
+                if let Some(superclass) = superclass {
+                    self.out.push_str(&format!(" < {}", superclass.lexeme()));
+                }
+                if !mixins.is_empty() {
+                    let mixins: Vec<String> = mixins.iter().map(Token::lexeme).collect();
+                    self.out.push_str(&format!(" with {}", mixins.join(", ")));
+                }
+                match self.config.brace_style {
+                    BraceStyle::SameLine => self.out.push(' '),
+                    BraceStyle::NextLine => {
+                        self.out.push('\n');
+                        self.indent(level);
+                    }
+                }
+                if methods.is_empty() && setters.is_empty() {
+                    self.out.push_str("{}\n");
+                    return;
+                }
+                self.out.push_str("{\n");
+                for (keyword, method) in methods.iter().map(|method| ("", method)).chain(setters.iter().map(|setter| ("set ", setter))) {
+                    self.indent(level + 1);
+                    self.out.push_str(keyword);
+                    self.out.push_str(&method.signature());
+                    self.braced(&method.body, level + 1);
+                    self.out.push('\n');
+                }
+                self.indent(level);
+                self.out.push_str("}\n");
+            }
+            Statement::Expression(expr) => self.line(|f| f.expression(expr, level, f.column()), ";"),
+            Statement::ForIn(_, name, iterable, body, label) => {
+                self.label(label.as_ref());
+                let text = format!("for (var {} in ", name.lexeme());
+                let iterable = self.expression(iterable, level, self.column() + width(&text));
+                self.out.push_str(&format!("{}{})", text, iterable));
+                if self.body(body, level) {
+                    self.out.push('\n');
+                }
+            }
+            Statement::Function(declaration) => {
+                self.out.push_str(&format!("{} {}", declaration.keyword(), declaration.signature()));
+                self.braced(&declaration.body, level);
+                self.out.push('\n');
+            }
+            Statement::Print(_, exprs) => self.line(
+                |f| {
+                    let mut text = String::from("print ");
+                    for (i, expr) in exprs.iter().enumerate() {
+                        if i > 0 {
+                            text.push_str(", ");
+                        }
+                        text.push_str(&f.expression(expr, level, f.column() + width(&text)));
+                    }
+                    text
+                },
+                ";",
+            ),
+            Statement::Return(_, value) => self.line(
+                |f| match value {
+                    Some(value) => format!("return {}", f.expression(value, level, f.column() + width("return "))),
+                    None => "return".to_string(),
+                },
+                ";",
+            ),
+            Statement::Throw(_, value) => {
+                self.line(|f| format!("throw {}", f.expression(value, level, f.column() + width("throw "))), ";")
+            }
+            Statement::Try(_, body, catches, finally) => {
+                self.out.push_str("try");
+                self.braced(body, level);
+                for (name, class, handler) in catches {
+                    self.continuation(level);
+                    match class {
+                        Some(class) => self.out.push_str(&format!("catch ({}: {})", name.lexeme(), class.lexeme())),
+                        None => self.out.push_str(&format!("catch ({})", name.lexeme())),
+                    }
+                    self.braced(handler, level);
+                }
+                if let Some(finally) = finally {
+                    self.continuation(level);
+                    self.out.push_str("finally");
+                    self.braced(finally, level);
+                }
+                self.out.push('\n');
+            }
+            Statement::Switch(_, subject, cases, default) => {
+                self.header("switch", subject, level);
+                match self.config.brace_style {
+                    BraceStyle::SameLine => self.out.push(' '),
+                    BraceStyle::NextLine => {
+                        self.out.push('\n');
+                        self.indent(level);
+                    }
+                }
+                if cases.is_empty() && default.is_none() {
+                    self.out.push_str("{}\n");
+                    return;
+                }
+                self.out.push_str("{\n");
+                for (values, body) in cases {
+                    self.indent(level + 1);
+                    let mut text = String::from("case ");
+                    for (i, value) in values.iter().enumerate() {
+                        if i > 0 {
+                            text.push_str(", ");
+                        }
+                        text.push_str(&self.expression(value, level + 1, self.column() + width(&text)));
+                    }
+                    self.out.push_str(&text);
+                    self.out.push_str(":\n");
+                    body.iter().for_each(|stmt| self.statement(stmt, level + 2));
+                }
+                if let Some(body) = default {
+                    self.indent(level + 1);
+                    self.out.push_str("default:\n");
+                    body.iter().for_each(|stmt| self.statement(stmt, level + 2));
+                }
+                self.indent(level);
+                self.out.push_str("}\n");
+            }
+            Statement::Var(name, annotation, init) => self.line(|f| f.var("var", name, annotation, init.as_ref(), level, f.column()), ";"),
+            Statement::Const(name, annotation, init) => self.line(|f| f.var("const", name, annotation, Some(init), level, f.column()), ";"),
+            Statement::Destructure(pattern, init) => self.line(|f| {
+                let text = format!("var {} = ", pattern);
+                let init = f.expression(init, level, f.column() + width(&text));
+                text + &init
+            }, ";"),
+        }
+    }
+
+    /// A `var` or `const` declaration without the semicolon, starting at `column`
+    fn var(
+        &self,
+        keyword: &str,
+        name: &Token,
+        annotation: &Option<TypeAnnotation>,
+        init: Option<&Expression>,
+        level: usize,
+        column: usize,
+    ) -> String {
+        let mut text = format!("{} {}", keyword, name.lexeme());
+        if let Some(annotation) = annotation {
+            text.push_str(&format!(": {}", annotation));
+        }
+        if let Some(init) = init {
+            text.push_str(" = ");
+            text.push_str(&self.expression(init, level, column + width(&text)));
+        }
+        text
+    }
+
+    /// Prints a loop the parser desugared from `for` the way it was written.
+    /// An omitted condition was turned into `true` and is left out again.
+    fn for_statement(&mut self, init: Option<&Statement>, stmt: &Statement, level: usize) {
+        let Statement::While(_, cond, body, increment, label) = stmt else {
+            return self.statement(stmt, level);
+        };
+        self.label(label.as_ref());
+        let start = self.column();
+        let mut text = String::from("for (");
+        match init {
+            Some(Statement::Var(name, annotation, value)) => {
+                text += &self.var("var", name, annotation, value.as_ref(), level, start + width(&text))
+            }
+            Some(Statement::Expression(expr)) => text += &self.expression(expr, level, start + width(&text)),
+            _ => {}
+        }
+        text.push(';');
+        if !matches!(cond, Expression::Literal(Value::Bool(true))) {
+            text.push(' ');
+            text += &self.expression(cond, level, end_column(start, &text));
+        }
+        text.push(';');
+        if let Some(increment) = increment {
+            text.push(' ');
+            text += &self.expression(increment, level, end_column(start, &text));
+        }
+        text.push(')');
+        self.out.push_str(&text);
+        if self.body(body, level) {
+            self.out.push('\n');
+        }
+    }
+
+    /// Prints an `if`, including `else if` chains, without indenting
+    /// the first line
+    fn if_statement(&mut self, stmt: &Statement, level: usize) {
+        let Statement::If(_, cond, then, els) = stmt else {
+            return self.statement(stmt, level);
+        };
+        self.header("if", cond, level);
+        let brace = self.body(then, level);
+
+        let Some(els) = els else {
+            if brace {
+                self.out.push('\n');
+            }
+            return;
+        };
+        if brace && self.config.brace_style == BraceStyle::SameLine {
+            self.out.push(' ');
+        } else {
+            if brace {
+                self.out.push('\n');
+            }
+            self.indent(level);
+        }
+        self.out.push_str("else");
+
+        if let Statement::If(..) = els.as_ref() {
+            self.out.push(' ');
+            self.if_statement(els, level);
+        } else if self.body(els, level) {
+            self.out.push('\n');
+        }
+    }
+
+    /// Prints `keyword (condition)` without a line break
+    /// Prints the label in front of a loop
+    fn label(&mut self, label: Option<&Token>) {
+        if let Some(label) = label {
+            self.out.push_str(&format!("{}: ", label.lexeme()));
+        }
+    }
+
+    fn header(&mut self, keyword: &str, cond: &Expression, level: usize) {
+        let start = self.column() + keyword.len() + 2;
+        let cond = self.expression(cond, level, start);
+        self.out.push_str(&format!("{} ({})", keyword, cond));
+    }
+
+    /// Prints the body of an `if`, `else` or `while` after its header.
+    /// Blocks end with their closing brace and return true, other
+    /// statements go on their own, indented line.
+    fn body(&mut self, stmt: &Statement, level: usize) -> bool {
+        match stmt {
+            Statement::Block(stmts) => {
+                self.braced(stmts, level);
+                true
+            }
+            _ => {
+                self.out.push('\n');
+                self.statement(stmt, level + 1);
+                false
+            }
+        }
+    }
+
+    /// Starts the part of a statement that follows a closing brace, like
+    /// `catch`: on the same line or the next one, depending on the brace style
+    fn continuation(&mut self, level: usize) {
+        match self.config.brace_style {
+            BraceStyle::SameLine => self.out.push(' '),
+            BraceStyle::NextLine => {
+                self.out.push('\n');
+                self.indent(level);
+            }
+        }
+    }
+
+    /// Prints a block after a header, with the opening brace where the
+    /// brace style puts it
+    fn braced(&mut self, stmts: &[Statement], level: usize) {
+        match self.config.brace_style {
+            BraceStyle::SameLine => self.out.push(' '),
+            BraceStyle::NextLine => {
+                self.out.push('\n');
+                self.indent(level);
+            }
+        }
+        self.block(stmts, level);
+    }
+
+    /// Prints a block from its opening to its closing brace. The opening
+    /// brace goes where the output currently ends.
+    fn block(&mut self, stmts: &[Statement], level: usize) {
+        if stmts.is_empty() {
+            self.out.push_str("{}");
+            return;
+        }
+        self.out.push_str("{\n");
+        for stmt in stmts {
+            self.statement(stmt, level + 1);
+        }
+        self.indent(level);
+        self.out.push('}');
+    }
+
+    /// Prints the rest of a simple statement: the text `build` returns
+    /// (it may need the current column), the suffix and a line break
+    fn line(&mut self, build: impl FnOnce(&Self) -> String, suffix: &str) {
+        let text = build(self);
+        self.out.push_str(&text);
+        self.out.push_str(suffix);
+        self.out.push('\n');
+    }
+
+    /// Prints an `if` expression or one of its blocks. A block with only a
+    /// value stays on one line, one with statements spans several.
+    fn value_expression(&self, expr: &Expression, level: usize) -> String {
+        match expr {
+            Expression::If(_, cond, then, els) => {
+                let mut text = format!("if ({}) {}", flat(cond), self.value_expression(then, level));
+                if let Some(els) = els {
+                    text.push_str(" else ");
+                    text.push_str(&self.value_expression(els, level));
+                }
+                text
+            }
+            Expression::Block(_, stmts, value) => {
+                let inner = (level + 1) * self.config.indent_width;
+                let value = value.as_ref().map(|value| self.expression(value, level + 1, inner));
+                match (stmts.is_empty(), &value) {
+                    (true, None) => return "{}".to_string(),
+                    (true, Some(value)) if !value.contains('\n') => return format!("{{ {} }}", value),
+                    _ => {}
+                }
+                let mut formatter = Formatter { config: self.config, out: String::from("{\n"), comments: &[] };
+                for stmt in stmts {
+                    formatter.statement(stmt, level + 1);
+                }
+                if let Some(value) = value {
+                    formatter.indent(level + 1);
+                    formatter.out.push_str(&value);
+                    formatter.out.push('\n');
+                }
+                formatter.indent(level);
+                formatter.out.push('}');
+                formatter.out
+            }
+            _ => self.expression(expr, level, level * self.config.indent_width),
+        }
+    }
+
+    /// Prints an expression that starts at `column`. Groupings from the
+    /// source are kept. Calls, lists and maps that would reach past the
+    /// maximum line length get one argument, element or entry per line.
+    fn expression(&self, expr: &Expression, level: usize, column: usize) -> String {
+        let line = flat(expr);
+        if column + width(&line) < self.config.max_line_length && !line.contains('\n') {
+            return line;
+        }
+
+        match expr {
+            Expression::Lambda(declaration) => {
+                let mut formatter = Formatter { config: self.config, out: format!("{} ", declaration.signature()), comments: &[] };
+                formatter.block(&declaration.body, level);
+                formatter.out
+            }
+            Expression::If(..) | Expression::Block(..) => self.value_expression(expr, level),
+            Expression::Call(callee, _, args) if !args.is_empty() => {
+                let mut text = self.expression(callee, level, column);
+                // Arguments stay on the line the callee ends on if they fit there. A
+                // function passed last only needs its first line to fit, its body goes below.
+                if let Some((last, rest)) = args.split_last() {
+                    let mut inline = format!("{}(", text);
+                    for arg in rest {
+                        inline.push_str(&flat(arg));
+                        inline.push_str(", ");
+                    }
+                    let last_line = flat(last);
+                    let first_line = last_line.lines().next().unwrap_or_default();
+                    let fits = end_column(column, &inline) + width(first_line) + 1 < self.config.max_line_length;
+                    let multiline = rest.iter().any(|arg| flat(arg).contains('\n'))
+                        || (last_line.contains('\n') && !matches!(last, Expression::Lambda(_)));
+                    if fits && !multiline {
+                        let last = self.expression(last, level, end_column(column, &inline));
+                        return inline + &last + ")";
+                    }
+                }
+                text.push_str("(\n");
+                let indent = self.indentation(level + 1);
+                for (i, arg) in args.iter().enumerate() {
+                    text.push_str(&indent);
+                    text.push_str(&self.expression(arg, level + 1, (level + 1) * self.config.indent_width));
+                    text.push_str(if i + 1 < args.len() { ",\n" } else { "\n" });
+                }
+                text.push_str(&self.indentation(level));
+                text.push(')');
+                text
+            }
+            Expression::List(_, items) if !items.is_empty() => {
+                let mut text = String::from("[\n");
+                let indent = self.indentation(level + 1);
+                for (i, item) in items.iter().enumerate() {
+                    text.push_str(&indent);
+                    text.push_str(&self.expression(item, level + 1, (level + 1) * self.config.indent_width));
+                    text.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                text.push_str(&self.indentation(level));
+                text.push(']');
+                text
+            }
+            Expression::Map(_, entries) if !entries.is_empty() => {
+                let mut text = String::from("{\n");
+                let indent = self.indentation(level + 1);
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    let column = (level + 1) * self.config.indent_width;
+                    let key = format!("{}: ", self.expression(key, level + 1, column));
+                    let value = self.expression(value, level + 1, end_column(column, &key));
+                    text.push_str(&indent);
+                    text.push_str(&key);
+                    text.push_str(&value);
+                    text.push_str(if i + 1 < entries.len() { ",\n" } else { "\n" });
+                }
+                text.push_str(&self.indentation(level));
+                text.push('}');
+                text
+            }
+            Expression::Assign(name, value) => {
+                let text = format!("{} = ", name.lexeme());
+                let value = self.expression(value, level, column + width(&text));
+                text + &value
+            }
+            Expression::Destructure(pattern, value) => {
+                let text = format!("{} = ", pattern);
+                let value = self.expression(value, level, column + width(&text));
+                text + &value
+            }
+            Expression::Binary(left, op, right) | Expression::Logical(left, op, right) => {
+                let left = self.expression(left, level, column);
+                let text = format!("{} {} ", left, op.lexeme());
+                let right = self.expression(right, level, end_column(column, &text));
+                text + &right
+            }
+            Expression::Grouping(inner) => format!("({})", self.expression(inner, level, column + 1)),
+            Expression::Get(object, name) => format!("{}.{}", self.expression(object, level, column), name.lexeme()),
+            Expression::OptionalGet(object, name) => {
+                format!("{}?.{}", self.expression(object, level, column), name.lexeme())
+            }
+            Expression::Index(list, _, index) => {
+                let text = format!("{}[", self.expression(list, level, column));
+                let index = self.expression(index, level, end_column(column, &text));
+                text + &index + "]"
+            }
+            Expression::IndexSet(list, _, index, value) => {
+                let text = format!("{}[", self.expression(list, level, column));
+                let text = format!("{}{}] = ", text, self.expression(index, level, end_column(column, &text)));
+                let value = self.expression(value, level, end_column(column, &text));
+                text + &value
+            }
+            Expression::Set(object, name, value) => {
+                let text = format!("{}.{} = ", self.expression(object, level, column), name.lexeme());
+                let value = self.expression(value, level, end_column(column, &text));
+                text + &value
+            }
+            Expression::Unary(op, right) => {
+                let op = unary_operator(op);
+                let right = self.expression(right, level, column + op.len());
+                op + &right
+            }
+            _ => line,
+        }
+    }
+
+    fn indent(&mut self, level: usize) {
+        let indentation = self.indentation(level);
+        self.out.push_str(&indentation);
+    }
+
+    fn indentation(&self, level: usize) -> String {
+        if self.config.use_tabs {
+            "\t".repeat(level)
+        } else {
+            " ".repeat(level * self.config.indent_width)
+        }
+    }
+
+    /// Column the output currently ends at, with tabs as wide as an indentation level
+    fn column(&self) -> usize {
+        let line = self.out.rsplit('\n').next().unwrap_or_default();
+        line.chars().map(|c| if c == '\t' { self.config.indent_width } else { 1 }).sum()
+    }
+}
+
+/// An expression on a single line
+fn flat(expr: &Expression) -> String {
+    match expr {
+        Expression::Assign(name, value) => format!("{} = {}", name.lexeme(), flat(value)),
+        Expression::Destructure(pattern, value) => format!("{} = {}", pattern, flat(value)),
+        Expression::Binary(left, op, right) | Expression::Logical(left, op, right) => {
+            format!("{} {} {}", flat(left), op.lexeme(), flat(right))
+        }
+        Expression::Call(callee, _, args) => {
+            let args: Vec<String> = args.iter().map(flat).collect();
+            format!("{}({})", flat(callee), args.join(", "))
+        }
+        Expression::Get(object, name) => format!("{}.{}", flat(object), name.lexeme()),
+        Expression::OptionalGet(object, name) => format!("{}?.{}", flat(object), name.lexeme()),
+        Expression::Set(object, name, value) => format!("{}.{} = {}", flat(object), name.lexeme(), flat(value)),
+        Expression::Grouping(inner) => format!("({})", flat(inner)),
+        Expression::Index(list, _, index) => format!("{}[{}]", flat(list), flat(index)),
+        Expression::IndexSet(list, _, index, value) => format!("{}[{}] = {}", flat(list), flat(index), flat(value)),
+        Expression::Slice(object, _, start, end) => {
+            let bound = |bound: &Option<Box<Expression>>| bound.as_deref().map(flat).unwrap_or_default();
+            format!("{}[{}:{}]", flat(object), bound(start), bound(end))
+        }
+        Expression::List(_, items) => {
+            let items: Vec<String> = items.iter().map(flat).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Expression::Map(_, entries) => {
+            let entries: Vec<String> = entries.iter().map(|(key, value)| format!("{}: {}", flat(key), flat(value))).collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        Expression::If(..) | Expression::Block(..) => {
+            let config = FmtConfig::default();
+            Formatter { config: &config, out: String::new(), comments: &[] }.value_expression(expr, 0)
+        }
+        Expression::Lambda(declaration) => {
+            // Bodies that aren't empty span several lines, which the formatter indents
+            let config = FmtConfig::default();
+            let mut formatter = Formatter { config: &config, out: format!("{} ", declaration.signature()), comments: &[] };
+            formatter.block(&declaration.body, 0);
+            formatter.out
+        }
+        Expression::Literal(Value::String(s)) => string_literal(s),
+        Expression::Literal(Value::Number(n)) => number_literal(n),
+        Expression::Literal(val) => val.to_string(),
+        Expression::Unary(op, right) => format!("{}{}", unary_operator(op), flat(right)),
+        Expression::Super(_, method) => format!("super.{}", method.lexeme()),
+        Expression::This(_) => "this".to_string(),
+        Expression::Variable(name) => name.lexeme(),
+    }
+}
+
+/// A `break` or `continue` with its label, without the semicolon
+fn jump(keyword: &str, label: Option<&Token>) -> String {
+    match label {
+        Some(label) => format!("{} {}", keyword, label.lexeme()),
+        None => keyword.to_string(),
+    }
+}
+
+/// A unary operator as it is written in front of its operand, `await` needs a space
+fn unary_operator(op: &Token) -> String {
+    match op.token_type() {
+        TokenType::Await => "await ".to_string(),
+        _ => op.lexeme(),
+    }
+}
+
+fn width(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Column after `text` if it starts at `column` and may span several lines
+fn end_column(column: usize, text: &str) -> usize {
+    match text.rsplit_once('\n') {
+        Some((_, last)) => width(last),
+        None => column + width(text),
+    }
+}
+
+// ---------- Tests for the formatter ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_source(source: &str, config: &FmtConfig) -> String {
+        let (statements, comments) = parse_with_comments(source.to_string()).expect("Parsing failed!");
+        format(&statements, &comments, config)
+    }
+
+    #[test]
+    fn formats_with_the_default_style() {
+        let source = "var a:Number=1;if(a<2){print a,(a+1)*2;}else if(!a)print nil;else{while(a<3)a=a+1;{}}";
+        let expected = "\
+var a: Number = 1;
+if (a < 2) {
+    print a, (a + 1) * 2;
+} else if (!a)
+    print nil;
+else {
+    while (a < 3)
+        a = a + 1;
+    {}
+}
+";
+        assert_eq!(format_source(source, &FmtConfig::default()), expected);
+    }
+
+    #[test]
+    fn formats_functions() {
+        let source = "fun add(a:Number,b):Number{return a+b;}fun nothing(){return;}print add(1,2);";
+        let expected = "fun add(a: Number, b): Number {\n    return a + b;\n}\nfun nothing() {\n    return;\n}\nprint add(1, 2);\n";
+        assert_eq!(format_source(source, &FmtConfig::default()), expected);
+    }
+
+    #[test]
+    fn formats_for_loops_as_written() {
+        let source = "for(var i=0;i<3;i=i+1){if(i==1)continue;print i;}for(;;)print 1;";
+        let expected = "for (var i = 0; i < 3; i = i + 1) {\n    if (i == 1)\n        continue;\n    print i;\n}\nfor (;;)\n    print 1;\n";
+        assert_eq!(format_source(source, &FmtConfig::default()), expected);
+
+        let source = "for(var x in [1,2]){print x;}for(var c in\"ab\")print c;";
+        let expected = "for (var x in [1, 2]) {\n    print x;\n}\nfor (var c in \"ab\")\n    print c;\n";
+        assert_eq!(format_source(source, &FmtConfig::default()), expected);
+    }
+
+    #[test]
+    fn formats_classes() {
+        let source = "class Point{move(p,x){p.x=x;}show(p){print p.x;}set y(v){print v;}}class Empty{}var p=Point();p.x=1;";
+        let expected = "\
+class Point {
+    move(p, x) {
+        p.x = x;
+    }
+    show(p) {
+        print p.x;
+    }
+    set y(v) {
+        print v;
+    }
+}
+class Empty {}
+var p = Point();
+p.x = 1;
+";
+        assert_eq!(format_source(source, &FmtConfig::default()), expected);
+    }
+
+    #[test]
+    fn formats_switches() {
+        let source = "switch(a){case 1,2:print 1;print 2;case\"b\":default:print 3;}switch(a){}";
+        let expected = "\
+switch (a) {
+    case 1, 2:
+        print 1;
+        print 2;
+    case \"b\":
+    default:
+        print 3;
+}
+switch (a) {}
+";
+        assert_eq!(format_source(source, &FmtConfig::default()), expected);
+    }
+
+    #[test]
+    fn applies_settings() {
+        let mut config = FmtConfig::default();
+        for setting in ["use_tabs = true", "brace_style = \"next_line\"", "trailing_newline = false"] {
+            assert_eq!(config.apply(setting), Some(()));
+        }
+        assert_eq!(config.apply("brace_style = next_line"), None); // Strings need quotes
+        assert_eq!(config.apply("indent = 2"), None);
+
+        let expected = "if (true)\n{\n\tprint 1;\n}\nelse\n{\n\tprint 2;\n}";
+        assert_eq!(format_source("if (true) { print 1; } else { print 2; }", &config), expected);
+    }
+
+    #[test]
+    fn breaks_long_calls() {
+        let config = FmtConfig { max_line_length: 30, ..FmtConfig::default() };
+        let formatted = format_source("{ print len(\"a long string argument\") + 1; }", &config);
+        assert_eq!(formatted, "{\n    print len(\n        \"a long string argument\"\n    ) + 1;\n}\n");
+    }
+
+    #[test]
+    fn breaks_long_lists() {
+        let config = FmtConfig { max_line_length: 30, ..FmtConfig::default() };
+        let formatted = format_source("var a=[\"first element\",\"second element\"];a[0]=[];", &config);
+        assert_eq!(formatted, "var a = [\n    \"first element\",\n    \"second element\"\n];\na[0] = [];\n");
+    }
+
+    #[test]
+    fn breaks_long_maps() {
+        let config = FmtConfig { max_line_length: 30, ..FmtConfig::default() };
+        let formatted = format_source("var m={\"first\":[1,2],\"second\":{}};print {1:2};", &config);
+        assert_eq!(formatted, "var m = {\n    \"first\": [1, 2],\n    \"second\": {}\n};\nprint {1: 2};\n");
+    }
+
+    #[test]
+    fn formats_anonymous_functions() {
+        let source = "var f=fun(a){return a;};print map([1],fun(x){print x;});print fun(){};";
+        let expected = "var f = fun (a) {\n    return a;\n};\nprint map([1], fun (x) {\n    print x;\n});\nprint fun () {};\n";
+        assert_eq!(format_source(source, &FmtConfig::default()), expected);
+    }
+
+    #[test]
+    fn keeps_quotes_in_strings() {
+        let formatted = format_source("print \"\"\"say \"hi\"\"\"\", \"plain\";", &FmtConfig::default());
+        assert_eq!(formatted, "print \"\"\"say \"hi\"\"\"\", \"plain\";\n");
+        assert_eq!(format_source(&formatted, &FmtConfig::default()), formatted);
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let source = "\
+// Header
+var a=1; // one
+if(a){
+// Inside
+print a;print \"//\";
+}else{print 2;}
+while(a<3){a=a+1;
+  // Trailing in block
+}
+// Last
+";
+        let expected = "\
+// Header
+var a = 1; // one
+if (a) {
+    // Inside
+    print a;
+    print \"//\";
+} else {
+    print 2;
+}
+while (a < 3) {
+    a = a + 1;
+}
+// Trailing in block
+// Last
+";
+        assert_eq!(format_source(source, &FmtConfig::default()), expected);
+        assert_eq!(format_source(expected, &FmtConfig::default()), expected);
+    }
+}
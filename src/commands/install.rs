@@ -0,0 +1,348 @@
+// External dependencies
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Internal dependencies
+use jlox::errors::PackageError;
+
+/// Name of the manifest at the root of a project
+const MANIFEST_FILE: &str = "lox.toml";
+
+/// Name of the lockfile next to the manifest
+const LOCK_FILE: &str = "lox.lock";
+
+/// Directory next to the manifest the dependencies get installed into,
+/// one subdirectory per dependency name
+const MODULES_DIR: &str = "lox_modules";
+
+/// Where a dependency comes from
+#[derive(Debug, Clone, PartialEq)]
+enum Source {
+    /// A directory, relative to the manifest
+    Path(String),
+    /// A git repository, optionally at a branch, tag or commit
+    Git { url: String, rev: Option<String> },
+}
+
+/// A dependency as it is written in the lockfile: its source and, for
+/// git dependencies, the commit it resolved to
+#[derive(Debug, Clone, PartialEq)]
+struct Locked {
+    source: Source,
+    commit: Option<String>,
+}
+
+/// One `name = { key = "value", ... }` line of a section
+struct Entry {
+    name: String,
+    fields: BTreeMap<String, String>,
+    /// Line number, starting at 1
+    line: usize,
+}
+
+/// The dependencies of a `lox.toml`, by name:
+///
+/// ```toml
+/// [dependencies]
+/// utils = { path = "../utils" }
+/// json = { git = "https://example.com/json.lox", rev = "v1.0" }
+/// ```
+///
+/// Other sections (like `[package]`) are allowed and ignored.
+#[derive(Debug, PartialEq)]
+struct Manifest {
+    dependencies: BTreeMap<String, Source>,
+}
+
+/// Installs every dependency of the closest `lox.toml` at or above `dir`
+/// into `lox_modules/` and writes the resolved versions to `lox.lock`.
+/// Git dependencies check out the locked commit while the manifest entry
+/// is unchanged, so installs are reproducible.
+pub fn run(dir: &str) -> Result<()> {
+    let start = std::fs::canonicalize(dir)?;
+    let root = start
+        .ancestors()
+        .find(|dir| dir.join(MANIFEST_FILE).is_file())
+        .ok_or_else(|| PackageError::NoManifest(dir.to_string()))?;
+
+    let manifest = Manifest::parse(&read(&root.join(MANIFEST_FILE))?, MANIFEST_FILE)?;
+    let lock_path = root.join(LOCK_FILE);
+    let lock = if lock_path.is_file() { parse_lock(&read(&lock_path)?)? } else { BTreeMap::new() };
+
+    let modules = root.join(MODULES_DIR);
+    std::fs::create_dir_all(&modules)?;
+    remove_undeclared(&modules, &manifest)?;
+
+    let mut resolved = BTreeMap::new();
+    for (name, source) in &manifest.dependencies {
+        let target = modules.join(name);
+        if target.exists() {
+            std::fs::remove_dir_all(&target)?;
+        }
+        let locked = lock.get(name).filter(|locked| locked.source == *source);
+        let commit = match source {
+            Source::Path(path) => {
+                copy_dir(&root.join(path), &target)?;
+                println!("installed {} (path {})", name, path);
+                None
+            }
+            Source::Git { url, rev } => {
+                let checkout = locked.and_then(|locked| locked.commit.as_ref()).or(rev.as_ref());
+                let commit = git_install(name, url, checkout, &target)?;
+                println!("installed {} (git {} at {})", name, url, &commit[..commit.len().min(12)]);
+                Some(commit)
+            }
+        };
+        resolved.insert(name.clone(), Locked { source: source.clone(), commit });
+    }
+
+    std::fs::write(lock_path, render_lock(&resolved))?;
+    Ok(())
+}
+
+impl Manifest {
+    /// Reads the `[dependencies]` section. `file` names the file in errors.
+    fn parse(text: &str, file: &str) -> Result<Manifest> {
+        let mut dependencies = BTreeMap::new();
+        for Entry { name, fields, line } in entries(text, file, "dependencies")? {
+            // The name becomes a directory in lox_modules, which gets replaced on every install
+            if !is_plain_name(&name) {
+                return Err(PackageError::InvalidDependency(name, "needs a plain name, without /, \\, : or a leading .").into());
+            }
+            if fields.get("rev").is_some_and(|rev| rev.starts_with('-')) {
+                return Err(PackageError::InvalidDependency(name, "has a rev that starts with -").into());
+            }
+            let source = match (fields.get("path"), fields.get("git")) {
+                (Some(path), None) => Source::Path(path.clone()),
+                (None, Some(url)) => Source::Git { url: url.clone(), rev: fields.get("rev").cloned() },
+                _ => return Err(PackageError::InvalidDependency(name, "needs either path or git").into()),
+            };
+            if dependencies.insert(name.clone(), source).is_some() {
+                return Err(PackageError::InvalidLine(file.to_string(), line, format!("{} is declared twice", name)).into());
+            }
+        }
+        Ok(Manifest { dependencies })
+    }
+}
+
+/// Reads a lockfile, which uses the manifest syntax with an additional
+/// `commit` field for git dependencies
+fn parse_lock(text: &str) -> Result<BTreeMap<String, Locked>> {
+    let mut lock = BTreeMap::new();
+    let manifest = Manifest::parse(text, LOCK_FILE)?;
+    for Entry { name, fields, .. } in entries(text, LOCK_FILE, "dependencies")? {
+        if let Some(source) = manifest.dependencies.get(&name) {
+            lock.insert(name, Locked { source: source.clone(), commit: fields.get("commit").cloned() });
+        }
+    }
+    Ok(lock)
+}
+
+fn render_lock(lock: &BTreeMap<String, Locked>) -> String {
+    let mut out = String::from("# Written by jlox install, do not edit\n\n[dependencies]\n");
+    for (name, locked) in lock {
+        let fields = match (&locked.source, &locked.commit) {
+            (Source::Path(path), _) => format!("path = {}", quote(path)),
+            (Source::Git { url, rev }, commit) => {
+                let mut fields = format!("git = {}", quote(url));
+                if let Some(rev) = rev {
+                    fields.push_str(&format!(", rev = {}", quote(rev)));
+                }
+                if let Some(commit) = commit {
+                    fields.push_str(&format!(", commit = {}", quote(commit)));
+                }
+                fields
+            }
+        };
+        out.push_str(&format!("{} = {{ {} }}\n", name, fields));
+    }
+    out
+}
+
+/// The entries of a section. Values are strings without escapes.
+fn entries(text: &str, file: &str, section: &str) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut current = String::new();
+    for (index, line) in text.lines().enumerate() {
+        let invalid = || PackageError::InvalidLine(file.to_string(), index + 1, line.trim().to_string());
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            current = header.strip_suffix(']').ok_or_else(invalid)?.trim().to_string();
+            continue;
+        }
+        if current != section {
+            continue;
+        }
+
+        let (name, value) = line.split_once('=').ok_or_else(invalid)?;
+        let table = value.trim().strip_prefix('{').and_then(|v| v.strip_suffix('}')).ok_or_else(invalid)?;
+        let mut fields = BTreeMap::new();
+        for field in table.split(',').filter(|field| !field.trim().is_empty()) {
+            let (key, value) = field.split_once('=').ok_or_else(invalid)?;
+            let value = value.trim().strip_prefix('"').and_then(|v| v.strip_suffix('"')).ok_or_else(invalid)?;
+            fields.insert(key.trim().to_string(), value.to_string());
+        }
+        entries.push(Entry { name: name.trim().to_string(), fields, line: index + 1 });
+    }
+    Ok(entries)
+}
+
+/// The line without its `#` comment. A `#` inside a string (like in a URL) stays.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Whether the dependency name is a single, ordinary path component
+fn is_plain_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\', ':'])
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text)
+}
+
+fn read(path: &Path) -> Result<String> {
+    Ok(std::fs::read_to_string(path)?)
+}
+
+/// Removes installed dependencies that the manifest no longer declares
+fn remove_undeclared(modules: &Path, manifest: &Manifest) -> Result<()> {
+    for entry in std::fs::read_dir(modules)? {
+        let path = entry?.path();
+        let declared = path.file_name().is_some_and(|name| manifest.dependencies.contains_key(&*name.to_string_lossy()));
+        if path.is_dir() && !declared {
+            std::fs::remove_dir_all(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies a directory with everything inside it
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    if !from.is_dir() {
+        return Err(PackageError::MissingPath(from.display().to_string()).into());
+    }
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let path = entry?.path();
+        let target: PathBuf = to.join(path.file_name().unwrap_or_default());
+        if path.is_dir() {
+            copy_dir(&path, &target)?;
+        } else {
+            std::fs::copy(&path, &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Clones the repository into `target`, checks out `checkout` if given
+/// and returns the commit that ended up checked out
+fn git_install(name: &str, url: &str, checkout: Option<&String>, target: &Path) -> Result<String> {
+    let target = target.to_string_lossy();
+    // `--` keeps a URL starting with - from being read as an option, revs can't be put behind it
+    git(name, &["clone", "--quiet", "--", url, &target])?;
+    if let Some(checkout) = checkout {
+        if checkout.starts_with('-') {
+            return Err(PackageError::InvalidDependency(name.to_string(), "has a rev that starts with -").into());
+        }
+        git(name, &["-C", &target, "checkout", "--quiet", checkout, "--"])?;
+    }
+    Ok(git(name, &["-C", &target, "rev-parse", "HEAD"])?.trim().to_string())
+}
+
+/// Runs git and returns its stdout
+fn git(name: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| PackageError::Git(name.to_string(), e.to_string()))?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(PackageError::Git(name.to_string(), message).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// ---------- Tests for the install module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_manifests() {
+        let text = "\
+[package]
+name = \"app\"   # ignored
+
+[dependencies]
+utils = { path = \"../utils\" }
+json = { git = \"https://example.com/json.lox#main\", rev = \"v1\" }
+";
+        let manifest = Manifest::parse(text, MANIFEST_FILE).expect("Parsing failed!");
+        assert_eq!(manifest.dependencies["utils"], Source::Path("../utils".to_string()));
+        assert_eq!(
+            manifest.dependencies["json"],
+            Source::Git { url: "https://example.com/json.lox#main".to_string(), rev: Some("v1".to_string()) }
+        );
+
+        assert!(Manifest::parse("[dependencies]\nutils = \"../utils\"", MANIFEST_FILE).is_err());
+        assert!(Manifest::parse("[dependencies]\nutils = { rev = \"v1\" }", MANIFEST_FILE).is_err());
+    }
+
+    #[test]
+    fn rejects_names_and_revs_that_escape() {
+        for name in ["../../victim", "a/b", "a\\b", "..", ".hidden", "C:"] {
+            let text = format!("[dependencies]\n{} = {{ path = \"dep\" }}", name);
+            assert!(Manifest::parse(&text, MANIFEST_FILE).is_err(), "{} was accepted", name);
+        }
+        let text = "[dependencies]\njson = { git = \"https://example.com/json\", rev = \"--upload-pack=evil\" }";
+        assert!(Manifest::parse(text, MANIFEST_FILE).is_err());
+        assert!(Manifest::parse("[dependencies]\njson-utils_2 = { path = \"dep\" }", MANIFEST_FILE).is_ok());
+    }
+
+    #[test]
+    fn lockfiles_round_trip() {
+        let lock = BTreeMap::from([
+            ("a".to_string(), Locked { source: Source::Path("lib/a".to_string()), commit: None }),
+            (
+                "b".to_string(),
+                Locked {
+                    source: Source::Git { url: "https://example.com/b".to_string(), rev: None },
+                    commit: Some("0123abcd".to_string()),
+                },
+            ),
+        ]);
+        assert_eq!(parse_lock(&render_lock(&lock)).expect("Parsing failed!"), lock);
+    }
+
+    #[test]
+    fn installs_path_dependencies() {
+        let root = std::env::temp_dir().join(format!("jlox-install-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("app/lox_modules/stale")).expect("Setup failed!");
+        std::fs::create_dir_all(root.join("utils/nested")).expect("Setup failed!");
+        std::fs::write(root.join("utils/nested/strings.lox"), "var empty = \"\";").expect("Setup failed!");
+        std::fs::write(root.join("app/lox.toml"), "[dependencies]\nutils = { path = \"../utils\" }\n").expect("Setup failed!");
+
+        run(&root.join("app").to_string_lossy()).expect("Install failed!");
+        assert!(root.join("app/lox_modules/utils/nested/strings.lox").is_file());
+        assert!(!root.join("app/lox_modules/stale").exists());
+        let lock = read(&root.join("app/lox.lock")).expect("No lockfile written");
+        assert!(lock.contains("utils = { path = \"../utils\" }"));
+
+        std::fs::remove_dir_all(root).expect("Cleanup failed!");
+    }
+}
@@ -0,0 +1,656 @@
+// External dependencies
+use anyhow::Result;
+use serde_json::{json, Value as Json};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+// Internal dependencies
+use crate::reporter;
+use jlox::diagnostic::{CollectingReporter, Severity};
+use jlox::errors::{KernelError, LoxError};
+use jlox::interpreter::{CancellationToken, Interpreter, InterpreterBuilder};
+use jlox::obj::statement::Statement;
+use jlox::obj::value::Value;
+use jlox::{analyzer, parser, scanner, typechecker};
+
+/// Version of the Jupyter messaging protocol the kernel speaks
+const PROTOCOL_VERSION: &str = "5.3";
+
+/// Separates the routing identities from the message itself on the wire
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+
+/// How often (in milliseconds) the socket loops check whether a shutdown was requested
+const POLL_INTERVAL: i64 = 100;
+
+/// Source of the message ids, which are unique within the session
+static NEXT_MESSAGE: AtomicU64 = AtomicU64::new(1);
+
+/// Where the client expects the kernel, read from the connection file
+/// Jupyter passes to `jlox kernel`
+struct Connection {
+    transport: String,
+    ip: String,
+    shell_port: u16,
+    iopub_port: u16,
+    stdin_port: u16,
+    control_port: u16,
+    hb_port: u16,
+    /// Key of the message signatures, empty if messages are not signed
+    key: String,
+}
+
+/// A message of the protocol, without its signature
+#[derive(Debug, Clone, PartialEq)]
+struct Message {
+    /// Routing identities of the peer (or the topic on iopub)
+    identities: Vec<Vec<u8>>,
+    header: Json,
+    parent_header: Json,
+    metadata: Json,
+    content: Json,
+}
+
+/// Signs messages and builds their headers
+#[derive(Clone)]
+struct Session {
+    id: String,
+    key: Vec<u8>,
+}
+
+/// A failed cell, in the shape of the `error` message
+struct Failure {
+    ename: String,
+    evalue: String,
+    traceback: Vec<String>,
+}
+
+/// Output handle of the interpreter. Everything a cell prints is
+/// collected and sent to the client as one stream message.
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+/// The shell side of the kernel: runs the cells in one interpreter
+/// session and publishes what happens on iopub
+struct Kernel {
+    session: Session,
+    shell: zmq::Socket,
+    iopub: zmq::Socket,
+    interpreter: Interpreter,
+    output: Captured,
+    execution_count: u64,
+}
+
+/// Runs a Jupyter kernel for the connection file until the client shuts
+/// it down. Cells run one after another in the same interpreter, so
+/// variables of one cell are visible in the next ones. Interrupts arrive
+/// as messages on the control socket, which is served on its own thread
+/// and cancels the running cell.
+pub fn run(connection_file: &str, builder: InterpreterBuilder) -> Result<()> {
+    let connection = Connection::parse(&std::fs::read_to_string(connection_file)?, connection_file)?;
+    let context = zmq::Context::new();
+    let bind = |kind, port| -> Result<zmq::Socket> {
+        let socket = context.socket(kind)?;
+        socket.bind(&connection.endpoint(port))?;
+        Ok(socket)
+    };
+    let shell = bind(zmq::ROUTER, connection.shell_port)?;
+    let control = bind(zmq::ROUTER, connection.control_port)?;
+    let iopub = bind(zmq::PUB, connection.iopub_port)?;
+    let heartbeat = bind(zmq::REP, connection.hb_port)?;
+    let _stdin = bind(zmq::ROUTER, connection.stdin_port)?; // Clients connect to it, but input never comes from it
+
+    // Reading the kernel's own stdin would block the cell, input and
+    // readLine in a cell get nil as if the input had ended
+    let output = Captured::default();
+    let interpreter = builder.output(output.clone()).input(std::io::empty()).build();
+    let session = Session::new(connection.key.as_bytes());
+    let shutdown = Arc::new(AtomicBool::new(false));
+
+    let threads = [
+        {
+            let shutdown = shutdown.clone();
+            std::thread::spawn(move || serve_heartbeat(heartbeat, &shutdown))
+        },
+        {
+            let (session, cancellation, shutdown) = (session.clone(), interpreter.cancellation_token(), shutdown.clone());
+            std::thread::spawn(move || serve_control(control, &session, &cancellation, &shutdown))
+        },
+    ];
+
+    let mut kernel = Kernel { session, shell, iopub, interpreter, output, execution_count: 0 };
+    let result = kernel.serve(&shutdown);
+    shutdown.store(true, Ordering::Relaxed);
+    for thread in threads {
+        thread.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))?;
+    }
+    result
+}
+
+/// Writes the kernel spec that makes Jupyter start `jlox kernel` for Lox
+/// notebooks, into `$JUPYTER_DATA_DIR` or `~/.local/share/jupyter`
+pub fn install() -> Result<()> {
+    let data_dir = std::env::var_os("JUPYTER_DATA_DIR")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share/jupyter")))
+        .ok_or(KernelError::NoDataDir)?;
+    let dir = data_dir.join("kernels").join("lox");
+    std::fs::create_dir_all(&dir)?;
+
+    let spec = json!({
+        "argv": [std::env::current_exe()?, "kernel", "{connection_file}"],
+        "display_name": "Lox",
+        "language": "lox",
+        "interrupt_mode": "message",
+    });
+    std::fs::write(dir.join("kernel.json"), serde_json::to_string_pretty(&spec)?)?;
+    println!("installed the Lox kernel into {}", dir.display());
+    Ok(())
+}
+
+impl Kernel {
+    /// Answers shell requests until a shutdown is requested
+    fn serve(&mut self, shutdown: &AtomicBool) -> Result<()> {
+        self.publish(&Json::Null, "status", json!({ "execution_state": "starting" }))?;
+        while !shutdown.load(Ordering::Relaxed) {
+            if self.shell.poll(zmq::POLLIN, POLL_INTERVAL)? == 0 {
+                continue;
+            }
+            match self.session.receive(&self.shell) {
+                Ok(request) => self.handle(&request, shutdown)?,
+                Err(error) => warn!("dropped a shell message: {}", error),
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, request: &Message, shutdown: &AtomicBool) -> Result<()> {
+        self.publish(&request.header, "status", json!({ "execution_state": "busy" }))?;
+        let reply = match request.msg_type() {
+            "kernel_info_request" => Some(kernel_info()),
+            "execute_request" => Some(self.execute(request)?),
+            "complete_request" => Some(self.complete(request)),
+            "is_complete_request" => Some(json!({ "status": "unknown" })),
+            "comm_info_request" => Some(json!({ "status": "ok", "comms": {} })),
+            "history_request" => Some(json!({ "status": "ok", "history": [] })),
+            "shutdown_request" => {
+                shutdown.store(true, Ordering::Relaxed);
+                Some(json!({ "status": "ok", "restart": request.content["restart"] }))
+            }
+            _ => None,
+        };
+        if let Some(content) = reply {
+            let reply_type = request.msg_type().replace("_request", "_reply");
+            self.session.send(&self.shell, &self.session.reply(request, &reply_type, content))?;
+        }
+        self.publish(&request.header, "status", json!({ "execution_state": "idle" }))
+    }
+
+    /// Runs a cell. What it printed goes out as a stream, the value of a
+    /// cell that is a single expression as its result. Returns the content
+    /// of the reply.
+    fn execute(&mut self, request: &Message) -> Result<Json> {
+        let code = request.content["code"].as_str().unwrap_or_default().to_string();
+        let silent = request.content["silent"].as_bool().unwrap_or(false);
+        if !silent {
+            self.execution_count += 1;
+            let input = json!({ "code": code, "execution_count": self.execution_count });
+            self.publish(&request.header, "execute_input", input)?;
+        }
+
+        self.interpreter.cancellation_token().reset(); // Interrupts that arrived while idle don't hit this cell
+        let (result, warnings) = self.evaluate(&code);
+        let printed = self.output.take();
+        for (name, text) in [("stderr", warnings.join("\n")), ("stdout", String::from_utf8_lossy(&printed).into_owned())] {
+            if !text.is_empty() && !silent {
+                self.publish(&request.header, "stream", json!({ "name": name, "text": text }))?;
+            }
+        }
+
+        let count = self.execution_count;
+        match result {
+            Ok(value) => {
+                if let Some(value) = value.filter(|value| *value != Value::Nil && !silent) {
+                    let data = json!({ "execution_count": count, "data": { "text/plain": value.to_text() }, "metadata": {} });
+                    self.publish(&request.header, "execute_result", data)?;
+                }
+                Ok(json!({ "status": "ok", "execution_count": count, "payload": [], "user_expressions": {} }))
+            }
+            Err(Failure { ename, evalue, traceback }) => {
+                let error = json!({ "ename": ename, "evalue": evalue, "traceback": traceback });
+                self.publish(&request.header, "error", error.clone())?;
+                let mut reply = json!({ "status": "error", "execution_count": count });
+                if let (Json::Object(reply), Json::Object(error)) = (&mut reply, error) {
+                    reply.extend(error);
+                }
+                Ok(reply)
+            }
+        }
+    }
+
+    /// Scans, parses and runs the code like the REPL does. Gives the value
+    /// if the cell is a single expression, and the warnings of the analyzer.
+    fn evaluate(&mut self, code: &str) -> (std::result::Result<Option<Value>, Failure>, Vec<String>) {
+        let mut reporter = CollectingReporter::default();
+        let typed = self.interpreter.config().typed;
+        let statements = scanner::scan_tokens(code.to_string(), &mut reporter)
+            .and_then(|tokens| parser::parse(tokens, &mut reporter))
+            .and_then(|statements| {
+                analyzer::analyze(&statements, &mut reporter);
+                if typed {
+                    typechecker::check(&statements, &mut reporter)?;
+                }
+                Ok(statements)
+            });
+
+        let (errors, warnings): (Vec<_>, Vec<_>) =
+            reporter.into_diagnostics().into_iter().partition(|d| d.severity == Severity::Error);
+        let warnings = warnings.into_iter().map(|d| d.message).collect();
+        let statements = match statements {
+            Ok(statements) => statements,
+            Err(error) => {
+                let traceback: Vec<String> = errors.into_iter().map(|d| d.message).collect();
+                let evalue = traceback.first().cloned().unwrap_or_else(|| error.to_string());
+                return (Err(Failure { ename: error_name(&error).to_string(), evalue, traceback }), warnings);
+            }
+        };
+
+        let result = match statements.as_slice() {
+            [Statement::Expression(expr)] => self.interpreter.evaluate(expr).map(Some),
+            _ => self.interpreter.interpret(statements).map(|()| None),
+        };
+        let result = result.map_err(|error| {
+            let ename = error_name(&error).to_string();
+            let evalue = error.to_string();
+            let traceback = reporter::with_excerpt(error.into(), self.interpreter.error_span(), code).to_string();
+            Failure { ename, evalue, traceback: traceback.lines().map(str::to_string).collect() }
+        });
+        (result, warnings)
+    }
+
+    /// Completes the name in front of the cursor with the global variables
+    fn complete(&self, request: &Message) -> Json {
+        let code: Vec<char> = request.content["code"].as_str().unwrap_or_default().chars().collect();
+        let cursor = (request.content["cursor_pos"].as_u64().unwrap_or(0) as usize).min(code.len());
+        let start = code[..cursor].iter().rposition(|c| !c.is_alphanumeric() && *c != '_').map_or(0, |index| index + 1);
+        let prefix: String = code[start..cursor].iter().collect();
+
+        let matches: Vec<String> = self
+            .interpreter
+            .globals()
+            .into_iter()
+            .map(|(name, _)| name)
+            .filter(|name| name.starts_with(&prefix))
+            .collect();
+        json!({ "status": "ok", "matches": matches, "cursor_start": start, "cursor_end": cursor, "metadata": {} })
+    }
+
+    /// Sends a message on iopub, where every client of the kernel sees it
+    fn publish(&self, parent: &Json, msg_type: &str, content: Json) -> Result<()> {
+        let topic = vec![msg_type.as_bytes().to_vec()];
+        self.session.send(&self.iopub, &self.session.message(topic, msg_type, parent.clone(), content))
+    }
+}
+
+/// Answers control requests on their own thread, so that interrupts and
+/// shutdowns arrive while a cell is running
+fn serve_control(control: zmq::Socket, session: &Session, cancellation: &CancellationToken, shutdown: &AtomicBool) -> Result<()> {
+    while !shutdown.load(Ordering::Relaxed) {
+        if control.poll(zmq::POLLIN, POLL_INTERVAL)? == 0 {
+            continue;
+        }
+        let request = match session.receive(&control) {
+            Ok(request) => request,
+            Err(error) => {
+                warn!("dropped a control message: {}", error);
+                continue;
+            }
+        };
+        let content = match request.msg_type() {
+            "interrupt_request" => {
+                cancellation.cancel();
+                json!({ "status": "ok" })
+            }
+            "shutdown_request" => {
+                cancellation.cancel();
+                shutdown.store(true, Ordering::Relaxed);
+                json!({ "status": "ok", "restart": request.content["restart"] })
+            }
+            "kernel_info_request" => kernel_info(),
+            _ => continue,
+        };
+        let reply_type = request.msg_type().replace("_request", "_reply");
+        session.send(&control, &session.reply(&request, &reply_type, content))?;
+    }
+    Ok(())
+}
+
+/// Echoes heartbeats, which tell the client that the kernel is alive
+fn serve_heartbeat(heartbeat: zmq::Socket, shutdown: &AtomicBool) -> Result<()> {
+    while !shutdown.load(Ordering::Relaxed) {
+        if heartbeat.poll(zmq::POLLIN, POLL_INTERVAL)? > 0 {
+            let ping = heartbeat.recv_bytes(0)?;
+            heartbeat.send(ping, 0)?;
+        }
+    }
+    Ok(())
+}
+
+/// Content of the `kernel_info_reply`
+fn kernel_info() -> Json {
+    let version = env!("CARGO_PKG_VERSION");
+    json!({
+        "status": "ok",
+        "protocol_version": PROTOCOL_VERSION,
+        "implementation": "jlox",
+        "implementation_version": version,
+        "language_info": { "name": "lox", "version": version, "mimetype": "text/x-lox", "file_extension": ".lox" },
+        "banner": format!("jlox {}", version),
+        "help_links": [],
+    })
+}
+
+/// The phase that failed, as the name of the error
+fn error_name(error: &LoxError) -> &'static str {
+    match error {
+        LoxError::Scan(_) => "ScanError",
+        LoxError::Parse(_) => "ParseError",
+        LoxError::Type(_) => "TypeError",
+        LoxError::Runtime(_) => "RuntimeError",
+        LoxError::Chunk(_) => "ChunkError",
+        LoxError::Value(_) => "ValueError",
+    }
+}
+
+impl Connection {
+    /// Reads a connection file. `path` names the file in errors.
+    fn parse(text: &str, path: &str) -> Result<Connection> {
+        let invalid = |reason: String| KernelError::InvalidConnectionFile(path.to_string(), reason);
+        let json: Json = serde_json::from_str(text).map_err(|e| invalid(e.to_string()))?;
+        if json["signature_scheme"].as_str().is_some_and(|scheme| scheme != "hmac-sha256") {
+            return Err(invalid("only hmac-sha256 signatures are supported".to_string()).into());
+        }
+
+        let text = |name: &str| json[name].as_str().map(str::to_string).ok_or_else(|| invalid(format!("{} is missing", name)));
+        let port = |name: &str| {
+            json[name]
+                .as_u64()
+                .and_then(|port| u16::try_from(port).ok())
+                .ok_or_else(|| invalid(format!("{} is not a port", name)))
+        };
+        Ok(Connection {
+            transport: text("transport")?,
+            ip: text("ip")?,
+            shell_port: port("shell_port")?,
+            iopub_port: port("iopub_port")?,
+            stdin_port: port("stdin_port")?,
+            control_port: port("control_port")?,
+            hb_port: port("hb_port")?,
+            key: text("key").unwrap_or_default(),
+        })
+    }
+
+    fn endpoint(&self, port: u16) -> String {
+        format!("{}://{}:{}", self.transport, self.ip, port)
+    }
+}
+
+impl Message {
+    fn msg_type(&self) -> &str {
+        self.header["msg_type"].as_str().unwrap_or_default()
+    }
+
+    /// The frames of the message on the wire, signed with `key`
+    fn to_frames(&self, key: &[u8]) -> Vec<Vec<u8>> {
+        let parts: Vec<Vec<u8>> = [&self.header, &self.parent_header, &self.metadata, &self.content]
+            .iter()
+            .map(|part| part.to_string().into_bytes())
+            .collect();
+        let mut frames = self.identities.clone();
+        frames.push(DELIMITER.to_vec());
+        frames.push(sign(key, &parts).into_bytes());
+        frames.extend(parts);
+        frames
+    }
+
+    /// Reads a message from its frames and checks its signature. Binary
+    /// buffers after the content are ignored.
+    fn from_frames(frames: Vec<Vec<u8>>, key: &[u8]) -> Result<Message> {
+        let delimiter = frames
+            .iter()
+            .position(|frame| frame == DELIMITER)
+            .ok_or(KernelError::InvalidMessage("the delimiter is missing"))?;
+        let (identities, rest) = frames.split_at(delimiter);
+        let [_, signature, header, parent_header, metadata, content, ..] = rest else {
+            return Err(KernelError::InvalidMessage("parts are missing").into());
+        };
+        let parts = [header, parent_header, metadata, content].map(Vec::clone);
+        if sign(key, &parts).as_bytes() != signature.as_slice() {
+            return Err(KernelError::BadSignature.into());
+        }
+
+        let [header, parent_header, metadata, content] = parts
+            .map(|part| serde_json::from_slice(&part).map_err(|_| KernelError::InvalidMessage("a part is not JSON")));
+        Ok(Message {
+            identities: identities.to_vec(),
+            header: header?,
+            parent_header: parent_header?,
+            metadata: metadata?,
+            content: content?,
+        })
+    }
+}
+
+impl Session {
+    fn new(key: &[u8]) -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        Self { id: format!("{:x}-{:x}", nanos, std::process::id()), key: key.to_vec() }
+    }
+
+    fn message(&self, identities: Vec<Vec<u8>>, msg_type: &str, parent_header: Json, content: Json) -> Message {
+        let header = json!({
+            "msg_id": format!("{}-{}", self.id, NEXT_MESSAGE.fetch_add(1, Ordering::Relaxed)),
+            "session": self.id,
+            "username": "jlox",
+            "date": timestamp(SystemTime::now()),
+            "msg_type": msg_type,
+            "version": PROTOCOL_VERSION,
+        });
+        let parent_header = if parent_header.is_null() { json!({}) } else { parent_header };
+        Message { identities, header, parent_header, metadata: json!({}), content }
+    }
+
+    /// A message to the peer that sent `request`
+    fn reply(&self, request: &Message, msg_type: &str, content: Json) -> Message {
+        self.message(request.identities.clone(), msg_type, request.header.clone(), content)
+    }
+
+    fn send(&self, socket: &zmq::Socket, message: &Message) -> Result<()> {
+        Ok(socket.send_multipart(message.to_frames(&self.key), 0)?)
+    }
+
+    fn receive(&self, socket: &zmq::Socket) -> Result<Message> {
+        Message::from_frames(socket.recv_multipart(0)?, &self.key)
+    }
+}
+
+impl Captured {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hex encoded HMAC-SHA256 of the parts. Empty without a key, which
+/// turns signing off.
+fn sign(key: &[u8], parts: &[Vec<u8>]) -> String {
+    const BLOCK_SIZE: usize = 64;
+    if key.is_empty() {
+        return String::new();
+    }
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    for part in parts {
+        inner.update(part);
+    }
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// ISO 8601 form of the time in UTC, like `2000-02-29T12:30:00.000000Z`
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (days, seconds) = ((seconds / 86_400) as i64, seconds % 86_400);
+
+    // Civil date from the days since 1970-01-01, after Howard Hinnant's algorithm
+    let shifted = days + 719_468; // Days since 0000-03-01
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153; // Starting with March
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60,
+        since_epoch.subsec_micros()
+    )
+}
+
+// ---------- Tests for the kernel module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn signs_with_hmac_sha256() {
+        // Test case 2 of RFC 4231
+        let signature = sign(b"Jefe", &[b"what do ya ".to_vec(), b"want for nothing?".to_vec()]);
+        assert_eq!(signature, "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+        assert_eq!(sign(b"", &[b"unsigned".to_vec()]), "");
+    }
+
+    #[test]
+    fn formats_timestamps() {
+        let time = UNIX_EPOCH + Duration::from_micros(951_827_445_000_042);
+        assert_eq!(timestamp(time), "2000-02-29T12:30:45.000042Z");
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000000Z");
+    }
+
+    #[test]
+    fn messages_round_trip_and_check_signatures() {
+        let session = Session::new(b"secret");
+        let message = session.message(vec![b"peer".to_vec()], "execute_request", json!({}), json!({ "code": "1;" }));
+        let frames = message.to_frames(b"secret");
+        assert_eq!(Message::from_frames(frames.clone(), b"secret").expect("Reading failed!"), message);
+
+        let error = Message::from_frames(frames, b"other").expect_err("A wrong key was accepted");
+        assert!(matches!(error.downcast_ref(), Some(KernelError::BadSignature)));
+    }
+
+    #[test]
+    fn runs_cells_over_zeromq() {
+        let ports: Vec<u16> = (0..5)
+            .map(|_| std::net::TcpListener::bind("127.0.0.1:0").and_then(|l| l.local_addr()).expect("No free port").port())
+            .collect();
+        let path = std::env::temp_dir().join(format!("jlox-kernel-{}.json", std::process::id()));
+        let connection = json!({
+            "transport": "tcp", "ip": "127.0.0.1", "key": "secret", "signature_scheme": "hmac-sha256",
+            "shell_port": ports[0], "iopub_port": ports[1], "stdin_port": ports[2], "control_port": ports[3], "hb_port": ports[4],
+        });
+        std::fs::write(&path, connection.to_string()).expect("Setup failed!");
+        let file = path.to_string_lossy().to_string();
+        let kernel = std::thread::spawn(move || run(&file, Interpreter::builder()).map_err(|e| e.to_string()));
+
+        let context = zmq::Context::new();
+        let connect = |kind, port: u16| {
+            let socket = context.socket(kind).expect("Socket failed!");
+            socket.set_rcvtimeo(5000).expect("Socket failed!");
+            socket.connect(&format!("tcp://127.0.0.1:{}", port)).expect("Connecting failed!");
+            socket
+        };
+        let (shell, iopub, control) = (connect(zmq::DEALER, ports[0]), connect(zmq::SUB, ports[1]), connect(zmq::DEALER, ports[3]));
+        iopub.set_subscribe(b"").expect("Subscribing failed!");
+        let session = Session::new(b"secret");
+        let request = |socket: &zmq::Socket, msg_type: &str, content: Json| {
+            let request = session.message(Vec::new(), msg_type, json!({}), content);
+            session.send(socket, &request).expect("Sending failed!");
+            let reply = session.receive(socket).expect("No reply");
+            assert_eq!(reply.parent_header, request.header);
+            (request, reply)
+        };
+
+        // Subscriptions take a moment, ask until the status messages arrive
+        while iopub.poll(zmq::POLLIN, 100).expect("Polling failed!") == 0 {
+            request(&shell, "kernel_info_request", json!({}));
+        }
+        while iopub.poll(zmq::POLLIN, 100).expect("Polling failed!") > 0 {
+            session.receive(&iopub).expect("Reading failed!");
+        }
+
+        let (cell, reply) = request(&shell, "execute_request", json!({ "code": "var a = 20; print a + 1;", "silent": false }));
+        assert_eq!(reply.content["status"], "ok");
+        let mut printed = String::new();
+        loop {
+            let message = session.receive(&iopub).expect("No iopub message");
+            assert_eq!(message.parent_header, cell.header);
+            match message.msg_type() {
+                "stream" => printed.push_str(message.content["text"].as_str().unwrap_or_default()),
+                "status" if message.content["execution_state"] == "idle" => break,
+                _ => {}
+            }
+        }
+        assert_eq!(printed, "21\n");
+
+        let (_, reply) = request(&shell, "execute_request", json!({ "code": "print missing;" }));
+        assert_eq!(reply.content["status"], "error");
+        assert_eq!(reply.content["ename"], "RuntimeError");
+
+        // The endless loop only ends through the interrupt on the control socket
+        let endless = session.message(Vec::new(), "execute_request", json!({}), json!({ "code": "while (true) {}" }));
+        session.send(&shell, &endless).expect("Sending failed!");
+        std::thread::sleep(Duration::from_millis(200));
+        let (_, reply) = request(&control, "interrupt_request", json!({}));
+        assert_eq!(reply.content["status"], "ok");
+        let reply = session.receive(&shell).expect("The cell was not interrupted");
+        assert_eq!(reply.content["status"], "error");
+
+        let (_, reply) = request(&control, "shutdown_request", json!({ "restart": false }));
+        assert_eq!(reply.content["status"], "ok");
+        assert_eq!(kernel.join().expect("The kernel panicked"), Ok(()));
+        std::fs::remove_file(path).expect("Cleanup failed!");
+    }
+}
@@ -0,0 +1,895 @@
+// External dependencies
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+
+// Internal dependencies
+use super::common::{desugared_for, number_literal, parse_file, string_literal};
+use jlox::analyzer::first_expression_token;
+use jlox::obj::annotation::TypeAnnotation;
+use jlox::obj::expression::{Expression, Pattern};
+use jlox::obj::function::FunctionDeclaration;
+use jlox::obj::statement::Statement;
+use jlox::obj::token::Token;
+use jlox::obj::token_type::TokenType;
+use jlox::obj::value::Value;
+use jlox::scanner::match_keyword;
+
+// Precedence levels of the expression grammar, from lowest to highest.
+// They mirror the rule functions of the parser.
+const ASSIGNMENT: u8 = 1;
+const COALESCE: u8 = 2;
+const OR: u8 = 3;
+const AND: u8 = 4;
+const EQUALITY: u8 = 5;
+const COMPARISON: u8 = 6;
+const TERM: u8 = 7;
+const FACTOR: u8 = 8;
+const UNARY: u8 = 9;
+const CALL: u8 = 10;
+
+/// Parses the script and prints it back with as little whitespace as
+/// possible. With `rename_locals`, variables declared inside blocks get
+/// the shortest names that don't clash with anything else in the script.
+pub fn run(path: &str, rename_locals: bool) -> Result<()> {
+    let statements = parse_file(path)?;
+    println!("{}", minify(&statements, rename_locals));
+    Ok(())
+}
+
+/// Turns the statements back into Lox source code with minimal whitespace
+pub fn minify(statements: &[Statement], rename_locals: bool) -> String {
+    let mut reserved = HashSet::new();
+    for stmt in statements {
+        collect_identifiers(stmt, &mut reserved);
+    }
+
+    let mut minifier = Minifier {
+        out: String::new(),
+        rename_locals,
+        reserved,
+        kept: HashSet::new(),
+        scopes: Vec::new(),
+    };
+    loop {
+        let kept = minifier.kept.len();
+        minifier.out.clear();
+        for stmt in statements {
+            minifier.statement(stmt);
+        }
+        // A map pattern can assign a local that was already renamed, print
+        // everything again with the names it needs kept
+        if minifier.kept.len() == kept {
+            break;
+        }
+    }
+    minifier.out
+}
+
+/// The local variables of one block and the names they were renamed to
+struct Scope {
+    renamed: HashMap<String, String>,
+    /// Whether the locals keep their names, because code in the scope calls
+    /// `eval`, and the evaluated code may use any of them by name
+    keep_names: bool,
+    /// Index of the next short name to try, see [short_name]
+    next_index: usize,
+}
+
+/// Contraption that prints the AST into a String and keeps track of
+/// block scopes while doing so, so locals can be renamed consistently.
+struct Minifier {
+    out: String,
+    rename_locals: bool,
+    /// Every identifier used in the script. Short names must not collide with them.
+    reserved: HashSet<String>,
+    /// Locals that keep their names, because a map pattern uses them as keys
+    kept: HashSet<String>,
+    /// Block scopes, innermost last. Globals live outside of them and keep their names.
+    scopes: Vec<Scope>,
+}
+
+impl Minifier {
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Block(stmts) => {
+                let desugared = desugared_for(stmts);
+                if desugared.is_none() {
+                    self.push("{");
+                }
+                self.enter(calls_eval(stmts));
+                match desugared {
+                    Some((init, stmt)) => self.for_statement(Some(init), stmt),
+                    None => stmts.iter().for_each(|stmt| self.statement(stmt)),
+                }
+                self.scopes.pop();
+                if desugared.is_none() {
+                    self.push("}");
+                }
+            }
+            Statement::Break(_, label) => self.jump("break", label.as_ref()),
+            Statement::Continue(_, label) => self.jump("continue", label.as_ref()),
+            Statement::Class(name, superclass, mixins, methods, setters) => {
+                let new_name = self.local_name(name);
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.renamed.insert(name.lexeme(), new_name.clone());
+                }
+                self.push("class");
+                self.push(&new_name);
+                if let Some(superclass) = superclass {
+                    self.push("<");
+                    let superclass = self.resolve(superclass);
+                    self.push(&superclass);
+                }
+                for (i, mixin) in mixins.iter().enumerate() {
+                    self.push(if i == 0 { "with" } else { "," });
+                    let mixin = self.resolve(mixin);
+                    self.push(&mixin);
+                }
+                self.push("{");
+                for method in methods {
+                    self.function(method, &method.name.lexeme()); // Method names are properties, not variables
+                }
+                for setter in setters {
+                    self.push("set");
+                    self.function(setter, &setter.name.lexeme());
+                }
+                self.push("}");
+            }
+            Statement::Expression(expr) => {
+                let parens = starts_statement(expr);
+                self.open(parens);
+                self.expression(expr, ASSIGNMENT);
+                self.close(parens);
+                self.push(";");
+            }
+            Statement::ForIn(_, name, iterable, body, label) => {
+                // The loop variable is a local of the loop, the iterated value is not
+                self.enter(calls_eval(std::slice::from_ref(body)));
+                let new_name = self.local_name(name);
+                self.label(label.as_ref());
+                self.push("for(var");
+                self.push(&new_name);
+                self.push("in");
+                self.expression(iterable, ASSIGNMENT);
+                self.push(")");
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.renamed.insert(name.lexeme(), new_name);
+                }
+                self.statement(body);
+                self.scopes.pop();
+            }
+            Statement::Function(declaration) => {
+                // Registered before the body, which may call the function
+                let name = self.local_name(&declaration.name);
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.renamed.insert(declaration.name.lexeme(), name.clone());
+                }
+                self.push(declaration.keyword());
+                self.function(declaration, &name);
+            }
+            Statement::If(_, cond, then, els) => {
+                self.push("if(");
+                self.expression(cond, ASSIGNMENT);
+                self.push(")");
+                self.statement(then);
+                if let Some(els) = els {
+                    self.push("else");
+                    self.statement(els);
+                }
+            }
+            Statement::Print(_, exprs) => {
+                self.push("print");
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        self.push(",");
+                    }
+                    self.expression(expr, ASSIGNMENT);
+                }
+                self.push(";");
+            }
+            Statement::Return(_, value) => {
+                self.push("return");
+                if let Some(value) = value {
+                    self.expression(value, ASSIGNMENT);
+                }
+                self.push(";");
+            }
+            Statement::Var(name, annotation, init) => self.var("var", name, annotation, init.as_ref()),
+            Statement::Const(name, annotation, init) => self.var("const", name, annotation, Some(init)),
+            Statement::Destructure(pattern, init) => {
+                let names = self.declared_pattern(pattern);
+                self.push("var");
+                self.pattern(pattern, &names);
+                self.push("=");
+                self.expression(init, ASSIGNMENT);
+                self.push(";");
+                if let Some(scope) = self.scopes.last_mut() {
+                    for (name, new_name) in pattern.names().iter().zip(names) {
+                        scope.renamed.insert(name.lexeme(), new_name);
+                    }
+                }
+            }
+            Statement::Switch(_, subject, cases, default) => {
+                self.push("switch(");
+                self.expression(subject, ASSIGNMENT);
+                self.push("){");
+                for (values, body) in cases {
+                    self.push("case");
+                    for (i, value) in values.iter().enumerate() {
+                        if i > 0 {
+                            self.push(",");
+                        }
+                        self.expression(value, ASSIGNMENT);
+                    }
+                    self.push(":");
+                    self.branch(body);
+                }
+                if let Some(body) = default {
+                    self.push("default:");
+                    self.branch(body);
+                }
+                self.push("}");
+            }
+            Statement::Throw(_, value) => {
+                self.push("throw");
+                self.expression(value, ASSIGNMENT);
+                self.push(";");
+            }
+            Statement::Try(_, body, catches, finally) => {
+                self.push("try{");
+                self.branch(body);
+                self.push("}");
+                for (name, class, handler) in catches {
+                    // The class is looked up outside, the caught value is a local of the catch block
+                    let class = class.as_ref().map(|class| self.resolve(class));
+                    self.enter(calls_eval(handler));
+                    let new_name = self.local_name(name);
+                    self.push("catch(");
+                    self.push(&new_name);
+                    if let Some(class) = class {
+                        self.push(":");
+                        self.push(&class);
+                    }
+                    self.push("){");
+                    if let Some(scope) = self.scopes.last_mut() {
+                        scope.renamed.insert(name.lexeme(), new_name);
+                    }
+                    self.branch(handler);
+                    self.push("}");
+                    self.scopes.pop();
+                }
+                if let Some(finally) = finally {
+                    self.push("finally{");
+                    self.branch(finally);
+                    self.push("}");
+                }
+            }
+            Statement::While(keyword, ..) if keyword.token_type() == TokenType::For => self.for_statement(None, stmt),
+            Statement::While(_, cond, body, _, label) => {
+                self.label(label.as_ref());
+                self.push("while(");
+                self.expression(cond, ASSIGNMENT);
+                self.push(")");
+                self.statement(body);
+            }
+        }
+    }
+
+    /// Prints a `var` or `const` declaration
+    fn var(&mut self, keyword: &str, name: &Token, annotation: &Option<TypeAnnotation>, init: Option<&Expression>) {
+        // The initializer still sees the outer variable of the same
+        // name, so the new name only gets registered afterwards
+        let new_name = self.local_name(name);
+        self.push(keyword);
+        self.push(&new_name);
+        if let Some(annotation) = annotation {
+            self.push(":");
+            self.push(&annotation.to_string());
+        }
+        if let Some(init) = init {
+            self.push("=");
+            self.expression(init, ASSIGNMENT);
+        }
+        self.push(";");
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.renamed.insert(name.lexeme(), new_name);
+        }
+    }
+
+    /// Picks the names for the variables a pattern declares, like [Minifier::var]
+    /// does for one. The names of a map pattern are keys and stay the same.
+    fn declared_pattern(&mut self, pattern: &Pattern) -> Vec<String> {
+        if let Pattern::Map(_, names) = pattern {
+            self.kept.extend(names.iter().map(Token::lexeme));
+        }
+        pattern.names().iter().map(|name| self.local_name(name)).collect()
+    }
+
+    /// Prints a pattern with the names it assigns
+    fn pattern(&mut self, pattern: &Pattern, names: &[String]) {
+        let (open, close) = match pattern {
+            Pattern::List(..) => ("[", "]"),
+            Pattern::Map(..) => ("{", "}"),
+        };
+        self.push(open);
+        self.push(&names.join(","));
+        self.push(close);
+    }
+
+    /// Prints the label of a loop, labels are no variables and keep their names
+    fn label(&mut self, label: Option<&Token>) {
+        if let Some(label) = label {
+            self.push(&label.lexeme());
+            self.push(":");
+        }
+    }
+
+    /// Prints a `break` or `continue` with its label
+    fn jump(&mut self, keyword: &str, label: Option<&Token>) {
+        self.push(keyword);
+        if let Some(label) = label {
+            self.push(&label.lexeme());
+        }
+        self.push(";");
+    }
+
+    /// Prints the statements of a switch branch or a try block, which are a block of their own
+    fn branch(&mut self, stmts: &[Statement]) {
+        self.enter(calls_eval(stmts));
+        stmts.iter().for_each(|stmt| self.statement(stmt));
+        self.scopes.pop();
+    }
+
+    /// Prints a loop the parser desugared from `for`, the initializer (if
+    /// any) prints its own semicolon
+    fn for_statement(&mut self, init: Option<&Statement>, stmt: &Statement) {
+        let Statement::While(_, cond, body, increment, label) = stmt else {
+            return self.statement(stmt);
+        };
+        self.label(label.as_ref());
+        self.push("for(");
+        match init {
+            Some(init) => self.statement(init),
+            None => self.push(";"),
+        }
+        if !matches!(cond, Expression::Literal(Value::Bool(true))) {
+            self.expression(cond, ASSIGNMENT);
+        }
+        self.push(";");
+        if let Some(increment) = increment {
+            self.expression(increment, ASSIGNMENT);
+        }
+        self.push(")");
+        self.statement(body);
+    }
+
+    /// Prints a function or method from its name on
+    fn function(&mut self, declaration: &FunctionDeclaration, name: &str) {
+        self.push(name);
+        self.push("(");
+
+        // The parameters are locals of the body
+        self.enter(calls_eval(&declaration.body));
+        for (i, param) in declaration.params.iter().enumerate() {
+            if i > 0 {
+                self.push(",");
+            }
+            let new_name = self.local_name(&param.name);
+            self.push(&new_name);
+            if let Some(annotation) = &param.annotation {
+                self.push(":");
+                self.push(&annotation.to_string());
+            }
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.renamed.insert(param.name.lexeme(), new_name);
+            }
+        }
+        self.push(")");
+        if let Some(return_type) = &declaration.return_type {
+            self.push(":");
+            self.push(&return_type.to_string());
+        }
+        self.push("{");
+        for stmt in &declaration.body {
+            self.statement(stmt);
+        }
+        self.push("}");
+        self.scopes.pop();
+    }
+
+    /// Prints an expression. `min` is the lowest precedence the expression
+    /// may have without being wrapped in parentheses. Groupings from the
+    /// source are dropped and only re-added where precedence requires them.
+    fn expression(&mut self, expr: &Expression, min: u8) {
+        match expr {
+            Expression::Assign(name, value) => {
+                let parens = ASSIGNMENT < min;
+                self.open(parens);
+                let name = self.resolve(name);
+                self.push(&name);
+                self.push("=");
+                self.expression(value, ASSIGNMENT); // Right associative
+                self.close(parens);
+            }
+            Expression::Destructure(pattern, value) => {
+                let parens = ASSIGNMENT < min;
+                self.open(parens);
+                let names: Vec<String> = pattern.names().iter().map(|name| self.resolve(name)).collect();
+                if let Pattern::Map(_, keys) = pattern {
+                    for (key, name) in keys.iter().zip(&names) {
+                        if key.lexeme() != *name {
+                            self.kept.insert(key.lexeme());
+                        }
+                    }
+                }
+                self.pattern(pattern, &names);
+                self.push("=");
+                self.expression(value, ASSIGNMENT);
+                self.close(parens);
+            }
+            Expression::Binary(left, op, right) | Expression::Logical(left, op, right) => {
+                let precedence = binary_precedence(op);
+                let parens = precedence < min;
+                self.open(parens);
+                self.expression(left, precedence);
+                self.push(&op.lexeme());
+                self.expression(right, precedence + 1); // Left associative
+                self.close(parens);
+            }
+            Expression::Call(callee, _, args) => {
+                self.expression(callee, CALL);
+                self.push("(");
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        self.push(",");
+                    }
+                    self.expression(arg, ASSIGNMENT);
+                }
+                self.push(")");
+            }
+            Expression::Get(object, name) => {
+                self.expression(object, CALL);
+                self.push(".");
+                self.push(&name.lexeme()); // Property names are not variables and never get renamed
+            }
+            Expression::OptionalGet(object, name) => {
+                self.expression(object, CALL);
+                self.push("?.");
+                self.push(&name.lexeme());
+            }
+            Expression::Set(object, name, value) => {
+                let parens = ASSIGNMENT < min;
+                self.open(parens);
+                self.expression(object, CALL);
+                self.push(".");
+                self.push(&name.lexeme());
+                self.push("=");
+                self.expression(value, ASSIGNMENT);
+                self.close(parens);
+            }
+            Expression::Grouping(inner) => self.expression(inner, min),
+            Expression::Index(list, _, index) => {
+                self.expression(list, CALL);
+                self.push("[");
+                self.expression(index, ASSIGNMENT);
+                self.push("]");
+            }
+            Expression::IndexSet(list, _, index, value) => {
+                let parens = ASSIGNMENT < min;
+                self.open(parens);
+                self.expression(list, CALL);
+                self.push("[");
+                self.expression(index, ASSIGNMENT);
+                self.push("]=");
+                self.expression(value, ASSIGNMENT);
+                self.close(parens);
+            }
+            Expression::Lambda(declaration) => self.function(declaration, declaration.keyword()),
+            Expression::If(_, cond, then, els) => {
+                self.push("if(");
+                self.expression(cond, ASSIGNMENT);
+                self.push(")");
+                self.expression(then, ASSIGNMENT);
+                if let Some(els) = els {
+                    self.push("else");
+                    self.expression(els, ASSIGNMENT);
+                }
+            }
+            Expression::Block(_, stmts, value) => {
+                // The value belongs to the scope of the block
+                self.enter(calls_eval(stmts) || value.as_deref().is_some_and(expression_calls_eval));
+                self.push("{");
+                stmts.iter().for_each(|stmt| self.statement(stmt));
+                if let Some(value) = value {
+                    let parens = starts_statement(value);
+                    self.open(parens);
+                    self.expression(value, ASSIGNMENT);
+                    self.close(parens);
+                }
+                self.push("}");
+                self.scopes.pop();
+            }
+            Expression::Slice(object, _, start, end) => {
+                self.expression(object, CALL);
+                self.push("[");
+                if let Some(start) = start {
+                    self.expression(start, ASSIGNMENT);
+                }
+                self.push(":");
+                if let Some(end) = end {
+                    self.expression(end, ASSIGNMENT);
+                }
+                self.push("]");
+            }
+            Expression::List(_, items) => {
+                self.push("[");
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        self.push(",");
+                    }
+                    self.expression(item, ASSIGNMENT);
+                }
+                self.push("]");
+            }
+            Expression::Map(_, entries) => {
+                self.push("{");
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        self.push(",");
+                    }
+                    self.expression(key, ASSIGNMENT);
+                    self.push(":");
+                    self.expression(value, ASSIGNMENT);
+                }
+                self.push("}");
+            }
+            Expression::Literal(Value::String(s)) => self.push(&string_literal(s)),
+            Expression::Literal(Value::Number(n)) => self.push(&number_literal(n)),
+            Expression::Literal(val) => self.push(&val.to_string()),
+            Expression::Unary(op, right) => {
+                let parens = UNARY < min;
+                self.open(parens);
+                self.push(&op.lexeme());
+                self.expression(right, UNARY);
+                self.close(parens);
+            }
+            Expression::Super(_, method) => {
+                self.push("super.");
+                self.push(&method.lexeme());
+            }
+            Expression::This(_) => self.push("this"),
+            Expression::Variable(name) => {
+                let name = self.resolve(name);
+                self.push(&name);
+            }
+        }
+    }
+
+    /// Appends a piece of code, separated by a space only if the two
+    /// neighbouring characters would otherwise merge into one token
+    fn push(&mut self, text: &str) {
+        let merges = match (self.out.chars().last(), text.chars().next()) {
+            (Some(last), Some(first)) => is_word_char(last) && is_word_char(first),
+            _ => false,
+        };
+        if merges {
+            self.out.push(' ');
+        }
+        self.out.push_str(text);
+    }
+
+    fn open(&mut self, parens: bool) {
+        if parens {
+            self.push("(");
+        }
+    }
+
+    fn close(&mut self, parens: bool) {
+        if parens {
+            self.push(")");
+        }
+    }
+
+    /// Opens a block scope, its short names continue after the ones of the enclosing scope
+    fn enter(&mut self, keep_names: bool) {
+        let next_index = self.scopes.last().map_or(0, |scope| scope.next_index);
+        self.scopes.push(Scope { renamed: HashMap::new(), keep_names, next_index });
+    }
+
+    /// Looks up the name a variable reference has to be printed with
+    fn resolve(&self, name: &Token) -> String {
+        let lexeme = name.lexeme();
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.renamed.get(&lexeme).cloned())
+            .unwrap_or(lexeme)
+    }
+
+    /// Picks the name for a variable declared in the current scope. Globals
+    /// and re-declarations inside the same scope keep their existing name.
+    fn local_name(&mut self, name: &Token) -> String {
+        let lexeme = name.lexeme();
+        let reserved = &self.reserved;
+        match self.scopes.last_mut() {
+            Some(scope) if self.rename_locals && !scope.keep_names && !self.kept.contains(&lexeme) => {
+                if let Some(existing) = scope.renamed.get(&lexeme) {
+                    return existing.clone();
+                }
+                loop {
+                    let candidate = short_name(scope.next_index);
+                    scope.next_index += 1;
+                    if !reserved.contains(&candidate) && match_keyword(&candidate).is_none() {
+                        return candidate;
+                    }
+                }
+            }
+            _ => lexeme,
+        }
+    }
+}
+
+/// Precedence of binary and logical operators
+fn binary_precedence(op: &Token) -> u8 {
+    match op.token_type() {
+        TokenType::QuestionQuestion => COALESCE,
+        TokenType::Or => OR,
+        TokenType::And => AND,
+        TokenType::BangEqual | TokenType::EqualEqual => EQUALITY,
+        TokenType::Minus | TokenType::Plus => TERM,
+        TokenType::Slash | TokenType::Star | TokenType::TildeSlash => FACTOR,
+        _ => COMPARISON,
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Maps 0, 1, 2, ... to "a", "b", ..., "Z", "aa", "ab", ...
+fn short_name(mut index: usize) -> String {
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let mut name = Vec::new();
+    loop {
+        name.push(ALPHABET[index % ALPHABET.len()]);
+        index /= ALPHABET.len();
+        if index == 0 {
+            break;
+        }
+        index -= 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// Collects every identifier that appears in a statement
+fn collect_identifiers(stmt: &Statement, names: &mut HashSet<String>) {
+    match stmt {
+        Statement::Block(stmts) => stmts.iter().for_each(|s| collect_identifiers(s, names)),
+        Statement::Class(name, superclass, mixins, methods, setters) => {
+            names.insert(name.lexeme());
+            names.extend(superclass.iter().chain(mixins).map(Token::lexeme));
+            methods.iter().chain(setters).for_each(|method| collect_function_identifiers(method, names));
+        }
+        Statement::Expression(expr) => collect_expression_identifiers(expr, names),
+        Statement::Function(declaration) => {
+            names.insert(declaration.name.lexeme());
+            collect_function_identifiers(declaration, names);
+        }
+        Statement::Return(_, value) => value.iter().for_each(|e| collect_expression_identifiers(e, names)),
+        Statement::Print(_, exprs) => exprs.iter().for_each(|e| collect_expression_identifiers(e, names)),
+        Statement::If(_, cond, then, els) => {
+            collect_expression_identifiers(cond, names);
+            collect_identifiers(then, names);
+            if let Some(els) = els {
+                collect_identifiers(els, names);
+            }
+        }
+        Statement::Var(name, _, init) => {
+            names.insert(name.lexeme());
+            if let Some(init) = init {
+                collect_expression_identifiers(init, names);
+            }
+        }
+        Statement::Const(name, _, init) => {
+            names.insert(name.lexeme());
+            collect_expression_identifiers(init, names);
+        }
+        Statement::Destructure(pattern, init) => {
+            names.extend(pattern.names().iter().map(Token::lexeme));
+            collect_expression_identifiers(init, names);
+        }
+        Statement::While(_, cond, body, increment, _) => {
+            collect_expression_identifiers(cond, names);
+            collect_identifiers(body, names);
+            increment.iter().for_each(|e| collect_expression_identifiers(e, names));
+        }
+        Statement::ForIn(_, name, iterable, body, _) => {
+            names.insert(name.lexeme());
+            collect_expression_identifiers(iterable, names);
+            collect_identifiers(body, names);
+        }
+        Statement::Switch(_, subject, cases, default) => {
+            collect_expression_identifiers(subject, names);
+            for (values, body) in cases {
+                values.iter().for_each(|value| collect_expression_identifiers(value, names));
+                body.iter().for_each(|s| collect_identifiers(s, names));
+            }
+            default.iter().flatten().for_each(|s| collect_identifiers(s, names));
+        }
+        Statement::Throw(_, value) => collect_expression_identifiers(value, names),
+        Statement::Try(_, body, catches, finally) => {
+            body.iter().for_each(|s| collect_identifiers(s, names));
+            for (name, class, handler) in catches {
+                names.insert(name.lexeme());
+                names.extend(class.iter().map(Token::lexeme));
+                handler.iter().for_each(|s| collect_identifiers(s, names));
+            }
+            finally.iter().flatten().for_each(|s| collect_identifiers(s, names));
+        }
+        Statement::Break(..) | Statement::Continue(..) => {}
+    }
+}
+
+/// Whether the statements, or functions declared in them, use `eval`
+fn calls_eval(stmts: &[Statement]) -> bool {
+    let mut names = HashSet::new();
+    stmts.iter().for_each(|stmt| collect_identifiers(stmt, &mut names));
+    names.contains("eval")
+}
+
+/// Whether the expression uses `eval`, like [calls_eval]
+fn expression_calls_eval(expr: &Expression) -> bool {
+    let mut names = HashSet::new();
+    collect_expression_identifiers(expr, &mut names);
+    names.contains("eval")
+}
+
+/// Whether the expression would be read as the start of another statement
+/// if it started one. A brace would open a block and an `if` would be a
+/// statement, so they keep their parentheses there.
+fn starts_statement(expr: &Expression) -> bool {
+    first_expression_token(expr).is_some_and(|token| matches!(token.token_type(), TokenType::LeftBrace | TokenType::If))
+}
+
+/// Collects the parameters and the identifiers of the body, but not the name
+fn collect_function_identifiers(declaration: &FunctionDeclaration, names: &mut HashSet<String>) {
+    names.extend(declaration.params.iter().map(|param| param.name.lexeme()));
+    declaration.body.iter().for_each(|s| collect_identifiers(s, names));
+}
+
+fn collect_expression_identifiers(expr: &Expression, names: &mut HashSet<String>) {
+    match expr {
+        Expression::Assign(name, value) => {
+            names.insert(name.lexeme());
+            collect_expression_identifiers(value, names);
+        }
+        Expression::Destructure(pattern, value) => {
+            names.extend(pattern.names().iter().map(Token::lexeme));
+            collect_expression_identifiers(value, names);
+        }
+        Expression::Binary(left, _, right) | Expression::Logical(left, _, right) => {
+            collect_expression_identifiers(left, names);
+            collect_expression_identifiers(right, names);
+        }
+        Expression::Call(callee, _, args) => {
+            collect_expression_identifiers(callee, names);
+            args.iter().for_each(|arg| collect_expression_identifiers(arg, names));
+        }
+        Expression::Set(object, _, value) => {
+            collect_expression_identifiers(object, names);
+            collect_expression_identifiers(value, names);
+        }
+        Expression::Index(list, _, index) => {
+            collect_expression_identifiers(list, names);
+            collect_expression_identifiers(index, names);
+        }
+        Expression::IndexSet(list, _, index, value) => {
+            collect_expression_identifiers(list, names);
+            collect_expression_identifiers(index, names);
+            collect_expression_identifiers(value, names);
+        }
+        Expression::Lambda(declaration) => collect_function_identifiers(declaration, names),
+        Expression::If(_, cond, then, els) => {
+            collect_expression_identifiers(cond, names);
+            collect_expression_identifiers(then, names);
+            els.iter().for_each(|els| collect_expression_identifiers(els, names));
+        }
+        Expression::Block(_, stmts, value) => {
+            stmts.iter().for_each(|s| collect_identifiers(s, names));
+            value.iter().for_each(|value| collect_expression_identifiers(value, names));
+        }
+        Expression::Slice(object, _, start, end) => {
+            collect_expression_identifiers(object, names);
+            [start, end].into_iter().flatten().for_each(|bound| collect_expression_identifiers(bound, names));
+        }
+        Expression::List(_, items) => items.iter().for_each(|item| collect_expression_identifiers(item, names)),
+        Expression::Map(_, entries) => {
+            for (key, value) in entries {
+                collect_expression_identifiers(key, names);
+                collect_expression_identifiers(value, names);
+            }
+        }
+        Expression::Get(inner, _)
+        | Expression::OptionalGet(inner, _)
+        | Expression::Grouping(inner)
+        | Expression::Unary(_, inner) => {
+            collect_expression_identifiers(inner, names)
+        }
+        Expression::Literal(_) | Expression::Super(..) | Expression::This(_) => {}
+        Expression::Variable(name) => {
+            names.insert(name.lexeme());
+        }
+    }
+}
+
+// ---------- Tests for the minifier ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jlox::diagnostic::CollectingReporter;
+    use jlox::{parser, scanner};
+
+    fn minify_source(source: &str, rename_locals: bool) -> String {
+        let mut reporter = CollectingReporter::default();
+        let tokens = scanner::scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+        let statements = parser::parse(tokens, &mut reporter).expect("Parsing failed!");
+        minify(&statements, rename_locals)
+    }
+
+    #[test]
+    fn keeps_only_required_parentheses() {
+        let minified = minify_source("print ((1 + 2)) * (3 * 4) - (5 - 6);", false);
+        assert_eq!(minified, "print(1+2)*(3*4)-(5-6);");
+    }
+
+    #[test]
+    fn renames_locals_but_not_globals() {
+        let minified = minify_source("var a: Number = 1; { var long = a; { var longer = long; } }", true);
+        assert_eq!(minified, "var a:Number=1;{var b=a;{var c=b;}}");
+    }
+
+    #[test]
+    fn renames_parameters_and_local_functions() {
+        let minified = minify_source("fun add(left, right: Number) { fun twice(value) { return value * 2; } return twice(left) + right; }", true);
+        assert_eq!(minified, "fun add(a,b:Number){fun c(d){return d*2;}return c(a)+b;}");
+    }
+
+    #[test]
+    fn prints_for_loops_as_written() {
+        let minified = minify_source("for (var index = 0; index < 3; index = index + 1) { if (index == 1) continue; print index; }", true);
+        assert_eq!(minified, "for(var a=0;a<3;a=a+1){if(a==1)continue;print a;}");
+    }
+
+    #[test]
+    fn keeps_method_and_field_names() {
+        let minified = minify_source("{ class Point { move(point, x) { point.x = x; } } var p = Point(); p.move(p, 1); }", true);
+        assert_eq!(minified, "{class a{move(b,c){b.x=c;}}var b=a();b.move(b,1);}");
+    }
+
+    #[test]
+    fn prints_lists_and_indices() {
+        let minified = minify_source("{ var items = [1, [2, 3]]; items[1][0] = items[0]; print [ ]; }", true);
+        assert_eq!(minified, "{var a=[1,[2,3]];a[1][0]=a[0];print[];}");
+    }
+
+    #[test]
+    fn keeps_maps_apart_from_blocks() {
+        let minified = minify_source("var key = \"k\"; ({key: 1, \"b\": [2]})[key]; print { };", true);
+        assert_eq!(minified, "var key=\"k\";({key:1,\"b\":[2]}[key]);print{};");
+    }
+
+    #[test]
+    fn keeps_local_names_where_eval_can_see_them() {
+        let minified = minify_source("fun local(long) { var y = 5; eval(\"y = y + long;\"); return y; } fun other(long) { return long; }", true);
+        assert_eq!(minified, "fun local(long){var y=5;eval(\"y = y + long;\");return y;}fun other(a){return a;}");
+    }
+
+    #[test]
+    fn short_names_skip_to_two_letters() {
+        assert_eq!(short_name(0), "a");
+        assert_eq!(short_name(51), "Z");
+        assert_eq!(short_name(52), "aa");
+    }
+}
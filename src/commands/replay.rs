@@ -0,0 +1,105 @@
+// External dependencies
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+// Internal dependencies
+use super::trace::{self, Event};
+
+/// Shown for `h` and unknown commands
+const HELP: &str = "\
+commands: n (or enter) next, b back, g <n> go to event n, v variables, q quit";
+
+/// Steps through a recorded trace. Commands are read from stdin, every
+/// step prints the event at the new position.
+pub fn run(path: &str) -> Result<()> {
+    let events = trace::load(path)?;
+    if events.is_empty() {
+        println!("the trace is empty");
+        return Ok(());
+    }
+    println!("{} events, {}", events.len(), HELP);
+
+    let mut position = 0;
+    print_event(&events, position);
+    loop {
+        print!("replay> ");
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        let (command, argument) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        match command {
+            "" | "n" if position + 1 < events.len() => position += 1,
+            "" | "n" => {
+                println!("at the end of the trace");
+                continue;
+            }
+            "b" if position > 0 => position -= 1,
+            "b" => {
+                println!("at the start of the trace");
+                continue;
+            }
+            "g" => match argument.parse::<usize>() {
+                Ok(target) if (1..=events.len()).contains(&target) => position = target - 1,
+                _ => {
+                    println!("g expects an event number from 1 to {}", events.len());
+                    continue;
+                }
+            },
+            "v" => {
+                for (name, value) in variables_at(&events, position) {
+                    println!("  {} = {}", name, value);
+                }
+                continue;
+            }
+            "q" => return Ok(()),
+            _ => {
+                println!("{}", HELP);
+                continue;
+            }
+        }
+        print_event(&events, position);
+    }
+}
+
+fn print_event(events: &[Event], position: usize) {
+    let text = match &events[position] {
+        Event::Statement(stmt) => format!("run    {}", stmt),
+        Event::Write { name, value } => format!("write  {} = {}", name, value),
+        Event::Print(text) => format!("print  {}", text),
+        Event::Error(message) => format!("error  {}", message),
+    };
+    println!("[{}/{}] {}", position + 1, events.len(), text);
+}
+
+/// The last value written to every variable up to and including the
+/// event at `position`. Scopes are not recorded, so a local variable
+/// hides a global one of the same name from then on.
+fn variables_at(events: &[Event], position: usize) -> BTreeMap<&str, &str> {
+    let mut variables = BTreeMap::new();
+    for event in &events[..=position] {
+        if let Event::Write { name, value } = event {
+            variables.insert(name.as_str(), value.as_str());
+        }
+    }
+    variables
+}
+
+// ---------- Tests for the replay module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variables_follow_the_position() {
+        let write = |name: &str, value: &str| Event::Write { name: name.to_string(), value: value.to_string() };
+        let events = vec![write("a", "1.0"), Event::Print("1".to_string()), write("a", "2.0"), write("b", "nil")];
+
+        assert_eq!(variables_at(&events, 1), BTreeMap::from([("a", "1.0")]));
+        assert_eq!(variables_at(&events, 3), BTreeMap::from([("a", "2.0"), ("b", "nil")]));
+    }
+}
@@ -0,0 +1,125 @@
+// External dependencies
+use anyhow::Result;
+
+// Internal dependencies
+use crate::reporter::StderrReporter;
+use jlox::diagnostic::Origin;
+use jlox::errors::TemplateError;
+use jlox::interpreter::Interpreter;
+use jlox::obj::value::Value;
+
+/// Opens an island of Lox statements, `<%=` one of an expression
+const OPEN: &str = "<%";
+
+/// Closes an island
+const CLOSE: &str = "%>";
+
+/// A template turned into a Lox program. Every piece of literal text is
+/// a global named `templateText<N>` that the program passes to `write`.
+#[derive(Debug, PartialEq)]
+struct Compiled {
+    program: String,
+    texts: Vec<String>,
+}
+
+/// Renders the template at `path` to stdout. The template is text with
+/// islands of Lox: `<% statements %>` run, `<%= expression %>` print the
+/// value of the expression. The islands form one program, so a loop can
+/// span several of them:
+///
+/// ```text
+/// <% for (var i = 1; i <= 3; i = i + 1) { %>
+/// item <%= i %>
+/// <% } %>
+/// ```
+///
+/// A line break right after a statement island is left out, so lines
+/// with only statements on them don't end up as empty lines.
+pub fn run(path: &str, mut interpreter: Interpreter) -> Result<()> {
+    let compiled = compile(&std::fs::read_to_string(path)?, path)?;
+    for (index, text) in compiled.texts.into_iter().enumerate() {
+        interpreter.define_global(&text_name(index), Value::String(text));
+    }
+
+    // The program has its code on the lines of the template, so diagnostics point into it
+    let mut reporter = StderrReporter::new();
+    let result = interpreter.run_at(compiled.program, &Origin::new(path, 1, 1), &mut reporter);
+    let denied = reporter.finish();
+    result?;
+    denied?;
+    Ok(())
+}
+
+/// Turns the template into a program. Every island is copied to the
+/// line it is on in the template, texts are replaced by a `write` call
+/// followed by as many line breaks as they contain.
+fn compile(template: &str, path: &str) -> Result<Compiled> {
+    let mut compiled = Compiled { program: String::new(), texts: Vec::new() };
+    let mut rest = template;
+    let mut line = 1;
+
+    while let Some(open) = rest.find(OPEN) {
+        compiled.text(&rest[..open]);
+        line += rest[..open].matches('\n').count();
+
+        let island = &rest[open + OPEN.len()..];
+        let close = island.find(CLOSE).ok_or_else(|| TemplateError::UnclosedIsland(path.to_string(), line))?;
+        let code = &island[..close];
+        line += code.matches('\n').count();
+        rest = &island[close + CLOSE.len()..];
+
+        match code.strip_prefix('=') {
+            Some(expression) => compiled.program.push_str(&format!("write({});", expression)),
+            None => {
+                compiled.program.push_str(code);
+                compiled.program.push(' ');
+                if let Some(after) = rest.strip_prefix("\r\n").or_else(|| rest.strip_prefix('\n')) {
+                    compiled.program.push('\n');
+                    line += 1;
+                    rest = after;
+                }
+            }
+        }
+    }
+    compiled.text(rest);
+    Ok(compiled)
+}
+
+impl Compiled {
+    fn text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.program.push_str(&format!("write({});", text_name(self.texts.len())));
+        self.program.push_str(&"\n".repeat(text.matches('\n').count()));
+        self.texts.push(text.to_string());
+    }
+}
+
+fn text_name(index: usize) -> String {
+    format!("templateText{}", index)
+}
+
+// ---------- Tests for the template module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compiles_islands_onto_their_lines() {
+        let template = "<h1><%= title %></h1>\n<% if (true) { %>\nyes\n<% } %>\n";
+        let compiled = compile(template, "page.tmpl").expect("Compiling failed!");
+        assert_eq!(
+            compiled.program,
+            "write(templateText0);write( title );write(templateText1);\n if (true) {  \nwrite(templateText2);\n }  \n"
+        );
+        assert_eq!(compiled.texts, ["<h1>", "</h1>\n", "yes\n"]);
+    }
+
+    #[test]
+    fn unclosed_islands_are_errors() {
+        let error = compile("line 1\n<%= 1 +\n2", "page.tmpl").expect_err("Compiling succeeded!");
+        assert!(matches!(error.downcast_ref(), Some(TemplateError::UnclosedIsland(_, 2))));
+    }
+}
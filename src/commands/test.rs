@@ -0,0 +1,102 @@
+// External dependencies
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Internal dependencies
+use super::common::collect_scripts;
+use jlox::errors::TestError;
+use crate::style;
+
+/// File extension of the committed golden output next to every test case
+const EXPECTED_EXTENSION: &str = "expected";
+
+/// Header that separates stdout from the diagnostics in the captured output
+const STDERR_HEADER: &str = "--- stderr ---\n";
+
+/// Runs every `.lox` script below `dir` in its own jlox process and compares
+/// what it printed against the `.expected` file next to it. With `bless`,
+/// the `.expected` files get (re)written from the actual output instead.
+pub fn run(dir: &str, bless: bool) -> Result<()> {
+    let mut cases: Vec<PathBuf> = Vec::new();
+    collect_scripts(Path::new(dir), &mut cases)?;
+    cases.sort();
+
+    if cases.is_empty() {
+        return Err(TestError::NoCases(dir.to_string()).into());
+    }
+
+    let mut failed = 0;
+    for case in &cases {
+        let actual = capture_output(case)?;
+        let expected_path = case.with_extension(EXPECTED_EXTENSION);
+
+        if bless {
+            std::fs::write(&expected_path, &actual)?;
+            println!("blessed {}", case.display());
+            continue;
+        }
+
+        match std::fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => println!("{}      {}", style::paint("ok", style::theme().success), case.display()),
+            Ok(expected) => {
+                failed += 1;
+                println!("{}  {}", style::paint("FAILED", style::theme().error), case.display());
+                print_mismatch(&expected, &actual);
+            }
+            Err(_) => {
+                failed += 1;
+                println!("{}  {} (no {} file, run with --bless)", style::paint("FAILED", style::theme().error), case.display(), EXPECTED_EXTENSION);
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(TestError::Failed(failed, cases.len()).into());
+    }
+
+    if bless {
+        println!("\n{} expected files written", cases.len());
+    } else {
+        println!("\n{} test cases passed", cases.len());
+    }
+    Ok(())
+}
+
+/// Runs a single script with the current jlox executable and returns its
+/// stdout, followed by its stderr (if there was any) below a separator line.
+fn capture_output(case: &Path) -> Result<String> {
+    let output = Command::new(std::env::current_exe()?)
+        .arg(case)
+        .env_remove("RUST_BACKTRACE") // Backtraces would make the output machine-dependent
+        .env_remove("RUST_LIB_BACKTRACE")
+        .env("JLOX_CONFIG", "") // The expected output uses the default theme, without colors
+        .arg("--color=never")
+        .output()?;
+
+    let mut captured = String::from_utf8_lossy(&output.stdout).into_owned();
+    if !output.stderr.is_empty() {
+        captured.push_str(STDERR_HEADER);
+        captured.push_str(&String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(captured)
+}
+
+/// Prints the first line where expected and actual output differ
+fn print_mismatch(expected: &str, actual: &str) {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(e), Some(a)) if e == a => line += 1,
+            (None, None) => break, // Only trailing newlines differ
+            (e, a) => {
+                println!("        line {}:", line);
+                println!("        expected: {}", e.unwrap_or("<end of output>"));
+                println!("        actual:   {}", a.unwrap_or("<end of output>"));
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// External dependencies
+use anyhow::Result;
+
+// Internal dependencies
+use jlox::errors::{LoxError, TraceError};
+use jlox::interpreter::ExecutionHooks;
+use jlox::obj::statement::Statement;
+use jlox::obj::value::Value;
+use jlox::shared::Shared;
+
+/// First line of every trace file
+const HEADER: &str = "jlox-trace 1";
+
+/// One thing that happened while a script ran
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// A statement started executing, in its syntax tree form
+    Statement(String),
+    /// A variable was defined or assigned, with the text form of the value
+    Write { name: String, value: String },
+    /// A print statement wrote a line
+    Print(String),
+    /// The run stopped with an error
+    Error(String),
+}
+
+impl Event {
+    /// One line of the trace file: a tag, a space and the payload. Free text
+    /// is written as an escaped string literal, so it never spans lines.
+    fn encode(&self) -> String {
+        let quoted = |text: &str| Value::String(text.to_string()).to_text();
+        match self {
+            Event::Statement(stmt) => format!("S {}", quoted(stmt)),
+            Event::Write { name, value } => format!("W {} {}", name, value),
+            Event::Print(text) => format!("P {}", quoted(text)),
+            Event::Error(message) => format!("E {}", quoted(message)),
+        }
+    }
+
+    fn decode(line: &str, number: usize) -> Result<Event> {
+        let invalid = || TraceError::InvalidLine(number);
+        let unquoted = |text: &str| match Value::from_text(text) {
+            Ok(Value::String(s)) => Ok(s),
+            _ => Err(invalid()),
+        };
+
+        let (tag, payload) = line.split_once(' ').ok_or_else(invalid)?;
+        let event = match tag {
+            "S" => Event::Statement(unquoted(payload)?),
+            "W" => {
+                let (name, value) = payload.split_once(' ').ok_or_else(invalid)?;
+                Event::Write { name: name.to_string(), value: value.to_string() }
+            }
+            "P" => Event::Print(unquoted(payload)?),
+            "E" => Event::Error(unquoted(payload)?),
+            _ => return Err(invalid().into()),
+        };
+        Ok(event)
+    }
+}
+
+/// Hooks that append everything the interpreter does to a shared event list
+pub struct TraceRecorder(pub Shared<Vec<Event>>);
+
+impl ExecutionHooks for TraceRecorder {
+    fn on_statement_enter(&mut self, stmt: &Statement) {
+        self.0.borrow_mut().push(Event::Statement(stmt.to_string()));
+    }
+
+    fn on_variable_write(&mut self, name: &str, value: &Value) {
+        self.0.borrow_mut().push(Event::Write { name: name.to_string(), value: value.to_text() });
+    }
+
+    fn on_print(&mut self, text: &str) {
+        self.0.borrow_mut().push(Event::Print(text.to_string()));
+    }
+
+    fn on_runtime_error(&mut self, error: &LoxError) {
+        self.0.borrow_mut().push(Event::Error(error.to_string()));
+    }
+}
+
+/// Writes the events into a trace file
+pub fn save(path: &str, events: &[Event]) -> Result<()> {
+    let mut text = format!("{}\n", HEADER);
+    for event in events {
+        text.push_str(&event.encode());
+        text.push('\n');
+    }
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+/// Reads the events of a trace file
+pub fn load(path: &str) -> Result<Vec<Event>> {
+    let text = std::fs::read_to_string(path)?;
+    let mut lines = text.lines();
+    if lines.next() != Some(HEADER) {
+        return Err(TraceError::NotATrace(path.to_string()).into());
+    }
+    lines
+        .enumerate()
+        .map(|(index, line)| Event::decode(line, index + 2)) // Line numbers start at 1, after the header
+        .collect()
+}
+
+// ---------- Tests for the trace module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_round_trip() {
+        let events = [
+            Event::Statement("(print \"a\nb\")".to_string()),
+            Event::Write { name: "a".to_string(), value: "\"x y\"".to_string() },
+            Event::Print("a\nb".to_string()),
+            Event::Error("Runtime Error [R0301]: Undefined variable b on line 2".to_string()),
+        ];
+        for (index, event) in events.iter().enumerate() {
+            let line = event.encode();
+            assert!(!line.contains('\n'));
+            assert_eq!(&Event::decode(&line, index).expect("Decoding failed!"), event);
+        }
+        assert!(Event::decode("X 1", 1).is_err());
+    }
+}
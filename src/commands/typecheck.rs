@@ -0,0 +1,19 @@
+// External dependencies
+use anyhow::Result;
+
+// Internal dependencies
+use super::common::parse_file;
+use crate::reporter::StderrReporter;
+use jlox::typechecker;
+
+/// Checks the script against its type annotations without running it.
+/// Type errors are printed like parse errors, nothing is printed if there are none.
+pub fn run(script: &str) -> Result<()> {
+    let statements = parse_file(script)?;
+    let mut reporter = StderrReporter::new();
+    let result = typechecker::check(&statements, &mut reporter);
+    let denied = reporter.finish();
+    result?;
+    denied?;
+    Ok(())
+}
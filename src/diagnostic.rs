@@ -0,0 +1,127 @@
+// External dependencies
+use std::fmt::Display;
+
+// Internal dependencies
+use crate::errors::LoxError;
+use crate::obj::token::Span;
+
+/// How severe a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single message produced by one of the phases (scanning, parsing, ...).
+/// Phases don't print anything themselves, they hand their diagnostics to
+/// a [Reporter] that decides where they end up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// Source line the diagnostic refers to, if it refers to one
+    pub line: Option<u32>,
+    /// Exact position in the source, if the phase knows it
+    pub span: Option<Span>,
+    /// File the source came from, if it was run with an [Origin] naming one
+    pub file: Option<String>,
+}
+
+/// Where a piece of source code starts in the file it was taken from, for
+/// hosts that embed Lox snippets in larger documents. Lines and columns
+/// of the snippet are shifted accordingly, so diagnostics point into the
+/// outer file. The column only shifts the first line of the snippet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Origin {
+    pub file: Option<String>,
+    /// Line of the first char of the snippet, starting at 1
+    pub line: u32,
+    /// Column of the first char of the snippet, counted in chars from 1
+    pub column: u32,
+}
+
+impl Origin {
+    /// The snippet starts at `line` and `column` of `file`
+    pub fn new(file: impl Into<String>, line: u32, column: u32) -> Self {
+        Self { file: Some(file.into()), line, column }
+    }
+}
+
+impl Default for Origin {
+    /// The start of a source that is not embedded anywhere
+    fn default() -> Self {
+        Self { file: None, line: 1, column: 1 }
+    }
+}
+
+impl Diagnostic {
+    /// Creates an error diagnostic from any of the phase errors
+    pub fn error(error: impl Into<LoxError>) -> Self {
+        let error: LoxError = error.into();
+        Self {
+            severity: Severity::Error,
+            message: error.to_string(),
+            line: error.line(),
+            span: None,
+            file: None,
+        }
+    }
+
+    /// Creates a warning diagnostic
+    pub fn warning(message: String, line: Option<u32>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+            line,
+            span: None,
+            file: None,
+        }
+    }
+
+    /// Attaches the exact position of what the diagnostic is about
+    pub fn with_span(mut self, span: Option<Span>) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+/// The message, prefixed with `file:line:` when the source came from a file
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{}: {}", file, line, self.message),
+            (Some(file), None) => write!(f, "{}: {}", file, self.message),
+            (None, _) => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Receives the diagnostics of every phase. The CLI installs a reporter
+/// that prints to stderr, embedders and tests install their own.
+pub trait Reporter {
+    fn report(&mut self, diagnostic: Diagnostic);
+}
+
+/// Reporter that simply keeps every diagnostic it receives
+#[derive(Debug, Default)]
+pub struct CollectingReporter {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl CollectingReporter {
+    /// All diagnostics reported so far, in order
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Consumes the reporter and returns the collected diagnostics
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+}
+
+impl Reporter for CollectingReporter {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+}
@@ -1,80 +1,945 @@
 // External dependencies
+use std::time::Duration;
 use thiserror::Error;
 
+/// Result type of every fallible function in the library
+pub type Result<T> = std::result::Result<T, LoxError>;
+
+/// The error type of the library. It tells which phase failed, and the
+/// wrapped phase error carries the position it refers to (see [LoxError::line]).
+#[derive(Debug, Error)]
+pub enum LoxError {
+    #[error(transparent)]
+    Scan(#[from] ScanError),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    Type(#[from] TypeError),
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+    #[error(transparent)]
+    Chunk(#[from] ChunkError),
+    #[error(transparent)]
+    Value(#[from] ValueError),
+}
+
+impl LoxError {
+    /// The stable code of the error (like `E0203`), if the phase has codes
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            LoxError::Scan(e) => Some(e.code()),
+            LoxError::Parse(e) => Some(e.code()),
+            LoxError::Type(e) => Some(e.code()),
+            LoxError::Runtime(e) => Some(e.code()),
+            LoxError::Chunk(_) | LoxError::Value(_) => None,
+        }
+    }
+
+    /// The source line the error refers to, if it refers to one
+    pub fn line(&self) -> Option<u32> {
+        match self {
+            LoxError::Scan(e) => e.line(),
+            LoxError::Parse(e) => e.line(),
+            LoxError::Type(e) => e.line(),
+            LoxError::Runtime(e) => e.line(),
+            LoxError::Chunk(_) | LoxError::Value(_) => None,
+        }
+    }
+
+    /// The exit code the script asked for with `exit()`, if this is how it
+    /// ended. It is no failure, hosts report the code instead of the error.
+    pub fn exit_code(&self) -> Option<u8> {
+        match self {
+            LoxError::Runtime(RuntimeError::Exit(code)) => Some(*code),
+            _ => None,
+        }
+    }
+}
+
+/// How jlox can be invoked, shown when the arguments are invalid
+const USAGE: &str = "\
+    jlox [-v | -vv | -q] [--max-errors N] [--deny-warnings] [--color always|auto|never] [--typed] [--no-prelude] [--allow|--deny fs,net,process,env,time,ffi] [script path]
+    jlox run [--record <trace>] <script or chunk>
+    jlox replay <trace>
+    jlox test [--bless] [directory]
+    jlox bench [--runs N] [--baseline <old.json>] [--threshold PCT] [-o <results.json>] [directory]
+    jlox diff <old> <new>
+    jlox minify [--rename-locals] <script>
+    jlox typecheck <script>
+    jlox install [directory]
+    jlox doc [--html] [-o <output>] <directory>
+    jlox fmt [--write] [--config key=value]... <script>
+    jlox template <template>
+    jlox kernel <connection file> | install    (with the kernel feature)
+    jlox compile <script> [-o <output>]
+    jlox --explain <error code>";
+
 /// This Error type can be used whenever there are Errors
 /// regarding command line arguments.
 #[derive(Debug, Error)]
 pub enum ArgumentError {
-    #[error("Argument Error: Invalid Arguments. Usage: jlox [script path]")]
+    #[error("Argument Error: Invalid Arguments. Usage:\n{}", USAGE)]
     InvalidArgs,
-    #[error("Argument Error: Cannot access command line arguments!")]
-    ArgAccessError,
+    #[error("Argument Error: Unknown flag {0}")]
+    /// 0: the flag as it was passed
+    UnknownFlag(String),
+    #[error("Argument Error: -q cannot be combined with -v or -vv")]
+    ConflictingVerbosity,
+    #[error("Argument Error: {0} expects a value")]
+    /// 0: the flag
+    MissingValue(String),
+    #[error("Argument Error: Invalid value {1} for {0}")]
+    /// 0: the flag, 1: the value as it was passed
+    InvalidValue(String, String),
+    #[error("Argument Error: {0} can only be used with the {1} command")]
+    /// 0: the flag, 1: the command it belongs to
+    FlagWithoutCommand(String, &'static str),
+    #[error("Argument Error: Unknown error code {0}")]
+    /// 0: the code as it was passed
+    UnknownErrorCode(String),
+}
+
+/// This Error type is used when the diagnostics of the front end
+/// make the run fail, even though no phase failed.
+#[derive(Debug, Error)]
+pub enum DiagnosticError {
+    #[error("Diagnostic Error: {0} warning(s) denied by --deny-warnings. Aborted!")]
+    /// 0: number of warnings
+    DeniedWarnings(usize),
+}
+
+/// This Error type is used when a trace recorded with `--record`
+/// cannot be read by `jlox replay`.
+#[derive(Debug, Error)]
+pub enum TraceError {
+    #[error("Trace Error: {0} is not a jlox trace")]
+    /// 0: path of the file
+    NotATrace(String),
+    #[error("Trace Error: Invalid event on line {0}")]
+    /// 0: line number in the trace file
+    InvalidLine(usize),
+}
+
+/// This Error type is used by `jlox fmt` when its configuration is
+/// invalid or the script cannot be formatted without losing anything.
+#[derive(Debug, Error)]
+pub enum FmtError {
+    #[error("Fmt Error: Invalid setting \"{2}\" on line {1} of {0}")]
+    /// 0: path of the config file, 1: line number, 2: the line
+    InvalidConfigLine(String, usize, String),
+    #[error("Fmt Error: Invalid setting \"{0}\" for --config")]
+    /// 0: the setting as it was passed
+    InvalidSetting(String),
+}
+
+/// This Error type is used by `jlox doc` when there is nothing to document.
+#[derive(Debug, Error)]
+pub enum DocError {
+    #[error("Doc Error: No scripts found in {0}")]
+    /// 0: the directory
+    NoScripts(String),
+}
+
+/// This Error type is used by `jlox bench` when there is nothing to
+/// measure, the baseline cannot be read or a script got slower.
+#[derive(Debug, Error)]
+pub enum BenchError {
+    #[error("Bench Error: No scripts found in {0}")]
+    /// 0: the directory
+    NoScripts(String),
+    #[error("Bench Error: {0} is not a results file written by jlox bench: {1}")]
+    /// 0: path of the file, 1: what is wrong with it
+    InvalidResults(String, String),
+    #[error("Bench Error: {0} of {1} scripts got significantly slower")]
+    /// 0: slower scripts, 1: total scripts
+    Regressed(usize, usize),
+}
+
+/// This Error type is used by `jlox template` when a template cannot be
+/// turned into a Lox program.
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("Template Error: {0}:{1}: \"<%\" is never closed with \"%>\"")]
+    /// 0: path of the template, 1: line of the opening "<%"
+    UnclosedIsland(String, usize),
+}
+
+/// This Error type is used by `jlox kernel` when the connection file is
+/// invalid or a client sends a message the kernel cannot accept.
+#[derive(Debug, Error)]
+pub enum KernelError {
+    #[error("Kernel Error: {0} is not a valid connection file: {1}")]
+    /// 0: path of the file, 1: what is wrong with it
+    InvalidConnectionFile(String, String),
+    #[error("Kernel Error: Invalid message, {0}")]
+    /// 0: what is wrong with it
+    InvalidMessage(&'static str),
+    #[error("Kernel Error: Message signature does not match")]
+    BadSignature,
+    #[error("Kernel Error: Neither JUPYTER_DATA_DIR nor HOME is set, cannot install the kernel spec")]
+    NoDataDir,
+}
+
+/// This Error type is used by `jlox install` when the manifest or
+/// lockfile is invalid or a dependency cannot be fetched.
+#[derive(Debug, Error)]
+pub enum PackageError {
+    #[error("Package Error: No lox.toml found in {0} or any directory above it")]
+    /// 0: the directory the search started in
+    NoManifest(String),
+    #[error("Package Error: Invalid line {1} in {0}: {2}")]
+    /// 0: name of the file, 1: line number, 2: the line or what is wrong with it
+    InvalidLine(String, usize, String),
+    #[error("Package Error: Dependency {0} {1}")]
+    /// 0: dependency name, 1: what is wrong with it
+    InvalidDependency(String, &'static str),
+    #[error("Package Error: The directory {0} does not exist")]
+    /// 0: the path of a path dependency
+    MissingPath(String),
+    #[error("Package Error: Fetching {0} with git failed: {1}")]
+    /// 0: dependency name, 1: the output of git
+    Git(String, String),
+}
+
+/// This Error type is used by `jlox test` when the golden-output
+/// tests could not be run or did not pass.
+#[derive(Debug, Error)]
+pub enum TestError {
+    #[error("Test Error: No test cases found in {0}")]
+    /// 0: test case directory
+    NoCases(String),
+    #[error("Test Error: {0} of {1} test cases failed")]
+    /// 0: failed cases, 1: total cases
+    Failed(usize, usize),
+}
+
+/// This Error type is used for invalid meta-commands
+/// (lines starting with `:`) in the REPL.
+#[derive(Debug, Error)]
+pub enum ReplError {
+    #[error("REPL Error: Unknown command :{0}")]
+    /// 0: the command without the colon
+    UnknownCommand(String),
+    #[error("REPL Error: :{0} expects {1}")]
+    /// 0: the command, 1: what it expects
+    MissingArgument(&'static str, &'static str),
+    #[error("REPL Error: Nothing is being recorded, start with :record <file>")]
+    NotRecording,
 }
 
 /// Whenever there are Errors during the scanning phase,
 /// this Error type can be used.
 #[derive(Debug, Error)]
 pub enum ScanError {
-    #[error("Scan Error: At least 1 error occurred while scanning. Aborted!")]
+    #[error("Scan Error [E0100]: At least 1 error occurred while scanning. Aborted!")]
     HadError,
-    #[error("Scan Error: Cannot access source code character on line {0}")]
+    #[error("Scan Error [E0104]: Cannot access source code character on line {0}")]
     /// 0: line number
     CharacterAccessError(usize),
-    #[error("Scan Error: Unexpected character {0} on line {1}")]
+    #[error("Scan Error [E0102]: Unexpected character {0} on line {1}")]
     /// 0: unexpected character, 1: starting line number
     UnexpectedCharacter(char, usize),
-    #[error("Scan Error: Unterminated string starting on line {0}")]
+    #[error("Scan Error [E0101]: Unterminated string starting on line {0}")]
     /// 0: line number
     UnterminatedString(usize),
+    #[error("Scan Error [E0103]: Invalid number {0} on line {1}")]
+    /// 0: lexeme of the number, 1: line number
+    InvalidNumber(String, usize),
+    #[error("Scan Error [E0105]: Malformed number {0}, {1} on line {2}")]
+    /// 0: lexeme of the number, 1: what is wrong with it, 2: line number
+    MalformedNumber(String, &'static str, usize),
+}
+
+impl ScanError {
+    /// The stable code of the error, explained by `jlox --explain <code>`
+    pub fn code(&self) -> &'static str {
+        match self {
+            ScanError::HadError => "E0100",
+            ScanError::UnterminatedString(..) => "E0101",
+            ScanError::UnexpectedCharacter(..) => "E0102",
+            ScanError::InvalidNumber(..) => "E0103",
+            ScanError::CharacterAccessError(..) => "E0104",
+            ScanError::MalformedNumber(..) => "E0105",
+        }
+    }
+
+    /// The source line the error refers to, if it refers to one
+    pub fn line(&self) -> Option<u32> {
+        match self {
+            ScanError::HadError => None,
+            ScanError::CharacterAccessError(line)
+            | ScanError::UnexpectedCharacter(_, line)
+            | ScanError::UnterminatedString(line)
+            | ScanError::InvalidNumber(_, line)
+            | ScanError::MalformedNumber(_, _, line) => Some(*line as u32),
+        }
+    }
 }
 
 /// This error type can be used whenever there are
 /// Errors during the parsing phase
 #[derive(Debug, Error)]
 pub enum ParseError {
-    #[error("Parse Error: At least 1 error occurred while parsing. Aborted!")]
+    #[error("Parse Error [E0200]: At least 1 error occurred while parsing. Aborted!")]
     HadError,
-    #[error("Parse Error: Cannot access token at index {0}")]
+    #[error("Parse Error [E0215]: Cannot access token at index {0}")]
     /// 0: token index
     TokenAccessError(usize),
-    #[error("Parse Error: Unterminated grouping on line {0}")]
+    #[error("Parse Error [E0205]: Unterminated grouping on line {0}")]
     UnterminatedGrouping(u32),
-    #[error("Parse Error: Unterminated print statement on line {0}")]
+    #[error("Parse Error [E0206]: Unterminated print statement on line {0}")]
     UnterminatedPrintStatement(u32),
-    #[error("Parse Error: Unterminated expression statement on line {0}")]
+    #[error("Parse Error [E0207]: Unterminated expression statement on line {0}")]
     UnterminatedExpressionStatement(u32),
-    #[error("Parse Error: Unterminated variable declaration on line {0}")]
+    #[error("Parse Error [E0208]: Unterminated variable declaration on line {0}")]
     UnterminatedVarDeclaration(u32),
-    #[error("Parse Error: Unterminated block on line {0}")]
+    #[error("Parse Error [E0209]: Unterminated block on line {0}")]
     UnterminatedBlock(u32),
-    #[error("Parse Error: Expected identifier on line {0}")]
+    #[error("Parse Error [E0210]: Expected identifier on line {0}")]
     ExpectedIdentifier(u32),
-    #[error("Parse Error: Expected opening parentheses \"(\" on line {0}")]
+    #[error("Parse Error [E0211]: Expected property name after \".\" on line {0}")]
+    ExpectedPropertyName(u32),
+    #[error("Parse Error [E0204]: Expected opening parentheses \"(\" on line {0}")]
     ExprectedLeftParen(u32),
-    #[error("Parse Error: Exprected closing parentheses \")\" on line {0}")]
+    #[error("Parse Error [E0203]: Exprected closing parentheses \")\" on line {0}")]
     ExpectedRightParen(u32),
     /// 0: Current token formatted as String. Should use `format!` for that
-    #[error("Parse Error: Expected expression on line {0}")]
+    #[error("Parse Error [E0201]: Expected expression on line {0}")]
     ExpectedExpression(u32),
-    #[error("Parse Error: Expected semicolon on line {0}")]
+    #[error("Parse Error [E0202]: Expected semicolon on line {0}")]
     ExpectedSemicolon(u32),
-    #[error("Parse Error: Expected literal on line {0}")]
+    #[error("Parse Error [E0212]: Expected literal on line {0}")]
     /// 0: token index
     NoLiteralOnToken(u32),
-    #[error("Parse Error: Invalid assignment target.")]
-    InvalidAssignmentTarget,
+    #[error("Parse Error [E0213]: Invalid assignment target on line {0}")]
+    InvalidAssignmentTarget(u32),
+    #[error("Parse Error [E0214]: Unexpected tokens after the expression on line {0}")]
+    TrailingTokens(u32),
+    #[error("Parse Error [E0216]: Expected type name after \":\" on line {0}")]
+    ExpectedTypeName(u32),
+    #[error("Parse Error [E0217]: Nesting is too deep on line {0}")]
+    TooDeeplyNested(u32),
+    #[error("Parse Error [E0218]: Cannot return from top-level code on line {0}")]
+    ReturnOutsideFunction(u32),
+    #[error("Parse Error [E0219]: Expected \"{{\" before the function body on line {0}")]
+    ExpectedFunctionBody(u32),
+    #[error("Parse Error [E0220]: Expected \"{{\" before the class body on line {0}")]
+    ExpectedClassBody(u32),
+    #[error("Parse Error [E0221]: Unterminated class body on line {0}")]
+    UnterminatedClassBody(u32),
+    #[error("Parse Error [E0222]: Cannot use \"this\" outside of a class on line {0}")]
+    ThisOutsideClass(u32),
+    #[error("Parse Error [E0223]: Expected superclass name after \"<\" on line {0}")]
+    ExpectedSuperclassName(u32),
+    #[error("Parse Error [E0224]: Class {0} cannot inherit from itself on line {1}")]
+    InheritsFromItself(String, u32),
+    #[error("Parse Error [E0225]: Cannot use \"super\" outside of a class with a superclass on line {0}")]
+    SuperWithoutSuperclass(u32),
+    #[error("Parse Error [E0226]: Expected \".\" and a method name after \"super\" on line {0}")]
+    ExpectedSuperMethod(u32),
+    #[error("Parse Error [E0227]: Cannot return a value from an initializer on line {0}")]
+    ReturnValueFromInitializer(u32),
+    #[error("Parse Error [E0228]: Setter {0} must take exactly one parameter on line {1}")]
+    SetterArity(String, u32),
+    #[error("Parse Error [E0229]: Cannot continue outside of a loop on line {0}")]
+    ContinueOutsideLoop(u32),
+    #[error("Parse Error [E0230]: Expected \"]\" after the elements of the list on line {0}")]
+    UnterminatedList(u32),
+    #[error("Parse Error [E0231]: Expected \"]\" after the index or slice on line {0}")]
+    UnterminatedIndex(u32),
+    #[error("Parse Error [E0232]: Expected \"}}\" after the entries of the map on line {0}")]
+    UnterminatedMap(u32),
+    #[error("Parse Error [E0233]: Expected \":\" after the key of a map entry on line {0}")]
+    ExpectedColonAfterKey(u32),
+    #[error("Parse Error [E0234]: Expected \"{{\" before the cases of the switch on line {0}")]
+    ExpectedSwitchBody(u32),
+    #[error("Parse Error [E0235]: Expected \":\" after the case on line {0}")]
+    ExpectedColonAfterCase(u32),
+    #[error("Parse Error [E0236]: Expected \"case\" or \"default\" in the switch on line {0}")]
+    ExpectedCase(u32),
+    #[error("Parse Error [E0237]: The default branch must be the last one of the switch on line {0}")]
+    DefaultNotLast(u32),
+    #[error("Parse Error [E0238]: Expected \"{{\" after {0} on line {1}")]
+    /// 0: the keyword the block belongs to, 1: line number
+    ExpectedBlock(&'static str, u32),
+    #[error("Parse Error [E0239]: A try block needs a catch or finally block after it on line {0}")]
+    TryWithoutHandler(u32),
+    #[error("Parse Error [E0240]: Expected a variable name in parentheses after catch on line {0}")]
+    ExpectedCatchVariable(u32),
+    #[error("Parse Error [E0241]: Expected a class name after \":\" in catch on line {0}")]
+    ExpectedCatchClass(u32),
+    #[error("Parse Error [E0242]: Constant {0} needs a value on line {1}")]
+    ConstWithoutInitializer(String, u32),
+    #[error("Parse Error [E0243]: Cannot assign to constant {0} on line {1}")]
+    AssignToConst(String, u32),
+    #[error("Parse Error [E0244]: Expected a class name after \"with\" on line {0}")]
+    ExpectedMixinName(u32),
+    #[error("Parse Error [E0245]: Expected a variable name in the destructuring pattern on line {0}")]
+    ExpectedPatternName(u32),
+    #[error("Parse Error [E0246]: A destructuring declaration needs a value on line {0}")]
+    PatternWithoutInitializer(u32),
+    #[error("Parse Error [E0247]: Cannot break outside of a loop on line {0}")]
+    BreakOutsideLoop(u32),
+    #[error("Parse Error [E0248]: No loop around the {0} is labeled {1} on line {2}")]
+    UndefinedLabel(&'static str, String, u32),
+    #[error("Parse Error [E0249]: The label {0} is already used by a loop around it on line {1}")]
+    DuplicateLabel(String, u32),
+    #[error("Parse Error [E0250]: Expected a while or for loop after the label {0} on line {1}")]
+    LabelWithoutLoop(String, u32),
+    #[error("Parse Error [E0251]: Cannot {0} out of the block of an if expression on line {1}")]
+    JumpOutOfValueBlock(&'static str, u32),
+    #[error("Parse Error [E0252]: Expected fun after async on line {0}")]
+    ExpectedFunAfterAsync(u32),
+}
+
+impl ParseError {
+    /// The stable code of the error, explained by `jlox --explain <code>`
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::HadError => "E0200",
+            ParseError::ExpectedExpression(..) => "E0201",
+            ParseError::ExpectedSemicolon(..) => "E0202",
+            ParseError::ExpectedRightParen(..) => "E0203",
+            ParseError::ExprectedLeftParen(..) => "E0204",
+            ParseError::UnterminatedGrouping(..) => "E0205",
+            ParseError::UnterminatedPrintStatement(..) => "E0206",
+            ParseError::UnterminatedExpressionStatement(..) => "E0207",
+            ParseError::UnterminatedVarDeclaration(..) => "E0208",
+            ParseError::UnterminatedBlock(..) => "E0209",
+            ParseError::ExpectedIdentifier(..) => "E0210",
+            ParseError::ExpectedPropertyName(..) => "E0211",
+            ParseError::NoLiteralOnToken(..) => "E0212",
+            ParseError::InvalidAssignmentTarget(..) => "E0213",
+            ParseError::TrailingTokens(..) => "E0214",
+            ParseError::TokenAccessError(..) => "E0215",
+            ParseError::ExpectedTypeName(..) => "E0216",
+            ParseError::TooDeeplyNested(..) => "E0217",
+            ParseError::ReturnOutsideFunction(..) => "E0218",
+            ParseError::ExpectedFunctionBody(..) => "E0219",
+            ParseError::ExpectedClassBody(..) => "E0220",
+            ParseError::UnterminatedClassBody(..) => "E0221",
+            ParseError::ThisOutsideClass(..) => "E0222",
+            ParseError::ExpectedSuperclassName(..) => "E0223",
+            ParseError::InheritsFromItself(..) => "E0224",
+            ParseError::SuperWithoutSuperclass(..) => "E0225",
+            ParseError::ExpectedSuperMethod(..) => "E0226",
+            ParseError::ReturnValueFromInitializer(..) => "E0227",
+            ParseError::SetterArity(..) => "E0228",
+            ParseError::ContinueOutsideLoop(..) => "E0229",
+            ParseError::UnterminatedList(..) => "E0230",
+            ParseError::UnterminatedIndex(..) => "E0231",
+            ParseError::UnterminatedMap(..) => "E0232",
+            ParseError::ExpectedColonAfterKey(..) => "E0233",
+            ParseError::ExpectedSwitchBody(..) => "E0234",
+            ParseError::ExpectedColonAfterCase(..) => "E0235",
+            ParseError::ExpectedCase(..) => "E0236",
+            ParseError::DefaultNotLast(..) => "E0237",
+            ParseError::ExpectedBlock(..) => "E0238",
+            ParseError::TryWithoutHandler(..) => "E0239",
+            ParseError::ExpectedCatchVariable(..) => "E0240",
+            ParseError::ExpectedCatchClass(..) => "E0241",
+            ParseError::ConstWithoutInitializer(..) => "E0242",
+            ParseError::AssignToConst(..) => "E0243",
+            ParseError::ExpectedMixinName(..) => "E0244",
+            ParseError::ExpectedPatternName(..) => "E0245",
+            ParseError::PatternWithoutInitializer(..) => "E0246",
+            ParseError::BreakOutsideLoop(..) => "E0247",
+            ParseError::UndefinedLabel(..) => "E0248",
+            ParseError::DuplicateLabel(..) => "E0249",
+            ParseError::LabelWithoutLoop(..) => "E0250",
+            ParseError::JumpOutOfValueBlock(..) => "E0251",
+            ParseError::ExpectedFunAfterAsync(_) => "E0252",
+        }
+    }
+
+    /// The source line the error refers to, if it refers to one
+    pub fn line(&self) -> Option<u32> {
+        match self {
+            ParseError::HadError | ParseError::TokenAccessError(_) => None,
+            ParseError::UnterminatedGrouping(line)
+            | ParseError::UnterminatedPrintStatement(line)
+            | ParseError::UnterminatedExpressionStatement(line)
+            | ParseError::UnterminatedVarDeclaration(line)
+            | ParseError::UnterminatedBlock(line)
+            | ParseError::ExpectedIdentifier(line)
+            | ParseError::ExpectedPropertyName(line)
+            | ParseError::ExpectedTypeName(line)
+            | ParseError::ExprectedLeftParen(line)
+            | ParseError::ExpectedRightParen(line)
+            | ParseError::ExpectedExpression(line)
+            | ParseError::ExpectedSemicolon(line)
+            | ParseError::NoLiteralOnToken(line)
+            | ParseError::InvalidAssignmentTarget(line)
+            | ParseError::TrailingTokens(line)
+            | ParseError::TooDeeplyNested(line)
+            | ParseError::ReturnOutsideFunction(line)
+            | ParseError::ExpectedFunctionBody(line)
+            | ParseError::ExpectedClassBody(line)
+            | ParseError::UnterminatedClassBody(line)
+            | ParseError::ThisOutsideClass(line)
+            | ParseError::ExpectedSuperclassName(line)
+            | ParseError::ExpectedMixinName(line)
+            | ParseError::ExpectedPatternName(line)
+            | ParseError::PatternWithoutInitializer(line)
+            | ParseError::InheritsFromItself(_, line)
+            | ParseError::SuperWithoutSuperclass(line)
+            | ParseError::ExpectedSuperMethod(line)
+            | ParseError::ReturnValueFromInitializer(line)
+            | ParseError::SetterArity(_, line)
+            | ParseError::ContinueOutsideLoop(line)
+            | ParseError::BreakOutsideLoop(line)
+            | ParseError::UndefinedLabel(_, _, line)
+            | ParseError::DuplicateLabel(_, line)
+            | ParseError::LabelWithoutLoop(_, line)
+            | ParseError::JumpOutOfValueBlock(_, line)
+            | ParseError::ExpectedFunAfterAsync(line)
+            | ParseError::UnterminatedList(line)
+            | ParseError::UnterminatedIndex(line)
+            | ParseError::UnterminatedMap(line)
+            | ParseError::ExpectedColonAfterKey(line)
+            | ParseError::ExpectedSwitchBody(line)
+            | ParseError::ExpectedColonAfterCase(line)
+            | ParseError::ExpectedCase(line)
+            | ParseError::DefaultNotLast(line)
+            | ParseError::ExpectedBlock(_, line)
+            | ParseError::TryWithoutHandler(line)
+            | ParseError::ExpectedCatchVariable(line)
+            | ParseError::ExpectedCatchClass(line)
+            | ParseError::ConstWithoutInitializer(_, line)
+            | ParseError::AssignToConst(_, line) => Some(*line),
+        }
+    }
+}
+
+/// This error type is used when a compiled `.loxc` chunk
+/// cannot be loaded.
+#[derive(Debug, Error)]
+pub enum ChunkError {
+    #[error("Chunk Error: Not a compiled Lox chunk")]
+    BadMagic,
+    #[error("Chunk Error: Chunk has format version {0}, but only version {1} is supported. Recompile the script.")]
+    /// 0: version of the chunk, 1: supported version
+    UnsupportedVersion(u16, u16),
+    #[error("Chunk Error: Checksum mismatch, the chunk is corrupted")]
+    ChecksumMismatch,
+    #[error("Chunk Error: Chunk ends unexpectedly")]
+    Truncated,
+    #[error("Chunk Error: Invalid {1} tag {0}")]
+    /// 0: tag byte, 1: what kind of node was being decoded
+    InvalidTag(u8, &'static str),
+    #[error("Chunk Error: String is not valid UTF-8")]
+    InvalidString,
+    #[error("Chunk Error: Syntax tree is nested deeper than {0} levels")]
+    /// 0: the deepest nesting the parser allows
+    TooDeeplyNested(usize),
+}
+
+/// This error type is used when the canonical text form
+/// of a value cannot be read back.
+#[derive(Debug, Error)]
+pub enum ValueError {
+    #[error("Value Error: Invalid value text {0}")]
+    /// 0: the text that could not be read
+    InvalidText(String),
+}
+
+/// This error type is used by the type checker when a value
+/// does not match the annotated or expected type.
+#[derive(Debug, Error)]
+pub enum TypeError {
+    #[error("Type Error [E0400]: At least 1 type error found. Aborted!")]
+    HadError,
+    #[error("Type Error [E0401]: Expected a value of type {0}, found {1} on line {2}")]
+    /// 0: declared type, 1: actual type, 2: line number
+    Mismatch(String, String, u32),
+    #[error("Type Error [E0402]: Cannot call a value of type {0} on line {1}")]
+    /// 0: type of the callee, 1: line number
+    NotCallable(String, u32),
+    #[error("Type Error [E0403]: Operator {0} cannot be applied to {1} and {2} on line {3}")]
+    /// 0: operator, 1: type of the left operand, 2: type of the right operand, 3: line number
+    OperandTypes(String, String, String, u32),
+    #[error("Type Error [E0404]: Expected {0} arguments but got {1} on line {2}")]
+    /// 0: number of parameters, 1: number of arguments, 2: line number
+    WrongArgumentCount(usize, usize, u32),
+    #[error("Type Error [E0405]: Argument {0} expects a value of type {1}, found {2} on line {3}")]
+    /// 0: position of the argument, starting at 1, 1: parameter type, 2: argument type, 3: line number
+    ArgumentType(usize, String, String, u32),
+    #[error("Type Error [E0406]: Unknown type {0} on line {1}")]
+    /// 0: the type name as written, 1: line number
+    UnknownType(String, u32),
+    #[error("Type Error [E0407]: Operator {0} cannot be applied to {1} on line {2}")]
+    /// 0: operator, 1: type of the operand, 2: line number
+    OperandType(String, String, u32),
+    #[error("Type Error [E0408]: {0} has no property {1} on line {2}")]
+    /// 0: type of the object, 1: property name, 2: line number
+    UndefinedProperty(String, String, u32),
+    #[error("Type Error [E0409]: Cannot index a value of type {0} on line {1}")]
+    /// 0: type of the indexed value, 1: line number
+    NotIndexable(String, u32),
+    #[error("Type Error [E0410]: Cannot slice a value of type {0} on line {1}")]
+    /// 0: type of the sliced value, 1: line number
+    NotSliceable(String, u32),
+    #[error("Type Error [E0411]: Cannot iterate over a value of type {0} on line {1}")]
+    /// 0: type of the iterated value, 1: line number
+    NotIterable(String, u32),
+}
+
+impl TypeError {
+    /// The stable code of the error, explained by `jlox --explain <code>`
+    pub fn code(&self) -> &'static str {
+        match self {
+            TypeError::HadError => "E0400",
+            TypeError::Mismatch(..) => "E0401",
+            TypeError::NotCallable(..) => "E0402",
+            TypeError::OperandTypes(..) => "E0403",
+            TypeError::WrongArgumentCount(..) => "E0404",
+            TypeError::ArgumentType(..) => "E0405",
+            TypeError::UnknownType(..) => "E0406",
+            TypeError::OperandType(..) => "E0407",
+            TypeError::UndefinedProperty(..) => "E0408",
+            TypeError::NotIndexable(..) => "E0409",
+            TypeError::NotSliceable(..) => "E0410",
+            TypeError::NotIterable(..) => "E0411",
+        }
+    }
+
+    /// The source line the error refers to, if it refers to one
+    pub fn line(&self) -> Option<u32> {
+        match self {
+            TypeError::HadError => None,
+            TypeError::Mismatch(_, _, line)
+            | TypeError::NotCallable(_, line)
+            | TypeError::OperandTypes(_, _, _, line)
+            | TypeError::WrongArgumentCount(_, _, line)
+            | TypeError::ArgumentType(_, _, _, line)
+            | TypeError::UnknownType(_, line)
+            | TypeError::OperandType(_, _, line)
+            | TypeError::UndefinedProperty(_, _, line)
+            | TypeError::NotIndexable(_, line)
+            | TypeError::NotSliceable(_, line)
+            | TypeError::NotIterable(_, line) => Some(*line),
+        }
+    }
 }
 
 /// This error type can be used whenever there is
 /// an Error during code execution.
 #[derive(Debug, Error)]
 pub enum RuntimeError {
-    #[error("Runtime Error: Operand must be a number.")]
-    NumberOperand,
-    #[error("Runtime Error: Incompatible types.")]
-    IncompatibleTypes,
-    #[error("Runtime Error: Undefined variable.")]
-    UndefinedVariable,
-    #[error("Runtime Error: Unknown error.")]
+    #[error("Runtime Error [R0303]: Operand must be a number on line {0}")]
+    /// 0: line number
+    NumberOperand(u32),
+    #[error("Runtime Error [R0305]: Result of the arithmetic is not representable (overflow or division by zero) on line {0}")]
+    /// 0: line number
+    InvalidArithmetic(u32),
+    #[error("Runtime Error [R0304]: Incompatible types on line {0}")]
+    /// 0: line number
+    IncompatibleTypes(u32),
+    #[error("Runtime Error [R0301]: Undefined variable {0} on line {2}{}", did_you_mean(.1))]
+    /// 0: variable name, 1: the most similar visible name, 2: line number
+    UndefinedVariable(String, Option<String>, u32),
+    #[error("Runtime Error [R0306]: Can only call functions on line {0}")]
+    /// 0: line number
+    NotCallable(u32),
+    #[error("Runtime Error [R0307]: Expected {0} arguments but got {1} on line {2}")]
+    /// 0: arity of the function, 1: number of arguments, 2: line number
+    WrongArgumentCount(usize, usize, u32),
+    #[error("Runtime Error [R0308]: {0}() expects {1}, got {2} on line {3}")]
+    /// 0: function name, 1: expected type, 2: actual type, 3: line number
+    ArgumentType(&'static str, &'static str, &'static str, u32),
+    #[error("Runtime Error [R0302]: Undefined property {0} on line {1}")]
+    /// 0: property name, 1: line number
+    UndefinedProperty(String, u32),
+    #[error("Runtime Error [R0312]: Memory limit of {0} bytes exceeded on line {1}")]
+    /// 0: the limit in bytes, 1: line number
+    MemoryLimitExceeded(usize, u32),
+    #[error("Runtime Error [R0317]: Size limit exceeded, the {0} may be at most {1} on line {2}")]
+    /// 0: what is limited, 1: the limit, 2: line number
+    SizeLimitExceeded(&'static str, usize, u32),
+    #[error("Runtime Error [R0313]: Step limit of {0} statements exceeded")]
+    /// 0: the limit
+    StepLimitExceeded(u64),
+    #[error("Runtime Error [R0314]: Time limit of {0:?} exceeded")]
+    /// 0: the limit
+    TimeLimitExceeded(Duration),
+    #[error("Runtime Error [R0310]: Variable {0} is already defined in this scope on line {1}")]
+    /// 0: variable name, 1: line number
+    AlreadyDefined(String, u32),
+    #[error("Runtime Error [R0315]: Execution was cancelled")]
+    Cancelled,
+    #[error("Runtime Error [R0311]: {0}() needs the {1} capability, which was not granted, on line {2}")]
+    /// 0: function name, 1: capability group, 2: line number
+    CapabilityDisabled(&'static str, &'static str, u32),
+    #[error("Runtime Error [R0309]: {0}() got an invalid argument: {1} on line {2}")]
+    /// 0: function name, 1: what is wrong with it, 2: line number
+    InvalidArgument(&'static str, &'static str, u32),
+    #[error("Runtime Error [R0318]: Foreign call failed: {0} on line {1}")]
+    /// 0: what went wrong, 1: line number
+    ForeignCall(String, u32),
+    #[error("Runtime Error [R0319]: Stack overflow, calls can be nested at most {0} deep, on line {1}")]
+    /// 0: the maximum call depth, 1: line number
+    StackOverflow(usize, u32),
+    #[error("Runtime Error [R0320]: Only instances have fields, cannot set {0} on line {1}")]
+    /// 0: property name, 1: line number
+    OnlyInstancesHaveFields(String, u32),
+    #[error("Runtime Error [R0321]: Superclass of {0} must be a class on line {1}")]
+    /// 0: name of the inheriting class, 1: line number
+    SuperclassMustBeClass(String, u32),
+    #[error("Runtime Error [R0322]: Cannot repeat a string {0} times on line {1}")]
+    /// 0: the count, 1: line number
+    InvalidRepeatCount(String, u32),
+    #[error("Runtime Error [R0323]: Index {0} is out of bounds for a {1} of length {2} on line {3}")]
+    /// 0: the index, 1: type of the indexed value, 2: its length, 3: line number
+    IndexOutOfBounds(String, &'static str, usize, u32),
+    #[error("Runtime Error [R0324]: Index must be a whole number, got {0} on line {1}")]
+    /// 0: text form of the index, 1: line number
+    InvalidIndex(String, u32),
+    #[error("Runtime Error [R0325]: Only lists, strings and maps can be indexed, not {0} values, on line {1}")]
+    /// 0: type of the indexed value, 1: line number
+    NotIndexable(&'static str, u32),
+    #[error("Runtime Error [R0326]: Map keys must be strings, numbers, booleans or nil, not {0} on line {1}")]
+    /// 0: the key as printed, 1: line number
+    InvalidKey(String, u32),
+    #[error("Runtime Error [R0327]: Map has no key {0} on line {1}")]
+    /// 0: text form of the key, 1: line number
+    MissingKey(String, u32),
+    #[error("Runtime Error [R0328]: Strings cannot be changed, build a new one instead of assigning to an index on line {0}")]
+    /// 0: line number
+    ImmutableString(u32),
+    #[error("Runtime Error [R0329]: Only lists and strings can be sliced, not {0} values, on line {1}")]
+    /// 0: type of the sliced value, 1: line number
+    NotSliceable(&'static str, u32),
+    #[error("Runtime Error [R0330]: Only lists, maps and strings can be iterated, not {0} values, on line {1}")]
+    /// 0: type of the iterated value, 1: line number
+    NotIterable(&'static str, u32),
+    #[error("Runtime Error [R0331]: Uncaught exception: {0} on line {1}")]
+    /// 0: the thrown value as printed, 1: line number
+    Uncaught(String, u32),
+    #[error("Runtime Error [R0332]: Catch blocks can only filter by class, {0} is not a class, on line {1}")]
+    CatchNotClass(String, u32),
+    #[error("Runtime Error [R0333]: Cannot assign to constant {0} on line {1}")]
+    AssignToConst(String, u32),
+    #[error("Runtime Error [R0334]: Mixin {0} of {1} must be a class on line {2}")]
+    /// 0: name of the mixin, 1: name of the class it is mixed into, 2: line number
+    MixinMustBeClass(String, String, u32),
+    #[error("Runtime Error [R0335]: A {0} pattern cannot destructure {1} values on line {2}")]
+    /// 0: kind of the pattern, 1: type of the destructured value, 2: line number
+    NotDestructurable(&'static str, &'static str, u32),
+    #[error("Runtime Error [R0336]: The pattern needs a list of length {0}, not {1}, on line {2}")]
+    /// 0: number of names in the pattern, 1: length of the list, 2: line number
+    PatternLength(usize, usize, u32),
+    #[error("Runtime Error [R0337]: Expected a class or the name of a type after is, not a {0} value, on line {1}")]
+    InvalidTypeTest(&'static str, u32),
+    #[error("Runtime Error [R0338]: There is no type named \"{0}\" on line {1}")]
+    UnknownTypeName(String, u32),
+    #[error("Runtime Error [R0339]: A frozen {0} can't be changed, on line {1}")]
+    /// 0: type of the frozen value, 1: line number
+    Frozen(&'static str, u32),
+    #[error("Runtime Error [R0340]: The code given to eval on line {1} has an error. {0}")]
+    /// 0: the first diagnostic of the code, 1: line number
+    InvalidEvalCode(String, u32),
+    #[error("Runtime Error [R0341]: The script exited with code {0}")]
+    /// 0: the exit code
+    Exit(u8),
+    #[error("Runtime Error [R0342]: Reading the input failed: {0}, on line {1}")]
+    /// 0: the cause, 1: line number
+    InputFailed(String, u32),
+    #[error("Runtime Error [R0343]: Not enough memory for a string of {0} bytes on line {1}")]
+    /// 0: the size of the string, 1: line number
+    OutOfMemory(usize, u32),
+    #[error("Runtime Error [R0344]: The awaited future can never complete, every async call waits on line {0}")]
+    /// 0: line number
+    NeverCompletes(u32),
+    #[error("Runtime Error [R0345]: Cannot start another {0}, {1}, on line {2}")]
+    /// 0: what needed a thread (coroutine, async call, task), 1: why it failed, 2: line number
+    ThreadUnavailable(&'static str, String, u32),
+    #[error("Runtime Error [R0346]: The joined task crashed: {0}, on line {1}")]
+    /// 0: the message of the panic, 1: line number
+    TaskPanicked(String, u32),
+    #[error("Runtime Error [R0347]: {0}() failed: {1}, on line {2}")]
+    /// 0: name of the native, 1: the cause, 2: line number
+    SystemFailed(&'static str, String, u32),
+    #[error("Runtime Error [R0316]: Cannot write output: {0}")]
+    /// 0: the underlying I/O error
+    OutputFailed(String),
+    #[error("Runtime Error [R0300]: Unknown error.")]
     Unknown,
-}
\ No newline at end of file
+}
+
+impl RuntimeError {
+    /// The stable code of the error, explained by `jlox --explain <code>`
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeError::Unknown => "R0300",
+            RuntimeError::UndefinedVariable(..) => "R0301",
+            RuntimeError::UndefinedProperty(..) => "R0302",
+            RuntimeError::NumberOperand(..) => "R0303",
+            RuntimeError::IncompatibleTypes(..) => "R0304",
+            RuntimeError::InvalidArithmetic(..) => "R0305",
+            RuntimeError::NotCallable(..) => "R0306",
+            RuntimeError::WrongArgumentCount(..) => "R0307",
+            RuntimeError::ArgumentType(..) => "R0308",
+            RuntimeError::InvalidArgument(..) => "R0309",
+            RuntimeError::AlreadyDefined(..) => "R0310",
+            RuntimeError::CapabilityDisabled(..) => "R0311",
+            RuntimeError::MemoryLimitExceeded(..) => "R0312",
+            RuntimeError::StepLimitExceeded(..) => "R0313",
+            RuntimeError::TimeLimitExceeded(..) => "R0314",
+            RuntimeError::Cancelled => "R0315",
+            RuntimeError::OutputFailed(..) => "R0316",
+            RuntimeError::SizeLimitExceeded(..) => "R0317",
+            RuntimeError::ForeignCall(..) => "R0318",
+            RuntimeError::StackOverflow(..) => "R0319",
+            RuntimeError::OnlyInstancesHaveFields(..) => "R0320",
+            RuntimeError::SuperclassMustBeClass(..) => "R0321",
+            RuntimeError::InvalidRepeatCount(..) => "R0322",
+            RuntimeError::IndexOutOfBounds(..) => "R0323",
+            RuntimeError::InvalidIndex(..) => "R0324",
+            RuntimeError::NotIndexable(..) => "R0325",
+            RuntimeError::InvalidKey(..) => "R0326",
+            RuntimeError::MissingKey(..) => "R0327",
+            RuntimeError::ImmutableString(..) => "R0328",
+            RuntimeError::NotSliceable(..) => "R0329",
+            RuntimeError::NotIterable(..) => "R0330",
+            RuntimeError::Uncaught(..) => "R0331",
+            RuntimeError::CatchNotClass(..) => "R0332",
+            RuntimeError::AssignToConst(..) => "R0333",
+            RuntimeError::MixinMustBeClass(..) => "R0334",
+            RuntimeError::NotDestructurable(..) => "R0335",
+            RuntimeError::PatternLength(..) => "R0336",
+            RuntimeError::InvalidTypeTest(..) => "R0337",
+            RuntimeError::UnknownTypeName(..) => "R0338",
+            RuntimeError::Frozen(..) => "R0339",
+            RuntimeError::InvalidEvalCode(..) => "R0340",
+            RuntimeError::Exit(_) => "R0341",
+            RuntimeError::InputFailed(..) => "R0342",
+            RuntimeError::OutOfMemory(..) => "R0343",
+            RuntimeError::NeverCompletes(_) => "R0344",
+            RuntimeError::ThreadUnavailable(..) => "R0345",
+            RuntimeError::TaskPanicked(..) => "R0346",
+            RuntimeError::SystemFailed(..) => "R0347",
+        }
+    }
+
+    /// The source line the error refers to, if it refers to one
+    pub fn line(&self) -> Option<u32> {
+        match self {
+            RuntimeError::NumberOperand(line)
+            | RuntimeError::IncompatibleTypes(line)
+            | RuntimeError::InvalidArithmetic(line)
+            | RuntimeError::UndefinedVariable(_, _, line)
+            | RuntimeError::NotCallable(line)
+            | RuntimeError::WrongArgumentCount(_, _, line)
+            | RuntimeError::ArgumentType(_, _, _, line)
+            | RuntimeError::CapabilityDisabled(_, _, line)
+            | RuntimeError::InvalidArgument(_, _, line)
+            | RuntimeError::ForeignCall(_, line)
+            | RuntimeError::UndefinedProperty(_, line)
+            | RuntimeError::MemoryLimitExceeded(_, line)
+            | RuntimeError::SizeLimitExceeded(_, _, line)
+            | RuntimeError::StackOverflow(_, line)
+            | RuntimeError::OnlyInstancesHaveFields(_, line)
+            | RuntimeError::SuperclassMustBeClass(_, line)
+            | RuntimeError::MixinMustBeClass(_, _, line)
+            | RuntimeError::InvalidRepeatCount(_, line)
+            | RuntimeError::IndexOutOfBounds(_, _, _, line)
+            | RuntimeError::InvalidIndex(_, line)
+            | RuntimeError::NotIndexable(_, line)
+            | RuntimeError::InvalidKey(_, line)
+            | RuntimeError::MissingKey(_, line)
+            | RuntimeError::ImmutableString(line)
+            | RuntimeError::NotSliceable(_, line)
+            | RuntimeError::NotIterable(_, line)
+            | RuntimeError::NotDestructurable(_, _, line)
+            | RuntimeError::PatternLength(_, _, line)
+            | RuntimeError::InvalidTypeTest(_, line)
+            | RuntimeError::UnknownTypeName(_, line)
+            | RuntimeError::Frozen(_, line)
+            | RuntimeError::InvalidEvalCode(_, line)
+            | RuntimeError::InputFailed(_, line)
+            | RuntimeError::OutOfMemory(_, line)
+            | RuntimeError::NeverCompletes(line)
+            | RuntimeError::ThreadUnavailable(_, _, line)
+            | RuntimeError::TaskPanicked(_, line)
+            | RuntimeError::SystemFailed(_, _, line)
+            | RuntimeError::Uncaught(_, line)
+            | RuntimeError::CatchNotClass(_, line)
+            | RuntimeError::AssignToConst(_, line)
+            | RuntimeError::AlreadyDefined(_, line) => Some(*line),
+            RuntimeError::StepLimitExceeded(_)
+            | RuntimeError::TimeLimitExceeded(_)
+            | RuntimeError::Cancelled
+            | RuntimeError::OutputFailed(_)
+            | RuntimeError::Exit(_)
+            | RuntimeError::Unknown => None,
+        }
+    }
+
+    /// Whether a `try` block can catch the error. Limits, cancellation,
+    /// failing output and `exit()` end the run no matter what the script does.
+    pub fn is_catchable(&self) -> bool {
+        !matches!(
+            self,
+            RuntimeError::MemoryLimitExceeded(..)
+                | RuntimeError::StepLimitExceeded(_)
+                | RuntimeError::TimeLimitExceeded(_)
+                | RuntimeError::Cancelled
+                | RuntimeError::OutputFailed(_)
+                | RuntimeError::Exit(_)
+                | RuntimeError::Unknown
+        )
+    }
+
+    /// Name of the standard error class a caught error is an instance of
+    pub fn class_name(&self) -> &'static str {
+        match self {
+            RuntimeError::UndefinedVariable(..) | RuntimeError::UndefinedProperty(..) | RuntimeError::AlreadyDefined(..) => {
+                "NameError"
+            },
+            RuntimeError::NumberOperand(..)
+            | RuntimeError::IncompatibleTypes(..)
+            | RuntimeError::NotCallable(..)
+            | RuntimeError::ArgumentType(..)
+            | RuntimeError::OnlyInstancesHaveFields(..)
+            | RuntimeError::SuperclassMustBeClass(..)
+            | RuntimeError::MixinMustBeClass(..)
+            | RuntimeError::NotIndexable(..)
+            | RuntimeError::InvalidKey(..)
+            | RuntimeError::ImmutableString(..)
+            | RuntimeError::NotSliceable(..)
+            | RuntimeError::NotIterable(..)
+            | RuntimeError::NotDestructurable(..)
+            | RuntimeError::CatchNotClass(..)
+            | RuntimeError::InvalidTypeTest(..)
+            | RuntimeError::UnknownTypeName(..)
+            | RuntimeError::Frozen(..)
+            | RuntimeError::AssignToConst(..) => "TypeError",
+            RuntimeError::InvalidArithmetic(..) => "ArithmeticError",
+            RuntimeError::WrongArgumentCount(..)
+            | RuntimeError::InvalidArgument(..)
+            | RuntimeError::InvalidRepeatCount(..)
+            | RuntimeError::InvalidEvalCode(..) => "ArgumentError",
+            RuntimeError::IndexOutOfBounds(..) | RuntimeError::InvalidIndex(..) | RuntimeError::PatternLength(..) => {
+                "IndexError"
+            },
+            RuntimeError::MissingKey(..) => "KeyError",
+            _ => "Error",
+        }
+    }
+
+    /// The message of the error, without its code and line
+    pub fn message(&self) -> String {
+        let text = self.to_string();
+        let text = text.split_once("]: ").map_or(text.as_str(), |(_, message)| message);
+        let text = text.rfind(" on line ").map_or(text, |end| &text[..end]);
+        text.trim_end_matches(',').to_string()
+    }
+}
+
+/// The hint appended to errors about unknown names, if there is a similar name
+fn did_you_mean(suggestion: &Option<String>) -> String {
+    match suggestion {
+        Some(name) => format!(". Did you mean {}?", name),
+        None => String::new(),
+    }
+}
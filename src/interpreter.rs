@@ -1,48 +1,517 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-
 // External dependencies
-use anyhow::Result;
+use indexmap::IndexMap;
+use std::cmp;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 // Internal dependencies
 use crate::obj::statement::Statement;
-use crate::obj::expression::Expression;
+use crate::obj::expression::{Expression, Pattern};
+use crate::obj::class::{Class, Instance};
+use crate::obj::coroutine::{Coroutine, Status, Yielder};
 use crate::obj::environment::Environment;
+use crate::obj::function::{Function, FunctionDeclaration};
+use crate::obj::future::Future;
+use crate::obj::list::List;
+use crate::obj::map::{Key, Map};
+use crate::obj::number::Number;
 use crate::obj::value::Value;
-use crate::obj::token::Token;
+use crate::obj::token::{Span, Token};
 use crate::obj::token_type::TokenType;
-use crate::errors::RuntimeError;
+use crate::diagnostic::{CollectingReporter, Diagnostic, Origin, Reporter};
+use crate::errors::{LoxError, Result, RuntimeError};
+use crate::shared::{MaybeSend, Shared, SharedRef};
+use crate::{analyzer, parser, scanner, typechecker};
+
+// Modules
+mod builder;
+mod events;
+#[cfg(feature = "ffi")]
+mod ffi;
+#[cfg(feature = "jit")]
+mod jit;
+mod lists;
+mod natives;
+mod random;
+mod report;
+mod strings;
+mod system;
+pub use builder::{Capabilities, Config, InputHandle, InterpreterBuilder, OutputHandle, SizeLimits};
+pub use report::RunReport;
+use events::{EventLoop, Next};
+use random::Random;
+
+/// Lox source of the standard prelude, see [Config::prelude]
+pub const PRELUDE: &str = include_str!("interpreter/prelude.lox");
+
+/// The standard error classes, defined in every session before the prelude
+pub const ERROR_CLASSES: &str = include_str!("interpreter/error_classes.lox");
 
-/// Only public function of the interpreter module. Takes in a collection
+/// Convenience function for running a program once. Takes in a collection
 /// of statements from the outside and interprets them one by one.
 /// It does this by creating an Interpreter instance which hosts the
 /// environment for storing variables.
 pub fn interpret(statements: Vec<Statement>) -> Result<()> {
-    let interpreter = Interpreter::new();
+    let mut interpreter = Interpreter::new();
     interpreter.interpret(statements)
 }
 
-/// Contraption that stores the currently used environment
-struct Interpreter {
-    environment: Rc<RefCell<Environment>>,
+/// How deeply function calls can be nested. The interpreter grows its
+/// stack as needed, the limit keeps runaway recursion from taking all
+/// the memory instead.
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+/// Calls after which the jlox tool compiles a function, see [Config::jit_threshold]
+#[cfg(feature = "jit")]
+pub const JIT_THRESHOLD: u32 = 100;
+
+/// Stack space that has to be left before the interpreter recurses any
+/// deeper, and how much it allocates when there is less
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_GROWTH: usize = 4 * 1024 * 1024;
+
+/// How long [eval_expression] lets an expression run
+pub const EVAL_TIME_LIMIT: Duration = Duration::from_secs(1);
+
+/// Evaluates a single expression, like `price * (1 + tax)`, with the
+/// variables the host passes in `bindings`. Meant for using Lox as a
+/// formula language: the expression runs in a fresh [sandboxed](InterpreterBuilder::sandbox)
+/// interpreter that prints nowhere and stops after [EVAL_TIME_LIMIT].
+/// Diagnostics of malformed expressions go to the `reporter`.
+pub fn eval_expression(source: &str, bindings: &HashMap<String, Value>, reporter: &mut dyn Reporter) -> Result<Value> {
+    let tokens = scanner::scan_tokens(source.to_string(), reporter)?;
+    let expr = parser::parse_expression(tokens, reporter)?;
+
+    let mut interpreter = Interpreter::builder()
+        .sandbox()
+        .time_limit(EVAL_TIME_LIMIT)
+        .output(std::io::sink())
+        .build();
+    for (name, value) in bindings {
+        interpreter.define_global(name, value.clone());
+    }
+    interpreter.evaluate(&expr)
+}
+
+/// Callbacks that get invoked while the interpreter runs. They allow tools
+/// like debuggers, tracers and profilers to observe execution without
+/// touching the interpreter loop. Every method does nothing by default,
+/// so implementors only override what they are interested in.
+/// With the `sync` feature, hooks have to be `Send`.
+pub trait ExecutionHooks: MaybeSend {
+    /// Called right before a statement gets executed. Fires for nested
+    /// statements (block contents, loop bodies, ...) as well.
+    fn on_statement_enter(&mut self, _stmt: &Statement) {}
+
+    /// Called right before a function gets invoked, with the already
+    /// evaluated callee and arguments
+    fn on_function_call(&mut self, _callee: &Value, _arguments: &[Value]) {}
+
+    /// Called after a variable got defined or assigned a new value
+    fn on_variable_write(&mut self, _name: &str, _value: &Value) {}
+
+    /// Called after a print statement wrote a line, with the line
+    /// without its line break
+    fn on_print(&mut self, _text: &str) {}
+
+    /// Called once when a runtime error aborts the execution, before the
+    /// error is returned to the caller of [Interpreter::interpret]
+    fn on_runtime_error(&mut self, _error: &LoxError) {}
+}
+
+/// Handle for stopping a running interpreter from the outside, for example
+/// from another thread or a signal handler. Obtained through
+/// [Interpreter::cancellation_token]. Cancelling makes the current (and
+/// every following) [Interpreter::interpret] call fail until [CancellationToken::reset].
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// One active function call
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// Name of the called function
+    pub function: String,
+    /// Line of the call
+    pub line: u32,
+}
+
+/// How a statement finished. A `return` unwinds through the statements
+/// that enclose it up to the call of the function, a `break` or `continue`
+/// up to the loop with their label, the innermost loop without one.
+enum Flow {
+    Next,
+    Break(Option<String>),
+    Continue(Option<String>),
+    Return(Value),
+}
+
+impl Flow {
+    /// Whether a `break` or `continue` with the `target` label is meant for
+    /// the loop with `label`
+    fn targets(target: &Option<String>, label: &Option<Token>) -> bool {
+        match (target, label) {
+            (None, _) => true,
+            (Some(target), Some(label)) => *target == label.lexeme(),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// Contraption that stores the currently used environment and the
+/// hooks that observe the execution. The environment persists between
+/// calls to [Interpreter::interpret], so a session can be fed piece by piece.
+/// Created with [Interpreter::new] or configured with [Interpreter::builder].
+pub struct Interpreter {
+    environment: Shared<Environment>,
+    hooks: Vec<Box<dyn ExecutionHooks>>,
+    config: Config,
+    output: Box<dyn OutputHandle>,
+    input: Box<dyn InputHandle>,
+    /// Approximate number of bytes held by variables, see [Environment::size]
+    memory_used: usize,
+    /// Statements executed during the current [Interpreter::interpret] call
+    steps: u64,
+    /// When the current [Interpreter::interpret] call runs out of time
+    deadline: Option<Instant>,
+    cancellation: CancellationToken,
+    /// Active calls, innermost last
+    frames: Vec<Frame>,
+    /// Setters that are running, by the address of their instance's fields
+    /// and the property. Assigning that property inside them stores the field.
+    setters: Vec<(usize, String)>,
+    /// Where the last runtime error happened, see [Interpreter::error_span]
+    error_span: Option<Span>,
+    /// The value of the last `throw`, until a `catch` takes it. Errors
+    /// have to cross threads, so the value itself stays here.
+    thrown: Option<Value>,
+    /// Number of scopes enclosing the current one
+    depth: usize,
+    /// When the current run started executing
+    started: Instant,
+    /// When the interpreter was created, `clock()` counts from here
+    created: Instant,
+    /// The generator of `random()` and `randomInt()`, `seed()` restarts it
+    random: Random,
+    report: RunReport,
+    /// Suspends the interpreter in `yield`, if it runs the function of a coroutine
+    yielder: Option<Yielder>,
+    /// Suspends the interpreter in `await`, if it runs an async call
+    awaiter: Option<Yielder>,
+    /// The async calls and timers that haven't completed, shared with the
+    /// interpreters of coroutines and async calls
+    events: Shared<EventLoop>,
+    /// Shared libraries loaded by `loadLibrary`, by path
+    #[cfg(feature = "ffi")]
+    libraries: std::collections::HashMap<String, libloading::Library>,
+    /// Hot functions compiled to native code, see [Config::jit_threshold]
+    #[cfg(feature = "jit")]
+    jit: jit::Jit,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
+    /// Creates an interpreter with the default configuration,
+    /// printing to stdout and reading from stdin
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Starts configuring an interpreter
+    pub fn builder() -> InterpreterBuilder {
+        InterpreterBuilder::default()
+    }
+
     // going brr
-    fn new() -> Self {
+    fn from_parts(
+        config: Config,
+        output: Box<dyn OutputHandle>,
+        input: Box<dyn InputHandle>,
+        hooks: Vec<Box<dyn ExecutionHooks>>,
+    ) -> Self {
+        let environment = Environment::new();
+        natives::define_all(&mut environment.borrow_mut());
+        let mut interpreter = Self::with_globals(config, environment, output, input, hooks);
+        interpreter.load(ERROR_CLASSES.to_string(), &mut CollectingReporter::default()).expect("The error classes failed to load");
+        if interpreter.config.prelude {
+            interpreter.load(PRELUDE.to_string(), &mut CollectingReporter::default()).expect("The prelude failed to run");
+        }
+        interpreter
+    }
+
+    /// An interpreter whose global scope is `environment`, which has to
+    /// hold the natives already. Coroutines use it to run on the globals
+    /// of their script.
+    fn with_globals(
+        config: Config,
+        environment: Shared<Environment>,
+        output: Box<dyn OutputHandle>,
+        input: Box<dyn InputHandle>,
+        hooks: Vec<Box<dyn ExecutionHooks>>,
+    ) -> Self {
+        let random = Random::seeded(config.deterministic);
         Self {
-            environment: Environment::new(),
+            environment,
+            hooks,
+            config,
+            output,
+            input,
+            memory_used: 0,
+            steps: 0,
+            deadline: None,
+            cancellation: CancellationToken::default(),
+            frames: Vec::new(),
+            setters: Vec::new(),
+            error_span: None,
+            thrown: None,
+            depth: 0,
+            started: Instant::now(),
+            created: Instant::now(),
+            random,
+            report: RunReport::default(),
+            yielder: None,
+            awaiter: None,
+            events: Shared::default(),
+            #[cfg(feature = "ffi")]
+            libraries: Default::default(),
+            #[cfg(feature = "jit")]
+            jit: Default::default(),
+        }
+    }
+
+    /// Runs setup code like the [PRELUDE] or the snippets of
+    /// [InterpreterBuilder::prelude_source]. The hooks do not observe it,
+    /// for them the session starts with the first user script.
+    fn load(&mut self, source: String, reporter: &mut dyn Reporter) -> Result<()> {
+        let hooks = std::mem::take(&mut self.hooks);
+        let result = self.run(source, reporter);
+        self.hooks = hooks;
+        self.report = RunReport::default();
+        result
+    }
+
+    /// The configuration the interpreter was built with
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// A handle that stops the execution when cancelled. The interpreter
+    /// checks it before every statement and while natives like `sleep` wait.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    /// The functions that are currently being called, outermost first
+    pub fn call_stack(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Where in the source the runtime error of the last run happened:
+    /// the operator, variable or call of the innermost failing expression.
+    /// `None` if the run succeeded or the error did not come from an
+    /// expression (like exceeding the step limit).
+    pub fn error_span(&self) -> Option<Span> {
+        self.error_span
+    }
+
+    /// Resource usage of the last run, see [RunReport]
+    pub fn last_run(&self) -> &RunReport {
+        &self.report
+    }
+
+    /// Approximate number of bytes currently held by variables
+    pub fn memory_used(&self) -> usize {
+        self.memory_used
+    }
+
+    /// Defines (or redefines) a global variable, as if the script had
+    /// declared it. The value counts towards [Interpreter::memory_used].
+    pub fn define_global(&mut self, name: &str, value: Value) {
+        let added = Environment::binding_size(name, &value);
+        let previous = self.environment.borrow_mut().define_inner(name.to_string(), value);
+        let freed = previous.map_or(0, |previous| Environment::binding_size(name, &previous));
+        self.memory_used = (self.memory_used + added).saturating_sub(freed);
+    }
+
+    /// Value of a global variable, `None` if it is not defined. Between
+    /// runs, this is how results are read out of a session.
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.environment.borrow().lookup(name)
+    }
+
+    /// Every global variable with its value, sorted by name. Includes the
+    /// natives and everything the prelude defined.
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        self.environment.borrow().bindings()
+    }
+
+    /// Registers hooks that get notified about the execution. Multiple
+    /// hooks can be registered, they are called in registration order.
+    pub fn add_hooks(&mut self, hooks: Box<dyn ExecutionHooks>) {
+        self.hooks.push(hooks);
+    }
+
+    /// Evaluates a single expression in the current environment and
+    /// returns its value. Changes the expression makes to variables
+    /// (like `a = 1`) are rolled back afterwards.
+    pub fn inspect(&mut self, expr: &Expression) -> Result<Value> {
+        let snapshot = self.environment.borrow().clone();
+        let memory_used = self.memory_used;
+
+        let result = self.evaluate_expression(expr);
+
+        *self.environment.borrow_mut() = snapshot;
+        self.memory_used = memory_used;
+        result
+    }
+
+    /// Scans, parses and executes Lox source code. Diagnostics of the
+    /// front end, including the warnings of the [analyzer](crate::analyzer),
+    /// go to the `reporter`. Unlike the other ways of running code, the
+    /// [RunReport] of this one includes the front end.
+    pub fn run(&mut self, source: String, reporter: &mut dyn Reporter) -> Result<()> {
+        self.run_at(source, &Origin::default(), reporter)
+    }
+
+    /// Like [Interpreter::run], for a snippet embedded in a larger file
+    /// that starts at `origin`. Diagnostics and runtime errors refer to the
+    /// lines of the outer file, diagnostics also name the file.
+    pub fn run_at(&mut self, source: String, origin: &Origin, reporter: &mut dyn Reporter) -> Result<()> {
+        let mut counter = CountingReporter { inner: reporter, count: 0, file: origin.file.clone() };
+
+        let start = Instant::now();
+        let tokens = scanner::scan_tokens_at(source, origin, &mut counter);
+        let scan_time = Some(start.elapsed());
+
+        let start = Instant::now();
+        let statements = tokens.and_then(|tokens| parser::parse(tokens, &mut counter));
+        let parse_time = statements.is_ok().then(|| start.elapsed());
+
+        // The static checks count as part of the front end, but not as parsing
+        let statements = statements.and_then(|statements| {
+            analyzer::analyze(&statements, &mut counter);
+            if self.config.typed {
+                typechecker::check(&statements, &mut counter)?;
+            }
+            Ok(statements)
+        });
+
+        let result = match statements {
+            Ok(statements) => self.interpret(statements),
+            Err(error) => {
+                self.report = RunReport::default();
+                Err(error)
+            }
+        };
+        self.report.scan_time = scan_time;
+        self.report.parse_time = parse_time;
+        self.report.diagnostics = counter.count;
+        result
+    }
+
+    /// Takes in a collection of statements and executes them. Stops at
+    /// the first runtime error, which is also handed to the hooks.
+    /// The step and time limits apply to each call separately.
+    pub fn interpret(&mut self, statements: Vec<Statement>) -> Result<()> {
+        self.begin_run();
+        let result = self.execute_statements(&statements).map(|_| ()); // The parser keeps `return` out of top-level code
+        let result = self.settle_events(result);
+        self.finish_run(result)
+    }
+
+    /// Evaluates a single expression in the current environment, like an
+    /// expression statement, but returns the value. Used by the REPL to
+    /// echo results. Limits and hooks apply like for [Interpreter::interpret].
+    pub fn evaluate(&mut self, expr: &Expression) -> Result<Value> {
+        self.begin_run();
+        let result = self.evaluate_expression(expr);
+        let result = self.settle_events(result);
+        self.finish_run(result)
+    }
+
+    /// Runs the async calls that can go on right now, for hosts that run
+    /// the event loop themselves (see [Config::external_event_loop]), like
+    /// next to a tokio runtime. Returns when the next `sleepAsync` timer is
+    /// due, the host calls this again then. `None` if no timer is left.
+    /// Limits and hooks apply like for [Interpreter::interpret].
+    pub fn run_pending(&mut self) -> Result<Option<Instant>> {
+        self.begin_run();
+        let result = self.run_ready();
+        if result.is_err() {
+            self.abandon_events();
         }
+        self.finish_run(result)
+    }
+
+    /// Resets the per-run state before executing anything
+    fn begin_run(&mut self) {
+        self.steps = 0;
+        self.frames.clear(); // A previous run might have been aborted in the middle of a function
+        self.error_span = None;
+        self.thrown = None;
+        self.depth = 0;
+        self.report = RunReport::default();
+        self.started = Instant::now();
+        self.deadline = self.config.time_limit.map(|limit| self.started + limit);
     }
 
-    /// Takes in a collection of statements and executes them.
-    /// Also consumes the interpreter because when the interpretation
-    /// is done, the program exits (obviously)
-    fn interpret(mut self, statements: Vec<Statement>) -> Result<()> {
-        for stmt in statements {
-            self.execute_statement(&stmt)?;
+    /// Runs the async calls that are left at the end of a run, unless the
+    /// host does that (see [Config::external_event_loop]). Calls that are
+    /// still waiting then can't complete anymore, they get dropped like
+    /// everything that is left when the run failed.
+    fn settle_events<T>(&mut self, result: Result<T>) -> Result<T> {
+        let external = self.config.external_event_loop;
+        let result = match result {
+            Ok(value) if !external => self.run_events(None, 0).map(|()| value),
+            result => result,
+        };
+        if result.is_err() || !external {
+            self.abandon_events();
         }
-        Ok(())
+        result
+    }
+
+    /// Drops the async calls and timers that haven't completed. The calls
+    /// end where they wait, without completing their future.
+    fn abandon_events(&mut self) {
+        let abandoned = std::mem::take(&mut *self.events.borrow_mut());
+        drop(abandoned); // Outside of the borrow, the calls' interpreters let go of the event loop while they end
+    }
+
+    /// Flushes the output and hands an error to the hooks
+    fn finish_run<T>(&mut self, result: Result<T>) -> Result<T> {
+        let flushed = self.output.flush().map_err(|e| RuntimeError::OutputFailed(e.to_string()).into());
+
+        let result = result.and_then(|value| flushed.map(|()| value)); // The output has to be flushed before reporting an error
+        self.report.execute_time = self.started.elapsed();
+        self.report.statements = self.steps;
+        if let Err(error) = &result {
+            for hooks in &mut self.hooks {
+                hooks.on_runtime_error(error);
+            }
+        }
+        result
     }
 }
 
@@ -50,66 +519,406 @@ impl Interpreter {
     /// Takes in a reference to a Statement and executes it based on it's type.
     /// Also calls statement executions an expression evaluations recursively,
     /// by passing the references to linked statements and expressions
-    fn execute_statement(&mut self, stmt: &Statement) -> Result<()> {
+    fn execute_statement(&mut self, stmt: &Statement) -> Result<Flow> {
+        stacker::maybe_grow(STACK_RED_ZONE, STACK_GROWTH, || self.execute_node(stmt))
+    }
+
+    fn execute_node(&mut self, stmt: &Statement) -> Result<Flow> {
+        for hooks in &mut self.hooks {
+            hooks.on_statement_enter(stmt);
+        }
+        self.count_step()?;
+
         match stmt {
             Statement::Block(stmts) => {
-                let prev_env = Rc::clone(&self.environment);
-                self.environment = Environment::new_enclosed(Rc::clone(&self.environment));
-                let result: Result<()> = (|| {              // When error, don't propagate immediately, because
-                    for stmt in stmts {                     // the environment first has to be set back to the
-                        self.execute_statement(stmt)?;      // previous one.
+                let environment = Environment::new_enclosed(self.environment.clone());
+                return self.execute_block(stmts, environment);
+            },
+            Statement::Switch(_, subject, cases, default) => {
+                let subject = self.evaluate_expression(subject)?;
+                let mut branch = default.as_ref();
+                'cases: for (values, body) in cases { // The first case with an equal value runs, nothing falls through
+                    for value in values {
+                        if is_equal(subject.clone(), self.evaluate_expression(value)?) {
+                            branch = Some(body);
+                            break 'cases;
+                        }
                     }
-                    Ok(())
-                })();
-                self.environment = prev_env;    // Set environment back to previous
-                result?                         // Propagate error, if there is one
+                }
+                if let Some(body) = branch {
+                    let environment = Environment::new_enclosed(self.environment.clone());
+                    return self.execute_block(body, environment);
+                }
+            },
+            Statement::Class(name, superclass, mixins, methods, setters) => {
+                let superclass = match superclass {
+                    Some(superclass) => match self.environment.borrow().get(superclass.clone())? {
+                        Value::Class(class) => Some(class),
+                        _ => return Err(RuntimeError::SuperclassMustBeClass(name.lexeme(), superclass.line()).into()),
+                    },
+                    None => None,
+                };
+                let mut included = Vec::with_capacity(mixins.len());
+                for mixin in mixins {
+                    match self.environment.borrow().get(mixin.clone())? {
+                        Value::Class(class) => included.push(class),
+                        _ => return Err(RuntimeError::MixinMustBeClass(mixin.lexeme(), name.lexeme(), mixin.line()).into()),
+                    }
+                }
+                // Methods of a subclass see the superclass as `super`, in a scope of its own
+                let closure = match &superclass {
+                    Some(superclass) => {
+                        let environment = Environment::new_enclosed(self.environment.clone());
+                        environment.borrow_mut().define_inner("super".to_string(), Value::Class(superclass.clone()));
+                        environment
+                    },
+                    None => self.environment.clone(),
+                };
+                let table = |members: &[SharedRef<FunctionDeclaration>]| -> HashMap<String, Function> {
+                    members.iter().map(|member| (member.name.lexeme(), Function::method(member.clone(), closure.clone()))).collect()
+                };
+                // The class's own members win over the mixins, and later mixins over earlier ones.
+                // Anything left is looked up in the superclass as usual.
+                let (mut all_methods, mut all_setters) = (HashMap::new(), HashMap::new());
+                for mixin in &included {
+                    all_methods.extend(mixin.methods.borrow().iter().map(|(name, method)| (name.clone(), method.clone())));
+                    all_setters.extend(mixin.setters.borrow().iter().map(|(name, setter)| (name.clone(), setter.clone())));
+                }
+                all_methods.extend(table(methods));
+                all_setters.extend(table(setters));
+                let (methods, setters) = (all_methods, all_setters);
+                // In interactive sessions, declaring a class again in the same scope updates it in
+                // place, so the instances that already exist get the new methods
+                let existing = match self.config.redefine_classes && !self.config.strict {
+                    true => self.environment.borrow().lookup_here(&name.lexeme()),
+                    false => None,
+                };
+                match existing {
+                    Some(Value::Class(class)) if class.name == name.lexeme() && class.superclass.as_deref() == superclass.as_ref() => {
+                        *class.methods.borrow_mut() = methods;
+                        *class.setters.borrow_mut() = setters;
+                    },
+                    _ => self.define(name, Value::Class(Class::new(name.lexeme(), superclass, methods, setters)), true)?,
+                }
             },
             Statement::Expression(expr) => {
                 self.evaluate_expression(expr)?;
             },
-            Statement::If(cond, then, els) => {
+            Statement::Function(declaration) => {
+                self.define(&declaration.name, Value::Function(Function::new(declaration.clone(), self.environment.clone())), true)?;
+            },
+            Statement::If(_, cond, then, els) => {
                 if is_truthy(self.evaluate_expression(cond)?) { // If truthy, run the then part
-                    self.execute_statement(then)?;
+                    return self.execute_statement(then);
                 } else if let Some(stmt) = els { // If there is an else clause, run that
-                    self.execute_statement(stmt)?;
+                    return self.execute_statement(stmt);
                 }
             },
-            Statement::Print(expr) => {
-                let value = self.evaluate_expression(expr)?;
-                println!("{}", value);
+            Statement::Print(_, exprs) => {
+                let mut line = Vec::with_capacity(exprs.len());
+                for expr in exprs {
+                    line.push(self.evaluate_expression(expr)?.to_string());
+                }
+                let text = line.join(" ");
+                writeln!(self.output, "{}", text).map_err(|e| RuntimeError::OutputFailed(e.to_string()))?;
+                for hooks in &mut self.hooks {
+                    hooks.on_print(&text);
+                }
+            },
+            Statement::Return(_, value) => {
+                let value = match value {
+                    Some(expr) => self.evaluate_expression(expr)?,
+                    None => Value::Nil,
+                };
+                return Ok(Flow::Return(value));
             },
-            Statement::Var(name, init) => {
+            Statement::Var(name, _, init) => {
                 let value = if let Some(expr) = init {
                     self.evaluate_expression(expr)?
                 } else {
                     Value::Nil
                 };
-                self.environment.borrow_mut().define_inner(name.lexeme(), value);
+                self.define(name, value, true)?;
+            },
+            Statement::Const(name, _, init) => {
+                let value = self.evaluate_expression(init)?;
+                self.define(name, value, false)?;
+            },
+            Statement::Destructure(pattern, init) => {
+                let value = self.evaluate_expression(init)?;
+                for (name, value) in pattern.names().iter().zip(destructure(pattern, value)?) {
+                    self.define(name, value, true)?;
+                }
             },
-            Statement::While(cond, body) => {
+            Statement::While(_, cond, body, increment, label) => {
                 while is_truthy(self.evaluate_expression(cond)?) {
-                    self.execute_statement(body)?;
+                    match self.execute_statement(body)? {
+                        Flow::Next => {},
+                        Flow::Break(target) if Flow::targets(&target, label) => break,
+                        Flow::Continue(target) if Flow::targets(&target, label) => {},
+                        flow => return Ok(flow), // Leaves this loop for an outer one or the function
+                    }
+                    if let Some(increment) = increment {
+                        self.evaluate_expression(increment)?;
+                    }
+                }
+            },
+            Statement::ForIn(keyword, name, iterable, body, label) => {
+                // The loop goes over the elements the collection had when it started
+                let items: Vec<Value> = match self.evaluate_expression(iterable)? {
+                    Value::List(list) => list.items.borrow().clone(),
+                    Value::Map(map) => map.keys(),
+                    Value::String(s) => s.chars().map(|c| Value::String(c.to_string())).collect(),
+                    other => return Err(RuntimeError::NotIterable(other.type_name(), keyword.line()).into()),
+                };
+                for item in items {
+                    // Every iteration gets its own variable, closures keep the element they saw
+                    let environment = Environment::new_enclosed(self.environment.clone());
+                    let added = Environment::binding_size(&name.lexeme(), &item);
+                    self.record_allocation(added);
+                    self.memory_used += added;
+                    environment.borrow_mut().define_inner(name.lexeme(), item);
+                    match self.execute_block(std::slice::from_ref(body.as_ref()), environment)? {
+                        Flow::Next => {},
+                        Flow::Break(target) if Flow::targets(&target, label) => break,
+                        Flow::Continue(target) if Flow::targets(&target, label) => {},
+                        flow => return Ok(flow),
+                    }
+                }
+            },
+            Statement::Throw(keyword, value) => {
+                let value = self.evaluate_expression(value)?;
+                self.error_span = keyword.span();
+                return Err(self.raise(value, keyword.line()));
+            },
+            Statement::Try(_, body, catches, finally) => {
+                let environment = Environment::new_enclosed(self.environment.clone());
+                let mut result = self.execute_block(body, environment);
+                let caught = match &result {
+                    Err(LoxError::Runtime(error)) if error.is_catchable() && !catches.is_empty() => {
+                        Some(self.caught_value(error)?)
+                    },
+                    _ => None,
+                };
+                if let Some(caught) = caught {
+                    let mut handler = None;
+                    for (name, class, body) in catches {
+                        if self.catches(class.as_ref(), &caught)? {
+                            handler = Some((name, body));
+                            break;
+                        }
+                    }
+                    if let Some((name, handler)) = handler {
+                        self.error_span = None;
+                        let environment = Environment::new_enclosed(self.environment.clone());
+                        let added = Environment::binding_size(&name.lexeme(), &caught);
+                        self.record_allocation(added);
+                        self.memory_used += added;
+                        environment.borrow_mut().define_inner(name.lexeme(), caught);
+                        result = self.execute_block(handler, environment);
+                    } else if matches!(result, Err(LoxError::Runtime(RuntimeError::Uncaught(..)))) {
+                        self.thrown = Some(caught); // No catch block takes it, an outer one might
+                    }
                 }
+                if let Some(finally) = finally {
+                    // The finally block runs in any case, leaving it early or failing in it wins
+                    let environment = Environment::new_enclosed(self.environment.clone());
+                    match self.execute_block(finally, environment)? {
+                        Flow::Next => {},
+                        flow => return Ok(flow),
+                    }
+                }
+                return result;
+            },
+            Statement::Break(_, label) => return Ok(Flow::Break(label.as_ref().map(Token::lexeme))),
+            Statement::Continue(_, label) => return Ok(Flow::Continue(label.as_ref().map(Token::lexeme))),
+        };
+        Ok(Flow::Next)
+    }
+
+    /// Executes statements one after another until one of them returns, breaks or continues
+    fn execute_statements(&mut self, stmts: &[Statement]) -> Result<Flow> {
+        for stmt in stmts {
+            match self.execute_statement(stmt)? {
+                Flow::Next => {},
+                flow => return Ok(flow),
             }
+        }
+        Ok(Flow::Next)
+    }
+
+    /// Runs statements that were parsed while the script runs, for the `eval`
+    /// natives. Gives the value of the last one if it is an expression
+    /// statement, nil otherwise. The parser keeps `return`, `break` and `continue` out.
+    fn execute_snippet(&mut self, stmts: &[Statement]) -> Result<Value> {
+        match stmts.split_last() {
+            Some((Statement::Expression(expr), init)) => {
+                self.execute_statements(init)?;
+                self.evaluate_expression(expr)
+            },
+            _ => self.execute_statements(stmts).map(|_| Value::Nil),
+        }
+    }
+
+    /// Executes statements in the given environment, which is a new scope
+    /// for a block or a function call
+    fn execute_block(&mut self, stmts: &[Statement], environment: Shared<Environment>) -> Result<Flow> {
+        self.in_scope(environment, |interpreter| interpreter.execute_statements(stmts))
+    }
+
+    /// Runs `run` in the given environment, which is a new scope. Afterwards,
+    /// the previous environment is active again and the variables of the scope are freed.
+    fn in_scope<T>(&mut self, environment: Shared<Environment>, run: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let prev_env = std::mem::replace(&mut self.environment, environment);
+        self.depth += 1;
+        self.report.peak_depth = self.report.peak_depth.max(self.depth);
+        let result = run(self);                         // When error, don't propagate immediately, because
+                                                        // the environment first has to be set back
+        let freed = self.environment.borrow().size();   // The variables of the scope are freed
+        self.memory_used = self.memory_used.saturating_sub(freed);
+        self.environment = prev_env;    // Set environment back to previous
+        self.depth -= 1;
+        result                          // Propagate error, if there is one
+    }
+
+    /// Binds a variable in the current scope, like a `var` declaration, or
+    /// a `const` one if it is not `mutable`
+    fn define(&mut self, name: &Token, value: Value, mutable: bool) -> Result<()> {
+        let added = Environment::binding_size(&name.lexeme(), &value);
+        for hooks in &mut self.hooks {
+            hooks.on_variable_write(&name.lexeme(), &value);
+        }
+        let replaced = if mutable {
+            self.environment.borrow_mut().define_inner(name.lexeme(), value)
+        } else {
+            self.environment.borrow_mut().define_constant(name.lexeme(), value)
         };
-        Ok(())
+        if replaced.is_some() && self.config.strict {
+            return Err(RuntimeError::AlreadyDefined(name.lexeme(), name.line()).into());
+        }
+        let freed = replaced.map_or(0, |old| Environment::binding_size(&name.lexeme(), &old));
+        self.account(added, freed, name.line())
+    }
+
+    /// Assigns an existing variable, like an `=` expression
+    fn assign(&mut self, name: &Token, value: Value) -> Result<()> {
+        let old = self.environment.borrow_mut().assign(name.clone(), value.clone())?; // Clone tokens
+        for hooks in &mut self.hooks {
+            hooks.on_variable_write(&name.lexeme(), &value);
+        }
+        self.account(value.heap_size(), old.heap_size(), name.line())
     }
 
     /// Takes in a reference to an Expression and evaluates it based on it's type.
-    /// Makes recursive calls to other expression evaluations.
+    /// Makes recursive calls to other expression evaluations. Remembers
+    /// the innermost expression an error came from.
     fn evaluate_expression(&mut self, expr: &Expression) -> Result<Value> {
+        let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_GROWTH, || self.evaluate_node(expr));
+        if result.is_err() && self.error_span.is_none() {
+            self.error_span = expr.token().and_then(Token::span);
+        }
+        result
+    }
+
+    fn evaluate_node(&mut self, expr: &Expression) -> Result<Value> {
         match expr {
             Expression::Assign(name, expr) => {
                 let value = self.evaluate_expression(expr)?;
-                self.environment.borrow_mut().assign(name.clone(), value.clone())?; // Clone tokens
+                self.assign(name, value.clone())?;
+                Ok(value)
+            },
+            Expression::Destructure(pattern, expr) => {
+                let value = self.evaluate_expression(expr)?;
+                for (name, value) in pattern.names().iter().zip(destructure(pattern, value.clone())?) {
+                    self.assign(name, value)?;
+                }
                 Ok(value)
             },
             Expression::Binary(left, op, right) => self.handle_binary(left, op.clone(), right),
+            Expression::Block(_, stmts, value) => {
+                let environment = Environment::new_enclosed(self.environment.clone());
+                self.in_scope(environment, |interpreter| {
+                    interpreter.execute_statements(stmts)?; // The parser keeps `return`, `break` and `continue` inside
+                    match value {
+                        Some(value) => interpreter.evaluate_expression(value),
+                        None => Ok(Value::Nil),
+                    }
+                })
+            },
+            Expression::If(_, cond, then, els) => {
+                if is_truthy(self.evaluate_expression(cond)?) {
+                    self.evaluate_expression(then)
+                } else if let Some(els) = els {
+                    self.evaluate_expression(els)
+                } else {
+                    Ok(Value::Nil)
+                }
+            },
+            Expression::Call(..) | Expression::Get(..) | Expression::OptionalGet(..) | Expression::Index(..) | Expression::Slice(..) => {
+                Ok(self.link_node(expr)?.unwrap_or(Value::Nil))
+            }
+            Expression::Set(object, name, value) => {
+                let instance = match self.evaluate_expression(object)? {
+                    Value::Instance(instance) => instance,
+                    _ => return Err(RuntimeError::OnlyInstancesHaveFields(name.lexeme(), name.line()).into()),
+                };
+                let value = self.evaluate_expression(value)?;
+                self.set_property(instance, name, value.clone())?;
+                Ok(value)
+            }
             Expression::Grouping(expr) => self.evaluate_expression(expr),
+            Expression::IndexSet(object, bracket, index, value) => {
+                let object = self.evaluate_expression(object)?;
+                let index = self.evaluate_expression(index)?;
+                let value = self.evaluate_expression(value)?;
+                match object {
+                    Value::List(list) => {
+                        if list.is_frozen() {
+                            return Err(RuntimeError::Frozen("list", bracket.line()).into());
+                        }
+                        // Checked last, the value might have changed the list
+                        let position = position(&index, list.len(), "list", false, bracket.line())?;
+                        let replaced = list.set(position, value.clone());
+                        let freed = replaced.map_or(0, |old| List::item_size(&old));
+                        self.account(List::item_size(&value), freed, bracket.line())?;
+                    }
+                    Value::Map(map) => {
+                        if map.is_frozen() {
+                            return Err(RuntimeError::Frozen("map", bracket.line()).into());
+                        }
+                        let key = map_key(&index, bracket)?;
+                        self.insert(&map, key, value.clone(), bracket.line())?;
+                    }
+                    Value::String(_) => return Err(RuntimeError::ImmutableString(bracket.line()).into()),
+                    other => return Err(RuntimeError::NotIndexable(other.type_name(), bracket.line()).into()),
+                }
+                Ok(value)
+            }
+            Expression::Lambda(declaration) => Ok(Value::Function(Function::new(declaration.clone(), self.environment.clone()))),
+            Expression::List(bracket, items) => {
+                let mut values = Vec::with_capacity(items.len());
+                for item in items {
+                    values.push(self.evaluate_expression(item)?);
+                }
+                self.new_list(values, bracket.line())
+            }
+            Expression::Map(brace, entries) => {
+                let mut map = IndexMap::with_capacity(entries.len());
+                for (key, value) in entries {
+                    let key = self.evaluate_expression(key)?;
+                    let key = map_key(&key, brace)?;
+                    map.insert(key, self.evaluate_expression(value)?); // A repeated key keeps the last value
+                }
+                self.new_map(map, brace.line())
+            }
             Expression::Literal(val) => Ok(val.to_owned()),
             Expression::Logical(left, op, right) => {
                 let left_val = self.evaluate_expression(left)?;
-                if op.token_type() == TokenType::Or {
+                if op.token_type() == TokenType::QuestionQuestion {
+                    if left_val != Value::Nil { // Only nil falls back to the right side, false does not
+                        return Ok(left_val);
+                    }
+                } else if op.token_type() == TokenType::Or {
                     if is_truthy(left_val.clone()) { // Cloning unnecessary, but idc.
                         return Ok(left_val);
                     }
@@ -122,10 +931,100 @@ impl Interpreter {
                 self.evaluate_expression(right)
             }
             Expression::Unary(op, right) => self.handle_unary(op.clone(), right),
+            Expression::Super(keyword, method) => {
+                let superclass = self.environment.borrow().get(keyword.clone())?;
+                let instance = self.environment.borrow().lookup("this");
+                match (superclass, instance) {
+                    (Value::Class(superclass), Some(Value::Instance(instance))) => superclass
+                        .find_method(&method.lexeme())
+                        .map(|found| Value::Function(found.bind(instance)))
+                        .ok_or_else(|| RuntimeError::UndefinedProperty(method.lexeme(), method.line()).into()),
+                    _ => Err(RuntimeError::UndefinedProperty(method.lexeme(), method.line()).into()),
+                }
+            }
+            Expression::This(keyword) => self.environment.borrow().get(keyword.clone()),
             Expression::Variable(name) => self.environment.borrow().get(name.clone()),
         }
     }
 
+    /// Evaluates the object of a link in a chain of calls, property
+    /// accesses, indexes and slices, like `a?.b.c()`. `None` if a `?.` in
+    /// the chain found nil, which skips the rest of the chain, arguments
+    /// and indexes included. Other expressions are evaluated as usual.
+    fn evaluate_link(&mut self, expr: &Expression) -> Result<Option<Value>> {
+        let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_GROWTH, || self.link_node(expr));
+        if result.is_err() && self.error_span.is_none() {
+            self.error_span = expr.token().and_then(Token::span);
+        }
+        result
+    }
+
+    fn link_node(&mut self, expr: &Expression) -> Result<Option<Value>> {
+        let object = match expr {
+            Expression::Call(object, ..)
+            | Expression::Get(object, _)
+            | Expression::OptionalGet(object, _)
+            | Expression::Index(object, ..)
+            | Expression::Slice(object, ..) => object,
+            _ => return self.evaluate_node(expr).map(Some),
+        };
+        let object = match self.evaluate_link(object)? {
+            Some(Value::Nil) if matches!(expr, Expression::OptionalGet(..)) => return Ok(None),
+            Some(object) => object,
+            None => return Ok(None),
+        };
+
+        match expr {
+            Expression::Call(_, paren, args) => {
+                let mut arguments = Vec::with_capacity(args.len());
+                for arg in args {
+                    arguments.push(self.evaluate_expression(arg)?);
+                }
+                self.call(object, arguments, paren.line())
+            }
+            Expression::Get(_, name) | Expression::OptionalGet(_, name) => get_property(object, name),
+            Expression::Index(_, bracket, index) => {
+                let index = self.evaluate_expression(index)?;
+                match object {
+                    Value::List(list) => {
+                        let position = position(&index, list.len(), "list", false, bracket.line())?;
+                        Ok(list.get(position).unwrap_or(Value::Nil)) // The index was checked against the length
+                    }
+                    Value::String(s) => {
+                        let position = position(&index, s.chars().count(), "string", false, bracket.line())?;
+                        Ok(Value::String(s.chars().nth(position).map(String::from).unwrap_or_default()))
+                    }
+                    Value::Map(map) => {
+                        let key = map_key(&index, bracket)?;
+                        map.get(&key).ok_or_else(|| RuntimeError::MissingKey(index.to_text(), bracket.line()).into())
+                    }
+                    other => Err(RuntimeError::NotIndexable(other.type_name(), bracket.line()).into()),
+                }
+            }
+            Expression::Slice(_, bracket, start, end) => {
+                let start = start.as_ref().map(|start| self.evaluate_expression(start)).transpose()?;
+                let end = end.as_ref().map(|end| self.evaluate_expression(end)).transpose()?;
+                let (length, kind) = match &object {
+                    Value::List(list) => (list.len(), "list"),
+                    Value::String(s) => (s.chars().count(), "string"),
+                    other => return Err(RuntimeError::NotSliceable(other.type_name(), bracket.line()).into()),
+                };
+                let start = start.map_or(Ok(0), |start| position(&start, length, kind, true, bracket.line()))?;
+                let end = end.map_or(Ok(length), |end| position(&end, length, kind, true, bracket.line()))?.max(start);
+                match object {
+                    Value::List(list) => {
+                        let items = list.items.borrow()[start..end].to_vec();
+                        self.new_list(items, bracket.line())
+                    }
+                    Value::String(s) => Ok(Value::String(s.chars().skip(start).take(end - start).collect())),
+                    _ => unreachable!("only lists and strings have a length"),
+                }
+            }
+            _ => unreachable!("only links of a chain get here"),
+        }
+        .map(Some)
+    }
+
     /// Outsourced binary expression evaluation. Takes in borrows, not Box'es
     fn handle_binary(&mut self, left: &Expression, operator: Token, right: &Expression) -> Result<Value> {
         let left_val = self.evaluate_expression(left)?;
@@ -133,46 +1032,59 @@ impl Interpreter {
     
         match operator.token_type() {
             // Arithmetic binary expressions
-            TokenType::Minus => Ok(Value::Number(
-                get_number_operand(left_val)? - get_number_operand(right_val)?, // Subtraction
-            )),
-            TokenType::Slash => Ok(Value::Number(
-                get_number_operand(left_val)? / get_number_operand(right_val)?, // Division
-            )),
-            TokenType::Star => Ok(Value::Number(
-                get_number_operand(left_val)? * get_number_operand(right_val)?, // Multiplication
-            )),
+            // Subtraction
+            TokenType::Minus => arithmetic(Some(i64::checked_sub), Number::checked_sub, left_val, right_val, &operator),
+            // Division, its result is a number even for ints
+            TokenType::Slash => arithmetic(None, Number::checked_div, left_val, right_val, &operator),
+            TokenType::Star => match (left_val, right_val) {
+                // A string times a count repeats it, the count can be on either side
+                (Value::String(text), count @ (Value::Number(_) | Value::Int(_)))
+                | (count @ (Value::Number(_) | Value::Int(_)), Value::String(text)) => self.repeat(text, count, &operator),
+                (left_val, right_val) => {
+                    arithmetic(Some(i64::checked_mul), Number::checked_mul, left_val, right_val, &operator) // Multiplication
+                },
+            },
+            // Floor division
+            TokenType::TildeSlash => arithmetic(Some(floor_div), Number::checked_floor_div, left_val, right_val, &operator),
             TokenType::Plus => {
                 // If both expressions (left and right) are numbers, we want an addition
-                if let Value::Number(left_num) = left_val {
-                    if let Value::Number(right_num) = right_val {
-                        return Ok(Value::Number(left_num + right_num));
-                    }
-                }
-                // If both are strings, we want a string concatenation
-                if let Value::String(left_str) = left_val {
-                    if let Value::String(right_str) = right_val {
-                        return Ok(Value::String(left_str + &right_str));
-                    }
+                if left_val.as_number().is_some() && right_val.as_number().is_some() {
+                    return arithmetic(Some(i64::checked_add), Number::checked_add, left_val, right_val, &operator);
                 }
-                // If both don't match up, we want an error
-                Err(RuntimeError::IncompatibleTypes.into())
+                // If both are strings, we want a string concatenation. A number next
+                // to a string is turned into its text first, so messages are easy to build.
+                let (left_str, right_str) = match (left_val, right_val) {
+                    (Value::String(left_str), Value::String(right_str)) => (left_str, right_str),
+                    (Value::String(left_str), right @ (Value::Number(_) | Value::Int(_))) => (left_str, right.to_string()),
+                    (left @ (Value::Number(_) | Value::Int(_)), Value::String(right_str)) => (left.to_string(), right_str),
+                    // If both don't match up, we want an error
+                    _ => return Err(RuntimeError::IncompatibleTypes(operator.line()).into()),
+                };
+                // The result doesn't live in a variable yet, but building it already costs memory
+                self.check_memory(left_str.len() + right_str.len(), operator.line())?;
+                self.record_allocation(left_str.len() + right_str.len());
+                self.check_string_length(left_str.chars().count() + right_str.chars().count(), operator.line())?;
+                Ok(Value::String(left_str + &right_str))
             }
     
             // Comparison binary expressions
-            TokenType::Greater => Ok(Value::Bool(
-                get_number_operand(left_val)? > get_number_operand(right_val)?, // Greater
-            )),
-            TokenType::GreaterEqual => Ok(Value::Bool(
-                get_number_operand(left_val)? >= get_number_operand(right_val)?, // Greater or Equal
-            )),
-            TokenType::Less => Ok(Value::Bool(
-                get_number_operand(left_val)? < get_number_operand(right_val)?, // Less than
-            )),
-            TokenType::LessEqual => Ok(Value::Bool(
-                get_number_operand(left_val)? <= get_number_operand(right_val)?, // Less than or Equal
-            )),
+            TokenType::Greater => compare(left_val, right_val, &operator, cmp::Ordering::is_gt), // Greater
+            TokenType::GreaterEqual => compare(left_val, right_val, &operator, cmp::Ordering::is_ge), // Greater or Equal
+            TokenType::Less => compare(left_val, right_val, &operator, cmp::Ordering::is_lt), // Less than
+            TokenType::LessEqual => compare(left_val, right_val, &operator, cmp::Ordering::is_le), // Less than or Equal
     
+            // Type tests, a class matches its instances and those of its subclasses
+            TokenType::Is => match right_val {
+                Value::Class(class) => {
+                    Ok(Value::Bool(matches!(left_val, Value::Instance(instance) if instance.class.is_subclass_of(&class))))
+                },
+                Value::String(name) => match left_val.has_type(&name) {
+                    Some(has) => Ok(Value::Bool(has)),
+                    None => Err(RuntimeError::UnknownTypeName(name, operator.line()).into()),
+                },
+                other => Err(RuntimeError::InvalidTypeTest(other.type_name(), operator.line()).into()),
+            },
+
             // Equality binary expressions
             TokenType::BangEqual => Ok(Value::Bool(!is_equal(left_val, right_val))), // Not equal
             TokenType::EqualEqual => Ok(Value::Bool(is_equal(left_val, right_val))), // Equal
@@ -181,17 +1093,544 @@ impl Interpreter {
         }
     }
 
+    /// Calls a function value with already evaluated arguments
+    fn call(&mut self, callee: Value, arguments: Vec<Value>, line: u32) -> Result<Value> {
+        for hooks in &mut self.hooks {
+            hooks.on_function_call(&callee, &arguments);
+        }
+
+        match callee {
+            Value::NativeFunction(native) => {
+                if arguments.len() > native.arity || arguments.len() + native.optional < native.arity {
+                    let expected = if arguments.len() > native.arity { native.arity } else { native.arity - native.optional };
+                    return Err(RuntimeError::WrongArgumentCount(expected, arguments.len(), line).into());
+                }
+                self.frames.push(Frame { function: native.name.to_string(), line });
+                let result = (native.function)(self, arguments, line);
+                self.frames.pop();
+                match result? {
+                    Value::String(s) => {
+                        self.check_string_length(s.chars().count(), line)?;
+                        Ok(Value::String(s))
+                    }
+                    value => Ok(value),
+                }
+            }
+            Value::Function(function) => self.call_function(&function, arguments, line),
+            Value::Class(class) => {
+                // Calling a class creates an instance and runs its initializer, if it has one
+                let instance = Instance::new(class.clone());
+                match class.find_method("init") {
+                    Some(initializer) => self.call_function(&initializer.bind(instance), arguments, line),
+                    None if !arguments.is_empty() => Err(RuntimeError::WrongArgumentCount(0, arguments.len(), line).into()),
+                    None => Ok(Value::Instance(instance)),
+                }
+            }
+            _ => Err(RuntimeError::NotCallable(line).into()),
+        }
+    }
+
+    /// Runs the body of a Lox function in a new scope below its closure.
+    /// An async function only starts running on the event loop, the call
+    /// returns the future of its result.
+    fn call_function(&mut self, function: &Function, arguments: Vec<Value>, line: u32) -> Result<Value> {
+        let declaration = &function.declaration;
+        if arguments.len() != declaration.params.len() {
+            return Err(RuntimeError::WrongArgumentCount(declaration.params.len(), arguments.len(), line).into());
+        }
+        if declaration.is_async {
+            return self.start_async(function.clone(), arguments, line);
+        }
+        #[cfg(feature = "jit")]
+        if let Some(value) = self.run_compiled(function, &arguments) {
+            return Ok(value);
+        }
+        self.run_function(function, arguments, line)
+    }
+
+    /// Runs the call as native code once the function is hot, see
+    /// [Config::jit_threshold]. `None` if the interpreter has to run it:
+    /// while the function is cold or cannot be compiled, and when the code
+    /// gave up on the way (an int overflowed, a limit was reached), so the
+    /// interpreter reports that as usual. Hooks and the memory limit keep
+    /// every function interpreted, compiled code doesn't tell them about
+    /// its statements and variables.
+    #[cfg(feature = "jit")]
+    fn run_compiled(&mut self, function: &Function, arguments: &[Value]) -> Option<Value> {
+        let threshold = self.config.jit_threshold?;
+        if !self.hooks.is_empty() || self.config.memory_limit.is_some() || function.initializer {
+            return None;
+        }
+        let kinds = arguments.iter().map(jit::Kind::of).collect::<Option<Vec<_>>>()?;
+        let compiled = self.jit.lookup(&function.declaration, kinds, threshold, self.config.strict)?;
+        // The code calls itself directly, so the name has to refer to this function
+        if compiled.recursive {
+            match function.closure.borrow().lookup(&function.declaration.name.lexeme()) {
+                Some(Value::Function(named)) if SharedRef::ptr_eq(&named.declaration, &function.declaration) => {},
+                _ => return None,
+            }
+        }
+        let (value, steps) = stacker::maybe_grow(STACK_RED_ZONE, STACK_GROWTH, || compiled.run(self, arguments))?;
+        self.steps = steps;
+        Some(value)
+    }
+
+    /// The part of [Interpreter::call_function] that runs the body, with
+    /// the arguments already checked
+    fn run_function(&mut self, function: &Function, arguments: Vec<Value>, line: u32) -> Result<Value> {
+        let declaration = &function.declaration;
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::StackOverflow(MAX_CALL_DEPTH, line).into());
+        }
+
+        // The parameters live in the scope of the call and get freed with it
+        let environment = Environment::new_enclosed(function.closure.clone());
+        for (param, value) in declaration.params.iter().zip(arguments) {
+            let added = Environment::binding_size(&param.name.lexeme(), &value);
+            self.record_allocation(added);
+            self.memory_used += added;
+            environment.borrow_mut().define_inner(param.name.lexeme(), value);
+        }
+        self.frames.push(Frame { function: function.name(), line });
+        let result = self.execute_block(&declaration.body, environment);
+        self.frames.pop();
+        let value = match result? {
+            Flow::Return(value) => value,
+            Flow::Next | Flow::Break(_) | Flow::Continue(_) => Value::Nil, // The parser keeps them inside of loops
+        };
+        if function.initializer {
+            return Ok(function.closure.borrow().lookup("this").unwrap_or(value)); // Initializers return the instance
+        }
+        Ok(value)
+    }
+
+    /// Starts an async call as a coroutine on the event loop. Its future
+    /// completes with what the function returns, or with what it throws.
+    /// Fails when no thread is left for the coroutine.
+    fn start_async(&mut self, function: Function, arguments: Vec<Value>, line: u32) -> Result<Value> {
+        let future = Future::new();
+        let settled = future.clone();
+        let call = natives::start_coroutine(self, "async call", line, move |interpreter, _, awaiter| {
+            interpreter.awaiter = Some(awaiter);
+            let result = match interpreter.run_function(&function, arguments, line) {
+                Ok(value) => Ok(value),
+                Err(LoxError::Runtime(error)) if error.is_catchable() => Err(interpreter.caught_value(&error)?),
+                Err(error) => return Err(error), // Limits and cancellation end the whole run
+            };
+            settled.complete(result);
+            Ok(Value::Nil)
+        })?;
+        self.events.borrow_mut().start(call);
+        Ok(Value::Future(future))
+    }
+
+    /// `await`: waits for the future to complete and gives its value, or
+    /// throws what its async call threw. Other values are there already.
+    /// An async call gets suspended meanwhile, so the event loop can go on
+    /// with the others. Anywhere else, the event loop runs right here.
+    fn await_value(&mut self, value: Value, line: u32) -> Result<Value> {
+        let Value::Future(future) = value else {
+            return Ok(value);
+        };
+        match &self.awaiter {
+            Some(awaiter) => {
+                while !future.is_done() {
+                    awaiter.suspend(Value::Future(future.clone()))?;
+                }
+            }
+            None => self.run_events(Some(&future), line)?,
+        }
+        match future.settled() {
+            Some(Ok(value)) => Ok(value),
+            Some(Err(thrown)) => Err(self.raise(thrown, line)),
+            None => Err(RuntimeError::NeverCompletes(line).into()), // Not reached, both wait until it completed
+        }
+    }
+
+    /// Runs the event loop until the future completes, or without one
+    /// until nothing is left to do. What the async calls print goes to the
+    /// output. Fails on `line` if nothing can complete the future anymore.
+    fn run_events(&mut self, until: Option<&Future>, line: u32) -> Result<()> {
+        loop {
+            if until.is_some_and(Future::is_done) {
+                return Ok(());
+            }
+            self.check_interrupted()?;
+            let next = self.events.borrow_mut().next(Instant::now());
+            match next {
+                Next::Resume(call) => self.resume_async(call)?,
+                Next::Sleep(due) => self.sleep(due.saturating_duration_since(Instant::now()))?,
+                Next::Idle if until.is_some() => return Err(RuntimeError::NeverCompletes(line).into()),
+                Next::Idle => return Ok(()),
+            }
+        }
+    }
+
+    /// Runs the async calls that can go on without waiting for a timer,
+    /// see [Interpreter::run_pending]
+    fn run_ready(&mut self) -> Result<Option<Instant>> {
+        loop {
+            self.check_interrupted()?;
+            let next = self.events.borrow_mut().next(Instant::now());
+            match next {
+                Next::Resume(call) => self.resume_async(call)?,
+                Next::Sleep(due) => return Ok(Some(due)),
+                Next::Idle => return Ok(None),
+            }
+        }
+    }
+
+    /// Resumes an async call until it awaits a future that isn't done, then
+    /// parks it until that future completes
+    fn resume_async(&mut self, call: Coroutine) -> Result<()> {
+        let result = call.resume(Value::Nil);
+        self.output.write_all(&call.take_printed()).map_err(|e| RuntimeError::OutputFailed(e.to_string()))?;
+        if let Some(Value::Future(future)) = result? {
+            if call.status() == Status::Suspended {
+                self.events.borrow_mut().wait(call, future);
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts an executed statement and enforces the step and time limits
+    fn count_step(&mut self) -> Result<()> {
+        self.steps += 1;
+        if let Some(limit) = self.config.step_limit {
+            if self.steps > limit {
+                return Err(RuntimeError::StepLimitExceeded(limit).into());
+            }
+        }
+        self.check_interrupted()
+    }
+
+    /// Fails if the execution got cancelled or ran out of time
+    fn check_interrupted(&self) -> Result<()> {
+        if self.cancellation.is_cancelled() {
+            return Err(RuntimeError::Cancelled.into());
+        }
+        if let (Some(deadline), Some(limit)) = (self.deadline, self.config.time_limit) {
+            if Instant::now() > deadline {
+                return Err(RuntimeError::TimeLimitExceeded(limit).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Blocks for the given duration, but wakes up regularly so
+    /// cancellation and the time limit still take effect
+    fn sleep(&self, duration: Duration) -> Result<()> {
+        const SLICE: Duration = Duration::from_millis(10);
+
+        let end = Instant::now() + duration;
+        loop {
+            self.check_interrupted()?;
+            let remaining = end.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(());
+            }
+            std::thread::sleep(remaining.min(SLICE));
+        }
+    }
+
+    /// Books `added` bytes and releases `freed` bytes, then checks the limit
+    fn account(&mut self, added: usize, freed: usize, line: u32) -> Result<()> {
+        self.record_allocation(added);
+        self.memory_used = (self.memory_used + added).saturating_sub(freed);
+        self.check_memory(0, line)
+    }
+
+    /// Counts an allocation of `bytes` bytes in the [RunReport]
+    fn record_allocation(&mut self, bytes: usize) {
+        if bytes > 0 {
+            self.report.allocations += 1;
+            self.report.allocated_bytes += bytes;
+        }
+    }
+
+    /// Fails if allocating `extra` bytes on top of the used memory would exceed the limit
+    fn check_memory(&self, extra: usize, line: u32) -> Result<()> {
+        match self.config.memory_limit {
+            Some(limit) if self.memory_used + extra > limit => {
+                Err(RuntimeError::MemoryLimitExceeded(limit, line).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Repeats a string `count` times, for `*` with a string operand
+    fn repeat(&mut self, text: String, count: Value, operator: &Token) -> Result<Value> {
+        let invalid = || RuntimeError::InvalidRepeatCount(count.to_string(), operator.line());
+        let count = count.to_count().ok_or_else(invalid)?;
+        // Checked before building the result, a huge count would take all memory
+        let bytes = text.len().checked_mul(count).ok_or_else(invalid)?;
+        self.check_memory(bytes, operator.line())?;
+        self.check_string_length(text.chars().count().saturating_mul(count), operator.line())?;
+        // Without a memory limit the size is only known to be too large once allocating fails
+        let mut repeated = String::new();
+        repeated.try_reserve_exact(bytes).map_err(|_| RuntimeError::OutOfMemory(bytes, operator.line()))?;
+        self.record_allocation(bytes);
+        for _ in 0..count {
+            repeated.push_str(&text);
+        }
+        Ok(Value::String(repeated))
+    }
+
+    /// Fails if a string of `length` characters exceeds the size limit
+    fn check_string_length(&self, length: usize, line: u32) -> Result<()> {
+        match self.config.size_limits.string_length {
+            Some(limit) if length > limit => Err(RuntimeError::SizeLimitExceeded("string length", limit, line).into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Creates a list of the values, within the size and memory limits.
+    /// Like fields, the elements are counted when they are set.
+    fn new_list(&mut self, values: Vec<Value>, line: u32) -> Result<Value> {
+        self.check_list_length(values.len(), line)?;
+        let added = values.iter().map(List::item_size).sum();
+        self.account(added, 0, line)?;
+        Ok(Value::List(List::new(values)))
+    }
+
+    /// Throws the value, like a `throw` statement on `line`
+    fn raise(&mut self, value: Value, line: u32) -> LoxError {
+        // Errors show their class and message, other values are printed as they are
+        let text = match &value {
+            Value::Instance(instance) => match instance.field("message") {
+                Some(message) => format!("{}: {}", instance.class.name, message),
+                None => value.to_string(),
+            },
+            _ => value.to_string(),
+        };
+        self.thrown = Some(value);
+        RuntimeError::Uncaught(text, line).into()
+    }
+
+    /// The value a `catch` block gets for a catchable error: the thrown
+    /// value, or the [error_instance](Self::error_instance) of a runtime error
+    fn caught_value(&mut self, error: &RuntimeError) -> Result<Value> {
+        match self.thrown.take() {
+            Some(value) if matches!(error, RuntimeError::Uncaught(..)) => Ok(value),
+            _ => self.error_instance(error),
+        }
+    }
+
+    /// The value a `catch` block gets for a runtime error: an instance of
+    /// the standard error class, with the message, line and code of the error
+    fn error_instance(&mut self, error: &RuntimeError) -> Result<Value> {
+        let class = match self.global(error.class_name()).or_else(|| self.global("Error")) {
+            Some(Value::Class(class)) => class,
+            _ => Class::new(error.class_name().to_string(), None, HashMap::new(), HashMap::new()), // The script replaced them
+        };
+        let instance = Instance::new(class);
+        let line = error.line().map_or(Value::Nil, |line| Value::Int(i64::from(line)));
+        let fields = [
+            ("message", Value::String(error.message())),
+            ("line", line),
+            ("code", Value::String(error.code().to_string())),
+        ];
+        for (name, value) in fields {
+            let added = Environment::binding_size(name, &value);
+            instance.set_field(name.to_string(), value);
+            self.account(added, 0, error.line().unwrap_or_default())?;
+        }
+        Ok(Value::Instance(instance))
+    }
+
+    /// Whether a `catch` block restricted to `class` takes the value. Without
+    /// a class it takes everything, otherwise instances of the class and its subclasses.
+    fn catches(&self, class: Option<&Token>, value: &Value) -> Result<bool> {
+        let Some(class) = class else {
+            return Ok(true);
+        };
+        match self.environment.borrow().get(class.clone())? {
+            Value::Class(class) => Ok(matches!(value, Value::Instance(instance) if instance.class.is_subclass_of(&class))),
+            _ => Err(RuntimeError::CatchNotClass(class.lexeme(), class.line()).into()),
+        }
+    }
+
+    /// Assigns a property of an instance, like `instance.name = value`
+    fn set_property(&mut self, instance: Instance, name: &Token, value: Value) -> Result<()> {
+        if instance.is_frozen() {
+            return Err(RuntimeError::Frozen("instance", name.line()).into());
+        }
+        // A setter intercepts the assignment, unless it is the one assigning the property
+        let property = (instance.fields.address(), name.lexeme());
+        if !self.setters.contains(&property) {
+            if let Some(setter) = instance.class.find_setter(&name.lexeme()) {
+                self.setters.push(property);
+                let result = self.call(Value::Function(setter.bind(instance)), vec![value], name.line());
+                self.setters.pop();
+                return result.map(|_| ());
+            }
+        }
+        let added = Environment::binding_size(&name.lexeme(), &value); // A field takes as much as a variable
+        let replaced = instance.set_field(name.lexeme(), value);
+        let freed = replaced.map_or(0, |old| Environment::binding_size(&name.lexeme(), &old));
+        self.account(added, freed, name.line())
+    }
+
+    /// Value of a variable in the global scope, ignoring the local ones that shadow it
+    fn global(&self, name: &str) -> Option<Value> {
+        let value = self.global_environment().borrow().lookup(name);
+        value
+    }
+
+    /// The outermost scope, which holds the globals
+    fn global_environment(&self) -> Shared<Environment> {
+        let mut environment = self.environment.clone();
+        loop {
+            let enclosing = environment.borrow().enclosing();
+            match enclosing {
+                Some(enclosing) => environment = enclosing,
+                None => return environment,
+            }
+        }
+    }
+
+    /// Creates a map of the entries, within the size and memory limits
+    fn new_map(&mut self, entries: IndexMap<Key, Value>, line: u32) -> Result<Value> {
+        self.check_map_entries(entries.len(), line)?;
+        let added = entries.iter().map(|(key, value)| Map::entry_size(key, value)).sum();
+        self.account(added, 0, line)?;
+        Ok(Value::Map(Map::new(entries)))
+    }
+
+    /// Sets the value of a key in a map, a new key must not exceed the size limit
+    fn insert(&mut self, map: &Map, key: Key, value: Value, line: u32) -> Result<()> {
+        if !map.contains(&key) {
+            self.check_map_entries(map.len() + 1, line)?;
+        }
+        let added = Map::entry_size(&key, &value);
+        let freed = map.insert(key.clone(), value).map_or(0, |old| Map::entry_size(&key, &old));
+        self.account(added, freed, line)
+    }
+
+    /// Fails if a list of `length` elements exceeds the size limit
+    fn check_list_length(&self, length: usize, line: u32) -> Result<()> {
+        match self.config.size_limits.list_length {
+            Some(limit) if length > limit => Err(RuntimeError::SizeLimitExceeded("list length", limit, line).into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails if a map of `entries` entries exceeds the size limit
+    fn check_map_entries(&self, entries: usize, line: u32) -> Result<()> {
+        match self.config.size_limits.map_entries {
+            Some(limit) if entries > limit => Err(RuntimeError::SizeLimitExceeded("number of map entries", limit, line).into()),
+            _ => Ok(()),
+        }
+    }
+
     fn handle_unary(&mut self, operator: Token, right: &Expression) -> Result<Value> {
         let right_val = self.evaluate_expression(right)?;
     
         match operator.token_type() {
-            TokenType::Minus => Ok(Value::Number(-get_number_operand(right_val)?)), // Negation of a number
+            TokenType::Minus => match right_val { // Negation of a number
+                Value::Int(int) => {
+                    int.checked_neg().map(Value::Int).ok_or_else(|| RuntimeError::InvalidArithmetic(operator.line()).into())
+                },
+                right_val => Ok(Value::Number(-get_number_operand(right_val, &operator)?)),
+            },
             TokenType::Bang => Ok(Value::Bool(!is_truthy(right_val))), // Negation of a boolean expression
+            TokenType::Await => self.await_value(right_val, operator.line()),
             _ => Err(RuntimeError::Unknown.into()), // Shouldn't be reached :)
         }
     }
 }
 
+/// Passes diagnostics on and counts them, for [RunReport::diagnostics].
+/// Diagnostics of snippets run with [Interpreter::run_at] get their file.
+struct CountingReporter<'a> {
+    inner: &'a mut dyn Reporter,
+    count: usize,
+    /// Attached to every diagnostic that doesn't name a file yet
+    file: Option<String>,
+}
+
+impl Reporter for CountingReporter<'_> {
+    fn report(&mut self, mut diagnostic: Diagnostic) {
+        self.count += 1;
+        if diagnostic.file.is_none() {
+            diagnostic.file = self.file.clone();
+        }
+        self.inner.report(diagnostic);
+    }
+}
+
+/// The values the names of a pattern get from the destructured value, in
+/// the order of the names
+fn destructure(pattern: &Pattern, value: Value) -> Result<Vec<Value>> {
+    let line = pattern.token().line();
+    match (pattern, value) {
+        (Pattern::List(_, names), Value::List(list)) => {
+            let items = list.items.borrow();
+            if items.len() != names.len() {
+                return Err(RuntimeError::PatternLength(names.len(), items.len(), line).into());
+            }
+            Ok(items.clone())
+        }
+        (Pattern::Map(_, names), Value::Map(map)) => names
+            .iter()
+            .map(|name| {
+                map.get(&Key::String(name.lexeme()))
+                    .ok_or_else(|| RuntimeError::MissingKey(Value::String(name.lexeme()).to_text(), name.line()).into())
+            })
+            .collect(),
+        (Pattern::Map(_, names), Value::Instance(instance)) => {
+            names.iter().map(|name| get_property(Value::Instance(instance.clone()), name)).collect()
+        }
+        (Pattern::List(..), other) => Err(RuntimeError::NotDestructurable("list", other.type_name(), line).into()),
+        (Pattern::Map(..), other) => Err(RuntimeError::NotDestructurable("map", other.type_name(), line).into()),
+    }
+}
+
+/// Looks up a property: a field or method of an instance (fields shadow
+/// methods), or a pseudo-property of a built-in value, like the length of a string
+fn get_property(object: Value, name: &Token) -> Result<Value> {
+    match (object, name.lexeme().as_str()) {
+        (Value::Instance(instance), property) => instance
+            .field(property)
+            .or_else(|| instance.class.find_method(property).map(|method| Value::Function(method.bind(instance.clone()))))
+            .ok_or_else(|| RuntimeError::UndefinedProperty(name.lexeme(), name.line()).into()),
+        (Value::String(s), "length") => Ok(Value::Int(s.chars().count() as i64)),
+        (Value::List(list), "length") => Ok(Value::Int(list.len() as i64)),
+        (Value::Map(map), "length") => Ok(Value::Int(map.len() as i64)),
+        (Value::NativeFunction(native), "arity") => Ok(Value::Int(native.arity as i64)),
+        (Value::Function(function), "arity") => Ok(Value::Int(function.arity() as i64)),
+        (Value::Class(class), "arity") => {
+            Ok(Value::Int(class.find_method("init").map_or(0, |initializer| initializer.arity()) as i64))
+        }
+        _ => Err(RuntimeError::UndefinedProperty(name.lexeme(), name.line()).into()),
+    }
+}
+
+/// Checks that the index refers to one of the `length` elements of a list
+/// or string (`kind`) and returns its position. Negative ints count from
+/// the end, whole numbers work like ints and everything else is no index
+/// at all. The bounds of a slice (`past_end`) may also be the length.
+fn position(index: &Value, length: usize, kind: &'static str, past_end: bool, line: u32) -> Result<usize> {
+    let int = match index {
+        Value::Int(int) => *int,
+        Value::Number(number) => number.to_i64().ok_or_else(|| RuntimeError::InvalidIndex(number.to_string(), line))?,
+        other => return Err(RuntimeError::InvalidIndex(other.to_text(), line).into()),
+    };
+    let position = match usize::try_from(int) {
+        Ok(position) => Some(position),
+        Err(_) => usize::try_from(int.unsigned_abs()).ok().and_then(|from_end| length.checked_sub(from_end)),
+    };
+    match position {
+        Some(position) if position < length || (past_end && position == length) => Ok(position),
+        _ => Err(RuntimeError::IndexOutOfBounds(int.to_string(), kind, length, line).into()),
+    }
+}
+
+/// The key of a map for the value, or an error if the value can't be one
+fn map_key(value: &Value, token: &Token) -> Result<Key> {
+    Key::from_value(value).ok_or_else(|| RuntimeError::InvalidKey(value.to_string(), token.line()).into())
+}
+
 /// Checks if a value is *truthy*
 fn is_truthy(value: Value) -> bool {
     !(value == Value::Nil || value == Value::Bool(false))
@@ -199,7 +1638,7 @@ fn is_truthy(value: Value) -> bool {
 
 /// Checks if two values are *equal* to eachother.
 /// Works seamlessly because Value derives the
-/// `PartialEq` trait.
+/// `PartialEq` trait. An int equals the number with the same value.
 fn is_equal(first: Value, second: Value) -> bool {
     if first == Value::Nil && second == Value::Nil {
         return true;
@@ -208,14 +1647,582 @@ fn is_equal(first: Value, second: Value) -> bool {
         return false;
     }
 
-    first == second
+    match (&first, &second) {
+        (Value::Int(int), Value::Number(number)) | (Value::Number(number), Value::Int(int)) => {
+            number.to_i64() == Some(*int)
+        },
+        _ => first == second,
+    }
+}
+
+/// Applies an arithmetic operation to both operands. Two ints give an int
+/// through `int_op`, if the operator has one. As soon as a number is
+/// involved, both operands become numbers and `op` applies.
+fn arithmetic(
+    int_op: Option<fn(i64, i64) -> Option<i64>>,
+    op: fn(Number, Number) -> Option<Number>,
+    left: Value,
+    right: Value,
+    operator: &Token,
+) -> Result<Value> {
+    if let (Some(int_op), Value::Int(left), Value::Int(right)) = (int_op, &left, &right) {
+        return int_op(*left, *right)
+            .map(Value::Int)
+            .ok_or(RuntimeError::InvalidArithmetic(operator.line()).into());
+    }
+    let left = get_number_operand(left, operator)?;
+    let right = get_number_operand(right, operator)?;
+    op(left, right)
+        .map(Value::Number)
+        .ok_or(RuntimeError::InvalidArithmetic(operator.line()).into())
+}
+
+/// Integer division rounded toward negative infinity, like
+/// [Number::checked_floor_div]. `None` on division by zero and overflow.
+fn floor_div(left: i64, right: i64) -> Option<i64> {
+    let quotient = left.checked_div(right)?;
+    if left % right != 0 && (left < 0) != (right < 0) {
+        Some(quotient - 1)
+    } else {
+        Some(quotient)
+    }
 }
 
-/// Checks if the given value is a Number value and if so,
-/// it returns it
-fn get_number_operand(value: Value) -> Result<f64> {
-    match value {
-        Value::Number(num) => Ok(num),
-        _ => Err(RuntimeError::NumberOperand.into()),
+/// Compares two numbers for a comparison operator, which `test`s their
+/// order. Two ints are compared exactly, NaN fails every test.
+fn compare(left: Value, right: Value, operator: &Token, test: fn(cmp::Ordering) -> bool) -> Result<Value> {
+    let order = match (&left, &right) {
+        (Value::Int(left), Value::Int(right)) => Some(left.cmp(right)),
+        _ => get_number_operand(left, operator)?.partial_cmp(&get_number_operand(right, operator)?),
+    };
+    Ok(Value::Bool(order.is_some_and(test)))
+}
+
+/// Checks if the given value is a number or an int and if so,
+/// it returns it as a number. The operator is used for the error position.
+fn get_number_operand(value: Value, operator: &Token) -> Result<Number> {
+    value.as_number().ok_or_else(|| RuntimeError::NumberOperand(operator.line()).into())
+}
+// ---------- Tests for the interpreter module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::TypeError;
+    use crate::{parser, scanner};
+
+    /// Hooks that record what they observed into a shared log
+    struct Recorder(Shared<Vec<String>>);
+
+    impl ExecutionHooks for Recorder {
+        fn on_statement_enter(&mut self, stmt: &Statement) {
+            self.0.borrow_mut().push(stmt.to_string());
+        }
+
+        fn on_runtime_error(&mut self, error: &LoxError) {
+            self.0.borrow_mut().push(format!("error on line {:?}", error.line()));
+        }
+    }
+
+    /// Output handle that writes into a shared buffer
+    struct Capture(Shared<Vec<u8>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn parse_source(source: &str) -> Vec<Statement> {
+        let mut reporter = CollectingReporter::default();
+        let tokens = scanner::scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+        parser::parse(tokens, &mut reporter).expect("Parsing failed!")
+    }
+
+    #[test]
+    fn hooks_observe_nested_statements_and_errors() {
+        let log = Shared::new(Vec::new());
+        let mut interpreter = Interpreter::new();
+        interpreter.add_hooks(Box::new(Recorder(log.clone())));
+
+        let result = interpreter.interpret(parse_source("{ var a = 1; }\nvar b = -nil;"));
+        assert!(result.is_err());
+        assert_eq!(
+            *log.borrow(),
+            vec!["(block (var a 1))", "(var a 1)", "(var b (- nil))", "error on line Some(2)"]
+        );
+    }
+
+    #[test]
+    fn read_line_reads_from_the_configured_input() {
+        let output = Shared::new(Vec::new());
+        let mut interpreter =
+            Interpreter::builder().input(std::io::Cursor::new("Ada\r\nlast")).output(Capture(output.clone())).build();
+        let source = "var name = input(\"Name? \");\nprint \"Hi \" + name;\nprint readLine();\nprint readLine();";
+        interpreter.interpret(parse_source(source)).expect("Run failed!");
+        assert_eq!(String::from_utf8_lossy(&output.borrow()), "Name? Hi Ada\nlast\nnil\n");
+    }
+
+    #[test]
+    fn environment_persists_between_runs() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret(parse_source("var a = 1;")).expect("First run failed!");
+        interpreter.interpret(parse_source("a = a + 1;")).expect("Second run failed!");
+    }
+
+    #[test]
+    fn strings_have_a_length_property() {
+        let output = Shared::new(Vec::new());
+        let mut interpreter = Interpreter::builder().output(Capture(output.clone())).build();
+        interpreter.interpret(parse_source("var s = \"hello\"; print s.length; print (s + s).length;")).expect("Run failed!");
+        assert_eq!(*output.borrow(), b"5\n10\n");
+
+        let result = interpreter.interpret(parse_source("print s.size;"));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::UndefinedProperty(_, 1)))));
+    }
+
+    #[test]
+    fn undefined_variables_suggest_similar_names() {
+        let suggestion = |source: &str| match Interpreter::new().interpret(parse_source(source)) {
+            Err(LoxError::Runtime(RuntimeError::UndefinedVariable(_, suggestion, _))) => suggestion,
+            other => panic!("Expected an undefined variable, got {:?}", other),
+        };
+        assert_eq!(suggestion("var counter = 1; { var total = 2; countr; }"), Some("counter".to_string()));
+        assert_eq!(suggestion("lne(\"abc\");"), Some("len".to_string()));
+        assert_eq!(suggestion("{ var inner = 1; } innr;"), None); // Out of scope
+        assert_eq!(suggestion("xyz;"), None);
+    }
+
+    #[test]
+    fn error_span_points_at_the_innermost_failing_expression() {
+        let output = Shared::new(Vec::new());
+        let mut interpreter = Interpreter::builder().output(Capture(output.clone())).build();
+        assert!(interpreter.interpret(parse_source("var a = 1;\nprint (a + 2) * (a + nil);")).is_err());
+        assert_eq!(interpreter.error_span(), Some(Span { line: 2, column: 20, length: 1 }));
+        assert!(output.borrow().is_empty()); // The print failed before writing anything
+
+        interpreter.interpret(parse_source("print a;")).expect("Run failed!");
+        assert_eq!(interpreter.error_span(), None);
+        assert_eq!(*output.borrow(), b"1\n");
+    }
+
+    #[test]
+    fn constants_of_earlier_runs_cannot_be_assigned() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret(parse_source("const a = 1;")).expect("Run failed!");
+        let error = interpreter.interpret(parse_source("a = 2;")).expect_err("Assignment succeeded!");
+        assert!(matches!(error, LoxError::Runtime(RuntimeError::AssignToConst(..))));
+        assert_eq!(interpreter.get_global("a"), Some(Value::Int(1)));
+
+        // Declaring it again replaces the constant
+        interpreter.interpret(parse_source("var a = 3; a = 4;")).expect("Run failed!");
+        assert_eq!(interpreter.get_global("a"), Some(Value::Int(4)));
+    }
+
+    #[test]
+    fn inspect_rolls_back_assignments() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret(parse_source("var a = 1;")).expect("Run failed!");
+
+        let mut reporter = CollectingReporter::default();
+        let tokens = scanner::scan_tokens("a = a + 1".to_string(), &mut reporter).expect("Token Scanning failed!");
+        let expr = parser::parse_expression(tokens, &mut reporter).expect("Parsing failed!");
+        assert_eq!(interpreter.inspect(&expr).expect("Inspecting failed!"), Value::Int(2));
+        assert_eq!(interpreter.inspect(&expr).expect("Inspecting failed!"), Value::Int(2));
+    }
+
+    #[test]
+    fn memory_limit_stops_growing_strings() {
+        let mut interpreter = Interpreter::builder().memory_limit(4096).build();
+        let result = interpreter.interpret(parse_source("var s = \"x\";\nwhile (true) s = s + s;"));
+        assert!(matches!(
+            result,
+            Err(LoxError::Runtime(RuntimeError::MemoryLimitExceeded(4096, 2)))
+        ));
+        assert!(interpreter.memory_used() <= 4096);
+    }
+
+    #[test]
+    fn huge_strings_fail_instead_of_aborting() {
+        let mut interpreter = Interpreter::builder().output(Vec::new()).build();
+        let result = interpreter.interpret(parse_source("print \"ab\" * 1000000000000000;"));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::OutOfMemory(2000000000000000, 1)))));
+    }
+
+    #[test]
+    fn memory_limit_covers_clones() {
+        let mut interpreter = Interpreter::builder().memory_limit(4096).build();
+        let source = "var l = [1, 2, 3, 4];\nfor (var i = 0; i < 1000; i = i + 1) clone(l);";
+        let result = interpreter.interpret(parse_source(source));
+        assert!(matches!(
+            result,
+            Err(LoxError::Runtime(RuntimeError::MemoryLimitExceeded(4096, 2)))
+        ));
+    }
+
+    #[test]
+    fn leaving_a_block_releases_its_memory() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret(parse_source("var a = \"global\";")).expect("Run failed!");
+        let before = interpreter.memory_used();
+        interpreter.interpret(parse_source("{ var b = \"local\"; b = b + b; }")).expect("Run failed!");
+        assert_eq!(interpreter.memory_used(), before);
+    }
+
+    #[test]
+    fn sandbox_limits_string_length() {
+        let limits = SizeLimits { string_length: Some(8), ..SizeLimits::sandbox() };
+        let mut interpreter = Interpreter::builder().sandbox().size_limits(limits).build();
+        interpreter.interpret(parse_source("var s = \"abcd\"; s = s + s;")).expect("Run failed!");
+
+        let result = interpreter.interpret(parse_source("s = s + \"!\";"));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::SizeLimitExceeded(_, 8, 1)))));
+        let result = interpreter.interpret(parse_source("repr(s);")); // Adds quotes
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::SizeLimitExceeded(_, 8, 1)))));
+    }
+
+    #[test]
+    fn sandbox_limits_list_length() {
+        let limits = SizeLimits { list_length: Some(3), ..SizeLimits::sandbox() };
+        let mut interpreter = Interpreter::builder().sandbox().size_limits(limits).build();
+        interpreter.interpret(parse_source("var l = [1, 2, 3];")).expect("Run failed!");
+
+        let result = interpreter.interpret(parse_source("l = [1, 2, 3, 4];"));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::SizeLimitExceeded("list length", 3, 1)))));
+    }
+
+    #[test]
+    fn sandbox_limits_map_entries() {
+        let limits = SizeLimits { map_entries: Some(2), ..SizeLimits::sandbox() };
+        let mut interpreter = Interpreter::builder().sandbox().size_limits(limits).build();
+        interpreter.interpret(parse_source("var m = {1: 1, 2: 2}; m[1] = 3;")).expect("Run failed!");
+
+        let result = interpreter.interpret(parse_source("m[3] = 3;"));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::SizeLimitExceeded(_, 2, 1)))));
+        let result = interpreter.interpret(parse_source("var n = {1: 1, 2: 2, 3: 3};"));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::SizeLimitExceeded(_, 2, 1)))));
+    }
+
+    #[test]
+    fn run_report_describes_the_last_run() {
+        let mut interpreter = Interpreter::builder().output(Vec::new()).build();
+        let mut reporter = CollectingReporter::default();
+        let source = "var a = \"x\";\n{ { print a + a; } }";
+        interpreter.run(source.to_string(), &mut reporter).expect("Run failed!");
+
+        let report = interpreter.last_run();
+        assert!(report.scan_time.is_some() && report.parse_time.is_some());
+        assert_eq!(report.statements, 4);
+        assert_eq!(report.peak_depth, 2);
+        assert_eq!(report.allocations, 2); // The variable and the concatenation
+        assert_eq!(report.diagnostics, 0);
+
+        assert!(interpreter.run("print (1;".to_string(), &mut reporter).is_err());
+        assert_eq!(interpreter.last_run().diagnostics, 1);
+        assert_eq!(interpreter.last_run().parse_time, None);
+        assert_eq!(interpreter.last_run().statements, 0);
+    }
+
+    #[test]
+    fn typed_mode_refuses_ill_typed_scripts() {
+        let source = "print \"never\";\nvar a: Number = \"one\";";
+        let mut reporter = CollectingReporter::default();
+        let mut untyped = Interpreter::builder().output(Vec::new()).build();
+        assert!(untyped.run(source.to_string(), &mut reporter).is_ok());
+
+        let mut typed = Interpreter::builder().output(Vec::new()).typed(true).build();
+        let result = typed.run(source.to_string(), &mut reporter);
+        assert!(matches!(result, Err(LoxError::Type(TypeError::HadError))));
+        assert_eq!(typed.last_run().statements, 0);
+        assert_eq!(reporter.diagnostics()[0].line, Some(2));
+    }
+
+    #[test]
+    fn prelude_is_loaded_on_request() {
+        let log = Shared::new(Vec::new());
+        let mut interpreter = Interpreter::builder().prelude(true).hooks(Recorder(log.clone())).build();
+        interpreter.interpret(parse_source("var tau = 2 * PI;")).expect("The prelude is missing!");
+        assert_eq!(*log.borrow(), vec!["(var tau (* 2 (var PI)))"]); // The prelude itself is not observed
+
+        let mut bare = Interpreter::new();
+        assert!(bare.interpret(parse_source("print PI;")).is_err());
+    }
+
+    #[test]
+    fn embedders_provide_globals_and_sources() {
+        let out = Shared::new(Vec::new());
+        let mut interpreter = Interpreter::builder()
+            .output(Capture(out.clone()))
+            .global("version", Value::String("1.2".to_string()))
+            .prelude_source("var banner = \"app \" + version;")
+            .build();
+        interpreter.interpret(parse_source("print banner;")).expect("Run failed!");
+        assert_eq!(String::from_utf8_lossy(&out.borrow()), "app 1.2\n");
+        assert!(interpreter.memory_used() > 0);
+
+        let mut reporter = CollectingReporter::default();
+        let result = Interpreter::builder().prelude_source("print missing;").try_build(&mut reporter);
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::UndefinedVariable(..)))));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn embedders_read_and_override_globals() {
+        let mut interpreter = Interpreter::builder().output(Vec::new()).build();
+        interpreter.define_global("input", Value::Number(Number::from(2)));
+        interpreter.interpret(parse_source("var result = input * 10;")).expect("Run failed!");
+        assert_eq!(interpreter.get_global("result"), Some(Value::Number(Number::from(20))));
+
+        interpreter.define_global("input", Value::Number(Number::from(5)));
+        interpreter.interpret(parse_source("result = input * 10;")).expect("Run failed!");
+        assert_eq!(interpreter.get_global("result"), Some(Value::Number(Number::from(50))));
+        assert_eq!(interpreter.get_global("missing"), None);
+
+        let names: Vec<String> = interpreter.globals().into_iter().map(|(name, _)| name).collect();
+        assert!(names.contains(&"input".to_string()) && names.contains(&"len".to_string()));
+        assert!(names.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn embedded_snippets_report_outer_locations() {
+        let mut reporter = CollectingReporter::default();
+        let mut interpreter = Interpreter::builder().output(Vec::new()).build();
+        let origin = Origin::new("README.md", 40, 5);
+        assert!(interpreter.run_at("print 1;\nprint (2;".to_string(), &origin, &mut reporter).is_err());
+        assert_eq!(reporter.diagnostics()[0].line, Some(41));
+        assert!(reporter.diagnostics()[0].to_string().starts_with("README.md:41: "));
+
+        let result = interpreter.run_at("\n\nprint missing;".to_string(), &origin, &mut reporter);
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::UndefinedVariable(_, _, 42)))));
+    }
+
+    #[test]
+    fn evaluates_formulas_with_bindings() {
+        let mut reporter = CollectingReporter::default();
+        let bindings = HashMap::from([
+            ("price".to_string(), Value::Number(Number::from(20))),
+            ("name".to_string(), Value::String("tea".to_string())),
+        ]);
+        let value = eval_expression("price * 3 ~/ 2", &bindings, &mut reporter).expect("Evaluation failed!");
+        assert_eq!(value, Value::Number(Number::from(30)));
+        let value = eval_expression("\"one \" + name", &bindings, &mut reporter).expect("Evaluation failed!");
+        assert_eq!(value, Value::String("one tea".to_string()));
+
+        assert!(matches!(eval_expression("price +", &bindings, &mut reporter), Err(LoxError::Parse(_))));
+        assert_eq!(reporter.diagnostics().len(), 1);
+        let result = eval_expression("receive(channel())", &bindings, &mut reporter);
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::TimeLimitExceeded(_)))));
+    }
+
+    #[test]
+    fn time_limit_interrupts_receive() {
+        let mut interpreter = Interpreter::builder().time_limit(Duration::from_millis(50)).build();
+        let result = interpreter.interpret(parse_source("receive(channel());"));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::TimeLimitExceeded(_)))));
+    }
+
+    #[test]
+    fn time_limit_interrupts_coroutines() {
+        let mut interpreter = Interpreter::builder().time_limit(Duration::from_millis(50)).build();
+        let result = interpreter.interpret(parse_source("resume(coroutine(fun () { while (true) {} }), nil);"));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::TimeLimitExceeded(_)))));
+    }
+
+    #[test]
+    fn hosts_can_run_the_event_loop_themselves() {
+        let capabilities = Capabilities { time: true, ..Capabilities::none() };
+        let mut interpreter = Interpreter::builder().capabilities(capabilities).external_event_loop(true).build();
+        let source = "var done = false; async fun wait() { await sleepAsync(10); done = true; } wait();";
+        interpreter.interpret(parse_source(source)).expect("Running failed!");
+        assert_eq!(interpreter.get_global("done"), Some(Value::Bool(false))); // Left to the host
+
+        let due = interpreter.run_pending().expect("Running failed!").expect("No timer is pending");
+        std::thread::sleep(due.saturating_duration_since(Instant::now()));
+        assert_eq!(interpreter.run_pending().expect("Running failed!"), None);
+        assert_eq!(interpreter.get_global("done"), Some(Value::Bool(true)));
+    }
+
+    #[test]
+    fn step_limit_stops_endless_loops() {
+        let mut interpreter = Interpreter::builder().step_limit(100).build();
+        let result = interpreter.interpret(parse_source("while (true) {}"));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::StepLimitExceeded(100)))));
+    }
+
+    #[test]
+    fn strict_mode_rejects_redeclarations() {
+        let source = "var a = 1;\nvar a = 2;";
+        assert!(Interpreter::builder().output(Vec::new()).build().interpret(parse_source(source)).is_ok());
+
+        let mut strict = Interpreter::builder().strict(true).build();
+        let result = strict.interpret(parse_source(source));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::AlreadyDefined(_, 2)))));
+    }
+
+    #[test]
+    fn redefinitions_replace_functions_and_classes_in_place() {
+        let output = Shared::new(Vec::new());
+        let mut interpreter = Interpreter::builder().redefine_classes(true).output(Capture(output.clone())).build();
+        // One run per prompt line, like the REPL does
+        interpreter.interpret(parse_source("class Dog { speak() { return \"woof\"; } }")).expect("Class failed!");
+        interpreter.interpret(parse_source("var rex = Dog(); fun greet() { return \"hi\"; }")).expect("Setup failed!");
+        interpreter.interpret(parse_source("class Dog { speak() { return \"WOOF\"; } }")).expect("Redefinition failed!");
+        interpreter.interpret(parse_source("fun greet() { return \"hello\"; }")).expect("Redefinition failed!");
+        interpreter.interpret(parse_source("print rex.speak(), greet(), rex is Dog;")).expect("Run failed!");
+        assert_eq!(*output.borrow(), b"WOOF hello true\n");
+    }
+
+    #[test]
+    fn scripts_declare_a_new_class_instead() {
+        let output = Shared::new(Vec::new());
+        let mut interpreter = Interpreter::builder().output(Capture(output.clone())).build();
+        let source = "class Dog { speak() { return \"woof\"; } } var rex = Dog();
+                      class Dog { speak() { return \"WOOF\"; } } print rex.speak(), Dog().speak(), rex is Dog;";
+        interpreter.interpret(parse_source(source)).expect("Run failed!");
+        assert_eq!(*output.borrow(), b"woof WOOF false\n");
+    }
+
+    #[test]
+    fn print_writes_to_the_configured_output() {
+        let output = Shared::new(Vec::new());
+        let mut interpreter = Interpreter::builder().output(Capture(output.clone())).build();
+        interpreter.interpret(parse_source("print 1 + 2; print \"lox\", nil, 4;")).expect("Run failed!");
+        assert_eq!(*output.borrow(), b"3\nlox nil 4\n");
+    }
+
+    #[test]
+    fn cancelling_stops_the_execution() {
+        let mut interpreter = Interpreter::new();
+        let token = interpreter.cancellation_token();
+        token.cancel();
+        let result = interpreter.interpret(parse_source("print 1;"));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::Cancelled))));
+
+        token.reset();
+        assert!(interpreter.interpret(parse_source("var a = 1;")).is_ok());
+    }
+
+    #[test]
+    fn exit_ends_the_run_with_its_code() {
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.interpret(parse_source("exit(3);"));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::CapabilityDisabled("exit", "process", 1)))));
+
+        let mut interpreter = Interpreter::builder().capabilities(Capabilities::all()).build();
+        let result = interpreter.interpret(parse_source("try { exit(3); } catch (error) { exit(1); }\nexit(2);"));
+        assert_eq!(result.expect_err("exit returned").exit_code(), Some(3));
+        let result = interpreter.interpret(parse_source("exit(256);"));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::InvalidArgument("exit", _, 1)))));
+    }
+
+    #[test]
+    fn clocks_stand_still_in_deterministic_runs() {
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.interpret(parse_source("clock();"));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::CapabilityDisabled("clock", "time", 1)))));
+
+        let output = Shared::new(Vec::new());
+        let builder = Interpreter::builder().capabilities(Capabilities::all()).deterministic(true);
+        let mut interpreter = builder.output(Capture(output.clone())).build();
+        interpreter.interpret(parse_source("sleep(5);\nprint clock();\nprint timeMillis();")).expect("Run failed!");
+        assert_eq!(String::from_utf8_lossy(&output.borrow()), "0\n0\n");
+    }
+
+    #[test]
+    fn sleep_needs_the_time_capability() {
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.interpret(parse_source("sleep(1);"));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::CapabilityDisabled("sleep", "time", 1)))));
+        let result = interpreter.interpret(parse_source("print 1;\nsleep(1);"));
+        let message = result.expect_err("sleep ran without the capability").to_string();
+        assert_eq!(message, "Runtime Error [R0311]: sleep() needs the time capability, which was not granted, on line 2");
+
+        let mut interpreter = Interpreter::builder().capabilities(Capabilities::all()).time_limit(Duration::from_millis(50)).build();
+        let result = interpreter.interpret(parse_source("sleep(10000);"));
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::TimeLimitExceeded(_)))));
+    }
+
+    /// The native and the group of the capability error the source stops
+    /// with, when every capability group but `denied` is granted
+    fn blocked_by(denied: &str, source: &str) -> Option<(&'static str, &'static str)> {
+        let mut capabilities = Capabilities::all();
+        assert!(capabilities.set(denied, false));
+        let mut interpreter = Interpreter::builder().capabilities(capabilities).output(Vec::new()).build();
+        match interpreter.interpret(parse_source(source)) {
+            Err(LoxError::Runtime(RuntimeError::CapabilityDisabled(native, group, _))) => Some((native, group)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn denying_fs_blocks_files() {
+        assert_eq!(blocked_by("fs", "readFile(\"notes.txt\");"), Some(("readFile", "fs")));
+        assert_eq!(blocked_by("fs", "writeFile(\"notes.txt\", \"hi\");"), Some(("writeFile", "fs")));
+        assert_eq!(blocked_by("fs", "sleep(0); getEnv(\"HOME\"); exit(0);"), None);
+
+        let path = std::env::temp_dir().join(format!("jlox-fs-{}.txt", std::process::id()));
+        let output = Shared::new(Vec::new());
+        let builder = Interpreter::builder().capabilities(Capabilities::all()).output(Capture(output.clone()));
+        let source = format!("writeFile(\"{0}\", \"hi\");\nprint readFile(\"{0}\");", path.display());
+        let result = builder.build().interpret(parse_source(&source));
+        std::fs::remove_file(&path).expect("writeFile wrote nothing");
+        result.expect("Run failed!");
+        assert_eq!(*output.borrow(), b"hi\n");
+    }
+
+    #[test]
+    fn denying_net_blocks_the_network() {
+        assert_eq!(blocked_by("net", "lookupHost(\"localhost\");"), Some(("lookupHost", "net")));
+        assert_eq!(blocked_by("net", "readFile(\"-\");"), None);
+
+        let mut interpreter = Interpreter::builder().capabilities(Capabilities::all()).build();
+        interpreter.interpret(parse_source("var a = lookupHost(\"127.0.0.1\");")).expect("Run failed!");
+        assert_eq!(interpreter.get_global("a").map(|a| a.to_string()).as_deref(), Some("[\"127.0.0.1\"]"));
+    }
+
+    #[test]
+    fn denying_process_blocks_exit() {
+        assert_eq!(blocked_by("process", "exit(0);"), Some(("exit", "process")));
+        assert_eq!(blocked_by("process", "sleep(0);"), None);
+    }
+
+    #[test]
+    fn denying_env_blocks_environment_variables() {
+        assert_eq!(blocked_by("env", "getEnv(\"HOME\");"), Some(("getEnv", "env")));
+        assert_eq!(blocked_by("env", "exit(0);"), None);
+
+        let mut interpreter = Interpreter::builder().capabilities(Capabilities::all()).build();
+        interpreter.interpret(parse_source("var a = getEnv(\"JLOX_NOT_SET_ANYWHERE\");")).expect("Run failed!");
+        assert_eq!(interpreter.get_global("a"), Some(Value::Nil));
+    }
+
+    #[test]
+    fn denying_time_blocks_clocks_and_sleeping() {
+        for (source, native) in [("clock();", "clock"), ("timeMillis();", "timeMillis"), ("sleep(1);", "sleep"), ("sleepAsync(1);", "sleepAsync")] {
+            assert_eq!(blocked_by("time", source), Some((native, "time")));
+        }
+        assert_eq!(blocked_by("time", "exit(0);"), None);
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn denying_ffi_blocks_native_libraries() {
+        assert_eq!(blocked_by("ffi", "loadLibrary(\"libm.so.6\");"), Some(("loadLibrary", "ffi")));
+        assert_eq!(blocked_by("ffi", "callNative(\"libm.so.6\", \"fabs\", \"double(double)\", [1]);"), Some(("callNative", "ffi")));
+        assert_eq!(blocked_by("ffi", "sleep(0);"), None);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn interpreter_can_move_to_another_thread() {
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret(parse_source("var a = 1;")).expect("First run failed!");
+        std::thread::spawn(move || interpreter.interpret(parse_source("a = a + 1;")))
+            .join()
+            .expect("Thread panicked!")
+            .expect("Second run failed!");
+    }
+}
@@ -0,0 +1,310 @@
+// External dependencies
+use std::io::{BufRead, BufReader, Write};
+use std::time::Duration;
+
+// Internal dependencies
+use super::{ExecutionHooks, Interpreter};
+use crate::diagnostic::{CollectingReporter, Reporter};
+use crate::errors::Result;
+use crate::obj::value::Value;
+use crate::shared::MaybeSend;
+
+/// Anything `print` can write to. With the `sync` feature it has to be `Send`.
+pub trait OutputHandle: Write + MaybeSend {}
+impl<T: Write + MaybeSend> OutputHandle for T {}
+
+/// Anything user input can be read from. With the `sync` feature it has to be `Send`.
+pub trait InputHandle: BufRead + MaybeSend {}
+impl<T: BufRead + MaybeSend> InputHandle for T {}
+
+/// Groups of native functions that reach outside of the interpreter.
+/// Natives of a group that was not granted fail with a runtime error
+/// when called.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Reading and writing files
+    pub fs: bool,
+    /// Network access
+    pub net: bool,
+    /// Spawning processes and exiting
+    pub process: bool,
+    /// Reading environment variables
+    pub env: bool,
+    /// Clocks and sleeping
+    pub time: bool,
+    /// Loading shared libraries and calling into them (`ffi` feature)
+    pub ffi: bool,
+}
+
+impl Capabilities {
+    /// Names of the groups, as [Capabilities::set] accepts them
+    pub const GROUPS: &'static [&'static str] = &["fs", "net", "process", "env", "time", "ffi"];
+
+    /// Every group enabled, like the jlox command line tool does
+    pub fn all() -> Self {
+        Self { fs: true, net: true, process: true, env: true, time: true, ffi: true }
+    }
+
+    /// Every group disabled. This is the default for embedders.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Grants or revokes the group with the given name (see [Capabilities::GROUPS]).
+    /// Returns `false` if there is no such group.
+    pub fn set(&mut self, group: &str, granted: bool) -> bool {
+        let flag = match group {
+            "fs" => &mut self.fs,
+            "net" => &mut self.net,
+            "process" => &mut self.process,
+            "env" => &mut self.env,
+            "time" => &mut self.time,
+            "ffi" => &mut self.ffi,
+            _ => return false,
+        };
+        *flag = granted;
+        true
+    }
+}
+
+/// Upper bounds for the size of single values. Exceeding one is a runtime
+/// error, so a script cannot build one huge value even when the memory
+/// limit would still allow it. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SizeLimits {
+    /// Maximum number of characters of a string
+    pub string_length: Option<usize>,
+    /// Maximum number of elements of a list
+    pub list_length: Option<usize>,
+    /// Maximum number of entries of a map
+    pub map_entries: Option<usize>,
+}
+
+impl SizeLimits {
+    /// The limits [InterpreterBuilder::sandbox] uses
+    pub fn sandbox() -> Self {
+        Self {
+            string_length: Some(1 << 20),
+            list_length: Some(1 << 16),
+            map_entries: Some(1 << 16),
+        }
+    }
+}
+
+/// The runtime knobs of an [Interpreter]. Set through [InterpreterBuilder],
+/// readable through [Interpreter::config].
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Redeclaring a variable in the same scope is a runtime error
+    pub strict: bool,
+    /// Declaring a class again in the same scope updates it in place, so
+    /// the instances that already exist get the new methods. For interactive
+    /// sessions like the REPL, scripts get a new class instead.
+    pub redefine_classes: bool,
+    /// [Interpreter::run] type checks the script (see [typechecker](crate::typechecker))
+    /// and refuses to run it if there are type errors
+    pub typed: bool,
+    /// The bundled [PRELUDE](super::PRELUDE) gets executed into the global
+    /// environment when the interpreter is created
+    pub prelude: bool,
+    /// Maximum number of statements a single [Interpreter::interpret] call may execute
+    pub step_limit: Option<u64>,
+    /// Maximum wall clock time a single [Interpreter::interpret] call may take
+    pub time_limit: Option<Duration>,
+    /// Maximum approximate number of bytes the script may hold, see [Interpreter::memory_used]
+    pub memory_limit: Option<usize>,
+    /// Maximum sizes of strings, lists and maps
+    pub size_limits: SizeLimits,
+    /// Which groups of native functions may be called
+    pub capabilities: Capabilities,
+    /// Natives that depend on the outside world (clocks, random numbers)
+    /// return reproducible results, so runs can be compared byte for byte
+    pub deterministic: bool,
+    /// The host runs the event loop with [Interpreter::run_pending], so a
+    /// run returns without waiting for the async calls it made. Only
+    /// `await` still runs the event loop until its future completes.
+    pub external_event_loop: bool,
+    /// Lox functions get compiled to native code once they were called this
+    /// often with the same kinds of arguments (`jit` feature). `None` keeps
+    /// every function interpreted.
+    #[cfg(feature = "jit")]
+    pub jit_threshold: Option<u32>,
+}
+
+/// Collects the configuration of an [Interpreter] before creating it.
+/// Obtained through [Interpreter::builder].
+///
+/// ```
+/// use jlox::interpreter::Interpreter;
+/// use std::time::Duration;
+///
+/// let interpreter = Interpreter::builder()
+///     .strict(true)
+///     .step_limit(10_000)
+///     .time_limit(Duration::from_secs(1))
+///     .output(Vec::new())
+///     .build();
+/// assert!(interpreter.config().strict);
+/// ```
+#[derive(Default)]
+pub struct InterpreterBuilder {
+    config: Config,
+    output: Option<Box<dyn OutputHandle>>,
+    input: Option<Box<dyn InputHandle>>,
+    hooks: Vec<Box<dyn ExecutionHooks>>,
+    /// Global variables defined before the snippets run
+    globals: Vec<(String, Value)>,
+    /// Lox sources that run once the interpreter is created
+    snippets: Vec<String>,
+}
+
+impl InterpreterBuilder {
+    /// See [Config::strict]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.config.strict = strict;
+        self
+    }
+
+    /// See [Config::redefine_classes]
+    pub fn redefine_classes(mut self, redefine: bool) -> Self {
+        self.config.redefine_classes = redefine;
+        self
+    }
+
+    /// See [Config::typed]
+    pub fn typed(mut self, typed: bool) -> Self {
+        self.config.typed = typed;
+        self
+    }
+
+    /// See [Config::prelude]
+    pub fn prelude(mut self, prelude: bool) -> Self {
+        self.config.prelude = prelude;
+        self
+    }
+
+    /// See [Config::step_limit]
+    pub fn step_limit(mut self, steps: u64) -> Self {
+        self.config.step_limit = Some(steps);
+        self
+    }
+
+    /// See [Config::time_limit]
+    pub fn time_limit(mut self, limit: Duration) -> Self {
+        self.config.time_limit = Some(limit);
+        self
+    }
+
+    /// See [Config::memory_limit]
+    pub fn memory_limit(mut self, bytes: usize) -> Self {
+        self.config.memory_limit = Some(bytes);
+        self
+    }
+
+    /// See [Config::size_limits]
+    pub fn size_limits(mut self, limits: SizeLimits) -> Self {
+        self.config.size_limits = limits;
+        self
+    }
+
+    /// Configuration for running untrusted scripts: every capability
+    /// disabled, 64 MiB of memory and the [SizeLimits::sandbox] limits.
+    /// Single settings can still be changed afterwards.
+    pub fn sandbox(self) -> Self {
+        self.capabilities(Capabilities::none())
+            .memory_limit(64 << 20)
+            .size_limits(SizeLimits::sandbox())
+    }
+
+    /// See [Config::capabilities]
+    pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.config.capabilities = capabilities;
+        self
+    }
+
+    /// See [Config::deterministic]
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.config.deterministic = deterministic;
+        self
+    }
+
+    /// See [Config::external_event_loop]
+    pub fn external_event_loop(mut self, external: bool) -> Self {
+        self.config.external_event_loop = external;
+        self
+    }
+
+    /// See [Config::jit_threshold]
+    #[cfg(feature = "jit")]
+    pub fn jit_threshold(mut self, calls: u32) -> Self {
+        self.config.jit_threshold = Some(calls);
+        self
+    }
+
+    /// Where `print` writes to. Defaults to stdout.
+    pub fn output(mut self, output: impl OutputHandle + 'static) -> Self {
+        self.output = Some(Box::new(output));
+        self
+    }
+
+    /// Where natives that read user input read from. Defaults to stdin.
+    pub fn input(mut self, input: impl InputHandle + 'static) -> Self {
+        self.input = Some(Box::new(input));
+        self
+    }
+
+    /// Registers execution hooks, see [Interpreter::add_hooks]
+    pub fn hooks(mut self, hooks: impl ExecutionHooks + 'static) -> Self {
+        self.hooks.push(Box::new(hooks));
+        self
+    }
+
+    /// Defines a global variable in the new interpreter, like application
+    /// specific constants or native functions. Globals are defined after the
+    /// bundled prelude and before the [prelude sources](Self::prelude_source).
+    pub fn global(mut self, name: &str, value: Value) -> Self {
+        self.globals.push((name.to_string(), value));
+        self
+    }
+
+    /// Lox source that runs in the new interpreter before any user script,
+    /// for declaring application specific helpers. Multiple sources run in
+    /// the order they were added, sharing the global environment.
+    pub fn prelude_source(mut self, source: impl Into<String>) -> Self {
+        self.snippets.push(source.into());
+        self
+    }
+
+    /// Creates the interpreter
+    ///
+    /// # Panics
+    ///
+    /// If one of the [prelude sources](Self::prelude_source) fails, use
+    /// [InterpreterBuilder::try_build] to handle that instead
+    pub fn build(self) -> Interpreter {
+        let mut reporter = CollectingReporter::default();
+        match self.try_build(&mut reporter) {
+            Ok(interpreter) => interpreter,
+            Err(error) => {
+                let diagnostics: Vec<String> = reporter.into_diagnostics().into_iter().map(|d| d.message).collect();
+                panic!("A prelude source failed: {} {:?}", error, diagnostics)
+            }
+        }
+    }
+
+    /// Creates the interpreter and runs the [prelude sources](Self::prelude_source).
+    /// Their diagnostics go to the `reporter`, the first one that fails
+    /// fails the whole build.
+    pub fn try_build(self, reporter: &mut dyn Reporter) -> Result<Interpreter> {
+        let output = self.output.unwrap_or_else(|| Box::new(std::io::stdout()));
+        let input = self.input.unwrap_or_else(|| Box::new(BufReader::new(std::io::stdin())));
+        let mut interpreter = Interpreter::from_parts(self.config, output, input, self.hooks);
+        for (name, value) in self.globals {
+            interpreter.define_global(&name, value);
+        }
+        for snippet in self.snippets {
+            interpreter.load(snippet, reporter)?;
+        }
+        Ok(interpreter)
+    }
+}
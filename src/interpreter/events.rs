@@ -0,0 +1,101 @@
+// External dependencies
+use std::collections::VecDeque;
+use std::time::Instant;
+
+// Internal dependencies
+use crate::obj::coroutine::Coroutine;
+use crate::obj::future::Future;
+use crate::obj::value::Value;
+
+/// The async calls and `sleepAsync` timers of an interpreter that haven't
+/// completed yet. Every async call is a coroutine, the interpreter resumes
+/// them one after the other while the script waits in an `await` and at
+/// the end of every run. The interpreters of the calls share it, so the
+/// async calls they make end up here too.
+#[derive(Debug, Default)]
+pub struct EventLoop {
+    /// Async calls that can go on, the longest waiting first
+    ready: VecDeque<Coroutine>,
+    /// Async calls suspended in an `await`, with the future they wait for
+    waiting: Vec<(Coroutine, Future)>,
+    /// Futures of `sleepAsync` and when they complete, the earliest first
+    timers: Vec<(Instant, Future)>,
+}
+
+/// What the interpreter does next, see [EventLoop::next]
+pub enum Next {
+    /// Resume the async call until it awaits a future that isn't done or returns
+    Resume(Coroutine),
+    /// Nothing can go on before the next timer completes, at this time
+    Sleep(Instant),
+    /// Nothing is left that could complete a future
+    Idle,
+}
+
+impl EventLoop {
+    /// Queues an async call that was just made, it starts once the ones before it suspended
+    pub fn start(&mut self, call: Coroutine) {
+        self.ready.push_back(call);
+    }
+
+    /// Parks an async call until the future it awaits completes
+    pub fn wait(&mut self, call: Coroutine, future: Future) {
+        self.waiting.push((call, future));
+    }
+
+    /// Completes the future with `nil` at the time `due`
+    pub fn add_timer(&mut self, due: Instant, future: Future) {
+        // After the timers that are due at the same time, those were started earlier
+        let position = self.timers.partition_point(|(time, _)| *time <= due);
+        self.timers.insert(position, (due, future));
+    }
+
+    /// Completes the timers that are due at `now` and wakes up the calls
+    /// whose future completed, then picks what to do next
+    pub fn next(&mut self, now: Instant) -> Next {
+        let due = self.timers.partition_point(|(time, _)| *time <= now);
+        let due: Vec<(Instant, Future)> = self.timers.drain(..due).collect();
+        for (_, timer) in due {
+            timer.complete(Ok(Value::Nil));
+            self.wake(|future| *future == timer); // The calls go on in the order of their timers
+        }
+        self.wake(Future::is_done); // The futures of async calls complete while they run
+
+        match (self.ready.pop_front(), self.timers.first()) {
+            (Some(call), _) => Next::Resume(call),
+            (None, Some((due, _))) => Next::Sleep(*due),
+            (None, None) => Next::Idle,
+        }
+    }
+
+    /// Moves the waiting calls whose future matches to the ready ones
+    fn wake(&mut self, matches: impl Fn(&Future) -> bool) {
+        let waiting = std::mem::take(&mut self.waiting);
+        let (woken, waiting): (Vec<_>, Vec<_>) = waiting.into_iter().partition(|(_, future)| matches(future));
+        self.waiting = waiting;
+        self.ready.extend(woken.into_iter().map(|(call, _)| call));
+    }
+}
+
+// ---------- Tests for the events module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn timers_complete_in_the_order_they_are_due() {
+        let mut events = EventLoop::default();
+        let start = Instant::now();
+        let (soon, later) = (Future::new(), Future::new());
+        events.add_timer(start + Duration::from_millis(20), later.clone());
+        events.add_timer(start + Duration::from_millis(10), soon.clone());
+
+        assert!(matches!(events.next(start), Next::Sleep(due) if due == start + Duration::from_millis(10)));
+        assert!(matches!(events.next(start + Duration::from_millis(10)), Next::Sleep(_)));
+        assert!(soon.is_done() && !later.is_done());
+        assert!(matches!(events.next(start + Duration::from_millis(20)), Next::Idle));
+        assert_eq!(later.settled(), Some(Ok(Value::Nil)));
+    }
+}
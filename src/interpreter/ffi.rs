@@ -0,0 +1,280 @@
+// External dependencies
+use libloading::Library;
+use std::ffi::{c_char, c_double, c_int, c_long, c_void, CStr, CString};
+
+// Internal dependencies
+use super::Interpreter;
+use crate::errors::{Result, RuntimeError};
+use crate::obj::number::Number;
+use crate::obj::value::Value;
+
+/// Highest number of parameters a foreign function may have
+const MAX_PARAMETERS: usize = 4;
+
+/// The C types a signature can name
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CType {
+    Void,
+    Double,
+    Int,
+    Long,
+    /// A NUL terminated `const char *`
+    String,
+}
+
+/// A parsed signature like `double(double, double)`. The parameters all
+/// have the same type, `None` if there are none.
+#[derive(Debug, PartialEq)]
+struct Signature {
+    ret: CType,
+    param: Option<CType>,
+    arity: usize,
+}
+
+/// What a foreign function returned, before it becomes a Lox value
+enum Returned {
+    Nil,
+    Number(f64),
+    Int(i64),
+    String(String),
+}
+
+/// An argument converted to its C representation. Strings own the
+/// buffer the pointer points into until the call returned.
+enum Argument {
+    Double(c_double),
+    Int(c_int),
+    Long(c_long),
+    String(CString),
+}
+
+/// `loadLibrary(path)`: loads a shared library and returns the handle for
+/// `callNative`, which is the path itself. Needs the ffi capability.
+/// Loading the same path again reuses the loaded library.
+pub fn load_library(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    check_capability(interpreter, "loadLibrary", line)?;
+    let path = match &args[0] {
+        Value::String(path) => path.clone(),
+        other => return Err(RuntimeError::ArgumentType("loadLibrary", "a string", other.type_name(), line).into()),
+    };
+    if !interpreter.libraries.contains_key(&path) {
+        // Loading runs the initialization code of the library, which is as unsafe as any foreign call
+        let library = unsafe { Library::new(&path) }
+            .map_err(|e| RuntimeError::ForeignCall(format!("cannot load {}: {}", path, e), line))?;
+        interpreter.libraries.insert(path.clone(), library);
+    }
+    Ok(Value::String(path))
+}
+
+/// `callNative(library, symbol, signature, args)`: calls the function
+/// `symbol` of a library loaded with `loadLibrary`. The signature is written
+/// like a C prototype without names, for example `double(double, double)`
+/// or `void()`. Parameters can be `double`, `int`, `long` or `string`, all
+/// of the same type and at most four of them. Return types can also be
+/// `void`. The arguments are passed as a list, `[]` for none. Needs the
+/// ffi capability.
+pub fn call_native(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    check_capability(interpreter, "callNative", line)?;
+    let text = |value: &Value| match value {
+        Value::String(text) => Ok(text.clone()),
+        other => Err(RuntimeError::ArgumentType("callNative", "a string", other.type_name(), line)),
+    };
+    let (path, symbol, signature) = (text(&args[0])?, text(&args[1])?, text(&args[2])?);
+
+    let signature = Signature::parse(&signature)
+        .ok_or_else(|| RuntimeError::ForeignCall(format!("invalid signature {}", signature), line))?;
+    let arguments = match &args[3] {
+        Value::List(list) => list.items.borrow().clone(),
+        other => return Err(RuntimeError::ArgumentType("callNative", "a list", other.type_name(), line).into()),
+    };
+    if arguments.len() != signature.arity {
+        return Err(RuntimeError::WrongArgumentCount(signature.arity, arguments.len(), line).into());
+    }
+    let arguments = arguments
+        .iter()
+        .map(|argument| convert(argument, signature.param.unwrap_or(CType::Void), line))
+        .collect::<Result<Vec<Argument>>>()?;
+
+    let library = interpreter
+        .libraries
+        .get(&path)
+        .ok_or_else(|| RuntimeError::ForeignCall(format!("{} was not loaded with loadLibrary", path), line))?;
+    let function = unsafe { library.get::<*const c_void>(symbol.as_bytes()) }
+        .map_err(|_| RuntimeError::ForeignCall(format!("{} has no symbol {}", path, symbol), line))?;
+
+    // The signature is the caller's promise about the function, nothing checks it
+    let result = unsafe { call(*function, &signature, &arguments) };
+    match result {
+        Returned::Nil => Ok(Value::Nil),
+        Returned::Number(n) => Number::from_f64(n)
+            .map(Value::Number)
+            .ok_or_else(|| RuntimeError::InvalidArithmetic(line).into()),
+        Returned::Int(i) => Ok(Value::Int(i)),
+        Returned::String(s) => Ok(Value::String(s)),
+    }
+}
+
+fn check_capability(interpreter: &Interpreter, name: &'static str, line: u32) -> Result<()> {
+    if interpreter.config().capabilities.ffi {
+        Ok(())
+    } else {
+        Err(RuntimeError::CapabilityDisabled(name, "ffi", line).into())
+    }
+}
+
+impl CType {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "void" => Some(CType::Void),
+            "double" => Some(CType::Double),
+            "int" => Some(CType::Int),
+            "long" => Some(CType::Long),
+            "string" => Some(CType::String),
+            _ => None,
+        }
+    }
+}
+
+impl Signature {
+    /// Parses `ret(param, ...)`. `None` if a type is unknown, the
+    /// parameters differ in type or there are too many of them.
+    fn parse(text: &str) -> Option<Self> {
+        let (ret, params) = text.trim().strip_suffix(')')?.split_once('(')?;
+        let ret = CType::parse(ret)?;
+        let params: Vec<CType> = match params.trim() {
+            "" | "void" => Vec::new(),
+            params => params.split(',').map(CType::parse).collect::<Option<_>>()?,
+        };
+        let param = params.first().copied();
+        let uniform = params.iter().all(|p| Some(*p) == param && *p != CType::Void);
+        (uniform && params.len() <= MAX_PARAMETERS).then_some(Signature { ret, param, arity: params.len() })
+    }
+}
+
+/// Converts a Lox value to the C parameter type
+fn convert(value: &Value, ctype: CType, line: u32) -> Result<Argument> {
+    let wrong = |expected| RuntimeError::ArgumentType("callNative", expected, value.type_name(), line);
+    match (ctype, value) {
+        (CType::Double, Value::Number(n)) => Ok(Argument::Double(n.to_f64())),
+        (CType::Double, Value::Int(i)) => Ok(Argument::Double(*i as c_double)),
+        (CType::Int, Value::Int(i)) => Ok(Argument::Int(*i as c_int)),
+        (CType::Long, Value::Int(i)) => Ok(Argument::Long(*i as c_long)),
+        (CType::Int, Value::Number(n)) => Ok(Argument::Int(n.to_f64() as c_int)),
+        (CType::Long, Value::Number(n)) => Ok(Argument::Long(n.to_f64() as c_long)),
+        (CType::String, Value::String(s)) => CString::new(s.as_str())
+            .map(Argument::String)
+            .map_err(|_| RuntimeError::ForeignCall("strings passed to C cannot contain NUL".to_string(), line).into()),
+        (CType::String, _) => Err(wrong("a string").into()),
+        _ => Err(wrong("a number").into()),
+    }
+}
+
+/// Calls `function` as a C function with the given signature
+///
+/// # Safety
+///
+/// `function` has to be a function with exactly that signature
+unsafe fn call(function: *const c_void, signature: &Signature, arguments: &[Argument]) -> Returned {
+    /// Transmutes the function pointer to `extern "C" fn(P, ...) -> R` for
+    /// the number of arguments and calls it with them
+    macro_rules! call_with {
+        ($ret:ty, $param:ty, $unwrap:expr) => {{
+            let a: Vec<$param> = arguments.iter().map($unwrap).collect();
+            match a.len() {
+                0 => std::mem::transmute::<*const c_void, extern "C" fn() -> $ret>(function)(),
+                1 => std::mem::transmute::<*const c_void, extern "C" fn($param) -> $ret>(function)(a[0]),
+                2 => std::mem::transmute::<*const c_void, extern "C" fn($param, $param) -> $ret>(function)(a[0], a[1]),
+                3 => std::mem::transmute::<*const c_void, extern "C" fn($param, $param, $param) -> $ret>(function)(
+                    a[0], a[1], a[2],
+                ),
+                _ => std::mem::transmute::<*const c_void, extern "C" fn($param, $param, $param, $param) -> $ret>(
+                    function,
+                )(a[0], a[1], a[2], a[3]),
+            }
+        }};
+    }
+
+    /// Picks the parameter type, then calls with the return type `$ret`
+    macro_rules! call_returning {
+        ($ret:ty) => {
+            match signature.param {
+                None | Some(CType::Void) | Some(CType::Double) => call_with!($ret, c_double, |a| match a {
+                    Argument::Double(d) => *d,
+                    _ => 0.0,
+                }),
+                Some(CType::Int) => call_with!($ret, c_int, |a| match a {
+                    Argument::Int(i) => *i,
+                    _ => 0,
+                }),
+                Some(CType::Long) => call_with!($ret, c_long, |a| match a {
+                    Argument::Long(l) => *l,
+                    _ => 0,
+                }),
+                Some(CType::String) => call_with!($ret, *const c_char, |a| match a {
+                    Argument::String(s) => s.as_ptr(),
+                    _ => std::ptr::null(),
+                }),
+            }
+        };
+    }
+
+    match signature.ret {
+        CType::Void => {
+            call_returning!(());
+            Returned::Nil
+        }
+        CType::Double => Returned::Number(call_returning!(c_double)),
+        CType::Int => Returned::Int(call_returning!(c_int) as i64),
+        CType::Long => Returned::Int(call_returning!(c_long) as i64),
+        CType::String => {
+            let text: *const c_char = call_returning!(*const c_char);
+            if text.is_null() {
+                Returned::Nil
+            } else {
+                // The library keeps owning the returned string
+                Returned::String(CStr::from_ptr(text).to_string_lossy().into_owned())
+            }
+        }
+    }
+}
+
+// ---------- Tests for the ffi module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::LoxError;
+    use crate::obj::list::List;
+
+    #[test]
+    fn parses_signatures() {
+        let parsed = |text| Signature::parse(text).map(|s| (s.ret, s.param, s.arity));
+        assert_eq!(parsed("double(double, double)"), Some((CType::Double, Some(CType::Double), 2)));
+        assert_eq!(parsed(" void ( ) "), Some((CType::Void, None, 0)));
+        assert_eq!(parsed("long(void)"), Some((CType::Long, None, 0)));
+        for invalid in ["double", "double(int, double)", "int(char)", "int(void, void)", "int(int,int,int,int,int)"] {
+            assert_eq!(parsed(invalid), None, "{} was accepted", invalid);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn calls_into_libm() {
+        use crate::interpreter::Capabilities;
+
+        let string = |s: &str| Value::String(s.to_string());
+        let number = |n: i32| Value::Number(Number::from(n));
+        let mut interpreter = Interpreter::builder().capabilities(Capabilities::all()).build();
+        let libm = load_library(&mut interpreter, vec![string("libm.so.6")], 1).expect("Loading libm failed!");
+
+        let list = |items: Vec<Value>| Value::List(List::new(items));
+        let args = vec![libm.clone(), string("fabs"), string("double(double)"), list(vec![number(-3)])];
+        assert_eq!(call_native(&mut interpreter, args, 1).expect("Call failed!"), number(3));
+        let args = vec![libm, string("no_such_symbol"), string("void()"), list(Vec::new())];
+        assert!(matches!(call_native(&mut interpreter, args, 1), Err(LoxError::Runtime(RuntimeError::ForeignCall(..)))));
+
+        let mut sandboxed = Interpreter::builder().build();
+        let result = load_library(&mut sandboxed, vec![string("libm.so.6")], 1);
+        assert!(matches!(result, Err(LoxError::Runtime(RuntimeError::CapabilityDisabled("loadLibrary", "ffi", 1)))));
+    }
+}
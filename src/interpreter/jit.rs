@@ -0,0 +1,849 @@
+// External dependencies
+use cranelift_codegen::ir::condcodes::{FloatCC, IntCC};
+use cranelift_codegen::ir::{types, AbiParam, Block, FuncRef, Function, InstBuilder, MemFlags, StackSlotData, StackSlotKind, Type};
+use cranelift_codegen::ir::Value as IrValue;
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, FuncId, Linkage, Module};
+use std::collections::HashMap;
+use std::mem::offset_of;
+
+// Internal dependencies
+use super::{Interpreter, MAX_CALL_DEPTH};
+use crate::obj::expression::Expression;
+use crate::obj::function::FunctionDeclaration;
+use crate::obj::number::Number;
+use crate::obj::statement::Statement;
+use crate::obj::token::Token;
+use crate::obj::token_type::TokenType;
+use crate::obj::value::Value;
+use crate::shared::SharedRef;
+
+/// Loop iterations and calls between two checks for cancellation and the time limit
+const POLL_INTERVAL: i64 = 1024;
+
+/// Status of compiled code that returned, the value is in [Context::result]
+const RETURNED: u8 = 0;
+/// Status of compiled code that gave up, the interpreter runs the call again
+const FALLBACK: u8 = 1;
+
+/// Name the compiled code calls [interrupted] by
+const INTERRUPTED: &str = "jlox_interrupted";
+
+/// Compiled code: takes the context and the arguments, one 64 bit slot each,
+/// and returns [RETURNED] or [FALLBACK]
+type Code = unsafe extern "C" fn(*mut Context, *const u64) -> u8;
+
+/// What a value in compiled code is. Every variable and expression of a
+/// compiled function has a single kind, which follows from the kinds of
+/// the arguments it got compiled for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Int,
+    Float,
+    Bool,
+}
+
+impl Kind {
+    /// The kind of an argument, `None` for values compiled code doesn't
+    /// handle. With `decimal`, numbers aren't `f64`s and stay interpreted.
+    pub fn of(value: &Value) -> Option<Kind> {
+        match value {
+            Value::Int(_) => Some(Kind::Int),
+            Value::Number(_) if cfg!(not(feature = "decimal")) => Some(Kind::Float),
+            Value::Bool(_) => Some(Kind::Bool),
+            _ => None,
+        }
+    }
+
+    fn ir_type(self) -> Type {
+        match self {
+            Kind::Int => types::I64,
+            Kind::Float => types::F64,
+            Kind::Bool => types::I8,
+        }
+    }
+
+    /// The 64 bits a value of this kind is passed in
+    fn bits(value: &Value) -> u64 {
+        match value {
+            Value::Int(int) => *int as u64,
+            Value::Number(number) => number.to_f64().to_bits(),
+            Value::Bool(bool) => *bool as u64,
+            _ => 0,
+        }
+    }
+
+    /// The value passed in the 64 bits
+    fn value(self, bits: u64) -> Option<Value> {
+        match self {
+            Kind::Int => Some(Value::Int(bits as i64)),
+            Kind::Float => Number::from_f64(f64::from_bits(bits)).map(Value::Number),
+            Kind::Bool => Some(Value::Bool(bits != 0)),
+        }
+    }
+}
+
+/// What compiled code shares with [Compiled::run] through a pointer. The
+/// code accesses the fields at their offsets, hence `repr(C)`.
+#[repr(C)]
+struct Context {
+    /// Statements executed, counted on from those of the interpreter
+    steps: u64,
+    /// The step limit, `u64::MAX` without one
+    step_limit: u64,
+    /// Active calls, as many as the interpreter has frames
+    depth: u64,
+    /// Checks left until the next call of [interrupted]
+    poll: i64,
+    /// Bits of the value the last call returned
+    result: u64,
+    interpreter: *const Interpreter,
+}
+
+/// Called by compiled code every [POLL_INTERVAL] loop iterations and
+/// calls, returns `1` if it has to stop because the run was cancelled or
+/// ran out of time
+extern "C" fn interrupted(context: *const Context) -> u8 {
+    // The context and the interpreter outlive the compiled code that got the pointer
+    let interpreter = unsafe { &*(*context).interpreter };
+    interpreter.check_interrupted().is_err() as u8
+}
+
+/// Compiles Lox functions to native code with Cranelift once they are hot
+/// (`jit` feature). A function gets compiled for the kinds of arguments it
+/// is called with, when its body only computes with ints, numbers and
+/// booleans in its parameters and local variables: arithmetic, comparisons,
+/// `if`, loops, `return` and calls of itself by its name. Anything else
+/// (other values, globals, closures, natives, `print`) keeps it interpreted.
+///
+/// Compiled code has no side effects besides counting steps, so whenever
+/// it cannot go on like the interpreter would (an int overflows, a limit
+/// is reached, the end of the body returns `nil`), it gives up and the
+/// interpreter runs the call again from the start, which reports the
+/// error as usual.
+#[derive(Default)]
+pub struct Jit {
+    /// Created with the first function that gets compiled
+    module: Option<JITModule>,
+    /// What is known about the functions called so far, by the address of
+    /// their declaration and the kinds of the arguments
+    functions: HashMap<(usize, Vec<Kind>), Entry>,
+}
+
+/// A function called with arguments of certain kinds
+struct Entry {
+    /// Keeps the declaration alive, so its address isn't reused by another one
+    _declaration: SharedRef<FunctionDeclaration>,
+    state: State,
+}
+
+enum State {
+    /// Not hot yet, with the number of calls so far
+    Counting(u32),
+    Compiled(Compiled),
+    /// The body does something compiled code cannot do
+    Interpreted,
+}
+
+/// Native code of a function for arguments of certain kinds
+#[derive(Clone, Copy)]
+pub struct Compiled {
+    code: Code,
+    returns: Kind,
+    /// The code calls the function by its name, which has to refer to
+    /// the function itself when the code runs
+    pub recursive: bool,
+}
+
+impl Jit {
+    /// Counts a call of the function with arguments of these kinds. Once
+    /// it was called `threshold` times, it gets compiled and the code is
+    /// returned for this and every further call. `None` while the function
+    /// is cold and if it cannot be compiled.
+    pub fn lookup(
+        &mut self,
+        declaration: &SharedRef<FunctionDeclaration>,
+        kinds: Vec<Kind>,
+        threshold: u32,
+        strict: bool,
+    ) -> Option<Compiled> {
+        let key = (SharedRef::as_ptr(declaration) as usize, kinds);
+        let entry = self
+            .functions
+            .entry(key.clone())
+            .or_insert_with(|| Entry { _declaration: declaration.clone(), state: State::Counting(0) });
+        match &mut entry.state {
+            State::Counting(calls) if *calls + 1 < threshold => {
+                *calls += 1;
+                return None;
+            },
+            State::Counting(_) => {},
+            State::Compiled(compiled) => return Some(*compiled),
+            State::Interpreted => return None,
+        }
+
+        let compiled = self.compile(declaration, &key.1, strict);
+        let state = compiled.map_or(State::Interpreted, State::Compiled);
+        if let Some(entry) = self.functions.get_mut(&key) {
+            entry.state = state;
+        }
+        compiled
+    }
+
+    /// Translates the function for arguments of these kinds and finalizes
+    /// the code, `None` if it uses something compiled code cannot do
+    fn compile(&mut self, declaration: &FunctionDeclaration, kinds: &[Kind], strict: bool) -> Option<Compiled> {
+        if self.module.is_none() {
+            self.module = new_module();
+        }
+        let module = self.module.as_mut()?;
+        let pointer = module.target_config().pointer_type();
+        let mut signature = module.make_signature();
+        signature.params.extend([AbiParam::new(pointer), AbiParam::new(pointer)]);
+        signature.returns.push(AbiParam::new(types::I8));
+        let id = module.declare_anonymous_function(&signature).ok()?;
+        let interrupted = declare_interrupted(module, pointer)?;
+
+        // A function returns the same kind everywhere, which one is found out by trying
+        let mut context = module.make_context();
+        for returns in [Kind::Int, Kind::Float, Kind::Bool] {
+            context.func.signature = signature.clone();
+            let recursive = match translate(module, &mut context.func, id, interrupted, declaration, kinds, returns, strict) {
+                Ok(recursive) => recursive,
+                Err(Unsupported) => {
+                    module.clear_context(&mut context);
+                    continue;
+                },
+            };
+            module.define_function(id, &mut context).ok()?;
+            module.finalize_definitions().ok()?;
+            // The signature of the function is the one of `Code`
+            let code = unsafe { std::mem::transmute::<*const u8, Code>(module.get_finalized_function(id)) };
+            return Some(Compiled { code, returns, recursive });
+        }
+        None
+    }
+}
+
+impl Compiled {
+    /// Runs the code with the arguments, which have the kinds it was
+    /// compiled for. Gives the returned value and the steps of the
+    /// interpreter with those of the call, `None` if the code gave up.
+    pub fn run(&self, interpreter: &Interpreter, arguments: &[Value]) -> Option<(Value, u64)> {
+        let arguments: Vec<u64> = arguments.iter().map(Kind::bits).collect();
+        let mut context = Context {
+            steps: interpreter.steps,
+            step_limit: interpreter.config.step_limit.unwrap_or(u64::MAX),
+            depth: interpreter.frames.len() as u64,
+            poll: POLL_INTERVAL,
+            result: 0,
+            interpreter,
+        };
+        // The arguments match the parameters the code reads, and both pointers outlive the call
+        let status = unsafe { (self.code)(&mut context, arguments.as_ptr()) };
+        if status != RETURNED {
+            return None;
+        }
+        Some((self.returns.value(context.result)?, context.steps))
+    }
+}
+
+/// The module for the host, `None` if Cranelift doesn't support it
+fn new_module() -> Option<JITModule> {
+    let mut flags = settings::builder();
+    flags.set("opt_level", "speed").ok()?;
+    // Like JITBuilder::new, which panics on unsupported hosts instead
+    flags.set("use_colocated_libcalls", "false").ok()?;
+    flags.set("is_pic", "true").ok()?;
+    let isa = cranelift_native::builder().ok()?.finish(settings::Flags::new(flags)).ok()?;
+    let mut builder = JITBuilder::with_isa(isa, default_libcall_names());
+    builder.symbol(INTERRUPTED, interrupted as *const u8);
+    Some(JITModule::new(builder))
+}
+
+/// Declares [interrupted] for the compiled code, importing it the first time
+fn declare_interrupted(module: &mut JITModule, pointer: Type) -> Option<FuncId> {
+    let mut signature = module.make_signature();
+    signature.params.push(AbiParam::new(pointer));
+    signature.returns.push(AbiParam::new(types::I8));
+    module.declare_function(INTERRUPTED, Linkage::Import, &signature).ok()
+}
+
+/// The function does something compiled code cannot do
+struct Unsupported;
+
+type Translation<T> = std::result::Result<T, Unsupported>;
+
+/// Translates the body of the declaration into `function`, which has the
+/// id `id`. Returns whether the function calls itself.
+#[allow(clippy::too_many_arguments)]
+fn translate(
+    module: &mut JITModule,
+    function: &mut Function,
+    id: FuncId,
+    interrupted: FuncId,
+    declaration: &FunctionDeclaration,
+    kinds: &[Kind],
+    returns: Kind,
+    strict: bool,
+) -> Translation<bool> {
+    let itself = module.declare_func_in_func(id, function);
+    let interrupted = module.declare_func_in_func(interrupted, function);
+    let mut builder_context = FunctionBuilderContext::new();
+    let mut builder = FunctionBuilder::new(function, &mut builder_context);
+
+    let entry = builder.create_block();
+    builder.append_block_params_for_function_params(entry);
+    builder.switch_to_block(entry);
+    let context = builder.block_params(entry)[0];
+    let arguments = builder.block_params(entry)[1];
+    let fallback = builder.create_block();
+
+    let mut translator = Translator {
+        builder,
+        context,
+        itself,
+        interrupted,
+        fallback,
+        name: (!declaration.is_lambda()).then(|| declaration.name.lexeme()),
+        params: kinds.to_vec(),
+        returns,
+        strict,
+        scopes: vec![HashMap::new()],
+        loops: Vec::new(),
+        variables: 0,
+        recursive: false,
+        returned: false,
+    };
+    translator.enter(arguments, declaration)?;
+    translator.statements(&declaration.body)?;
+    if !translator.returned {
+        return Err(Unsupported); // It would always give up at the end
+    }
+    let recursive = translator.recursive;
+    translator.finish();
+    Ok(recursive)
+}
+
+/// A local variable or parameter of compiled code
+#[derive(Clone, Copy)]
+struct Local {
+    variable: Variable,
+    kind: Kind,
+    /// Declared with `var`, not `const`
+    mutable: bool,
+}
+
+/// A loop around the code being translated
+struct Loop {
+    label: Option<String>,
+    /// Where `break` continues
+    exit: Block,
+    /// Where `continue` continues, the increment of a `for` loop
+    next: Block,
+}
+
+/// Translates a function body statement by statement, like the interpreter
+/// executes it
+struct Translator<'a> {
+    builder: FunctionBuilder<'a>,
+    /// Pointer to the [Context]
+    context: IrValue,
+    itself: FuncRef,
+    interrupted: FuncRef,
+    /// Returns [FALLBACK]
+    fallback: Block,
+    /// The name recursive calls use, `None` for anonymous functions
+    name: Option<String>,
+    params: Vec<Kind>,
+    returns: Kind,
+    strict: bool,
+    /// Local variables by name, the innermost scope last
+    scopes: Vec<HashMap<String, Local>>,
+    loops: Vec<Loop>,
+    /// Variables created so far, the next one gets this index
+    variables: u32,
+    recursive: bool,
+    /// Whether there is a `return`
+    returned: bool,
+}
+
+impl Translator<'_> {
+    /// Binds the parameters and checks the call depth, the steps and for interruptions
+    fn enter(&mut self, arguments: IrValue, declaration: &FunctionDeclaration) -> Translation<()> {
+        for (index, (param, kind)) in declaration.params.iter().zip(self.params.clone()).enumerate() {
+            let offset = (index * 8) as i32;
+            let value = self.load(kind, arguments, offset);
+            self.declare(&param.name, value, kind, true)?;
+        }
+        let depth = self.builder.ins().load(types::I64, MemFlags::trusted(), self.context, offset_of!(Context, depth) as i32);
+        let too_deep = self.builder.ins().icmp_imm(IntCC::UnsignedGreaterThanOrEqual, depth, MAX_CALL_DEPTH as i64);
+        self.fall_back_if(too_deep);
+        self.check_limits();
+        Ok(())
+    }
+
+    /// Lets the code fall back to the interpreter at the end and seals the function
+    fn finish(mut self) {
+        let fallback = self.fallback;
+        self.builder.ins().jump(fallback, &[]); // The end of the body returns nil
+        self.builder.switch_to_block(fallback);
+        let status = self.builder.ins().iconst(types::I8, FALLBACK as i64);
+        self.builder.ins().return_(&[status]);
+        self.builder.seal_all_blocks();
+        self.builder.finalize();
+    }
+
+    fn statements(&mut self, stmts: &[Statement]) -> Translation<()> {
+        stmts.iter().try_for_each(|stmt| self.statement(stmt))
+    }
+
+    fn statement(&mut self, stmt: &Statement) -> Translation<()> {
+        self.add_to(offset_of!(Context, steps), 1);
+        match stmt {
+            Statement::Block(stmts) => {
+                self.scopes.push(HashMap::new());
+                self.statements(stmts)?;
+                self.scopes.pop();
+            },
+            Statement::Expression(expr) => {
+                self.expression(expr)?;
+            },
+            Statement::Var(name, _, Some(init)) => {
+                let (value, kind) = self.expression(init)?;
+                self.declare(name, value, kind, true)?;
+            },
+            Statement::Const(name, _, init) => {
+                let (value, kind) = self.expression(init)?;
+                self.declare(name, value, kind, false)?;
+            },
+            Statement::If(_, cond, then, els) => {
+                let (then_block, else_block, end) = (self.builder.create_block(), self.builder.create_block(), self.builder.create_block());
+                self.branch(cond, then_block, else_block)?;
+                self.builder.switch_to_block(then_block);
+                self.statement(then)?;
+                self.builder.ins().jump(end, &[]);
+                self.builder.switch_to_block(else_block);
+                if let Some(els) = els {
+                    self.statement(els)?;
+                }
+                self.builder.ins().jump(end, &[]);
+                self.builder.switch_to_block(end);
+            },
+            Statement::While(_, cond, body, increment, label) => {
+                let (header, body_block) = (self.builder.create_block(), self.builder.create_block());
+                let (next, exit) = (self.builder.create_block(), self.builder.create_block());
+                self.builder.ins().jump(header, &[]);
+                self.builder.switch_to_block(header);
+                self.check_limits();
+                self.branch(cond, body_block, exit)?;
+
+                self.builder.switch_to_block(body_block);
+                self.loops.push(Loop { label: label.as_ref().map(Token::lexeme), exit, next });
+                self.statement(body)?;
+                self.loops.pop();
+                self.builder.ins().jump(next, &[]);
+
+                self.builder.switch_to_block(next);
+                if let Some(increment) = increment {
+                    self.expression(increment)?;
+                }
+                self.builder.ins().jump(header, &[]);
+                self.builder.switch_to_block(exit);
+            },
+            Statement::Break(_, label) | Statement::Continue(_, label) => {
+                let label = label.as_ref().map(Token::lexeme);
+                let target = self.loops.iter().rev().find(|target| label.is_none() || target.label == label).ok_or(Unsupported)?;
+                let block = if matches!(stmt, Statement::Break(..)) { target.exit } else { target.next };
+                self.builder.ins().jump(block, &[]);
+                self.unreachable();
+            },
+            Statement::Return(_, Some(expr)) => {
+                let (value, kind) = self.expression(expr)?;
+                if kind != self.returns {
+                    return Err(Unsupported);
+                }
+                self.store(kind, value, self.context, offset_of!(Context, result) as i32);
+                let status = self.builder.ins().iconst(types::I8, RETURNED as i64);
+                self.builder.ins().return_(&[status]);
+                self.unreachable();
+                self.returned = true;
+            },
+            _ => return Err(Unsupported),
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self, expr: &Expression) -> Translation<(IrValue, Kind)> {
+        match expr {
+            Expression::Literal(Value::Int(int)) => Ok((self.builder.ins().iconst(types::I64, *int), Kind::Int)),
+            Expression::Literal(Value::Number(number)) if cfg!(not(feature = "decimal")) => {
+                Ok((self.builder.ins().f64const(number.to_f64()), Kind::Float))
+            },
+            Expression::Literal(Value::Bool(bool)) => Ok((self.builder.ins().iconst(types::I8, *bool as i64), Kind::Bool)),
+            Expression::Grouping(expr) => self.expression(expr),
+            Expression::Variable(name) => {
+                let local = self.local(name)?;
+                Ok((self.builder.use_var(local.variable), local.kind))
+            },
+            Expression::Assign(name, expr) => {
+                let (value, kind) = self.expression(expr)?;
+                let local = self.local(name)?;
+                if !local.mutable || local.kind != kind {
+                    return Err(Unsupported); // The interpreter reports constants, kinds don't change
+                }
+                self.builder.def_var(local.variable, value);
+                Ok((value, kind))
+            },
+            Expression::Unary(operator, right) => self.unary(operator, right),
+            Expression::Binary(left, operator, right) => self.binary(left, operator, right),
+            Expression::Logical(left, operator, right) => self.logical(left, operator, right),
+            Expression::Call(callee, _, args) => self.call(callee, args),
+            _ => Err(Unsupported),
+        }
+    }
+
+    fn unary(&mut self, operator: &Token, right: &Expression) -> Translation<(IrValue, Kind)> {
+        let (value, kind) = self.expression(right)?;
+        match (operator.token_type(), kind) {
+            (TokenType::Minus, Kind::Int) => {
+                let overflows = self.builder.ins().icmp_imm(IntCC::Equal, value, i64::MIN);
+                self.fall_back_if(overflows);
+                Ok((self.builder.ins().ineg(value), Kind::Int))
+            },
+            (TokenType::Minus, Kind::Float) => Ok((self.builder.ins().fneg(value), Kind::Float)),
+            (TokenType::Bang, Kind::Bool) => Ok((self.builder.ins().bxor_imm(value, 1), Kind::Bool)),
+            (TokenType::Bang, _) => Ok((self.builder.ins().iconst(types::I8, 0), Kind::Bool)), // Numbers are truthy
+            _ => Err(Unsupported),
+        }
+    }
+
+    fn binary(&mut self, left: &Expression, operator: &Token, right: &Expression) -> Translation<(IrValue, Kind)> {
+        let (left, left_kind) = self.expression(left)?;
+        let (right, right_kind) = self.expression(right)?;
+        let ints = left_kind == Kind::Int && right_kind == Kind::Int;
+        let operation = operator.token_type();
+        match operation {
+            // Two ints stay ints, except for `/`
+            TokenType::Plus | TokenType::Minus | TokenType::Star if ints => {
+                let (value, overflows) = match operation {
+                    TokenType::Plus => self.builder.ins().sadd_overflow(left, right),
+                    TokenType::Minus => self.builder.ins().ssub_overflow(left, right),
+                    _ => self.builder.ins().smul_overflow(left, right),
+                };
+                self.fall_back_if(overflows);
+                return Ok((value, Kind::Int));
+            },
+            TokenType::TildeSlash if ints => return Ok((self.floor_div(left, right), Kind::Int)),
+            TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Slash | TokenType::TildeSlash => {
+                let (left, right) = (self.float(left, left_kind)?, self.float(right, right_kind)?);
+                let value = match operation {
+                    TokenType::Plus => self.builder.ins().fadd(left, right),
+                    TokenType::Minus => self.builder.ins().fsub(left, right),
+                    TokenType::Star => self.builder.ins().fmul(left, right),
+                    TokenType::Slash => self.builder.ins().fdiv(left, right),
+                    _ => {
+                        let quotient = self.builder.ins().fdiv(left, right);
+                        self.builder.ins().floor(quotient)
+                    },
+                };
+                return Ok((value, Kind::Float));
+            },
+            _ => {},
+        }
+
+        // Comparisons and equality give booleans
+        let (int_cc, float_cc) = match operation {
+            TokenType::Greater => (IntCC::SignedGreaterThan, FloatCC::GreaterThan),
+            TokenType::GreaterEqual => (IntCC::SignedGreaterThanOrEqual, FloatCC::GreaterThanOrEqual),
+            TokenType::Less => (IntCC::SignedLessThan, FloatCC::LessThan),
+            TokenType::LessEqual => (IntCC::SignedLessThanOrEqual, FloatCC::LessThanOrEqual),
+            // An int equals a number only if the number converts back to it exactly, which is left to the interpreter
+            TokenType::EqualEqual | TokenType::BangEqual if left_kind != right_kind => return Err(Unsupported),
+            TokenType::EqualEqual => (IntCC::Equal, FloatCC::Equal),
+            TokenType::BangEqual => (IntCC::NotEqual, FloatCC::NotEqual),
+            _ => return Err(Unsupported),
+        };
+        let equality = matches!(operation, TokenType::EqualEqual | TokenType::BangEqual);
+        let value = if ints || (equality && left_kind == Kind::Bool) {
+            self.builder.ins().icmp(int_cc, left, right)
+        } else {
+            let (left, right) = (self.float(left, left_kind)?, self.float(right, right_kind)?);
+            self.builder.ins().fcmp(float_cc, left, right)
+        };
+        Ok((value, Kind::Bool))
+    }
+
+    /// `~/` of two ints, rounded toward negative infinity. Division by zero
+    /// and the overflow of `i64::MIN ~/ -1` fall back.
+    fn floor_div(&mut self, left: IrValue, right: IrValue) -> IrValue {
+        let by_zero = self.builder.ins().icmp_imm(IntCC::Equal, right, 0);
+        self.fall_back_if(by_zero);
+        let min = self.builder.ins().icmp_imm(IntCC::Equal, left, i64::MIN);
+        let minus_one = self.builder.ins().icmp_imm(IntCC::Equal, right, -1);
+        let overflows = self.builder.ins().band(min, minus_one);
+        self.fall_back_if(overflows);
+
+        let quotient = self.builder.ins().sdiv(left, right);
+        let remainder = self.builder.ins().srem(left, right);
+        let inexact = self.builder.ins().icmp_imm(IntCC::NotEqual, remainder, 0);
+        let signs = self.builder.ins().bxor(left, right);
+        let negative = self.builder.ins().icmp_imm(IntCC::SignedLessThan, signs, 0);
+        let adjust = self.builder.ins().band(inexact, negative);
+        let adjust = self.builder.ins().uextend(types::I64, adjust);
+        self.builder.ins().isub(quotient, adjust)
+    }
+
+    /// `and`, `or` and `??` give one of their operands, so both need the same kind
+    fn logical(&mut self, left: &Expression, operator: &Token, right: &Expression) -> Translation<(IrValue, Kind)> {
+        let (left, kind) = self.expression(left)?;
+        match (operator.token_type(), kind) {
+            (TokenType::QuestionQuestion, _) | (TokenType::Or, Kind::Int | Kind::Float) => Ok((left, kind)), // Never nil, always truthy
+            (TokenType::And, Kind::Int | Kind::Float) => self.expression(right),
+            (TokenType::And | TokenType::Or, Kind::Bool) => {
+                let (right_block, end) = (self.builder.create_block(), self.builder.create_block());
+                self.builder.append_block_param(end, types::I8);
+                if operator.token_type() == TokenType::And {
+                    self.builder.ins().brif(left, right_block, &[], end, &[left]);
+                } else {
+                    self.builder.ins().brif(left, end, &[left], right_block, &[]);
+                }
+                self.builder.switch_to_block(right_block);
+                let (right, right_kind) = self.expression(right)?;
+                if right_kind != Kind::Bool {
+                    return Err(Unsupported);
+                }
+                self.builder.ins().jump(end, &[right]);
+                self.builder.switch_to_block(end);
+                Ok((self.builder.block_params(end)[0], Kind::Bool))
+            },
+            _ => Err(Unsupported),
+        }
+    }
+
+    /// A call of the function itself, by its name, with arguments of the
+    /// same kinds. Other calls stay with the interpreter.
+    fn call(&mut self, callee: &Expression, args: &[Expression]) -> Translation<(IrValue, Kind)> {
+        let Expression::Variable(name) = callee else {
+            return Err(Unsupported);
+        };
+        if self.name != Some(name.lexeme()) || self.local(name).is_ok() || args.len() != self.params.len() {
+            return Err(Unsupported);
+        }
+        let size = (args.len().max(1) * 8) as u32;
+        let slot = self.builder.create_sized_stack_slot(StackSlotData::new(StackSlotKind::ExplicitSlot, size, 3));
+        for (index, arg) in args.iter().enumerate() {
+            let (value, kind) = self.expression(arg)?;
+            if kind != self.params[index] {
+                return Err(Unsupported);
+            }
+            let value = if kind == Kind::Bool { self.builder.ins().uextend(types::I64, value) } else { value };
+            self.builder.ins().stack_store(value, slot, (index * 8) as i32);
+        }
+        let pointer = self.builder.func.dfg.value_type(self.context);
+        let arguments = self.builder.ins().stack_addr(pointer, slot, 0);
+
+        self.add_to(offset_of!(Context, depth), 1);
+        let call = self.builder.ins().call(self.itself, &[self.context, arguments]);
+        let status = self.builder.inst_results(call)[0];
+        self.add_to(offset_of!(Context, depth), -1);
+        self.fall_back_if(status);
+        self.recursive = true;
+        let returns = self.returns;
+        Ok((self.load(returns, self.context, offset_of!(Context, result) as i32), returns))
+    }
+
+    /// Jumps to `then` if the condition is truthy, to `otherwise` if not
+    fn branch(&mut self, cond: &Expression, then: Block, otherwise: Block) -> Translation<()> {
+        match self.expression(cond)? {
+            (value, Kind::Bool) => self.builder.ins().brif(value, then, &[], otherwise, &[]),
+            _ => self.builder.ins().jump(then, &[]), // Numbers are truthy
+        };
+        Ok(())
+    }
+
+    /// Converts an int operand to a number. With `decimal`, numbers aren't `f64`s.
+    fn float(&mut self, value: IrValue, kind: Kind) -> Translation<IrValue> {
+        match kind {
+            _ if cfg!(feature = "decimal") => Err(Unsupported),
+            Kind::Int => Ok(self.builder.ins().fcvt_from_sint(types::F64, value)),
+            Kind::Float => Ok(value),
+            Kind::Bool => Err(Unsupported),
+        }
+    }
+
+    fn declare(&mut self, name: &Token, value: IrValue, kind: Kind, mutable: bool) -> Translation<()> {
+        let scope = self.scopes.last_mut().ok_or(Unsupported)?;
+        if self.strict && scope.contains_key(&name.lexeme()) {
+            return Err(Unsupported); // The interpreter reports the redeclaration
+        }
+        let variable = Variable::from_u32(self.variables);
+        self.variables += 1;
+        scope.insert(name.lexeme(), Local { variable, kind, mutable });
+        self.builder.declare_var(variable, kind.ir_type());
+        self.builder.def_var(variable, value);
+        Ok(())
+    }
+
+    /// The local variable or parameter, variables of enclosing scopes stay with the interpreter
+    fn local(&self, name: &Token) -> Translation<Local> {
+        let name = name.lexeme();
+        self.scopes.iter().rev().find_map(|scope| scope.get(&name).copied()).ok_or(Unsupported)
+    }
+
+    /// Falls back to the interpreter if the step limit was exceeded, and
+    /// every [POLL_INTERVAL] times if the run got cancelled or ran out of time
+    fn check_limits(&mut self) {
+        let steps = self.builder.ins().load(types::I64, MemFlags::trusted(), self.context, offset_of!(Context, steps) as i32);
+        let limit = self.builder.ins().load(types::I64, MemFlags::trusted(), self.context, offset_of!(Context, step_limit) as i32);
+        let exceeded = self.builder.ins().icmp(IntCC::UnsignedGreaterThan, steps, limit);
+        self.fall_back_if(exceeded);
+
+        let poll = self.add_to(offset_of!(Context, poll), -1);
+        let (check, go_on) = (self.builder.create_block(), self.builder.create_block());
+        self.builder.ins().brif(poll, go_on, &[], check, &[]);
+        self.builder.switch_to_block(check);
+        let reset = self.builder.ins().iconst(types::I64, POLL_INTERVAL);
+        self.builder.ins().store(MemFlags::trusted(), reset, self.context, offset_of!(Context, poll) as i32);
+        let call = self.builder.ins().call(self.interrupted, &[self.context]);
+        let interrupted = self.builder.inst_results(call)[0];
+        self.fall_back_if(interrupted);
+        self.builder.ins().jump(go_on, &[]);
+        self.builder.switch_to_block(go_on);
+    }
+
+    /// Adds to a counter of the context, returns the new value
+    fn add_to(&mut self, offset: usize, amount: i64) -> IrValue {
+        let value = self.builder.ins().load(types::I64, MemFlags::trusted(), self.context, offset as i32);
+        let value = self.builder.ins().iadd_imm(value, amount);
+        self.builder.ins().store(MemFlags::trusted(), value, self.context, offset as i32);
+        value
+    }
+
+    /// Reads a value of the kind from its 64 bit slot
+    fn load(&mut self, kind: Kind, address: IrValue, offset: i32) -> IrValue {
+        match kind {
+            Kind::Bool => {
+                let bits = self.builder.ins().load(types::I64, MemFlags::trusted(), address, offset);
+                self.builder.ins().icmp_imm(IntCC::NotEqual, bits, 0)
+            },
+            _ => self.builder.ins().load(kind.ir_type(), MemFlags::trusted(), address, offset),
+        }
+    }
+
+    /// Writes a value of the kind to its 64 bit slot
+    fn store(&mut self, kind: Kind, value: IrValue, address: IrValue, offset: i32) {
+        let value = if kind == Kind::Bool { self.builder.ins().uextend(types::I64, value) } else { value };
+        self.builder.ins().store(MemFlags::trusted(), value, address, offset);
+    }
+
+    fn fall_back_if(&mut self, condition: IrValue) {
+        let go_on = self.builder.create_block();
+        self.builder.ins().brif(condition, self.fallback, &[], go_on, &[]);
+        self.builder.switch_to_block(go_on);
+    }
+
+    /// Continues in a block nothing jumps to, after a jump or return
+    fn unreachable(&mut self) {
+        let block = self.builder.create_block();
+        self.builder.switch_to_block(block);
+    }
+}
+
+// ---------- Tests for the jit module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::CollectingReporter;
+    use crate::errors::{LoxError, Result, RuntimeError};
+    use crate::shared::Shared;
+    use std::io::Write;
+
+    /// Output handle that writes into a shared buffer
+    struct Capture(Shared<Vec<u8>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Runs the script, with functions compiled on their first call if
+    /// `jit` is set. Gives what it printed and the number of compiled functions.
+    fn run(source: &str, jit: bool, step_limit: Option<u64>) -> (Result<String>, usize) {
+        let output = Shared::new(Vec::new());
+        let mut builder = Interpreter::builder().output(Capture(output.clone()));
+        if jit {
+            builder = builder.jit_threshold(1);
+        }
+        if let Some(steps) = step_limit {
+            builder = builder.step_limit(steps);
+        }
+        let mut interpreter = builder.build();
+        let result = interpreter.run(source.to_string(), &mut CollectingReporter::default());
+        let compiled = interpreter.jit.functions.values().filter(|entry| matches!(entry.state, State::Compiled(_))).count();
+        let printed = String::from_utf8_lossy(&output.borrow()).into_owned();
+        (result.map(|_| printed), compiled)
+    }
+
+    #[test]
+    fn compiled_functions_compute_like_the_interpreter() {
+        let source = r#"
+            fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+            fun sum(n) {
+                var total = 0;
+                for (var i = 0; i < n; i = i + 1) {
+                    if (i == 3) continue;
+                    if (i > 50 and !(i < 0)) break;
+                    total = total + i * 2 - i ~/ 4;
+                }
+                return total;
+            }
+            fun mixed(a, b) { return a / b + a ~/ b - -a * 1.5; }
+            fun between(x, low, high) { return x >= low and x <= high or x != x; }
+            for (var i = 0; i < 3; i = i + 1) {
+                print fib(15), sum(100), mixed(-7, 2), mixed(7.5, 2), between(3, 1, 5), between(0.5, 1, 5);
+            }
+        "#;
+        let (interpreted, none) = run(source, false, None);
+        let (compiled, count) = run(source, true, None);
+        assert_eq!(compiled.unwrap(), interpreted.unwrap());
+        // mixed and between for two kinds of arguments each, with decimal numbers only between for ints
+        assert_eq!((none, count), (0, if cfg!(feature = "decimal") { 3 } else { 6 }));
+    }
+
+    #[test]
+    fn compiled_code_falls_back_to_report_errors() {
+        let overflow = "fun square(x) { return x * x; }\nprint square(3);\nprint square(4000000000);";
+        assert!(matches!(run(overflow, true, None).0, Err(LoxError::Runtime(RuntimeError::InvalidArithmetic(1)))));
+
+        let endless = "fun spin(n) { while (true) n = n + 1; return n; }\nspin(0);";
+        assert!(matches!(run(endless, true, Some(10_000)).0, Err(LoxError::Runtime(RuntimeError::StepLimitExceeded(10_000)))));
+
+        let deep = "fun down(n) { if (n == 0) return 0; return down(n - 1); }\nprint down(10);\ndown(5000);";
+        assert!(matches!(run(deep, true, None).0, Err(LoxError::Runtime(RuntimeError::StackOverflow(MAX_CALL_DEPTH, 1)))));
+    }
+
+    #[test]
+    fn functions_beyond_the_numeric_subset_stay_interpreted() {
+        let source = r#"
+            var scale = 2;
+            fun scaled(x) { return x * scale; }
+            fun greet(name) { return "Hi " + name; }
+            fun count(n) { if (n == 0) return 0; return count(n - 1) + 1; }
+            var original = count;
+            count = fun (n) { return -1; };
+            print scaled(3), greet("Ada"), original(3);
+        "#;
+        // count and the function that replaced it get compiled, but count mustn't call itself directly
+        let (printed, compiled) = run(source, true, None);
+        assert_eq!((printed.unwrap(), compiled), ("6 Hi Ada 0\n".to_string(), 2));
+    }
+}
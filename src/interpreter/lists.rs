@@ -0,0 +1,102 @@
+// Internal dependencies
+use super::Interpreter;
+use crate::errors::{Result, RuntimeError};
+use crate::obj::list::List;
+use crate::obj::value::Value;
+
+// The natives for working with lists. The ones that change a list change
+// it in place, for every copy of it. `map`, `filter` and `reduce` call a
+// Lox function for every element, they go over the elements the list had
+// when they were called, even if the function changes the list.
+
+/// `filter(list, fn)`: a new list of the elements the function returns a
+/// truthy value for
+pub fn filter(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let items = expect_list("filter", &args[0], line)?.items.borrow().clone();
+    let mut kept = Vec::new();
+    for item in items {
+        if super::is_truthy(interpreter.call(args[1].clone(), vec![item.clone()], line)?) {
+            kept.push(item);
+        }
+    }
+    interpreter.new_list(kept, line)
+}
+
+/// `insert(list, index, value)`: inserts the value before the element at
+/// the index, or at the end if the index is the length of the list
+pub fn insert(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let list = expect_mutable_list("insert", &args[0], line)?;
+    let index = super::position(&args[1], list.len(), "list", true, line)?;
+    interpreter.check_list_length(list.len() + 1, line)?;
+    interpreter.account(List::item_size(&args[2]), 0, line)?;
+    list.items.borrow_mut().insert(index, args[2].clone());
+    Ok(Value::Nil)
+}
+
+/// `map(list, fn)`: a new list of what the function returns for each element
+pub fn map(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let items = expect_list("map", &args[0], line)?.items.borrow().clone();
+    let mut mapped = Vec::with_capacity(items.len());
+    for item in items {
+        mapped.push(interpreter.call(args[1].clone(), vec![item], line)?);
+    }
+    interpreter.new_list(mapped, line)
+}
+
+/// `pop(list)`: removes the last element of the list and returns it
+pub fn pop(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let list = expect_mutable_list("pop", &args[0], line)?;
+    let Some(value) = list.items.borrow_mut().pop() else {
+        return Err(RuntimeError::InvalidArgument("pop", "the list is empty", line).into());
+    };
+    interpreter.account(0, List::item_size(&value), line)?;
+    Ok(value)
+}
+
+/// `push(list, value)`: appends the value to the end of the list
+pub fn push(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let list = expect_mutable_list("push", &args[0], line)?;
+    interpreter.check_list_length(list.len() + 1, line)?;
+    interpreter.account(List::item_size(&args[1]), 0, line)?;
+    list.items.borrow_mut().push(args[1].clone());
+    Ok(Value::Nil)
+}
+
+/// `reduce(list, fn, initial)`: combines the elements into one value. The
+/// function gets the value so far, starting with the initial one, and the
+/// next element, and returns the new value so far.
+pub fn reduce(interpreter: &mut Interpreter, mut args: Vec<Value>, line: u32) -> Result<Value> {
+    let items = expect_list("reduce", &args[0], line)?.items.borrow().clone();
+    let mut value = args.remove(2);
+    for item in items {
+        value = interpreter.call(args[1].clone(), vec![value, item], line)?;
+    }
+    Ok(value)
+}
+
+/// `remove(list, index)`: removes the element at the index and returns it,
+/// the elements after it move up. `remove` on a map is in the natives module.
+pub fn remove(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let list = expect_mutable_list("remove", &args[0], line)?;
+    let index = super::position(&args[1], list.len(), "list", false, line)?;
+    let value = list.items.borrow_mut().remove(index);
+    interpreter.account(0, List::item_size(&value), line)?;
+    Ok(value)
+}
+
+/// The list argument of the native `name`
+fn expect_list<'a>(name: &'static str, value: &'a Value, line: u32) -> Result<&'a List> {
+    match value {
+        Value::List(list) => Ok(list),
+        other => Err(RuntimeError::ArgumentType(name, "a list", other.type_name(), line).into()),
+    }
+}
+
+/// The list argument of the native `name` that changes it, which it can't if the list is frozen
+fn expect_mutable_list<'a>(name: &'static str, value: &'a Value, line: u32) -> Result<&'a List> {
+    let list = expect_list(name, value, line)?;
+    if list.is_frozen() {
+        return Err(RuntimeError::Frozen("list", line).into());
+    }
+    Ok(list)
+}
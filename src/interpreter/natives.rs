@@ -0,0 +1,644 @@
+// External dependencies
+use indexmap::IndexMap;
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Internal dependencies
+use super::Interpreter;
+use crate::diagnostic::{CollectingReporter, Origin};
+use crate::errors::{Result, RuntimeError};
+use crate::obj::channel::Channel;
+use crate::obj::class::Instance;
+use crate::obj::coroutine::{Coroutine, Status, Yielder};
+use crate::obj::detached::Detached;
+use crate::obj::environment::Environment;
+use crate::obj::future::Future;
+use crate::obj::map::{Key, Map};
+use crate::obj::native::NativeFunction;
+use crate::obj::number::Number;
+use crate::obj::statement::Statement;
+use crate::obj::task::Task;
+use crate::obj::token::Token;
+use crate::obj::token_type::TokenType;
+use crate::obj::value::Value;
+use crate::{parser, scanner};
+
+/// Every native function, in the order they get defined
+const NATIVES: &[NativeFunction] = &[
+    #[cfg(feature = "ffi")]
+    NativeFunction { name: "callNative", arity: 4, optional: 0, function: super::ffi::call_native },
+    NativeFunction { name: "callstack", arity: 0, optional: 0, function: callstack },
+    NativeFunction { name: "channel", arity: 0, optional: 0, function: channel },
+    NativeFunction { name: "clock", arity: 0, optional: 0, function: clock },
+    NativeFunction { name: "clone", arity: 1, optional: 0, function: clone },
+    NativeFunction { name: "contains", arity: 2, optional: 0, function: super::strings::contains },
+    NativeFunction { name: "coroutine", arity: 1, optional: 0, function: coroutine },
+    NativeFunction { name: "endsWith", arity: 2, optional: 0, function: super::strings::ends_with },
+    NativeFunction { name: "eval", arity: 1, optional: 0, function: eval },
+    NativeFunction { name: "evalIn", arity: 2, optional: 0, function: eval_in },
+    NativeFunction { name: "exit", arity: 1, optional: 0, function: exit },
+    NativeFunction { name: "fields", arity: 1, optional: 0, function: fields },
+    NativeFunction { name: "filter", arity: 2, optional: 0, function: super::lists::filter },
+    NativeFunction { name: "freeze", arity: 1, optional: 0, function: freeze },
+    NativeFunction { name: "gc", arity: 0, optional: 0, function: gc },
+    NativeFunction { name: "getEnv", arity: 1, optional: 0, function: super::system::get_env },
+    NativeFunction { name: "getattr", arity: 2, optional: 0, function: getattr },
+    NativeFunction { name: "has", arity: 2, optional: 0, function: has },
+    NativeFunction { name: "indexOf", arity: 2, optional: 0, function: super::strings::index_of },
+    NativeFunction { name: "input", arity: 1, optional: 0, function: input },
+    NativeFunction { name: "insert", arity: 3, optional: 0, function: super::lists::insert },
+    NativeFunction { name: "join", arity: 1, optional: 0, function: join },
+    NativeFunction { name: "joinWith", arity: 2, optional: 1, function: super::strings::join_with },
+    NativeFunction { name: "keys", arity: 1, optional: 0, function: keys },
+    NativeFunction { name: "len", arity: 1, optional: 0, function: len },
+    NativeFunction { name: "length", arity: 1, optional: 0, function: len },
+    #[cfg(feature = "ffi")]
+    NativeFunction { name: "loadLibrary", arity: 1, optional: 0, function: super::ffi::load_library },
+    NativeFunction { name: "lookupHost", arity: 1, optional: 0, function: super::system::lookup_host },
+    NativeFunction { name: "map", arity: 2, optional: 0, function: super::lists::map },
+    NativeFunction { name: "memory", arity: 0, optional: 0, function: memory },
+    NativeFunction { name: "methods", arity: 1, optional: 0, function: methods },
+    NativeFunction { name: "pop", arity: 1, optional: 0, function: super::lists::pop },
+    NativeFunction { name: "push", arity: 2, optional: 0, function: super::lists::push },
+    NativeFunction { name: "random", arity: 0, optional: 0, function: super::random::random },
+    NativeFunction { name: "randomInt", arity: 2, optional: 0, function: super::random::random_int },
+    NativeFunction { name: "readFile", arity: 1, optional: 0, function: super::system::read_file },
+    NativeFunction { name: "readLine", arity: 0, optional: 0, function: read_line },
+    NativeFunction { name: "receive", arity: 1, optional: 0, function: receive },
+    NativeFunction { name: "reduce", arity: 3, optional: 0, function: super::lists::reduce },
+    NativeFunction { name: "remove", arity: 2, optional: 0, function: remove },
+    NativeFunction { name: "replace", arity: 3, optional: 0, function: super::strings::replace },
+    NativeFunction { name: "repr", arity: 1, optional: 0, function: repr },
+    NativeFunction { name: "resume", arity: 2, optional: 0, function: resume },
+    NativeFunction { name: "seed", arity: 1, optional: 0, function: super::random::seed },
+    NativeFunction { name: "send", arity: 2, optional: 0, function: send },
+    NativeFunction { name: "setattr", arity: 3, optional: 0, function: setattr },
+    NativeFunction { name: "sleep", arity: 1, optional: 0, function: sleep },
+    NativeFunction { name: "sleepAsync", arity: 1, optional: 0, function: sleep_async },
+    NativeFunction { name: "spawn", arity: 1, optional: 0, function: spawn },
+    NativeFunction { name: "split", arity: 2, optional: 0, function: super::strings::split },
+    NativeFunction { name: "startsWith", arity: 2, optional: 0, function: super::strings::starts_with },
+    NativeFunction { name: "status", arity: 1, optional: 0, function: status },
+    NativeFunction { name: "substring", arity: 3, optional: 0, function: super::strings::substring },
+    NativeFunction { name: "timeMillis", arity: 0, optional: 0, function: time_millis },
+    NativeFunction { name: "toLower", arity: 1, optional: 0, function: super::strings::to_lower },
+    NativeFunction { name: "toUpper", arity: 1, optional: 0, function: super::strings::to_upper },
+    NativeFunction { name: "trim", arity: 1, optional: 0, function: super::strings::trim },
+    NativeFunction { name: "type", arity: 1, optional: 0, function: type_of },
+    NativeFunction { name: "values", arity: 1, optional: 0, function: values },
+    NativeFunction { name: "write", arity: 1, optional: 0, function: write },
+    NativeFunction { name: "writeFile", arity: 2, optional: 0, function: super::system::write_file },
+    NativeFunction { name: "yield", arity: 1, optional: 0, function: yield_value },
+];
+
+/// Defines every native function as a global variable
+pub fn define_all(globals: &mut Environment) {
+    for native in NATIVES {
+        globals.define_inner(native.name.to_string(), Value::NativeFunction(*native));
+    }
+}
+
+/// `callstack()`: the active calls, innermost first, as a list of maps
+/// with the `"function"` name and the `"line"` of the call. The call to
+/// `callstack` itself is left out.
+fn callstack(interpreter: &mut Interpreter, _: Vec<Value>, line: u32) -> Result<Value> {
+    let frames: Vec<(String, u32)> = interpreter.call_stack().iter().map(|frame| (frame.function.clone(), frame.line)).collect();
+    let mut calls = Vec::with_capacity(frames.len());
+    for (function, call_line) in frames[..frames.len().saturating_sub(1)].iter().rev() {
+        let mut entries = IndexMap::new();
+        entries.insert(Key::String("function".to_string()), Value::String(function.clone()));
+        entries.insert(Key::String("line".to_string()), Value::Int(i64::from(*call_line)));
+        calls.push(interpreter.new_map(entries, line)?);
+    }
+    interpreter.new_list(calls, line)
+}
+
+/// `channel()`: a new, empty channel, see `send` and `receive`
+fn channel(_: &mut Interpreter, _: Vec<Value>, _: u32) -> Result<Value> {
+    Ok(Value::Channel(Channel::new()))
+}
+
+/// `clock()`: the seconds since the interpreter was created, with a
+/// fractional part, for timing parts of a script. Needs the time capability.
+/// Stays 0 in [deterministic](super::Config::deterministic) runs.
+fn clock(interpreter: &mut Interpreter, _: Vec<Value>, line: u32) -> Result<Value> {
+    if !interpreter.config().capabilities.time {
+        return Err(RuntimeError::CapabilityDisabled("clock", "time", line).into());
+    }
+    if interpreter.config().deterministic {
+        return Ok(Value::Number(Number::from(0)));
+    }
+    let seconds = interpreter.created.elapsed().as_secs_f64();
+    Ok(Value::Number(Number::from_f64(seconds).unwrap_or_default()))
+}
+
+/// `clone(x)`: a deep copy of the value, see [Value::deep_copy]. The
+/// copied instances, lists and maps count towards the memory limit.
+fn clone(interpreter: &mut Interpreter, mut args: Vec<Value>, line: u32) -> Result<Value> {
+    let copy = args.remove(0).deep_copy();
+    interpreter.account(copy.reachable_size(), 0, line)?;
+    Ok(copy)
+}
+
+/// `coroutine(fn)`: a coroutine that calls the function on its first
+/// `resume`, with the resumed value if the function takes a parameter.
+/// It runs in an interpreter of its own with the same configuration and
+/// globals, so the function sees the script's variables themselves, not a copy.
+fn coroutine(interpreter: &mut Interpreter, mut args: Vec<Value>, line: u32) -> Result<Value> {
+    let function = args.remove(0);
+    let arity = match &function {
+        Value::NativeFunction(native) => native.arity,
+        Value::Function(function) => function.arity(),
+        other => return Err(RuntimeError::ArgumentType("coroutine", "a function", other.type_name(), line).into()),
+    };
+    if arity > 1 {
+        return Err(RuntimeError::InvalidArgument("coroutine", "expected a function with at most one parameter", line).into());
+    }
+    let coroutine = start_coroutine(interpreter, "coroutine", line, move |coroutine, first, yielder| {
+        coroutine.yielder = Some(yielder);
+        let arguments = if arity == 1 { vec![first] } else { Vec::new() };
+        coroutine.call(function, arguments, line)
+    })?;
+    Ok(Value::Coroutine(coroutine))
+}
+
+/// Starts a coroutine whose body runs in an interpreter of its own, with
+/// the configuration, globals and event loop of `interpreter`. The body
+/// gets that interpreter, the first resumed value and the [Yielder] of the
+/// coroutine. Async calls are coroutines like these too. Fails if no
+/// thread can be started for it, `what` names it in the error.
+pub(super) fn start_coroutine(
+    interpreter: &Interpreter,
+    what: &'static str,
+    line: u32,
+    body: impl FnOnce(&mut Interpreter, Value, Yielder) -> Result<Value> + 'static,
+) -> Result<Coroutine> {
+    let config = interpreter.config().clone();
+    let cancellation = interpreter.cancellation_token();
+    let globals = interpreter.global_environment();
+    let events = interpreter.events.clone();
+
+    Coroutine::start(move |first, yielder, printed| {
+        let output = Box::new(TaskOutput(printed));
+        let mut coroutine = Interpreter::with_globals(config, globals, output, Box::new(std::io::empty()), Vec::new());
+        coroutine.cancellation = cancellation;
+        coroutine.events = events;
+        coroutine.begin_run();
+        let result = body(&mut coroutine, first, yielder);
+        coroutine.finish_run(result)
+    })
+    .map_err(|reason| RuntimeError::ThreadUnavailable(what, reason, line).into())
+}
+
+/// `eval(code)`: runs the Lox code in the scope `eval` is called from, the
+/// variables, functions and classes it declares stay there. Returns the
+/// value of the last statement if it is an expression statement, `nil`
+/// otherwise. The lines of the code count from the line of the call.
+fn eval(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let statements = eval_source("eval", &args[0], line)?;
+    interpreter.execute_snippet(&statements)
+}
+
+/// `evalIn(code, variables)`: like `eval`, but in a fresh scope below the
+/// global one. It starts with the variables of the map, named by its keys.
+/// What the code declares is gone afterwards, only the value is left.
+fn eval_in(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let statements = eval_source("evalIn", &args[0], line)?;
+    let mut variables = Vec::new();
+    for (name, value) in expect_map("evalIn", &args[1], line)?.entries.borrow().iter() {
+        let Key::String(name) = name else {
+            return Err(RuntimeError::InvalidArgument("evalIn", "variable names have to be strings", line).into());
+        };
+        variables.push((Token::new(TokenType::Identifier, name.clone(), None, line), value.clone()));
+    }
+
+    let environment = Environment::new_enclosed(interpreter.global_environment());
+    interpreter.in_scope(environment, |interpreter| {
+        for (name, value) in variables {
+            interpreter.define(&name, value, true)?;
+        }
+        interpreter.execute_snippet(&statements)
+    })
+}
+
+/// `exit(code)`: ends the script with the exit code, from 0 to 255. It
+/// unwinds like an error that no `catch` block takes, `finally` blocks
+/// still run. Hosts get the code from [LoxError::exit_code](crate::errors::LoxError::exit_code).
+fn exit(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    if !interpreter.config().capabilities.process {
+        return Err(RuntimeError::CapabilityDisabled("exit", "process", line).into());
+    }
+    match args[0].to_count().and_then(|code| u8::try_from(code).ok()) {
+        Some(code) => Err(RuntimeError::Exit(code).into()),
+        None => Err(RuntimeError::InvalidArgument("exit", "exit codes are whole numbers from 0 to 255", line).into()),
+    }
+}
+
+/// `fields(instance)`: a list of the names of the fields the instance has,
+/// in alphabetical order
+fn fields(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let mut names: Vec<String> = expect_instance("fields", &args[0], line)?.fields.borrow().keys().cloned().collect();
+    names.sort();
+    interpreter.new_list(names.into_iter().map(Value::String).collect(), line)
+}
+
+/// `freeze(x)`: forbids changing the list, map or instance from now on and
+/// returns it. Only the value itself is frozen, not the values inside it.
+fn freeze(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    match &args[0] {
+        Value::List(list) => list.freeze(),
+        Value::Map(map) => map.freeze(),
+        Value::Instance(instance) => instance.freeze(),
+        other => {
+            return Err(RuntimeError::ArgumentType("freeze", "a list, map or instance", other.type_name(), line).into());
+        }
+    }
+    Ok(args[0].clone())
+}
+
+/// `gc()`: forces a garbage collection. Values are reference counted and
+/// freed as soon as they are unreachable, so there is never anything left
+/// to collect. Exists so scripts can already call it.
+fn gc(_: &mut Interpreter, _: Vec<Value>, _: u32) -> Result<Value> {
+    Ok(Value::Nil)
+}
+
+/// `getattr(x, name)`: the property of the value with the name, like
+/// `x.name` would get it. Methods come bound to the instance.
+fn getattr(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let name = property_name("getattr", &args[1], line)?;
+    super::get_property(args[0].clone(), &name)
+}
+
+/// `has(map, key)`: whether the map has an entry for the key
+fn has(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let map = expect_map("has", &args[0], line)?;
+    Ok(Value::Bool(Key::from_value(&args[1]).is_some_and(|key| map.contains(&key))))
+}
+
+/// `input(prompt)`: prints the prompt without a line break and reads a
+/// line like `readLine` does
+fn input(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    write!(interpreter.output, "{}", args[0])
+        .and_then(|_| interpreter.output.flush())
+        .map_err(|e| RuntimeError::OutputFailed(e.to_string()))?;
+    read_line(interpreter, Vec::new(), line)
+}
+
+/// `join(task)`: waits for a task started by `spawn` and returns the
+/// result of its function. What the task printed gets written to the
+/// output now, so the output of tasks never interleaves. A runtime error
+/// of the task is raised again here. Lists are joined with `joinWith`.
+fn join(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let Value::Task(task) = &args[0] else {
+        return Err(RuntimeError::ArgumentType("join", "a task", args[0].type_name(), line).into());
+    };
+    let (result, printed) = task
+        .join()
+        .ok_or(RuntimeError::InvalidArgument("join", "the task was already joined", line))?
+        .map_err(|message| RuntimeError::TaskPanicked(message, line))?;
+    interpreter
+        .output
+        .write_all(&printed)
+        .map_err(|e| RuntimeError::OutputFailed(e.to_string()))?;
+    result?.attach()
+}
+
+/// `keys(map)`: a list of the keys of the map, in the order they were added
+fn keys(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let keys = expect_map("keys", &args[0], line)?.keys();
+    interpreter.new_list(keys, line)
+}
+
+/// `len(x)`, or `length(x)`: the number of characters of a string,
+/// elements of a list or entries of a map
+fn len(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    match &args[0] {
+        Value::String(s) => Ok(Value::Int(s.chars().count() as i64)),
+        Value::List(list) => Ok(Value::Int(list.len() as i64)),
+        Value::Map(map) => Ok(Value::Int(map.len() as i64)),
+        other => Err(RuntimeError::ArgumentType("len", "a string, list or map", other.type_name(), line).into()),
+    }
+}
+
+/// `memory()`: approximate number of bytes held by live variables,
+/// see [Interpreter::memory_used]
+fn memory(interpreter: &mut Interpreter, _: Vec<Value>, _: u32) -> Result<Value> {
+    Ok(Value::Int(interpreter.memory_used() as i64))
+}
+
+/// `methods(x)`: a list of the names of the methods of a class or of the
+/// class of an instance, including the inherited ones, in alphabetical order
+fn methods(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let mut class = match &args[0] {
+        Value::Class(class) => Some(class),
+        Value::Instance(instance) => Some(&instance.class),
+        other => return Err(RuntimeError::ArgumentType("methods", "a class or instance", other.type_name(), line).into()),
+    };
+    let mut names = Vec::new();
+    while let Some(current) = class {
+        names.extend(current.methods.borrow().keys().cloned());
+        class = current.superclass.as_deref();
+    }
+    names.sort();
+    names.dedup(); // Overridden methods are listed once
+    interpreter.new_list(names.into_iter().map(Value::String).collect(), line)
+}
+
+/// `readLine()`: the next line of the input, without its line break, or
+/// `nil` at the end of the input. Reads from the input the interpreter was
+/// built with, stdin by default. Tasks and coroutines have no input.
+fn read_line(interpreter: &mut Interpreter, _: Vec<Value>, line: u32) -> Result<Value> {
+    let mut text = String::new();
+    match interpreter.input.read_line(&mut text) {
+        Ok(0) => Ok(Value::Nil),
+        Ok(_) => {
+            let end = text.trim_end_matches(['\n', '\r']).len();
+            text.truncate(end);
+            Ok(Value::String(text))
+        }
+        Err(e) => Err(RuntimeError::InputFailed(e.to_string(), line).into()),
+    }
+}
+
+/// `receive(channel)`: takes the oldest value out of the channel. Waits
+/// until there is one, but cancellation and the time limit still
+/// interrupt the wait.
+fn receive(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    const SLICE: Duration = Duration::from_millis(10);
+
+    let channel = expect_channel("receive", &args[0], line)?;
+    loop {
+        interpreter.check_interrupted()?;
+        if let Some(value) = channel.receive_timeout(SLICE)? {
+            return Ok(value);
+        }
+    }
+}
+
+/// `remove(map, key)`: removes the entry of the key from the map and
+/// returns its value, `nil` if there was none. `remove(list, index)` is
+/// [lists::remove](super::lists::remove).
+fn remove(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let map = match &args[0] {
+        Value::Map(map) => map,
+        Value::List(_) => return super::lists::remove(interpreter, args, line),
+        other => return Err(RuntimeError::ArgumentType("remove", "a map or list", other.type_name(), line).into()),
+    };
+    if map.is_frozen() {
+        return Err(RuntimeError::Frozen("map", line).into());
+    }
+    let Some(key) = Key::from_value(&args[1]) else {
+        return Ok(Value::Nil); // Such a key can't be in the map
+    };
+    match map.remove(&key) {
+        Some(value) => {
+            interpreter.account(0, Map::entry_size(&key, &value), line)?;
+            Ok(value)
+        }
+        None => Ok(Value::Nil),
+    }
+}
+
+/// `repr(x)`: an unambiguous text form of the value, with quoted strings
+/// and numbers that always show their fractional part (see [Value::to_text])
+fn repr(_: &mut Interpreter, args: Vec<Value>, _: u32) -> Result<Value> {
+    Ok(Value::String(args[0].to_text()))
+}
+
+/// `resume(co, value)`: runs the coroutine until it yields or returns,
+/// and returns the value it yielded or returned. The resumed value is what
+/// the `yield` the coroutine waits in returns. What the coroutine printed
+/// gets written to the output now. An error of the coroutine is raised
+/// again here and leaves it dead.
+fn resume(interpreter: &mut Interpreter, mut args: Vec<Value>, line: u32) -> Result<Value> {
+    let value = args.remove(1);
+    let coroutine = expect_coroutine("resume", &args[0], line)?;
+    let result = coroutine.resume(value);
+    interpreter
+        .output
+        .write_all(&coroutine.take_printed())
+        .map_err(|e| RuntimeError::OutputFailed(e.to_string()))?;
+    match result? {
+        Some(value) => Ok(value),
+        None if coroutine.status() == Status::Dead => {
+            Err(RuntimeError::InvalidArgument("resume", "the coroutine is dead", line).into())
+        }
+        None => Err(RuntimeError::InvalidArgument("resume", "the coroutine is already running", line).into()),
+    }
+}
+
+/// `spawn(fn)`: calls the function without arguments on a new thread and
+/// returns the task, see `join`. The task runs in a fresh interpreter
+/// with the same configuration. The function takes a copy of the
+/// variables it can see along (see [Detached]), so the task can call the
+/// script's functions but shares no variables with it. Cancelling the
+/// script cancels its tasks as well.
+fn spawn(interpreter: &mut Interpreter, mut args: Vec<Value>, line: u32) -> Result<Value> {
+    let function = args.remove(0);
+    if !matches!(function, Value::NativeFunction(_) | Value::Function(_)) {
+        return Err(RuntimeError::ArgumentType("spawn", "a function", function.type_name(), line).into());
+    }
+    let config = interpreter.config().clone();
+    let cancellation = interpreter.cancellation_token();
+    let function = Detached::new(&function);
+
+    let task = Task::spawn(move || {
+        let printed = TaskOutput::default();
+        let mut task = Interpreter::from_parts(config, Box::new(printed.clone()), Box::new(std::io::empty()), Vec::new());
+        task.cancellation = cancellation;
+        task.begin_run();
+        let result = function.attach().and_then(|function| task.call(function, Vec::new(), line));
+        let result = task.finish_run(result);
+        (result.map(|value| Detached::new(&value)), printed.take())
+    })
+    .map_err(|reason| RuntimeError::ThreadUnavailable("task", reason, line))?;
+    Ok(Value::Task(task))
+}
+
+/// `send(channel, value)`: puts a deep copy of the value into the channel
+fn send(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    expect_channel("send", &args[0], line)?.send(&args[1]);
+    Ok(Value::Nil)
+}
+
+/// `setattr(instance, name, value)`: assigns the property of the instance
+/// with the name, like `instance.name = value` would, setters included
+fn setattr(interpreter: &mut Interpreter, mut args: Vec<Value>, line: u32) -> Result<Value> {
+    let name = property_name("setattr", &args[1], line)?;
+    let instance = expect_instance("setattr", &args[0], line)?.clone();
+    let value = args.remove(2);
+    interpreter.set_property(instance, &name, value)?;
+    Ok(Value::Nil)
+}
+
+/// `sleep(ms)`: pauses the script for the given number of milliseconds.
+/// Needs the time capability. Cancellation and the time limit
+/// interrupt the sleep.
+fn sleep(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    if !interpreter.config().capabilities.time {
+        return Err(RuntimeError::CapabilityDisabled("sleep", "time", line).into());
+    }
+    let duration = milliseconds("sleep", &args[0], line)?;
+    interpreter.sleep(duration)?;
+    Ok(Value::Nil)
+}
+
+/// `sleepAsync(ms)`: a future that completes with nil after the given
+/// number of milliseconds. Unlike `sleep`, the script and its async calls
+/// go on meanwhile, until something awaits the future. Needs the time capability.
+fn sleep_async(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    if !interpreter.config().capabilities.time {
+        return Err(RuntimeError::CapabilityDisabled("sleepAsync", "time", line).into());
+    }
+    let duration = milliseconds("sleepAsync", &args[0], line)?;
+    let due = Instant::now()
+        .checked_add(duration)
+        .ok_or(RuntimeError::InvalidArgument("sleepAsync", "the number of milliseconds is too large", line))?;
+    let future = Future::new();
+    interpreter.events.borrow_mut().add_timer(due, future.clone());
+    Ok(Value::Future(future))
+}
+
+/// The milliseconds argument of the native `name`, as a duration
+fn milliseconds(name: &'static str, value: &Value, line: u32) -> Result<Duration> {
+    let millis = match value.as_number() {
+        Some(n) => n.to_f64(),
+        None => return Err(RuntimeError::ArgumentType(name, "a number", value.type_name(), line).into()),
+    };
+    Duration::try_from_secs_f64(millis / 1000.0)
+        .map_err(|_| RuntimeError::InvalidArgument(name, "expected a non-negative number of milliseconds", line).into())
+}
+
+/// `status(co)`: `"suspended"`, `"running"` or `"dead"`, see [Status]
+fn status(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    Ok(Value::String(expect_coroutine("status", &args[0], line)?.status().name().to_string()))
+}
+
+/// `timeMillis()`: the milliseconds since the Unix epoch, the current
+/// date and time. Needs the time capability. Stays 0 in
+/// [deterministic](super::Config::deterministic) runs.
+fn time_millis(interpreter: &mut Interpreter, _: Vec<Value>, line: u32) -> Result<Value> {
+    if !interpreter.config().capabilities.time {
+        return Err(RuntimeError::CapabilityDisabled("timeMillis", "time", line).into());
+    }
+    if interpreter.config().deterministic {
+        return Ok(Value::Int(0));
+    }
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis());
+    Ok(Value::Int(i64::try_from(millis).unwrap_or(i64::MAX)))
+}
+
+/// `type(x)`: the name of the value's type, like `"number"` or `"instance"`
+fn type_of(_: &mut Interpreter, args: Vec<Value>, _: u32) -> Result<Value> {
+    Ok(Value::String(args[0].type_name().to_string()))
+}
+
+/// `values(map)`: a list of the values of the map, in the order their keys were added
+fn values(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let values = expect_map("values", &args[0], line)?.values();
+    interpreter.new_list(values, line)
+}
+
+/// `write(x)`: prints the value like `print` does, but without the line
+/// break. Used by templates to put text and values next to each other.
+fn write(interpreter: &mut Interpreter, args: Vec<Value>, _: u32) -> Result<Value> {
+    write!(interpreter.output, "{}", args[0]).map_err(|e| RuntimeError::OutputFailed(e.to_string()))?;
+    Ok(Value::Nil)
+}
+
+/// `yield(value)`: suspends the coroutine that calls it and hands the value
+/// to the `resume` that ran it. Returns the value of the next `resume`.
+fn yield_value(interpreter: &mut Interpreter, mut args: Vec<Value>, line: u32) -> Result<Value> {
+    match &interpreter.yielder {
+        Some(yielder) => yielder.suspend(args.swap_remove(0)),
+        None => Err(RuntimeError::InvalidArgument("yield", "only a coroutine can yield", line).into()),
+    }
+}
+
+/// The channel argument of the native `name`
+fn expect_channel<'a>(name: &'static str, value: &'a Value, line: u32) -> Result<&'a Channel> {
+    match value {
+        Value::Channel(channel) => Ok(channel),
+        other => Err(RuntimeError::ArgumentType(name, "a channel", other.type_name(), line).into()),
+    }
+}
+
+/// The coroutine argument of the native `name`
+fn expect_coroutine<'a>(name: &'static str, value: &'a Value, line: u32) -> Result<&'a Coroutine> {
+    match value {
+        Value::Coroutine(coroutine) => Ok(coroutine),
+        other => Err(RuntimeError::ArgumentType(name, "a coroutine", other.type_name(), line).into()),
+    }
+}
+
+/// The code argument of the `eval` natives, scanned and parsed. It is
+/// either a single expression, like `1 + 2`, or statements. The first
+/// error in the code fails the call.
+fn eval_source(name: &'static str, value: &Value, line: u32) -> Result<Vec<Statement>> {
+    let Value::String(code) = value else {
+        return Err(RuntimeError::ArgumentType(name, "a string", value.type_name(), line).into());
+    };
+    let mut reporter = CollectingReporter::default();
+    let origin = Origin { file: None, line, column: 1 };
+    let statements = scanner::scan_tokens_at(code.clone(), &origin, &mut reporter).and_then(|tokens| {
+        match parser::parse_expression(tokens.clone(), &mut CollectingReporter::default()) {
+            Ok(expr) => Ok(vec![Statement::Expression(expr)]),
+            Err(_) => parser::parse(tokens, &mut reporter),
+        }
+    });
+    statements.map_err(|error| {
+        let message = match reporter.diagnostics().first() {
+            Some(diagnostic) => diagnostic.to_string(),
+            None => error.to_string(),
+        };
+        RuntimeError::InvalidEvalCode(message, line).into()
+    })
+}
+
+/// The instance argument of the native `name`
+fn expect_instance<'a>(name: &'static str, value: &'a Value, line: u32) -> Result<&'a Instance> {
+    match value {
+        Value::Instance(instance) => Ok(instance),
+        other => Err(RuntimeError::ArgumentType(name, "an instance", other.type_name(), line).into()),
+    }
+}
+
+/// The property name argument of the native `name`, as the token a
+/// property access would have
+fn property_name(name: &'static str, value: &Value, line: u32) -> Result<Token> {
+    match value {
+        Value::String(property) => Ok(Token::new(TokenType::Identifier, property.clone(), None, line)),
+        other => Err(RuntimeError::ArgumentType(name, "a string", other.type_name(), line).into()),
+    }
+}
+
+/// The map argument of the native `name`
+fn expect_map<'a>(name: &'static str, value: &'a Value, line: u32) -> Result<&'a Map> {
+    match value {
+        Value::Map(map) => Ok(map),
+        other => Err(RuntimeError::ArgumentType(name, "a map", other.type_name(), line).into()),
+    }
+}
+
+/// Output handle of a task. The task writes into the buffer, `join`
+/// takes its content once the task is done.
+#[derive(Clone, Default)]
+struct TaskOutput(Arc<Mutex<Vec<u8>>>);
+
+impl TaskOutput {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+impl Write for TaskOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+// External dependencies
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Internal dependencies
+use super::Interpreter;
+use crate::errors::{Result, RuntimeError};
+use crate::obj::number::Number;
+use crate::obj::value::Value;
+
+/// The random number generator behind `random`, `randomInt` and `seed`.
+/// It is SplitMix64: tiny, fast and good enough for games and tests, but
+/// not for anything secret. The same seed always gives the same numbers,
+/// on every platform and in every version of jlox.
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// A generator seeded from the current time, or from 0 if `deterministic`
+    pub fn seeded(deterministic: bool) -> Self {
+        if deterministic {
+            return Self::new(0);
+        }
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_nanos());
+        Self::new(nanos as u64)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number from 0 up to, but not including, 1
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A number from 0 up to, but not including, `bound`, every one equally
+    /// likely. A `bound` of 0 stands for 2^64, any number is fine then.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            return self.next_u64();
+        }
+        // Numbers above the last whole multiple of the bound would favor the small results
+        let limit = u64::MAX - u64::MAX % bound;
+        loop {
+            let number = self.next_u64();
+            if number < limit {
+                return number % bound;
+            }
+        }
+    }
+}
+
+/// `random()`: a random number from 0 up to, but not including, 1
+pub fn random(interpreter: &mut Interpreter, _: Vec<Value>, _: u32) -> Result<Value> {
+    let number = interpreter.random.next_f64();
+    Ok(Value::Number(Number::from_f64(number).unwrap_or_default()))
+}
+
+/// `randomInt(lo, hi)`: a random int from lo to hi, both included
+pub fn random_int(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let (low, high) = (expect_int("randomInt", &args[0], line)?, expect_int("randomInt", &args[1], line)?);
+    if low > high {
+        return Err(RuntimeError::InvalidArgument("randomInt", "the low end can't be above the high end", line).into());
+    }
+    let span = high.wrapping_sub(low) as u64 + 1; // Wraps to 0 for every int, which `below` takes as 2^64
+    Ok(Value::Int(low.wrapping_add(interpreter.random.below(span) as i64)))
+}
+
+/// `seed(n)`: restarts the random numbers from the int, the same seed
+/// gives the same numbers every time
+pub fn seed(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    interpreter.random = Random::new(expect_int("seed", &args[0], line)? as u64);
+    Ok(Value::Nil)
+}
+
+/// A whole number argument of the native `name`
+fn expect_int(name: &'static str, value: &Value, line: u32) -> Result<i64> {
+    match value {
+        Value::Int(int) => Ok(*int),
+        Value::Number(number) => {
+            number.to_i64().ok_or_else(|| RuntimeError::ArgumentType(name, "an int", "number", line).into())
+        }
+        other => Err(RuntimeError::ArgumentType(name, "an int", other.type_name(), line).into()),
+    }
+}
+
+// ---------- Tests for the random module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_same_seed_gives_the_same_numbers() {
+        let (mut a, mut b) = (Random::new(42), Random::new(42));
+        let numbers: Vec<u64> = (0..5).map(|_| a.next_u64()).collect();
+        assert_eq!(numbers, (0..5).map(|_| b.next_u64()).collect::<Vec<_>>());
+        let mut other = Random::new(43);
+        assert_ne!(numbers, (0..5).map(|_| other.next_u64()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn numbers_stay_in_their_range() {
+        let mut random = Random::new(7);
+        for _ in 0..1000 {
+            let number = random.next_f64();
+            assert!((0.0..1.0).contains(&number));
+            assert!(random.below(6) < 6);
+        }
+        assert_eq!(random.below(1), 0);
+    }
+}
@@ -0,0 +1,28 @@
+// External dependencies
+use std::time::Duration;
+
+/// Resource usage of the last run of an [Interpreter](super::Interpreter),
+/// for hosts that log or bill script executions. Obtained through
+/// [Interpreter::last_run](super::Interpreter::last_run) after
+/// [Interpreter::run](super::Interpreter::run), [Interpreter::interpret](super::Interpreter::interpret)
+/// or [Interpreter::evaluate](super::Interpreter::evaluate) returned, also when they failed.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RunReport {
+    /// Time spent scanning, `None` if the run got already scanned tokens
+    pub scan_time: Option<Duration>,
+    /// Time spent parsing, `None` if the run got an already parsed syntax tree
+    pub parse_time: Option<Duration>,
+    /// Time spent executing, zero if the front end failed
+    pub execute_time: Duration,
+    /// Number of statements executed, including nested ones
+    pub statements: u64,
+    /// Deepest nesting of scopes, `0` if only the global scope was used
+    pub peak_depth: usize,
+    /// Number of values the script allocated memory for (strings built, variables bound)
+    pub allocations: u64,
+    /// Approximate number of bytes of those allocations, see [Interpreter::memory_used](super::Interpreter::memory_used)
+    pub allocated_bytes: usize,
+    /// Number of diagnostics (errors and warnings) the front end reported
+    pub diagnostics: usize,
+}
@@ -0,0 +1,102 @@
+// Internal dependencies
+use super::Interpreter;
+use crate::errors::{Result, RuntimeError};
+use crate::obj::value::Value;
+
+// The natives for working with strings. Positions and lengths count
+// characters, like indexing a string does. The length of a string is what
+// `len` gives.
+
+/// `contains(s, part)`: whether the part occurs anywhere in the string
+pub fn contains(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let (s, part) = (expect_string("contains", &args[0], line)?, expect_string("contains", &args[1], line)?);
+    Ok(Value::Bool(s.contains(part)))
+}
+
+/// `endsWith(s, suffix)`: whether the string ends with the suffix
+pub fn ends_with(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let (s, suffix) = (expect_string("endsWith", &args[0], line)?, expect_string("endsWith", &args[1], line)?);
+    Ok(Value::Bool(s.ends_with(suffix)))
+}
+
+/// `indexOf(s, part)`: the position where the part first occurs in the
+/// string, -1 if it doesn't occur at all
+pub fn index_of(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let (s, part) = (expect_string("indexOf", &args[0], line)?, expect_string("indexOf", &args[1], line)?);
+    Ok(Value::Int(s.find(part).map_or(-1, |byte| s[..byte].chars().count() as i64)))
+}
+
+/// `joinWith(list, separator)`: the elements of the list in their printed
+/// form, with the separator (or nothing) between them. The opposite of `split`.
+pub fn join_with(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let Value::List(list) = &args[0] else {
+        return Err(RuntimeError::ArgumentType("joinWith", "a list", args[0].type_name(), line).into());
+    };
+    let separator = match args.get(1) {
+        Some(separator) => expect_string("joinWith", separator, line)?,
+        None => "",
+    };
+    let items: Vec<String> = list.items.borrow().iter().map(Value::to_string).collect();
+    Ok(Value::String(items.join(separator)))
+}
+
+/// `replace(s, old, new)`: the string with every occurrence of old replaced by new
+pub fn replace(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let s = expect_string("replace", &args[0], line)?;
+    let (old, new) = (expect_string("replace", &args[1], line)?, expect_string("replace", &args[2], line)?);
+    if old.is_empty() {
+        return Err(RuntimeError::InvalidArgument("replace", "the replaced part can't be empty", line).into());
+    }
+    Ok(Value::String(s.replace(old, new)))
+}
+
+/// `split(s, separator)`: a list of the parts of the string between the
+/// separators. An empty separator splits the string into its characters.
+pub fn split(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let (s, separator) = (expect_string("split", &args[0], line)?, expect_string("split", &args[1], line)?);
+    let parts: Vec<Value> = if separator.is_empty() {
+        s.chars().map(|c| Value::String(c.to_string())).collect()
+    } else {
+        s.split(separator).map(|part| Value::String(part.to_string())).collect()
+    };
+    interpreter.new_list(parts, line)
+}
+
+/// `startsWith(s, prefix)`: whether the string starts with the prefix
+pub fn starts_with(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let (s, prefix) = (expect_string("startsWith", &args[0], line)?, expect_string("startsWith", &args[1], line)?);
+    Ok(Value::Bool(s.starts_with(prefix)))
+}
+
+/// `substring(s, start, end)`: the characters from start up to, but not
+/// including, end. Same as `s[start:end]`, negative positions count from the end.
+pub fn substring(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    let s = expect_string("substring", &args[0], line)?;
+    let length = s.chars().count();
+    let start = super::position(&args[1], length, "string", true, line)?;
+    let end = super::position(&args[2], length, "string", true, line)?.max(start);
+    Ok(Value::String(s.chars().skip(start).take(end - start).collect()))
+}
+
+/// `toLower(s)`: the string in lowercase
+pub fn to_lower(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    Ok(Value::String(expect_string("toLower", &args[0], line)?.to_lowercase()))
+}
+
+/// `toUpper(s)`: the string in uppercase
+pub fn to_upper(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    Ok(Value::String(expect_string("toUpper", &args[0], line)?.to_uppercase()))
+}
+
+/// `trim(s)`: the string without the whitespace at its start and end
+pub fn trim(_: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    Ok(Value::String(expect_string("trim", &args[0], line)?.trim().to_string()))
+}
+
+/// A string argument of the native `name`
+pub(super) fn expect_string<'a>(name: &'static str, value: &'a Value, line: u32) -> Result<&'a str> {
+    match value {
+        Value::String(s) => Ok(s),
+        other => Err(RuntimeError::ArgumentType(name, "a string", other.type_name(), line).into()),
+    }
+}
@@ -0,0 +1,60 @@
+// External dependencies
+use std::net::ToSocketAddrs;
+
+// Internal dependencies
+use super::Interpreter;
+use super::strings::expect_string;
+use crate::errors::{Result, RuntimeError};
+use crate::obj::value::Value;
+
+// The natives that reach outside of the interpreter: files (the fs
+// capability), environment variables (env) and the network (net).
+
+/// `getEnv(name)`: the value of the environment variable, nil if it is
+/// not set. Needs the env capability.
+pub fn get_env(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    if !interpreter.config().capabilities.env {
+        return Err(RuntimeError::CapabilityDisabled("getEnv", "env", line).into());
+    }
+    let name = expect_string("getEnv", &args[0], line)?;
+    Ok(std::env::var(name).map_or(Value::Nil, Value::String))
+}
+
+/// `lookupHost(name)`: the IP addresses the host name resolves to, as a
+/// list of strings. Needs the net capability.
+pub fn lookup_host(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    if !interpreter.config().capabilities.net {
+        return Err(RuntimeError::CapabilityDisabled("lookupHost", "net", line).into());
+    }
+    let name = expect_string("lookupHost", &args[0], line)?;
+    let resolved = (name, 0).to_socket_addrs().map_err(|e| RuntimeError::SystemFailed("lookupHost", e.to_string(), line))?;
+    let mut addresses: Vec<Value> = Vec::new();
+    for address in resolved.map(|address| Value::String(address.ip().to_string())) {
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+    interpreter.new_list(addresses, line)
+}
+
+/// `readFile(path)`: the content of the file as a string. Needs the fs capability.
+pub fn read_file(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    if !interpreter.config().capabilities.fs {
+        return Err(RuntimeError::CapabilityDisabled("readFile", "fs", line).into());
+    }
+    let path = expect_string("readFile", &args[0], line)?;
+    let text = std::fs::read_to_string(path).map_err(|e| RuntimeError::SystemFailed("readFile", e.to_string(), line))?;
+    interpreter.check_string_length(text.chars().count(), line)?;
+    Ok(Value::String(text))
+}
+
+/// `writeFile(path, text)`: replaces the content of the file with the
+/// text, creating the file if needed. Needs the fs capability.
+pub fn write_file(interpreter: &mut Interpreter, args: Vec<Value>, line: u32) -> Result<Value> {
+    if !interpreter.config().capabilities.fs {
+        return Err(RuntimeError::CapabilityDisabled("writeFile", "fs", line).into());
+    }
+    let (path, text) = (expect_string("writeFile", &args[0], line)?, expect_string("writeFile", &args[1], line)?);
+    std::fs::write(path, text).map_err(|e| RuntimeError::SystemFailed("writeFile", e.to_string(), line))?;
+    Ok(Value::Nil)
+}
@@ -0,0 +1,37 @@
+//! jlox is a tree-walk interpreter for the Lox language. The library
+//! exposes every phase on its own (scanning, parsing, interpreting), so
+//! the binary, the fuzz targets and embedders can drive them directly.
+//! Every fallible function returns a [Result] with a typed [LoxError].
+
+// Modules
+pub mod obj {
+    pub mod annotation;
+    pub mod channel;
+    pub mod class;
+    pub mod coroutine;
+    pub mod detached;
+    pub mod environment;
+    pub mod expression;
+    pub mod function;
+    pub mod future;
+    pub mod list;
+    pub mod map;
+    pub mod native;
+    pub mod number;
+    pub mod statement;
+    pub mod task;
+    pub mod token_type;
+    pub mod token;
+    pub mod value;
+}
+pub mod analyzer;
+pub mod diagnostic;
+pub mod errors;
+pub mod interpreter;
+pub mod loxc;
+pub mod parser;
+pub mod scanner;
+pub mod shared;
+pub mod typechecker;
+
+pub use errors::{LoxError, Result};
@@ -0,0 +1,951 @@
+// Internal dependencies
+use crate::errors::{ChunkError, Result, ValueError};
+use crate::obj::annotation::TypeAnnotation;
+use crate::obj::expression::{Expression, Pattern};
+use crate::obj::function::{FunctionDeclaration, Parameter};
+use crate::obj::statement::Statement;
+use crate::obj::token::Token;
+use crate::obj::token_type::TokenType;
+use crate::obj::value::Value;
+use crate::parser::MAX_DEPTH;
+use crate::shared::SharedRef;
+
+/// Every `.loxc` file starts with these bytes
+pub const MAGIC: &[u8; 4] = b"LOXC";
+
+/// Version of the binary format. Has to be bumped whenever the encoding
+/// of any node changes, so old chunks get rejected instead of misread.
+pub const FORMAT_VERSION: u16 = 30;
+
+/// Size of the header: magic, version, checksum and payload length
+const HEADER_LEN: usize = 4 + 2 + 4 + 4;
+
+/// Tags of the expressions whose first field is another expression: binary and logical
+/// operators, property accesses, calls, indices and slices. They nest as deep as a chain
+/// like `a + b + c` or `a.b().c` is long, which the parser doesn't limit.
+const CHAINED: [u8; 7] = [1, 3, 7, 8, 13, 16, 18];
+
+/// Token types in the order of their encoded tag. Append only, the
+/// position of a token type is part of the file format.
+const TOKEN_TYPES: [TokenType; 60] = [
+    TokenType::LeftParen, TokenType::RightParen, TokenType::LeftBrace, TokenType::RightBrace,
+    TokenType::Comma, TokenType::Dot, TokenType::Minus, TokenType::Plus, TokenType::Semicolon,
+    TokenType::Slash, TokenType::Star, TokenType::Bang, TokenType::BangEqual, TokenType::Equal,
+    TokenType::EqualEqual, TokenType::Greater, TokenType::GreaterEqual, TokenType::Less,
+    TokenType::LessEqual, TokenType::Identifier, TokenType::String, TokenType::Number,
+    TokenType::And, TokenType::Class, TokenType::Else, TokenType::False, TokenType::Fun,
+    TokenType::For, TokenType::If, TokenType::Nil, TokenType::Or, TokenType::Print,
+    TokenType::Return, TokenType::Super, TokenType::This, TokenType::True, TokenType::Var,
+    TokenType::While, TokenType::Eof, TokenType::TildeSlash, TokenType::Colon, TokenType::Continue,
+    TokenType::LeftBracket, TokenType::RightBracket, TokenType::Switch, TokenType::Case, TokenType::Default,
+    TokenType::In, TokenType::Throw, TokenType::Try, TokenType::Catch, TokenType::Finally,
+    TokenType::Const, TokenType::QuestionQuestion, TokenType::QuestionDot, TokenType::With,
+    TokenType::Break, TokenType::Is, TokenType::Async, TokenType::Await,
+];
+
+/// Compiles parsed statements into a `.loxc` chunk. The chunk contains the
+/// whole syntax tree, so running it skips scanning and parsing entirely.
+///
+/// Layout (little endian): magic, format version (u16), FNV-1a checksum
+/// of the payload (u32), payload length (u32), payload.
+pub fn compile(statements: &[Statement]) -> Vec<u8> {
+    let mut encoder = Encoder { bytes: Vec::new() };
+    encoder.u32(statements.len() as u32);
+    for stmt in statements {
+        encoder.statement(stmt);
+    }
+    chunk(encoder.bytes)
+}
+
+/// Compiles a single function declaration into a chunk, read back with
+/// [load_function]. The syntax tree can't be moved to another thread,
+/// this is how functions get there (see [Detached](crate::obj::detached::Detached)).
+pub fn compile_function(declaration: &FunctionDeclaration) -> Vec<u8> {
+    let mut encoder = Encoder { bytes: Vec::new() };
+    encoder.function(declaration);
+    chunk(encoder.bytes)
+}
+
+/// Puts the header in front of an encoded payload
+fn chunk(payload: Vec<u8>) -> Vec<u8> {
+    let mut chunk = Vec::with_capacity(HEADER_LEN + payload.len());
+    chunk.extend_from_slice(MAGIC);
+    chunk.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    chunk.extend_from_slice(&checksum(&payload).to_le_bytes());
+    chunk.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    chunk.extend_from_slice(&payload);
+    chunk
+}
+
+/// Checks whether the bytes look like a `.loxc` chunk (and not like source code)
+pub fn is_chunk(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Validates the header of a `.loxc` chunk and decodes the statements in it
+pub fn load(bytes: &[u8]) -> Result<Vec<Statement>> {
+    let mut decoder = Decoder { bytes: payload(bytes)?, pos: 0, depth: 0 };
+    let count = decoder.u32()?;
+    let mut statements = Vec::new();
+    for _ in 0..count {
+        statements.push(decoder.statement()?);
+    }
+    Ok(statements)
+}
+
+/// Decodes a chunk created by [compile_function]
+pub fn load_function(bytes: &[u8]) -> Result<FunctionDeclaration> {
+    Decoder { bytes: payload(bytes)?, pos: 0, depth: 0 }.function()
+}
+
+/// Validates the header of a chunk and returns the payload after it
+fn payload(bytes: &[u8]) -> Result<&[u8]> {
+    if !is_chunk(bytes) {
+        return Err(ChunkError::BadMagic.into());
+    }
+    let mut header = Decoder { bytes, pos: MAGIC.len(), depth: 0 };
+
+    let version = header.u16()?;
+    if version != FORMAT_VERSION {
+        return Err(ChunkError::UnsupportedVersion(version, FORMAT_VERSION).into());
+    }
+    let expected_checksum = header.u32()?;
+    let payload_len = header.u32()? as usize;
+    let payload = bytes
+        .get(HEADER_LEN..HEADER_LEN + payload_len)
+        .ok_or(ChunkError::Truncated)?;
+    if checksum(payload) != expected_checksum {
+        return Err(ChunkError::ChecksumMismatch.into());
+    }
+    Ok(payload)
+}
+
+/// 32 bit FNV-1a hash, used to detect corrupted chunks
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+/// Writes AST nodes into a byte buffer. Every node starts with a tag
+/// byte naming its variant, followed by its fields in declaration order.
+struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Block(stmts) => {
+                self.u8(0);
+                self.u32(stmts.len() as u32);
+                for stmt in stmts {
+                    self.statement(stmt);
+                }
+            }
+            Statement::Expression(expr) => {
+                self.u8(1);
+                self.expression(expr);
+            }
+            Statement::If(keyword, cond, then, els) => {
+                self.u8(2);
+                self.token(keyword);
+                self.expression(cond);
+                self.statement(then);
+                self.bool(els.is_some());
+                if let Some(els) = els {
+                    self.statement(els);
+                }
+            }
+            Statement::Print(keyword, exprs) => {
+                self.u8(3);
+                self.token(keyword);
+                self.u32(exprs.len() as u32);
+                for expr in exprs {
+                    self.expression(expr);
+                }
+            }
+            Statement::Var(name, annotation, init) => {
+                self.u8(4);
+                self.token(name);
+                self.annotation(annotation.as_ref());
+                self.bool(init.is_some());
+                if let Some(init) = init {
+                    self.expression(init);
+                }
+            }
+            Statement::Const(name, annotation, init) => {
+                self.u8(14);
+                self.token(name);
+                self.annotation(annotation.as_ref());
+                self.expression(init);
+            }
+            Statement::Destructure(pattern, init) => {
+                self.u8(15);
+                self.pattern(pattern);
+                self.expression(init);
+            }
+            Statement::While(keyword, cond, body, increment, label) => {
+                self.u8(5);
+                self.token(keyword);
+                self.expression(cond);
+                self.statement(body);
+                self.bool(increment.is_some());
+                if let Some(increment) = increment {
+                    self.expression(increment);
+                }
+                self.optional_token(label.as_ref());
+            }
+            Statement::Break(keyword, label) => {
+                self.u8(16);
+                self.token(keyword);
+                self.optional_token(label.as_ref());
+            }
+            Statement::Continue(keyword, label) => {
+                self.u8(9);
+                self.token(keyword);
+                self.optional_token(label.as_ref());
+            }
+            Statement::ForIn(keyword, name, iterable, body, label) => {
+                self.u8(11);
+                self.token(keyword);
+                self.token(name);
+                self.expression(iterable);
+                self.statement(body);
+                self.optional_token(label.as_ref());
+            }
+            Statement::Switch(keyword, subject, cases, default) => {
+                self.u8(10);
+                self.token(keyword);
+                self.expression(subject);
+                self.u32(cases.len() as u32);
+                for (values, body) in cases {
+                    self.u32(values.len() as u32);
+                    for value in values {
+                        self.expression(value);
+                    }
+                    self.statements(body);
+                }
+                self.bool(default.is_some());
+                if let Some(body) = default {
+                    self.statements(body);
+                }
+            }
+            Statement::Throw(keyword, value) => {
+                self.u8(12);
+                self.token(keyword);
+                self.expression(value);
+            }
+            Statement::Try(keyword, body, catches, finally) => {
+                self.u8(13);
+                self.token(keyword);
+                self.statements(body);
+                self.u32(catches.len() as u32);
+                for (name, class, handler) in catches {
+                    self.token(name);
+                    self.bool(class.is_some());
+                    if let Some(class) = class {
+                        self.token(class);
+                    }
+                    self.statements(handler);
+                }
+                self.bool(finally.is_some());
+                if let Some(finally) = finally {
+                    self.statements(finally);
+                }
+            }
+            Statement::Function(declaration) => {
+                self.u8(6);
+                self.function(declaration);
+            }
+            Statement::Return(keyword, value) => {
+                self.u8(7);
+                self.token(keyword);
+                self.bool(value.is_some());
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            Statement::Class(name, superclass, mixins, methods, setters) => {
+                self.u8(8);
+                self.token(name);
+                self.bool(superclass.is_some());
+                if let Some(superclass) = superclass {
+                    self.token(superclass);
+                }
+                self.u32(mixins.len() as u32);
+                for mixin in mixins {
+                    self.token(mixin);
+                }
+                self.u32(methods.len() as u32);
+                for method in methods {
+                    self.function(method);
+                }
+                self.u32(setters.len() as u32);
+                for setter in setters {
+                    self.function(setter);
+                }
+            }
+        }
+    }
+
+    fn statements(&mut self, stmts: &[Statement]) {
+        self.u32(stmts.len() as u32);
+        for stmt in stmts {
+            self.statement(stmt);
+        }
+    }
+
+    fn function(&mut self, declaration: &FunctionDeclaration) {
+        self.token(&declaration.name);
+        self.bool(declaration.is_async);
+        self.u32(declaration.params.len() as u32);
+        for param in &declaration.params {
+            self.token(&param.name);
+            self.annotation(param.annotation.as_ref());
+        }
+        self.annotation(declaration.return_type.as_ref());
+        self.u32(declaration.body.len() as u32);
+        for stmt in &declaration.body {
+            self.statement(stmt);
+        }
+    }
+
+    fn annotation(&mut self, annotation: Option<&TypeAnnotation>) {
+        self.bool(annotation.is_some());
+        if let Some(annotation) = annotation {
+            self.token(&annotation.name);
+        }
+    }
+
+    /// The opening token tells list and map patterns apart
+    fn pattern(&mut self, pattern: &Pattern) {
+        self.token(pattern.token());
+        self.u32(pattern.names().len() as u32);
+        for name in pattern.names() {
+            self.token(name);
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Assign(name, value) => {
+                self.u8(0);
+                self.token(name);
+                self.expression(value);
+            }
+            Expression::Binary(left, op, right) => {
+                self.u8(1);
+                self.expression(left);
+                self.token(op);
+                self.expression(right);
+            }
+            Expression::Grouping(inner) => {
+                self.u8(2);
+                self.expression(inner);
+            }
+            Expression::Logical(left, op, right) => {
+                self.u8(3);
+                self.expression(left);
+                self.token(op);
+                self.expression(right);
+            }
+            Expression::Unary(op, right) => {
+                self.u8(4);
+                self.token(op);
+                self.expression(right);
+            }
+            Expression::Literal(value) => {
+                self.u8(5);
+                self.value(value);
+            }
+            Expression::Variable(name) => {
+                self.u8(6);
+                self.token(name);
+            }
+            Expression::Call(callee, paren, args) => {
+                self.u8(8);
+                self.expression(callee);
+                self.token(paren);
+                self.u32(args.len() as u32);
+                for arg in args {
+                    self.expression(arg);
+                }
+            }
+            Expression::Get(object, name) => {
+                self.u8(7);
+                self.expression(object);
+                self.token(name);
+            }
+            Expression::Set(object, name, value) => {
+                self.u8(9);
+                self.expression(object);
+                self.token(name);
+                self.expression(value);
+            }
+            Expression::This(keyword) => {
+                self.u8(10);
+                self.token(keyword);
+            }
+            Expression::Super(keyword, method) => {
+                self.u8(11);
+                self.token(keyword);
+                self.token(method);
+            }
+            Expression::List(bracket, items) => {
+                self.u8(12);
+                self.token(bracket);
+                self.u32(items.len() as u32);
+                for item in items {
+                    self.expression(item);
+                }
+            }
+            Expression::Index(object, bracket, index) => {
+                self.u8(13);
+                self.expression(object);
+                self.token(bracket);
+                self.expression(index);
+            }
+            Expression::IndexSet(object, bracket, index, value) => {
+                self.u8(14);
+                self.expression(object);
+                self.token(bracket);
+                self.expression(index);
+                self.expression(value);
+            }
+            Expression::Map(brace, entries) => {
+                self.u8(15);
+                self.token(brace);
+                self.u32(entries.len() as u32);
+                for (key, value) in entries {
+                    self.expression(key);
+                    self.expression(value);
+                }
+            }
+            Expression::Lambda(declaration) => {
+                self.u8(17);
+                self.function(declaration);
+            }
+            Expression::OptionalGet(object, name) => {
+                self.u8(18);
+                self.expression(object);
+                self.token(name);
+            }
+            Expression::Destructure(pattern, value) => {
+                self.u8(19);
+                self.pattern(pattern);
+                self.expression(value);
+            }
+            Expression::If(keyword, cond, then, els) => {
+                self.u8(20);
+                self.token(keyword);
+                self.expression(cond);
+                self.expression(then);
+                self.bool(els.is_some());
+                if let Some(els) = els {
+                    self.expression(els);
+                }
+            }
+            Expression::Block(brace, stmts, value) => {
+                self.u8(21);
+                self.token(brace);
+                self.statements(stmts);
+                self.bool(value.is_some());
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            Expression::Slice(object, bracket, start, end) => {
+                self.u8(16);
+                self.expression(object);
+                self.token(bracket);
+                for bound in [start, end] {
+                    self.bool(bound.is_some());
+                    if let Some(bound) = bound {
+                        self.expression(bound);
+                    }
+                }
+            }
+        }
+    }
+
+    fn optional_token(&mut self, token: Option<&Token>) {
+        self.bool(token.is_some());
+        if let Some(token) = token {
+            self.token(token);
+        }
+    }
+
+    fn token(&mut self, token: &Token) {
+        let tag = TOKEN_TYPES
+            .iter()
+            .position(|token_type| *token_type == token.token_type())
+            .unwrap_or_default(); // Every token type is listed, so this always finds one
+        self.u8(tag as u8);
+        self.string(&token.lexeme());
+        self.bool(token.literal().is_some());
+        if let Some(literal) = token.literal() {
+            self.value(&literal);
+        }
+        self.u32(token.line());
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::String(s) => {
+                self.u8(0);
+                self.string(s);
+            }
+            Value::Number(n) => {
+                // Numbers are stored in their text form, which is exact for both number backends
+                self.u8(1);
+                self.string(&n.to_text());
+            }
+            Value::Int(i) => {
+                self.u8(4);
+                self.string(&i.to_string());
+            }
+            Value::Bool(b) => {
+                self.u8(2);
+                self.bool(*b);
+            }
+            // Functions, classes, instances, lists, maps, tasks, channels, coroutines and futures only exist at runtime, literals never contain them
+            Value::Nil
+            | Value::NativeFunction(_)
+            | Value::Function(_)
+            | Value::Class(_)
+            | Value::Instance(_)
+            | Value::List(_)
+            | Value::Map(_)
+            | Value::Task(_)
+            | Value::Channel(_)
+            | Value::Coroutine(_)
+            | Value::Future(_) => self.u8(3),
+        }
+    }
+
+    fn string(&mut self, s: &str) {
+        self.u32(s.len() as u32);
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    fn bool(&mut self, b: bool) {
+        self.u8(b as u8);
+    }
+
+    fn u8(&mut self, n: u8) {
+        self.bytes.push(n);
+    }
+
+    fn u32(&mut self, n: u32) {
+        self.bytes.extend_from_slice(&n.to_le_bytes());
+    }
+}
+
+/// Reads AST nodes back from a byte buffer written by the [Encoder]
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// How deeply the node being read is nested, limited like in the parser
+    depth: usize,
+}
+
+impl Decoder<'_> {
+    fn statement(&mut self) -> Result<Statement> {
+        self.nested(Self::unnested_statement)
+    }
+
+    fn unnested_statement(&mut self) -> Result<Statement> {
+        let stmt = match self.u8()? {
+            0 => {
+                let count = self.u32()?;
+                let mut stmts = Vec::new();
+                for _ in 0..count {
+                    stmts.push(self.statement()?);
+                }
+                Statement::Block(stmts)
+            }
+            1 => Statement::Expression(self.expression()?),
+            2 => {
+                let keyword = self.token()?;
+                let cond = self.expression()?;
+                let then = Box::new(self.statement()?);
+                let els = if self.bool()? { Some(Box::new(self.statement()?)) } else { None };
+                Statement::If(keyword, cond, then, els)
+            }
+            3 => {
+                let keyword = self.token()?;
+                let count = self.u32()?;
+                let mut exprs = Vec::new();
+                for _ in 0..count {
+                    exprs.push(self.expression()?);
+                }
+                Statement::Print(keyword, exprs)
+            }
+            4 => {
+                let name = self.token()?;
+                let annotation = self.annotation()?;
+                let init = if self.bool()? { Some(self.expression()?) } else { None };
+                Statement::Var(name, annotation, init)
+            }
+            5 => {
+                let (keyword, cond, body) = (self.token()?, self.expression()?, Box::new(self.statement()?));
+                let increment = if self.bool()? { Some(self.expression()?) } else { None };
+                Statement::While(keyword, cond, body, increment, self.optional_token()?)
+            }
+            6 => Statement::Function(SharedRef::new(self.function()?)),
+            7 => {
+                let keyword = self.token()?;
+                let value = if self.bool()? { Some(self.expression()?) } else { None };
+                Statement::Return(keyword, value)
+            }
+            8 => {
+                let name = self.token()?;
+                let superclass = if self.bool()? { Some(self.token()?) } else { None };
+                let count = self.u32()?;
+                let mut mixins = Vec::new();
+                for _ in 0..count {
+                    mixins.push(self.token()?);
+                }
+                let count = self.u32()?;
+                let mut methods = Vec::new();
+                for _ in 0..count {
+                    methods.push(SharedRef::new(self.function()?));
+                }
+                let count = self.u32()?;
+                let mut setters = Vec::new();
+                for _ in 0..count {
+                    setters.push(SharedRef::new(self.function()?));
+                }
+                Statement::Class(name, superclass, mixins, methods, setters)
+            }
+            9 => Statement::Continue(self.token()?, self.optional_token()?),
+            10 => {
+                let (keyword, subject) = (self.token()?, self.expression()?);
+                let count = self.u32()?;
+                let mut cases = Vec::new();
+                for _ in 0..count {
+                    let count = self.u32()?;
+                    let mut values = Vec::new();
+                    for _ in 0..count {
+                        values.push(self.expression()?);
+                    }
+                    cases.push((values, self.statements()?));
+                }
+                let default = if self.bool()? { Some(self.statements()?) } else { None };
+                Statement::Switch(keyword, subject, cases, default)
+            }
+            11 => {
+                let (keyword, name, iterable) = (self.token()?, self.token()?, self.expression()?);
+                Statement::ForIn(keyword, name, iterable, Box::new(self.statement()?), self.optional_token()?)
+            }
+            12 => Statement::Throw(self.token()?, self.expression()?),
+            13 => {
+                let (keyword, body) = (self.token()?, self.statements()?);
+                let count = self.u32()?;
+                let mut catches = Vec::new();
+                for _ in 0..count {
+                    let name = self.token()?;
+                    let class = if self.bool()? { Some(self.token()?) } else { None };
+                    catches.push((name, class, self.statements()?));
+                }
+                let finally = if self.bool()? { Some(self.statements()?) } else { None };
+                Statement::Try(keyword, body, catches, finally)
+            }
+            14 => Statement::Const(self.token()?, self.annotation()?, self.expression()?),
+            15 => Statement::Destructure(self.pattern()?, self.expression()?),
+            16 => Statement::Break(self.token()?, self.optional_token()?),
+            tag => return Err(ChunkError::InvalidTag(tag, "statement").into()),
+        };
+        Ok(stmt)
+    }
+
+    fn statements(&mut self) -> Result<Vec<Statement>> {
+        let count = self.u32()?;
+        let mut stmts = Vec::new();
+        for _ in 0..count {
+            stmts.push(self.statement()?);
+        }
+        Ok(stmts)
+    }
+
+    fn function(&mut self) -> Result<FunctionDeclaration> {
+        let name = self.token()?;
+        let is_async = self.bool()?;
+        let count = self.u32()?;
+        let mut params = Vec::new();
+        for _ in 0..count {
+            params.push(Parameter { name: self.token()?, annotation: self.annotation()? });
+        }
+        let return_type = self.annotation()?;
+        let count = self.u32()?;
+        let mut body = Vec::new();
+        for _ in 0..count {
+            body.push(self.statement()?);
+        }
+        Ok(FunctionDeclaration { name, is_async, params, return_type, body })
+    }
+
+    fn annotation(&mut self) -> Result<Option<TypeAnnotation>> {
+        Ok(if self.bool()? { Some(TypeAnnotation::new(self.token()?)) } else { None })
+    }
+
+    fn pattern(&mut self) -> Result<Pattern> {
+        let open = self.token()?;
+        let count = self.u32()?;
+        let mut names = Vec::new();
+        for _ in 0..count {
+            names.push(self.token()?);
+        }
+        Ok(match open.token_type() {
+            TokenType::LeftBracket => Pattern::List(open, names),
+            _ => Pattern::Map(open, names),
+        })
+    }
+
+    fn expression(&mut self) -> Result<Expression> {
+        self.nested(|decoder| {
+            // The parser builds chains in a loop, so they are read back in one as well:
+            // the tags of the chain come first, the innermost operand follows them.
+            let mut chain = Vec::new();
+            let mut tag = decoder.u8()?;
+            while CHAINED.contains(&tag) {
+                chain.push(tag);
+                tag = decoder.u8()?;
+            }
+            let mut expr = decoder.operand(tag)?;
+            for tag in chain.into_iter().rev() {
+                expr = decoder.chained(tag, expr)?;
+            }
+            Ok(expr)
+        })
+    }
+
+    /// Reads the fields after the first one of a [CHAINED] expression
+    fn chained(&mut self, tag: u8, first: Expression) -> Result<Expression> {
+        let first = Box::new(first);
+        let expr = match tag {
+            1 => Expression::Binary(first, self.token()?, Box::new(self.expression()?)),
+            3 => Expression::Logical(first, self.token()?, Box::new(self.expression()?)),
+            7 => Expression::Get(first, self.token()?),
+            8 => {
+                let paren = self.token()?;
+                let count = self.u32()?;
+                let mut args = Vec::new();
+                for _ in 0..count {
+                    args.push(self.expression()?);
+                }
+                Expression::Call(first, paren, args)
+            }
+            13 => Expression::Index(first, self.token()?, Box::new(self.expression()?)),
+            16 => {
+                let bracket = self.token()?;
+                let start = if self.bool()? { Some(Box::new(self.expression()?)) } else { None };
+                let end = if self.bool()? { Some(Box::new(self.expression()?)) } else { None };
+                Expression::Slice(first, bracket, start, end)
+            }
+            18 => Expression::OptionalGet(first, self.token()?),
+            tag => return Err(ChunkError::InvalidTag(tag, "expression").into()),
+        };
+        Ok(expr)
+    }
+
+    /// Reads an expression that isn't [CHAINED], after its tag
+    fn operand(&mut self, tag: u8) -> Result<Expression> {
+        let expr = match tag {
+            0 => Expression::Assign(self.token()?, Box::new(self.expression()?)),
+            2 => Expression::Grouping(Box::new(self.expression()?)),
+            4 => Expression::Unary(self.token()?, Box::new(self.expression()?)),
+            5 => Expression::Literal(self.value()?),
+            6 => Expression::Variable(self.token()?),
+            9 => Expression::Set(Box::new(self.expression()?), self.token()?, Box::new(self.expression()?)),
+            10 => Expression::This(self.token()?),
+            11 => Expression::Super(self.token()?, self.token()?),
+            12 => {
+                let bracket = self.token()?;
+                let count = self.u32()?;
+                let mut items = Vec::new();
+                for _ in 0..count {
+                    items.push(self.expression()?);
+                }
+                Expression::List(bracket, items)
+            }
+            14 => Expression::IndexSet(
+                Box::new(self.expression()?),
+                self.token()?,
+                Box::new(self.expression()?),
+                Box::new(self.expression()?),
+            ),
+            15 => {
+                let brace = self.token()?;
+                let count = self.u32()?;
+                let mut entries = Vec::new();
+                for _ in 0..count {
+                    entries.push((self.expression()?, self.expression()?));
+                }
+                Expression::Map(brace, entries)
+            }
+            17 => Expression::Lambda(SharedRef::new(self.function()?)),
+            19 => Expression::Destructure(self.pattern()?, Box::new(self.expression()?)),
+            20 => {
+                let (keyword, cond, then) = (self.token()?, self.expression()?, self.expression()?);
+                let els = if self.bool()? { Some(Box::new(self.expression()?)) } else { None };
+                Expression::If(keyword, Box::new(cond), Box::new(then), els)
+            }
+            21 => {
+                let (brace, stmts) = (self.token()?, self.statements()?);
+                let value = if self.bool()? { Some(Box::new(self.expression()?)) } else { None };
+                Expression::Block(brace, stmts, value)
+            }
+            tag => return Err(ChunkError::InvalidTag(tag, "expression").into()),
+        };
+        Ok(expr)
+    }
+
+    /// Runs `decode` one nesting level deeper, or fails if that is
+    /// deeper than the parser would have allowed
+    fn nested<T>(&mut self, decode: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_DEPTH {
+            return Err(ChunkError::TooDeeplyNested(MAX_DEPTH).into());
+        }
+        self.depth += 1;
+        let result = decode(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn optional_token(&mut self) -> Result<Option<Token>> {
+        Ok(if self.bool()? { Some(self.token()?) } else { None })
+    }
+
+    fn token(&mut self) -> Result<Token> {
+        let tag = self.u8()?;
+        let token_type = TOKEN_TYPES
+            .get(tag as usize)
+            .cloned()
+            .ok_or(ChunkError::InvalidTag(tag, "token type"))?;
+        let lexeme = self.string()?;
+        let literal = if self.bool()? { Some(self.value()?) } else { None };
+        let line = self.u32()?;
+        Ok(Token::new(token_type, lexeme, literal, line))
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        let value = match self.u8()? {
+            0 => Value::String(self.string()?),
+            1 => {
+                let text = self.string()?;
+                Value::Number(text.parse().map_err(|_| ValueError::InvalidText(text))?)
+            }
+            2 => Value::Bool(self.bool()?),
+            3 => Value::Nil,
+            4 => {
+                let text = self.string()?;
+                Value::Int(text.parse().map_err(|_| ValueError::InvalidText(text))?)
+            }
+            tag => return Err(ChunkError::InvalidTag(tag, "value").into()),
+        };
+        Ok(value)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or(ChunkError::Truncated)?;
+        self.pos += len;
+        Ok(String::from_utf8(bytes.to_vec()).map_err(|_| ChunkError::InvalidString)?)
+    }
+
+    fn bool(&mut self) -> Result<bool> {
+        Ok(self.u8()? != 0)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take::<2>()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take::<4>()?))
+    }
+
+    /// Reads the next `N` bytes, failing if the chunk ends early
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let bytes: [u8; N] = self
+            .bytes
+            .get(self.pos..self.pos + N)
+            .and_then(|slice| slice.try_into().ok())
+            .ok_or(ChunkError::Truncated)?;
+        self.pos += N;
+        Ok(bytes)
+    }
+}
+
+// ---------- Tests for the loxc module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::CollectingReporter;
+    use crate::errors::LoxError;
+    use crate::{parser, scanner};
+
+    fn parse(source: &str) -> Vec<Statement> {
+        let mut reporter = CollectingReporter::default();
+        let tokens = scanner::scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+        parser::parse(tokens, &mut reporter).expect("Parsing failed!")
+    }
+
+    #[test]
+    fn round_trip() {
+        let statements = parse(
+            "var a: Number = 1.5; { print a + \"x\"; } if (!a) a = nil; else while (a < 2) a = -a;
+             fun f(x: Number): Number { return x; } class P { m(v) { v.x = f(1); return; } }
+             async fun g() { return await f(2); }",
+        );
+        let loaded = load(&compile(&statements)).expect("Loading failed!");
+        let original: Vec<String> = statements.iter().map(|s| s.to_string()).collect();
+        let loaded: Vec<String> = loaded.iter().map(|s| s.to_string()).collect();
+        assert_eq!(original, loaded);
+    }
+
+    #[test]
+    fn rejects_corrupted_payload() {
+        let mut chunk = compile(&parse("print 1;"));
+        let last = chunk.len() - 1;
+        chunk[last] ^= 0xff;
+        assert!(load(&chunk).is_err());
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        // `print nil;` ends with the literal, two million groupings go in front of it.
+        // Like in the parser tests, unoptimized builds need a bigger stack than test threads have.
+        let print = compile(&parse("print nil;"));
+        let (prefix, literal) = print[HEADER_LEN..].split_at(print.len() - HEADER_LEN - 2);
+        let payload = [prefix, &[2; 2_000_000], literal].concat();
+        let load_deep = move || matches!(load(&chunk(payload)), Err(LoxError::Chunk(ChunkError::TooDeeplyNested(_))));
+        let thread = std::thread::Builder::new().stack_size(8 << 20).spawn(load_deep).expect("Spawning failed!");
+        assert!(thread.join().expect("Loading deeply nested input panicked"));
+    }
+
+    #[test]
+    fn long_chains_are_not_nesting() {
+        let source = format!("print {}1;\nprint a{}.c;", "1 + ".repeat(1000), ".b(1)[0]".repeat(200));
+        let statements = parse(&source);
+        let loaded = load(&compile(&statements)).expect("Loading failed!");
+        assert_eq!(statements[1].to_string(), loaded[1].to_string());
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut chunk = compile(&parse("print 1;"));
+        chunk[4] = chunk[4].wrapping_add(1);
+        assert!(load(&chunk).is_err());
+    }
+}
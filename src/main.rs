@@ -1,86 +1,221 @@
 // External dependencies
 use anyhow::Result;
-use std::io::Write;
+use std::process::ExitCode;
+use std::time::Instant;
+use tracing::{debug, debug_span, trace, Level};
 
 // Internal dependencies
-use crate::errors::*;
+use jlox::errors::LoxError;
+use jlox::obj::statement::Statement;
+use jlox::interpreter::{Capabilities, Interpreter, InterpreterBuilder};
+use jlox::shared::Shared;
+use jlox::{analyzer, loxc, parser, scanner, typechecker};
+use crate::cli::Command;
+use crate::commands::trace::TraceRecorder;
+use crate::reporter::StderrReporter;
 
 // Modules
-mod obj {
-    pub mod environment;
-    pub mod expression;
-    pub mod statement;
-    pub mod token_type;
-    pub mod token;
-    pub mod value;
+mod cli;
+mod repl;
+mod reporter;
+mod style;
+mod commands {
+    pub mod bench;
+    pub mod common;
+    pub mod compile;
+    pub mod diff;
+    pub mod doc;
+    pub mod explain;
+    pub mod install;
+    #[cfg(feature = "kernel")]
+    pub mod kernel;
+    pub mod fmt;
+    pub mod minify;
+    pub mod replay;
+    pub mod template;
+    pub mod test;
+    pub mod trace;
+    pub mod typecheck;
 }
-mod errors;
-mod interpreter;
-mod parser;
-mod scanner;
 
 /// Takes in command line arguments and decides whether to run
-/// jlox on a source file or to open the prompt mode. If nothing
-/// matches, it will return an Error with the desired message.
-/// Also, if the code execution fails, an Error is returned.
-fn main() -> Result<()> {
+/// jlox on a source file or to open the prompt mode. If the arguments
+/// are invalid, it will return an Error with the desired message.
+/// Also, if the code execution fails, an Error is returned. A script
+/// that calls `exit(n)` ends the process with exit code `n`.
+fn main() -> Result<ExitCode> {
     // Retreive command line arguments
     let argv: Vec<String> = std::env::args().collect();
-    let argc: usize = argv.len();
-
-    // Check argument vector length to either run a script
-    // from a source file or run the prompt mode of jlox
-    match argc {
-        2 => {
-            let file_path = argv
-                .get(1)
-                .ok_or(ArgumentError::ArgAccessError)?
-                .to_string();
-            run_file(file_path)
+    let options = cli::parse_args(&argv)?;
+
+    let color = style::use_color(options.color);
+    init_logging(options.log_level, color);
+    style::init(color);
+    reporter::init(options.max_errors, options.deny_warnings);
+
+    // Either run a script from a source file, run the prompt mode of jlox
+    // or run one of the tooling commands
+    let result = match options.command {
+        Command::RunFile { script, record } => run_file(script, record, interpreter_builder(options.typed, options.prelude, options.capabilities).build()),
+        Command::Prompt => run_prompt(interpreter_builder(options.typed, options.prelude, options.capabilities).redefine_classes(true).build()),
+        Command::Test { dir, bless } => commands::test::run(&dir, bless),
+        Command::Bench(bench) => commands::bench::run(bench),
+        Command::Diff { old, new } => commands::diff::run(&old, &new),
+        Command::Minify { script, rename_locals } => commands::minify::run(&script, rename_locals),
+        Command::Compile { script, output } => commands::compile::run(&script, output),
+        Command::Explain(code) => commands::explain::run(&code),
+        Command::Replay(trace) => commands::replay::run(&trace),
+        Command::Fmt { script, write, settings } => commands::fmt::run(&script, write, &settings),
+        Command::Typecheck(script) => commands::typecheck::run(&script),
+        Command::Doc { dir, html, output } => commands::doc::run(&dir, html, output),
+        Command::Install(dir) => commands::install::run(&dir),
+        Command::Template(template) => {
+            commands::template::run(&template, interpreter_builder(options.typed, options.prelude, options.capabilities).build())
         }
-        1 => run_prompt(),
-        _ => Err(ArgumentError::InvalidArgs.into()),
+        #[cfg(feature = "kernel")]
+        Command::Kernel(connection_file) if connection_file == "install" => commands::kernel::install(),
+        #[cfg(feature = "kernel")]
+        Command::Kernel(connection_file) => commands::kernel::run(
+            &connection_file,
+            Interpreter::builder()
+                .capabilities(options.capabilities)
+                .typed(options.typed)
+                .prelude(options.prelude)
+                .redefine_classes(true),
+        ),
+    };
+
+    match result {
+        Ok(()) => Ok(ExitCode::SUCCESS),
+        Err(error) => match exit_code(&error) {
+            Some(code) => Ok(ExitCode::from(code)),
+            None => Err(error),
+        },
     }
 }
 
-/// Takes in a file path as a `String`, loads the file content
-/// into memory as another `String` and runs the source code
-/// by calling [run]
-fn run_file(file_path: String) -> Result<()> {
-    let source = std::fs::read_to_string(file_path)?;
-    run(source) // Return the Result of the run function
+/// The exit code of a script that ended through `exit()`
+fn exit_code(error: &anyhow::Error) -> Option<u8> {
+    error.downcast_ref::<LoxError>().and_then(LoxError::exit_code)
 }
 
-/// Runs the prompt mode of jlox. It takes in user input from the
-/// cli and runs the given source code by calling [run]
-fn run_prompt() -> Result<()> {
-    loop {
-        print!("> ");
-        std::io::stdout().flush()?; // Print '> ' to the cli
+/// Installs the global tracing subscriber that writes log messages
+/// up to the given level to stderr, colored if `color` is set.
+fn init_logging(level: Level, color: bool) {
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .with_ansi(color)
+        .with_target(false)
+        .without_time()
+        .init();
+}
 
-        let mut line = String::new();
-        std::io::stdin().read_line(&mut line)?; // Read cli input into a String
+/// Takes in a file path as a `String`, loads the file content
+/// into memory as another `String` and runs the source code
+/// by calling [run]. With `record`, a trace of the execution is
+/// written to that path, also when the script fails.
+fn run_file(file_path: String, record: Option<String>, mut interpreter: Interpreter) -> Result<()> {
+    let bytes = std::fs::read(file_path)?;
 
-        if line.trim().is_empty() {
-            break Ok(()); // If no input was given, the prompt mode is exited with an Ok
-        }
+    let events = Shared::new(Vec::new());
+    if record.is_some() {
+        interpreter.add_hooks(Box::new(TraceRecorder(events.clone())));
+    }
+
+    // Compiled chunks already contain the syntax tree, so the front end is skipped
+    let result = if loxc::is_chunk(&bytes) {
+        let statements = {
+            let _span = debug_span!("load").entered();
+            let start = Instant::now();
+            let statements = loxc::load(&bytes)?;
+            debug!(statements = statements.len(), elapsed = ?start.elapsed(), "finished");
+            statements
+        };
+        execute(&mut interpreter, statements)
+    } else {
+        let source = String::from_utf8(bytes)?;
+        run(&mut interpreter, source)
+    };
 
-        run(line)?; // Run the source code given by the cli
+    if let Some(path) = record {
+        commands::trace::save(&path, &events.borrow())?;
     }
+    result
+}
+
+/// Runs the prompt mode of jlox in a single interpreter session
+fn run_prompt(interpreter: Interpreter) -> Result<()> {
+    repl::Repl::new(interpreter).run()
+}
+
+/// Configures the interpreter jlox runs scripts and prompt sessions with.
+/// Built with the `jit` feature, it compiles hot functions.
+fn interpreter_builder(typed: bool, prelude: bool, capabilities: Capabilities) -> InterpreterBuilder {
+    let builder = Interpreter::builder().capabilities(capabilities).typed(typed).prelude(prelude);
+    #[cfg(feature = "jit")]
+    let builder = builder.jit_threshold(jlox::interpreter::JIT_THRESHOLD);
+    builder
 }
 
 /// Takes in Lox source code as a `String` and starts the running
-/// process on it.
-fn run(source: String) -> Result<()> {
+/// process on it. Every phase is wrapped in its own span and logs
+/// how long it took on the debug level. Runtime errors get the
+/// offending source line attached.
+fn run(interpreter: &mut Interpreter, source: String) -> Result<()> {
+    let statements = parse(source.clone(), interpreter.config().typed)?;
+    execute(interpreter, statements).map_err(|error| reporter::with_excerpt(error, interpreter.error_span(), &source))
+}
 
-    let tokens = scanner::scan_tokens(source)?; // Convert source code into tokens (scanning)
-    let statements = parser::parse(tokens)?;    // Convert tokens into syntax tree (parsing)
+/// Scans and parses Lox source code into a syntax tree, type checking it
+/// if `typed` is set. The phases share one reporter, so `--max-errors`
+/// limits their errors together.
+fn parse(source: String, typed: bool) -> Result<Vec<Statement>> {
+    let mut reporter = StderrReporter::new();
+    let statements = parse_with(source, &mut reporter).and_then(|statements| {
+        if typed {
+            let _span = debug_span!("typecheck").entered();
+            typechecker::check(&statements, &mut reporter)?;
+        }
+        Ok(statements)
+    });
+    let denied = reporter.finish();
+    let statements = statements?; // Errors of the phases take precedence
+    denied?;
+    Ok(statements)
+}
+
+/// [parse] with the given reporter
+fn parse_with(source: String, reporter: &mut StderrReporter) -> Result<Vec<Statement>> {
+    let tokens = {
+        let _span = debug_span!("scan").entered();
+        let start = Instant::now();
+        let tokens = scanner::scan_tokens(source, reporter)?; // Convert source code into tokens (scanning)
+        debug!(tokens = tokens.len(), elapsed = ?start.elapsed(), "finished");
+        tokens
+    };
 
-    // for stmt in &statements {
-    //     println!("{}", stmt);
-    // }
+    let statements = {
+        let _span = debug_span!("parse").entered();
+        let start = Instant::now();
+        let statements = parser::parse(tokens, reporter)?; // Convert tokens into syntax tree (parsing)
+        analyzer::analyze(&statements, reporter); // Warn about code that cannot behave as written
+        debug!(statements = statements.len(), elapsed = ?start.elapsed(), "finished");
+        for stmt in &statements {
+            trace!("{}", stmt);
+        }
+        statements
+    };
+
+    Ok(statements)
+}
 
-    interpreter::interpret(statements)?;        // Interpret the syntax tree (execution)
+/// Interprets an already parsed syntax tree
+fn execute(interpreter: &mut Interpreter, statements: Vec<Statement>) -> Result<()> {
+    let _span = debug_span!("execute").entered();
+    let start = Instant::now();
+    interpreter.interpret(statements)?; // Interpret the syntax tree (execution)
+    debug!(elapsed = ?start.elapsed(), "finished");
 
     Ok(())
 }
@@ -0,0 +1,26 @@
+// External dependencies
+use std::fmt::Display;
+
+// Internal dependencies
+use super::token::Token;
+
+/// A type written after a colon in the source, like the `Number` in
+/// `var x: Number = 3;`. Annotations are optional and the interpreter
+/// ignores them, they are there for tooling like a type checker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeAnnotation {
+    /// Name of the type, as written
+    pub name: Token,
+}
+
+impl TypeAnnotation {
+    pub fn new(name: Token) -> Self {
+        Self { name }
+    }
+}
+
+impl Display for TypeAnnotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name.lexeme())
+    }
+}
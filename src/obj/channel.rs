@@ -0,0 +1,99 @@
+// External dependencies
+use std::collections::VecDeque;
+use std::fmt::{Debug, Display};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+// Internal dependencies
+use crate::errors::Result;
+use super::detached::Detached;
+use super::value::Value;
+
+/// Source of the channel ids, which are unique within the process
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Queue of values that tasks use to talk to each other, created by the
+/// `channel` native. Copies of the handle refer to the same queue, so one
+/// side can `send` while the other side `receive`s. Values are copied on
+/// the way in (see [Detached]), the two sides never share anything mutable.
+#[derive(Clone)]
+pub struct Channel {
+    id: u64,
+    queue: Arc<(Mutex<VecDeque<Detached>>, Condvar)>,
+}
+
+impl Channel {
+    pub fn new() -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            queue: Arc::new((Mutex::new(VecDeque::new()), Condvar::new())),
+        }
+    }
+
+    /// Appends a copy of the value and wakes up a waiting receiver
+    pub fn send(&self, value: &Value) {
+        let (queue, ready) = &*self.queue;
+        queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push_back(Detached::new(value));
+        ready.notify_one();
+    }
+
+    /// Takes the oldest value, waiting at most `timeout` for one to arrive.
+    /// Fails if a function in the value can't be decoded.
+    pub fn receive_timeout(&self, timeout: Duration) -> Result<Option<Value>> {
+        let (queue, ready) = &*self.queue;
+        let queue = queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (mut queue, _) = ready
+            .wait_timeout_while(queue, timeout, |queue| queue.is_empty())
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        queue.pop_front().map(Detached::attach).transpose()
+    }
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Channels are identified by their id, copies of a handle are equal
+impl PartialEq for Channel {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Debug for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Channel({})", self.id)
+    }
+}
+
+impl Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<channel {}>", self.id)
+    }
+}
+
+// ---------- Tests for the channel module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_cross_threads_in_order() {
+        let channel = Channel::new();
+        let sender = channel.clone();
+        let producer = std::thread::spawn(move || {
+            for n in 0..3 {
+                sender.send(&Value::String(n.to_string()));
+            }
+        });
+
+        let received: Vec<Value> = (0..3).filter_map(|_| channel.receive_timeout(Duration::from_secs(5)).unwrap()).collect();
+        producer.join().expect("The producer panicked");
+        assert_eq!(received, ["0", "1", "2"].map(|s| Value::String(s.to_string())));
+        assert_eq!(channel.receive_timeout(Duration::ZERO).unwrap(), None);
+    }
+}
@@ -0,0 +1,118 @@
+// External dependencies
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+
+// Internal dependencies
+use crate::shared::Shared;
+use super::function::Function;
+use super::value::Value;
+
+/// A class declared in Lox, the value a `class` declaration binds.
+/// Calling it creates an [Instance]. The method table is shared by every
+/// copy of the class and its instances, so a class that gets declared
+/// again (like in the REPL) updates the instances that already exist.
+#[derive(Clone)]
+pub struct Class {
+    pub name: String,
+    pub superclass: Option<Box<Class>>,
+    pub methods: Shared<HashMap<String, Function>>,
+    /// Methods that run instead of storing a field when the property is assigned
+    pub setters: Shared<HashMap<String, Function>>,
+}
+
+impl Class {
+    pub fn new(name: String, superclass: Option<Class>, methods: HashMap<String, Function>, setters: HashMap<String, Function>) -> Self {
+        Self { name, superclass: superclass.map(Box::new), methods: Shared::new(methods), setters: Shared::new(setters) }
+    }
+
+    /// The method with the given name, looked up in the class and then
+    /// in its superclasses
+    pub fn find_method(&self, name: &str) -> Option<Function> {
+        let method = self.methods.borrow().get(name).cloned();
+        method.or_else(|| self.superclass.as_ref().and_then(|superclass| superclass.find_method(name)))
+    }
+
+    /// Whether the class is `other` or inherits from it
+    pub fn is_subclass_of(&self, other: &Class) -> bool {
+        self == other || self.superclass.as_ref().is_some_and(|superclass| superclass.is_subclass_of(other))
+    }
+
+    /// The setter of a property, looked up like methods
+    pub fn find_setter(&self, name: &str) -> Option<Function> {
+        let setter = self.setters.borrow().get(name).cloned();
+        setter.or_else(|| self.superclass.as_ref().and_then(|superclass| superclass.find_setter(name)))
+    }
+}
+
+// Classes are identified by their method table, copies of a class are equal
+impl PartialEq for Class {
+    fn eq(&self, other: &Self) -> bool {
+        self.methods.address() == other.methods.address()
+    }
+}
+
+impl Debug for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Class({})", self.name)
+    }
+}
+
+impl Display for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<class {}>", self.name)
+    }
+}
+
+/// An object created by calling a [Class]. Fields are created by assigning
+/// to them. Copies of an instance refer to the same fields.
+#[derive(Clone)]
+pub struct Instance {
+    pub class: Class,
+    pub fields: Shared<HashMap<String, Value>>,
+    /// Set by the `freeze` native, shared by the copies like the fields
+    frozen: Shared<bool>,
+}
+
+impl Instance {
+    pub fn new(class: Class) -> Self {
+        Self { class, fields: Shared::new(HashMap::new()), frozen: Shared::new(false) }
+    }
+
+    /// Forbids assigning the fields from now on
+    pub fn freeze(&self) {
+        *self.frozen.borrow_mut() = true;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        *self.frozen.borrow()
+    }
+
+    /// The value of a field, `None` if it was never set
+    pub fn field(&self, name: &str) -> Option<Value> {
+        self.fields.borrow().get(name).cloned()
+    }
+
+    /// Sets a field and returns the value it had before
+    pub fn set_field(&self, name: String, value: Value) -> Option<Value> {
+        self.fields.borrow_mut().insert(name, value)
+    }
+}
+
+// Instances are identified by their fields, copies of an instance are equal
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        self.fields.address() == other.fields.address()
+    }
+}
+
+impl Debug for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Instance({})", self.class.name)
+    }
+}
+
+impl Display for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{} instance>", self.class.name)
+    }
+}
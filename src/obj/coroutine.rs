@@ -0,0 +1,319 @@
+// External dependencies
+use std::fmt::{Debug, Display};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+// Internal dependencies
+use crate::errors::{Result, RuntimeError};
+use crate::shared::SharedRef;
+use super::value::Value;
+
+/// Source of the coroutine ids, which are unique within the process
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// How many coroutine threads can exist at the same time in the process.
+/// Every thread costs the system a stack and a process slot, so starting
+/// more fails with an error instead of running the system out of threads.
+pub const MAX_LIVE: usize = 10_000;
+
+/// Coroutine threads that have been started and haven't ended yet
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// Where a coroutine is in its life, see [Coroutine::status]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    /// Waiting to be resumed, before its function started or in a `yield`
+    Suspended,
+    /// Resumed and not back yet
+    Running,
+    /// Its function returned or failed, it can't be resumed anymore
+    Dead,
+}
+
+impl Status {
+    /// The name the `status` native returns
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Suspended => "suspended",
+            Status::Running => "running",
+            Status::Dead => "dead",
+        }
+    }
+}
+
+/// Why a coroutine handed control back to `resume`
+enum Suspension {
+    Yielded(Value),
+    Returned(Result<Value>),
+}
+
+/// Something on its way between the thread that resumes a coroutine and
+/// the coroutine's own thread
+struct HandOff<T>(T);
+
+impl<T> HandOff<T> {
+    // Taking the whole hand-off, a closure that only used the field would move the field alone
+    fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+// SAFETY: Values share their state with the script, without the `sync`
+// feature through `Rc` and `RefCell`, which can't cross threads on their
+// own. A hand-off carries the body of a coroutine to its thread, and the
+// values passed by `resume` and `yield` back and forth. This is sound
+// because the two threads never run at the same time:
+//
+// - The coroutine's thread blocks in `recv` until the first `resume`, and
+//   after that in [Yielder::suspend] until the next one. The resuming
+//   thread blocks in [Coroutine::resume] until the coroutine yielded or
+//   returned. So exactly one of them runs, and only that one touches
+//   reference counts and borrow flags.
+// - The channels order the memory: everything one side did before sending
+//   happens before what the other side does after receiving.
+// - Dropping the last handle of a coroutine closes its channel and joins
+//   the thread, so the thread drops the values it still holds before the
+//   resuming thread goes on.
+// - Without `sync`, a [Coroutine] is not `Send` (see its `script` field),
+//   so only the script's own threads take turns like this, never a third
+//   thread that holds on to the same values.
+// - If the thread can't be started, the body comes back to the thread that
+//   tried to start it and gets dropped there.
+unsafe impl<T> Send for HandOff<T> {}
+
+/// A function that runs bit by bit, created by the `coroutine` native.
+/// `resume` runs it until it calls `yield`, the next `resume` continues
+/// right after that. It runs on its own thread and in its own interpreter,
+/// but with the same variables, lists and instances as the script, and
+/// only while the script waits for it in `resume`. So the two take turns
+/// instead of running at the same time. Copies of the handle refer to the
+/// same coroutine.
+#[derive(Clone)]
+pub struct Coroutine {
+    id: u64,
+    status: Arc<Mutex<Status>>,
+    thread: Arc<Mutex<Thread>>,
+    /// What the coroutine printed since it was last resumed
+    printed: Arc<Mutex<Vec<u8>>>,
+    /// Keeps the handle on the threads of its script, see [HandOff]
+    script: PhantomData<SharedRef<()>>,
+}
+
+/// The resuming end of a coroutine's thread
+struct Thread {
+    /// Sends the resumed values, `None` once the coroutine is being dropped
+    resume: Option<Sender<HandOff<Value>>>,
+    /// Receives what the coroutine hands back
+    suspended: Receiver<HandOff<Suspension>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// One coroutine thread in [LIVE], from its start until it is dropped
+struct Alive(usize);
+
+impl Alive {
+    /// Adds a thread, the number includes it
+    fn count() -> Self {
+        Alive(LIVE.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+}
+
+impl Drop for Alive {
+    fn drop(&mut self) {
+        LIVE.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The coroutine's end of the channel, `yield` suspends it through this
+pub struct Yielder {
+    resumed: Receiver<HandOff<Value>>,
+    suspended: Sender<HandOff<Suspension>>,
+}
+
+impl Coroutine {
+    /// Starts `body` on a new thread, where it waits for the first resume.
+    /// It gets the first resumed value, the [Yielder] to suspend itself
+    /// with and the buffer to print into. Fails with the reason if there
+    /// are [MAX_LIVE] coroutines already or the system has no thread left.
+    pub fn start(
+        body: impl FnOnce(Value, Yielder, Arc<Mutex<Vec<u8>>>) -> Result<Value> + 'static,
+    ) -> std::result::Result<Self, String> {
+        Self::start_within(MAX_LIVE, body)
+    }
+
+    /// [Coroutine::start] with at most `limit` coroutine threads alive
+    fn start_within(
+        limit: usize,
+        body: impl FnOnce(Value, Yielder, Arc<Mutex<Vec<u8>>>) -> Result<Value> + 'static,
+    ) -> std::result::Result<Self, String> {
+        let alive = Alive::count();
+        if alive.0 > limit {
+            return Err(format!("{} coroutines and async calls are alive already", limit));
+        }
+        let (resume, resumed) = mpsc::channel();
+        let (suspend, suspended) = mpsc::channel();
+        let printed = Arc::new(Mutex::new(Vec::new()));
+        let output = printed.clone();
+        let body = HandOff(body);
+        let spawned = std::thread::Builder::new().name("jlox-coroutine".to_string()).spawn(move || {
+            let _alive = alive; // Counts until the thread ends, also if it panics
+            let body = body.into_inner();
+            // Dropping every handle before the first resume ends the thread here
+            if let Ok(first) = resumed.recv() {
+                let yielder = Yielder { resumed, suspended: suspend.clone() };
+                let result = body(first.into_inner(), yielder, output);
+                let _ = suspend.send(HandOff(Suspension::Returned(result))); // Nobody might be waiting anymore
+            }
+        });
+        let handle = spawned.map_err(|e| format!("the system cannot start another thread ({})", e))?;
+
+        Ok(Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            status: Arc::new(Mutex::new(Status::Suspended)),
+            thread: Arc::new(Mutex::new(Thread { resume: Some(resume), suspended, handle: Some(handle) })),
+            printed,
+            script: PhantomData,
+        })
+    }
+
+    pub fn status(&self) -> Status {
+        *self.status.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Hands the value to the coroutine and waits until it yields or
+    /// returns, then returns that value. `None` if the coroutine isn't
+    /// suspended. An error of the coroutine's function is passed on.
+    pub fn resume(&self, value: Value) -> Result<Option<Value>> {
+        {
+            let mut status = self.status.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if *status != Status::Suspended {
+                return Ok(None);
+            }
+            *status = Status::Running;
+        }
+
+        let thread = self.thread.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(resume) = &thread.resume {
+            let _ = resume.send(HandOff(value)); // A suspended coroutine always waits for it
+        }
+        // No timeout, the coroutine checks for cancellation and the time limit itself
+        let suspension = match thread.suspended.recv() {
+            Ok(suspension) => suspension.into_inner(),
+            // The thread only ends without a word if it panicked
+            Err(_) => Suspension::Returned(Err(RuntimeError::Unknown.into())),
+        };
+
+        let (status, result) = match suspension {
+            Suspension::Yielded(value) => (Status::Suspended, Ok(value)),
+            Suspension::Returned(result) => (Status::Dead, result),
+        };
+        *self.status.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = status;
+        result.map(Some)
+    }
+
+    /// Takes what the coroutine printed since this was last called
+    pub fn take_printed(&self) -> Vec<u8> {
+        std::mem::take(&mut self.printed.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+impl Drop for Thread {
+    /// Ends the thread of a coroutine that nothing can resume anymore, and
+    /// waits for it to let go of the values it holds
+    fn drop(&mut self) {
+        self.resume = None;
+        if let Some(handle) = self.handle.take() {
+            if handle.thread().id() != std::thread::current().id() {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+impl Yielder {
+    /// Hands the value to the waiting `resume` and waits to be resumed,
+    /// then returns the resumed value. Once every handle of the coroutine
+    /// is gone nothing can resume it anymore, then this fails with
+    /// [RuntimeError::Cancelled] to end its function.
+    pub fn suspend(&self, value: Value) -> Result<Value> {
+        let _ = self.suspended.send(HandOff(Suspension::Yielded(value)));
+        match self.resumed.recv() {
+            Ok(value) => Ok(value.into_inner()),
+            Err(_) => Err(RuntimeError::Cancelled.into()),
+        }
+    }
+}
+
+// Coroutines are identified by their id, copies of a handle are equal
+impl PartialEq for Coroutine {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Debug for Coroutine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Coroutine({})", self.id)
+    }
+}
+
+impl Display for Coroutine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<coroutine {}>", self.id)
+    }
+}
+
+// ---------- Tests for the coroutine module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::list::List;
+
+    #[test]
+    fn resume_and_yield_take_turns() {
+        let coroutine = Coroutine::start(|first, yielder, _| {
+            let mut total = match first {
+                Value::Int(n) => n,
+                _ => 0,
+            };
+            while let Value::Int(n) = yielder.suspend(Value::Int(total))? {
+                total += n;
+            }
+            Ok(Value::String("done".to_string()))
+        })
+        .unwrap();
+        let resume = |value| coroutine.resume(value).unwrap();
+
+        assert_eq!(resume(Value::Int(1)), Some(Value::Int(1)));
+        assert_eq!(resume(Value::Int(2)), Some(Value::Int(3)));
+        assert_eq!(coroutine.status(), Status::Suspended);
+        assert_eq!(resume(Value::Nil), Some(Value::String("done".to_string())));
+        assert_eq!(coroutine.status(), Status::Dead);
+        assert_eq!(resume(Value::Nil), None);
+    }
+
+    #[test]
+    fn dropping_a_suspended_coroutine_ends_it() {
+        let list = List::new(Vec::new());
+        let shared = list.clone();
+        let coroutine = Coroutine::start(move |_, yielder, _| {
+            shared.items.borrow_mut().push(Value::Int(1));
+            yielder.suspend(Value::Nil)?;
+            unreachable!("nothing resumes the coroutine a second time");
+        })
+        .unwrap();
+        assert_eq!(coroutine.resume(Value::Nil).unwrap(), Some(Value::Nil));
+        drop(coroutine);
+        assert_eq!(list.len(), 1); // The coroutine changed the list itself, not a copy
+    }
+
+    #[test]
+    fn starting_beyond_the_limit_fails() {
+        let error = Coroutine::start_within(0, |_, _, _| Ok(Value::Nil)).unwrap_err();
+        assert_eq!(error, "0 coroutines and async calls are alive already");
+    }
+}
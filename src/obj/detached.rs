@@ -0,0 +1,386 @@
+// External dependencies
+use std::collections::HashMap;
+
+// Internal dependencies
+use crate::errors::Result;
+use crate::loxc;
+use crate::shared::{Shared, SharedRef};
+use super::channel::Channel;
+use super::class::{Class, Instance};
+use super::environment::Environment;
+use super::function::{Function, FunctionDeclaration};
+use super::list::List;
+use super::map::{Key, Map};
+use super::native::NativeFunction;
+use super::number::Number;
+use super::task::Task;
+use super::value::Value;
+
+/// A copy of a value that is detached from the interpreter it came from,
+/// so it can be moved to another thread. Functions take a copy of the
+/// variables they captured along, environments that are shared between
+/// several functions stay shared in the copy, just like classes,
+/// instances, lists and maps that are reached more than once. Used wherever values cross
+/// threads: by channels and by tasks for their function and result.
+/// Coroutines and futures can't come along, they become `nil`.
+pub struct Detached {
+    value: Packed,
+    environments: Vec<PackedEnvironment>,
+    classes: Vec<PackedClass>,
+    instances: Vec<PackedInstance>,
+    lists: Vec<Vec<Packed>>,
+    maps: Vec<Vec<(Key, Packed)>>,
+    /// Function declarations compiled with [loxc::compile_function], the
+    /// syntax tree itself can't leave its thread
+    declarations: Vec<Vec<u8>>,
+}
+
+/// A value that refers to environments and declarations by their index in [Detached]
+enum Packed {
+    String(String),
+    Number(Number),
+    Int(i64),
+    Bool(bool),
+    Nil,
+    NativeFunction(NativeFunction),
+    /// 0: declaration, 1: closure, 2: whether it is an initializer
+    Function(usize, usize, bool),
+    Task(Task),
+    Channel(Channel),
+    Class(usize),
+    Instance(usize),
+    List(usize),
+    Map(usize),
+}
+
+/// The variables of an environment, refers to the enclosing one by its index
+struct PackedEnvironment {
+    values: Vec<(String, Packed)>,
+    enclosing: Option<usize>,
+}
+
+/// Methods and setters of a class as 0: name, 1: declaration, 2: closure.
+/// The superclass always has a lower index than the class.
+struct PackedClass {
+    name: String,
+    superclass: Option<usize>,
+    methods: Vec<(String, usize, usize)>,
+    setters: Vec<(String, usize, usize)>,
+}
+
+/// The fields of an instance, refers to its class by its index
+struct PackedInstance {
+    class: usize,
+    fields: Vec<(String, Packed)>,
+}
+
+impl Detached {
+    /// Copies the value together with every environment it refers to
+    pub fn new(value: &Value) -> Self {
+        let mut packer = Packer {
+            environments: Vec::new(),
+            indices: HashMap::new(),
+            declarations: Vec::new(),
+            declaration_indices: HashMap::new(),
+            classes: Vec::new(),
+            class_indices: HashMap::new(),
+            instances: Vec::new(),
+            instance_indices: HashMap::new(),
+            lists: Vec::new(),
+            list_indices: HashMap::new(),
+            maps: Vec::new(),
+            map_indices: HashMap::new(),
+        };
+        let value = packer.value(value);
+        Self {
+            value,
+            environments: packer.environments,
+            classes: packer.classes,
+            instances: packer.instances,
+            lists: packer.lists,
+            maps: packer.maps,
+            declarations: packer.declarations,
+        }
+    }
+
+    /// Turns the copy into a value of the current thread
+    pub fn attach(self) -> Result<Value> {
+        let declarations = self
+            .declarations
+            .iter()
+            .map(|chunk| loxc::load_function(chunk).map(SharedRef::new))
+            .collect::<Result<Vec<_>>>()?;
+
+        // The environments, classes, instances, lists and maps are created empty first, so values can
+        // refer to any of them, even to the one they are stored in
+        let mut created: Vec<Option<Shared<Environment>>> = vec![None; self.environments.len()];
+        for index in 0..self.environments.len() {
+            create(index, &self.environments, &mut created);
+        }
+        let mut classes: Vec<Class> = Vec::with_capacity(self.classes.len());
+        for class in &self.classes {
+            let superclass = class.superclass.map(|index| classes[index].clone());
+            classes.push(Class::new(class.name.clone(), superclass, HashMap::new(), HashMap::new()));
+        }
+        let objects = Objects {
+            environments: created.into_iter().flatten().collect(),
+            instances: self.instances.iter().map(|instance| Instance::new(classes[instance.class].clone())).collect(),
+            lists: self.lists.iter().map(|items| List::new(Vec::with_capacity(items.len()))).collect(),
+            maps: self.maps.iter().map(|_| Map::new(Default::default())).collect(),
+            classes,
+            declarations,
+        };
+
+        for (packed, environment) in self.environments.into_iter().zip(&objects.environments) {
+            let mut environment = environment.borrow_mut();
+            for (name, value) in packed.values {
+                environment.define_inner(name, objects.unpack(value));
+            }
+        }
+        for (packed, class) in self.classes.into_iter().zip(&objects.classes) {
+            for (table, members) in [(&class.methods, packed.methods), (&class.setters, packed.setters)] {
+                let mut table = table.borrow_mut();
+                for (name, declaration, closure) in members {
+                    table.insert(name, Function::method(objects.declarations[declaration].clone(), objects.environments[closure].clone()));
+                }
+            }
+        }
+        for (packed, instance) in self.instances.into_iter().zip(&objects.instances) {
+            for (name, value) in packed.fields {
+                instance.set_field(name, objects.unpack(value));
+            }
+        }
+        for (packed, list) in self.lists.into_iter().zip(&objects.lists) {
+            let items: Vec<Value> = packed.into_iter().map(|value| objects.unpack(value)).collect();
+            *list.items.borrow_mut() = items;
+        }
+        for (packed, map) in self.maps.into_iter().zip(&objects.maps) {
+            let entries = packed.into_iter().map(|(key, value)| (key, objects.unpack(value))).collect();
+            *map.entries.borrow_mut() = entries;
+        }
+        Ok(objects.unpack(self.value))
+    }
+}
+
+/// Walks a value and copies the environments, classes, instances, lists
+/// and maps it reaches, each one once
+struct Packer {
+    environments: Vec<PackedEnvironment>,
+    /// Index of each copied environment by its address
+    indices: HashMap<usize, usize>,
+    declarations: Vec<Vec<u8>>,
+    declaration_indices: HashMap<usize, usize>,
+    classes: Vec<PackedClass>,
+    /// Index of each copied class by the address of its method table
+    class_indices: HashMap<usize, usize>,
+    instances: Vec<PackedInstance>,
+    /// Index of each copied instance by the address of its fields
+    instance_indices: HashMap<usize, usize>,
+    lists: Vec<Vec<Packed>>,
+    /// Index of each copied list by the address of its elements
+    list_indices: HashMap<usize, usize>,
+    maps: Vec<Vec<(Key, Packed)>>,
+    /// Index of each copied map by the address of its entries
+    map_indices: HashMap<usize, usize>,
+}
+
+impl Packer {
+    fn value(&mut self, value: &Value) -> Packed {
+        match value {
+            Value::String(s) => Packed::String(s.clone()),
+            Value::Number(n) => Packed::Number(*n),
+            Value::Int(i) => Packed::Int(*i),
+            Value::Bool(b) => Packed::Bool(*b),
+            Value::Nil => Packed::Nil,
+            Value::NativeFunction(native) => Packed::NativeFunction(*native),
+            Value::Function(function) => {
+                let declaration = self.declaration(&function.declaration);
+                Packed::Function(declaration, self.environment(&function.closure), function.initializer)
+            },
+            Value::Task(task) => Packed::Task(task.clone()),
+            Value::Channel(channel) => Packed::Channel(channel.clone()),
+            Value::Coroutine(_) => Packed::Nil, // A coroutine shares the variables of its script, it stays with it
+            Value::Future(_) => Packed::Nil, // Only the event loop of its script completes it
+            Value::Class(class) => Packed::Class(self.class(class)),
+            Value::Instance(instance) => Packed::Instance(self.instance(instance)),
+            Value::List(list) => Packed::List(self.list(list)),
+            Value::Map(map) => Packed::Map(self.map(map)),
+        }
+    }
+
+    fn list(&mut self, list: &List) -> usize {
+        if let Some(index) = self.list_indices.get(&list.items.address()) {
+            return *index;
+        }
+        // Like instances, a list can contain itself
+        let index = self.lists.len();
+        self.list_indices.insert(list.items.address(), index);
+        self.lists.push(Vec::new());
+
+        let items: Vec<Value> = list.items.borrow().clone();
+        self.lists[index] = items.iter().map(|item| self.value(item)).collect();
+        index
+    }
+
+    fn map(&mut self, map: &Map) -> usize {
+        if let Some(index) = self.map_indices.get(&map.entries.address()) {
+            return *index;
+        }
+        let index = self.maps.len();
+        self.map_indices.insert(map.entries.address(), index);
+        self.maps.push(Vec::new());
+
+        let entries: Vec<(Key, Value)> = map.entries.borrow().iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        self.maps[index] = entries.into_iter().map(|(key, value)| (key, self.value(&value))).collect();
+        index
+    }
+
+    fn class(&mut self, class: &Class) -> usize {
+        if let Some(index) = self.class_indices.get(&class.methods.address()) {
+            return *index;
+        }
+        // The superclass goes first, its methods may already reach this class
+        let superclass = class.superclass.as_ref().map(|superclass| self.class(superclass));
+        if let Some(index) = self.class_indices.get(&class.methods.address()) {
+            return *index;
+        }
+        let index = self.classes.len();
+        self.class_indices.insert(class.methods.address(), index);
+        self.classes.push(PackedClass { name: class.name.clone(), superclass, methods: Vec::new(), setters: Vec::new() });
+
+        self.classes[index].methods = self.members(&class.methods);
+        self.classes[index].setters = self.members(&class.setters);
+        index
+    }
+
+    /// Packs a method or setter table of a class
+    fn members(&mut self, table: &Shared<HashMap<String, Function>>) -> Vec<(String, usize, usize)> {
+        let members: Vec<(String, Function)> = table.borrow().iter().map(|(name, method)| (name.clone(), method.clone())).collect();
+        members
+            .into_iter()
+            .map(|(name, method)| (name, self.declaration(&method.declaration), self.environment(&method.closure)))
+            .collect()
+    }
+
+    fn instance(&mut self, instance: &Instance) -> usize {
+        if let Some(index) = self.instance_indices.get(&instance.fields.address()) {
+            return *index;
+        }
+        // Like environments, the index is taken first, an instance can be stored in its own fields
+        let index = self.instances.len();
+        self.instance_indices.insert(instance.fields.address(), index);
+        self.instances.push(PackedInstance { class: 0, fields: Vec::new() });
+
+        let class = self.class(&instance.class);
+        let fields: Vec<(String, Value)> =
+            instance.fields.borrow().iter().map(|(name, value)| (name.clone(), value.clone())).collect();
+        let fields = fields.into_iter().map(|(name, value)| (name, self.value(&value))).collect();
+        self.instances[index] = PackedInstance { class, fields };
+        index
+    }
+
+    fn declaration(&mut self, declaration: &SharedRef<FunctionDeclaration>) -> usize {
+        let address = SharedRef::as_ptr(declaration) as usize;
+        if let Some(index) = self.declaration_indices.get(&address) {
+            return *index;
+        }
+        self.declarations.push(loxc::compile_function(declaration));
+        self.declaration_indices.insert(address, self.declarations.len() - 1);
+        self.declarations.len() - 1
+    }
+
+    fn environment(&mut self, environment: &Shared<Environment>) -> usize {
+        if let Some(index) = self.indices.get(&environment.address()) {
+            return *index;
+        }
+        // The index is taken before the variables are copied, a function
+        // stored in its own closure refers back to it
+        let index = self.environments.len();
+        self.indices.insert(environment.address(), index);
+        self.environments.push(PackedEnvironment { values: Vec::new(), enclosing: None });
+
+        let (bindings, enclosing) = {
+            let environment = environment.borrow();
+            (environment.bindings(), environment.enclosing())
+        };
+        let enclosing = enclosing.map(|enclosing| self.environment(&enclosing));
+        let values = bindings.iter().map(|(name, value)| (name.clone(), self.value(value))).collect();
+        self.environments[index] = PackedEnvironment { values, enclosing };
+        index
+    }
+}
+
+/// Creates the environment with the given index, after the one enclosing it
+fn create(index: usize, environments: &[PackedEnvironment], created: &mut [Option<Shared<Environment>>]) -> Shared<Environment> {
+    if let Some(environment) = &created[index] {
+        return environment.clone();
+    }
+    let environment = match environments[index].enclosing {
+        Some(enclosing) => Environment::new_enclosed(create(enclosing, environments, created)),
+        None => Environment::new(),
+    };
+    created[index] = Some(environment.clone());
+    environment
+}
+
+/// Everything a packed value can refer to, recreated on the current thread
+struct Objects {
+    declarations: Vec<SharedRef<FunctionDeclaration>>,
+    environments: Vec<Shared<Environment>>,
+    classes: Vec<Class>,
+    instances: Vec<Instance>,
+    lists: Vec<List>,
+    maps: Vec<Map>,
+}
+
+impl Objects {
+    fn unpack(&self, value: Packed) -> Value {
+        match value {
+            Packed::String(s) => Value::String(s),
+            Packed::Number(n) => Value::Number(n),
+            Packed::Int(i) => Value::Int(i),
+            Packed::Bool(b) => Value::Bool(b),
+            Packed::Nil => Value::Nil,
+            Packed::NativeFunction(native) => Value::NativeFunction(native),
+            Packed::Function(declaration, closure, initializer) => Value::Function(Function {
+                declaration: self.declarations[declaration].clone(),
+                closure: self.environments[closure].clone(),
+                initializer,
+            }),
+            Packed::Task(task) => Value::Task(task),
+            Packed::Channel(channel) => Value::Channel(channel),
+            Packed::Class(index) => Value::Class(self.classes[index].clone()),
+            Packed::Instance(index) => Value::Instance(self.instances[index].clone()),
+            Packed::List(index) => Value::List(self.lists[index].clone()),
+            Packed::Map(index) => Value::Map(self.maps[index].clone()),
+        }
+    }
+}
+
+// ---------- Tests for the detached module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_survive_another_thread() {
+        let detached = Detached::new(&Value::String("text".to_string()));
+        let value = std::thread::spawn(move || Detached::new(&detached.attach().unwrap()))
+            .join()
+            .expect("The thread panicked");
+        assert_eq!(value.attach().unwrap(), Value::String("text".to_string()));
+    }
+
+    #[test]
+    fn lists_that_contain_themselves_stay_cyclic() {
+        let list = List::new(vec![Value::Nil]);
+        list.set(0, Value::List(list.clone()));
+        let copy = match Detached::new(&Value::List(list.clone())).attach().unwrap() {
+            Value::List(copy) => copy,
+            other => panic!("Expected a list, got {:?}", other),
+        };
+        assert_ne!(copy, list);
+        assert_eq!(copy.get(0), Some(Value::List(copy.clone())));
+    }
+}
@@ -1,60 +1,172 @@
 // External dependencies
-use anyhow::Result;
-use std::cell::RefCell;
-use std::collections::{hash_map, HashMap};
-use std::hash::Hash;
-use std::rc::Rc;
+use std::collections::HashMap;
 
 // Internal dependencies
-use crate::RuntimeError;
+use crate::errors::{LoxError, Result, RuntimeError};
+use crate::shared::Shared;
 use super::value::Value;
 use super::token::Token;
 
+/// The value of a variable and whether it can be assigned
+#[derive(Clone)]
+struct Binding {
+    value: Value,
+    mutable: bool,
+}
+
 #[derive(Clone)]
 pub struct Environment {
-    values: HashMap<String, Value>,
-    enclosing: Option<Rc<RefCell<Environment>>>,
+    values: HashMap<String, Binding>,
+    enclosing: Option<Shared<Environment>>,
 }
 
 impl Environment {
 
-    pub fn new() -> Rc<RefCell<Self>> {
-        Rc::new(RefCell::new(Self {
+    pub fn new() -> Shared<Self> {
+        Shared::new(Self {
             values: HashMap::new(),
             enclosing: None,
-        }))
+        })
     }
 
-    pub fn new_enclosed(enclosing: Rc<RefCell<Environment>>) -> Rc<RefCell<Environment>> {
-        Rc::new(RefCell::new(Self {
+    pub fn new_enclosed(enclosing: Shared<Environment>) -> Shared<Environment> {
+        Shared::new(Self {
             values: HashMap::new(),
             enclosing: Some(enclosing),
-        }))
+        })
+    }
+
+    /// Defines a variable in this environment. Returns the previous value
+    /// if the variable was already defined here.
+    pub fn define_inner(&mut self, name: String, value: Value) -> Option<Value> {
+        self.values.insert(name, Binding { value, mutable: true }).map(|old| old.value)
     }
 
-    pub fn define_inner(&mut self, name: String, value: Value) {
-        self.values.insert(name, value);
+    /// Defines a variable that cannot be assigned, like a `const`
+    /// declaration. Returns the previous value like [Environment::define_inner].
+    pub fn define_constant(&mut self, name: String, value: Value) -> Option<Value> {
+        self.values.insert(name, Binding { value, mutable: false }).map(|old| old.value)
     }
 
     pub fn get(&self, name: Token) -> Result<Value> {
-        if let Some(inner) = self.values.get(name.lexeme().as_str()).cloned() {
-            return Ok(inner);
-        }
-        if let Some(encl) = &self.enclosing {
-            return encl.borrow().get(name);
+        self.lookup(&name.lexeme()).ok_or_else(|| self.undefined(&name))
+    }
+
+    /// Value of a visible variable, `None` if there is no such variable
+    pub fn lookup(&self, name: &str) -> Option<Value> {
+        if let Some(inner) = self.values.get(name) {
+            return Some(inner.value.clone());
         }
+        self.enclosing.as_ref().and_then(|encl| encl.borrow().lookup(name))
+    }
+
+    /// Value of a variable of this environment, without looking into the
+    /// enclosing ones
+    pub fn lookup_here(&self, name: &str) -> Option<Value> {
+        self.values.get(name).map(|binding| binding.value.clone())
+    }
 
-        Err(RuntimeError::UndefinedVariable.into())
+    /// Assigns to an existing variable in this or an enclosing environment.
+    /// Returns the value that got replaced. Constants cannot be assigned.
+    pub fn assign(&mut self, name: Token, value: Value) -> Result<Value> {
+        self.replace(&name, value)?.ok_or_else(|| self.undefined(&name))
     }
 
-    pub fn assign(&mut self, name: Token, value: Value) -> Result<()> {
-        if self.values.contains_key(name.lexeme().as_str()) {
-            self.values.insert(name.lexeme(), value);
+    /// Replaces the value of a visible variable, `None` if there is no such variable
+    fn replace(&mut self, name: &Token, value: Value) -> Result<Option<Value>> {
+        if let Some(binding) = self.values.get_mut(&name.lexeme()) {
+            if !binding.mutable {
+                return Err(RuntimeError::AssignToConst(name.lexeme(), name.line()).into());
+            }
+            Ok(Some(std::mem::replace(&mut binding.value, value)))
         } else if let Some(encl) = &self.enclosing {
-            encl.borrow_mut().assign(name, value)?;
+            encl.borrow_mut().replace(name, value)
         } else {
-            return Err(RuntimeError::UndefinedVariable.into());
+            Ok(None)
         }
-        Ok(())
     }
-}
\ No newline at end of file
+
+    /// Every variable of this environment (not the enclosing ones),
+    /// sorted by name
+    pub fn bindings(&self) -> Vec<(String, Value)> {
+        let mut bindings: Vec<(String, Value)> = self.values.iter().map(|(n, b)| (n.clone(), b.value.clone())).collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
+    }
+
+    /// The environment this one is nested in, `None` for the global one
+    pub fn enclosing(&self) -> Option<Shared<Environment>> {
+        self.enclosing.clone()
+    }
+
+    /// Names of every variable visible from this environment, including
+    /// the enclosing ones. Shadowed names appear more than once.
+    pub fn visible_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.values.keys().cloned().collect();
+        if let Some(encl) = &self.enclosing {
+            names.extend(encl.borrow().visible_names());
+        }
+        names
+    }
+
+    /// The error for an unknown variable, with the most similar visible name
+    fn undefined(&self, name: &Token) -> LoxError {
+        let lexeme = name.lexeme();
+        let names = self.visible_names();
+        let suggestion = closest_name(&lexeme, names.iter().map(String::as_str)).map(str::to_string);
+        RuntimeError::UndefinedVariable(lexeme, suggestion, name.line()).into()
+    }
+
+    /// Approximate number of bytes the variables of this environment
+    /// (not the enclosing ones) occupy
+    pub fn size(&self) -> usize {
+        self.values
+            .iter()
+            .map(|(name, binding)| Self::binding_size(name, &binding.value))
+            .sum()
+    }
+
+    /// Approximate number of bytes a single variable occupies
+    pub fn binding_size(name: &str, value: &Value) -> usize {
+        name.len() + std::mem::size_of::<Value>() + value.heap_size()
+    }
+}
+/// The candidate that is most similar to `name`, if any is similar enough
+/// to be a likely typo: at most one edit per three characters, and at
+/// least one. Ties are broken alphabetically, so the result is stable.
+pub fn closest_name<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Edit distance between two strings, counted in characters. Swapping two
+/// neighbouring characters counts as a single edit, like inserting,
+/// removing or replacing one.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // distances[i][j]: distance between the first i chars of a and the first j chars of b
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
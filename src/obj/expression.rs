@@ -2,6 +2,9 @@
 use std::fmt::Display;
 
 // Internal dependencies
+use crate::shared::SharedRef;
+use super::function::FunctionDeclaration;
+use super::statement::Statement;
 use super::token::Token;
 use super::value::Value;
 
@@ -11,7 +14,7 @@ use super::value::Value;
 /// finally hold terminal expressions at the leaves of the tree.
 /// The expression tree (AST) can be traversed recursively to
 /// produce values.
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub enum Expression {
     // I <3 Rust enums
     // Non-Terminals
@@ -19,32 +22,184 @@ pub enum Expression {
     Assign(Token, Box<Expression>),
     /// 0: left, 1: operator, 2: right
     Binary(Box<Expression>, Token, Box<Expression>),
+    /// The block of an `if` expression, which has the value of the expression
+    /// it ends with, `nil` without one. 0: opening brace, 1: statements, 2: value
+    Block(Token, Vec<Statement>, Option<Box<Expression>>),
+    /// 0: callee, 1: closing parenthesis (for the line), 2: arguments
+    Call(Box<Expression>, Token, Vec<Expression>),
+    /// 0: object, 1: property name
+    Get(Box<Expression>, Token),
+    /// 0: the names assigned, 1: the destructured value
+    Destructure(Pattern, Box<Expression>),
     /// 0: expr
     Grouping(Box<Expression>),
+    /// An `if` in the place of a value, which has the value of the branch that
+    /// runs. 0: `if` keyword, 1: condition, 2: then block, 3: else block or `if`
+    If(Token, Box<Expression>, Box<Expression>, Option<Box<Expression>>),
+    /// 0: list, 1: opening bracket, 2: index
+    Index(Box<Expression>, Token, Box<Expression>),
+    /// 0: list, 1: opening bracket, 2: index, 3: assigned value
+    IndexSet(Box<Expression>, Token, Box<Expression>, Box<Expression>),
+    /// 0: anonymous function, its name is the `fun` keyword
+    Lambda(SharedRef<FunctionDeclaration>),
+    /// 0: opening bracket, 1: elements
+    List(Token, Vec<Expression>),
+    /// 0: opening brace, 1: keys and values
+    Map(Token, Vec<(Expression, Expression)>),
     /// 0: left, 1: operator, 2: right
     Logical(Box<Expression>, Token, Box<Expression>),
+    /// 0: object, 1: property name. Like [Expression::Get], but `nil` if the object is `nil`
+    OptionalGet(Box<Expression>, Token),
+    /// 0: object, 1: property name, 2: assigned value
+    Set(Box<Expression>, Token, Box<Expression>),
+    /// 0: list or string, 1: opening bracket, 2: start, 3: end (both optional)
+    Slice(Box<Expression>, Token, Option<Box<Expression>>, Option<Box<Expression>>),
     /// 0: operator, 1: right
     Unary(Token, Box<Expression>),
 
     // Terminals
     /// 0: value
     Literal(Value),
+    /// 0: the `super` keyword, 1: method name
+    Super(Token, Token),
+    /// 0: the `this` keyword
+    This(Token),
     /// 0: name
     Variable(Token),
 }
 
+/// The names a destructuring declaration or assignment binds
+#[derive(Debug)]
+pub enum Pattern {
+    /// `[a, b]` binds the elements of a list with exactly as many elements, in order.
+    /// 0: opening bracket, 1: names
+    List(Token, Vec<Token>),
+    /// `{x, y}` binds the entries of a map under the names as keys, or the
+    /// properties of an instance. 0: opening brace, 1: names
+    Map(Token, Vec<Token>),
+}
+
+impl Pattern {
+    /// The opening bracket or brace
+    pub fn token(&self) -> &Token {
+        match self {
+            Pattern::List(token, _) | Pattern::Map(token, _) => token,
+        }
+    }
+
+    pub fn names(&self) -> &[Token] {
+        match self {
+            Pattern::List(_, names) | Pattern::Map(_, names) => names,
+        }
+    }
+}
+
+/// Prints the pattern like it is written
+impl Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<String> = self.names().iter().map(Token::lexeme).collect();
+        match self {
+            Pattern::List(..) => write!(f, "[{}]", names.join(", ")),
+            Pattern::Map(..) => write!(f, "{{{}}}", names.join(", ")),
+        }
+    }
+}
+
+impl Expression {
+    /// The token the expression is named after (operator, variable name,
+    /// ...), which error output points at. Literals and groupings have none.
+    pub fn token(&self) -> Option<&Token> {
+        match self {
+            Expression::Assign(token, _)
+            | Expression::Binary(_, token, _)
+            | Expression::Block(token, _, _)
+            | Expression::Get(_, token)
+            | Expression::If(token, _, _, _)
+            | Expression::Index(_, token, _)
+            | Expression::IndexSet(_, token, _, _)
+            | Expression::List(token, _)
+            | Expression::Map(token, _)
+            | Expression::Logical(_, token, _)
+            | Expression::OptionalGet(_, token)
+            | Expression::Set(_, token, _)
+            | Expression::Slice(_, token, _, _)
+            | Expression::Super(_, token)
+            | Expression::This(token)
+            | Expression::Unary(token, _)
+            | Expression::Variable(token) => Some(token),
+            Expression::Call(callee, paren, _) => callee.token().or(Some(paren)), // Point at the function name
+            Expression::Destructure(pattern, _) => Some(pattern.token()),
+            Expression::Lambda(declaration) => Some(&declaration.name),
+            Expression::Grouping(_) | Expression::Literal(_) => None,
+        }
+    }
+}
+
 impl Display for Expression { // recursive printing of expressions
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Expression::Binary(left, operator, right) => {
                 write!(f, "({} {} {})", operator.lexeme(), left, right)
             }
+            Expression::Call(callee, _, args) => {
+                write!(f, "(call {}", callee)?;
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Expression::Get(object, name) => write!(f, "(get {} {})", object, name.lexeme()),
+            Expression::OptionalGet(object, name) => write!(f, "(get? {} {})", object, name.lexeme()),
             Expression::Grouping(expr) => write!(f, "(group {})", expr),
+            Expression::Block(_, stmts, value) => {
+                write!(f, "(block")?;
+                for stmt in stmts {
+                    write!(f, " {}", stmt)?;
+                }
+                match value {
+                    Some(value) => write!(f, " {})", value),
+                    None => write!(f, ")"),
+                }
+            }
+            Expression::If(_, cond, then, Some(els)) => write!(f, "(if {} {} {})", cond, then, els),
+            Expression::If(_, cond, then, None) => write!(f, "(if {} {})", cond, then),
+            Expression::Index(list, _, index) => write!(f, "(index {} {})", list, index),
+            Expression::IndexSet(list, _, index, value) => write!(f, "(index= {} {} {})", list, index, value),
+            Expression::Lambda(declaration) => write!(f, "{}", declaration),
+            Expression::List(_, items) => {
+                write!(f, "(list")?;
+                for item in items {
+                    write!(f, " {}", item)?;
+                }
+                write!(f, ")")
+            }
+            Expression::Map(_, entries) => {
+                write!(f, "(map")?;
+                for (key, value) in entries {
+                    write!(f, " ({} {})", key, value)?;
+                }
+                write!(f, ")")
+            }
+            Expression::Literal(Value::String(s)) => write!(f, "\"{}\"", s), // quoted, so "1" and 1 differ
             Expression::Literal(val) => write!(f, "{}", val),
             Expression::Unary(op, right) => write!(f, "({} {})", op.lexeme(), right),
+            Expression::Super(_, method) => write!(f, "(super {})", method.lexeme()),
+            Expression::This(_) => write!(f, "this"),
             Expression::Variable(name) => write!(f, "(var {})", name.lexeme()),
             Expression::Assign(name, expr) => write!(f, "(= {} {})", name.lexeme(), expr),
+            Expression::Destructure(pattern, expr) => write!(f, "(= {} {})", pattern, expr),
             Expression::Logical(left, op, right) => write!(f, "(logical {} {} {})", left, op.lexeme(), right),
+            Expression::Set(object, name, value) => write!(f, "(set {} {} {})", object, name.lexeme(), value),
+            Expression::Slice(object, _, start, end) => {
+                write!(f, "(slice {}", object)?;
+                for bound in [start, end] {
+                    match bound {
+                        Some(bound) => write!(f, " {}", bound)?,
+                        None => write!(f, " _")?,
+                    }
+                }
+                write!(f, ")")
+            }
         }
     }
 }
@@ -0,0 +1,147 @@
+// External dependencies
+use std::fmt::{Debug, Display};
+
+// Internal dependencies
+use crate::shared::{Shared, SharedRef};
+use super::annotation::TypeAnnotation;
+use super::class::Instance;
+use super::environment::Environment;
+use super::statement::Statement;
+use super::token::Token;
+use super::token_type::TokenType;
+use super::value::Value;
+
+/// A `fun` declaration as the parser produced it. Function values share
+/// it with the syntax tree instead of copying the body.
+#[derive(Debug)]
+pub struct FunctionDeclaration {
+    /// The `fun` keyword for anonymous functions
+    pub name: Token,
+    /// Declared with `async fun`, calling it returns a future
+    pub is_async: bool,
+    pub params: Vec<Parameter>,
+    /// Type annotation after the parameter list
+    pub return_type: Option<TypeAnnotation>,
+    pub body: Vec<Statement>,
+}
+
+/// A parameter of a [FunctionDeclaration]
+#[derive(Debug)]
+pub struct Parameter {
+    pub name: Token,
+    pub annotation: Option<TypeAnnotation>,
+}
+
+impl FunctionDeclaration {
+    /// Whether this is an anonymous function, written as an expression
+    pub fn is_lambda(&self) -> bool {
+        self.name.token_type() == TokenType::Fun
+    }
+
+    /// The keyword that starts a named declaration, `fun` or `async fun`
+    pub fn keyword(&self) -> &'static str {
+        if self.is_async { "async fun" } else { "fun" }
+    }
+
+    /// The declaration as it would be written, without [keyword](Self::keyword)
+    /// and body, like `add(a: Number, b): Number`. Anonymous functions keep
+    /// their keyword: `fun (a, b)` or `async fun (a, b)`.
+    pub fn signature(&self) -> String {
+        let params: Vec<String> = self.params.iter().map(Parameter::to_string).collect();
+        let name = if self.is_lambda() { format!("{} ", self.keyword()) } else { self.name.lexeme() };
+        let mut signature = format!("{}({})", name, params.join(", "));
+        if let Some(return_type) = &self.return_type {
+            signature.push_str(&format!(": {}", return_type));
+        }
+        signature
+    }
+}
+
+/// Prints the declaration as an S-expression, like [Statement] does
+impl Display for FunctionDeclaration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_lambda() {
+            write!(f, "({}", self.signature())?;
+        } else {
+            write!(f, "({} {}", self.keyword(), self.signature())?;
+        }
+        for stmt in &self.body {
+            write!(f, " {}", stmt)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name.lexeme())?;
+        if let Some(annotation) = &self.annotation {
+            write!(f, ": {}", annotation)?;
+        }
+        Ok(())
+    }
+}
+
+/// A function declared in Lox, the value a `fun` declaration binds.
+/// It captures the environment it was declared in: calling it runs the
+/// body in a new scope below that environment, so the function can still
+/// read and assign the variables around its declaration after their
+/// block has ended.
+#[derive(Clone)]
+pub struct Function {
+    pub declaration: SharedRef<FunctionDeclaration>,
+    pub closure: Shared<Environment>,
+    /// Whether this is the `init` method of a class, which always returns
+    /// the instance it was called on
+    pub initializer: bool,
+}
+
+impl Function {
+    pub fn new(declaration: SharedRef<FunctionDeclaration>, closure: Shared<Environment>) -> Self {
+        Self { declaration, closure, initializer: false }
+    }
+
+    /// A method of a class, the one named `init` is the initializer
+    pub fn method(declaration: SharedRef<FunctionDeclaration>, closure: Shared<Environment>) -> Self {
+        let initializer = declaration.name.lexeme() == "init";
+        Self { declaration, closure, initializer }
+    }
+
+    /// The name it was declared with, `lambda` for anonymous functions
+    pub fn name(&self) -> String {
+        if self.declaration.is_lambda() { "lambda".to_string() } else { self.declaration.name.lexeme() }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    /// The method bound to an instance: a copy whose closure has `this`
+    /// defined as the instance, so it keeps working when it is stored
+    /// in a variable and called later
+    pub fn bind(&self, instance: Instance) -> Function {
+        let environment = Environment::new_enclosed(self.closure.clone());
+        environment.borrow_mut().define_inner("this".to_string(), Value::Instance(instance));
+        Function { declaration: self.declaration.clone(), closure: environment, initializer: self.initializer }
+    }
+}
+
+// Functions are identified by their declaration and the environment they
+// captured, so every run of a declaration creates a new function
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        SharedRef::ptr_eq(&self.declaration, &other.declaration) && self.closure.address() == other.closure.address()
+    }
+}
+
+impl Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Function({})", self.name())
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<fn {}>", self.name())
+    }
+}
@@ -0,0 +1,69 @@
+// External dependencies
+use std::fmt::{Debug, Display};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Internal dependencies
+use crate::shared::Shared;
+use super::value::Value;
+
+/// Source of the future ids, which are unique within the process
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// How a future completed: with the value its async call returned, or
+/// with the value it threw (a caught runtime error becomes an instance
+/// of its error class, like in a `catch` block)
+pub type Settled = std::result::Result<Value, Value>;
+
+/// The result of an async call or a `sleepAsync` timer, which isn't there
+/// yet. `await` waits for it, the event loop of the interpreter completes
+/// it. Copies of the handle refer to the same future.
+#[derive(Clone)]
+pub struct Future {
+    id: u64,
+    settled: Shared<Option<Settled>>,
+}
+
+impl Future {
+    pub fn new() -> Self {
+        Self { id: NEXT_ID.fetch_add(1, Ordering::Relaxed), settled: Shared::new(None) }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.settled.borrow().is_some()
+    }
+
+    /// Completes the future, if it isn't already
+    pub fn complete(&self, settled: Settled) {
+        self.settled.borrow_mut().get_or_insert(settled);
+    }
+
+    /// How the future completed, `None` while it is pending
+    pub fn settled(&self) -> Option<Settled> {
+        self.settled.borrow().clone()
+    }
+}
+
+impl Default for Future {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Futures are identified by their id, copies of a handle are equal
+impl PartialEq for Future {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Debug for Future {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Future({})", self.id)
+    }
+}
+
+impl Display for Future {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<future {}>", self.id)
+    }
+}
@@ -0,0 +1,112 @@
+// External dependencies
+use std::fmt::{Debug, Display};
+
+// Internal dependencies
+use crate::shared::Shared;
+use super::value::Value;
+
+/// A list of values, created by a `[1, 2, 3]` literal. Copies of a list
+/// refer to the same elements, so changing one through `list[i] = x`
+/// changes it everywhere.
+#[derive(Clone)]
+pub struct List {
+    pub items: Shared<Vec<Value>>,
+    /// Set by the `freeze` native, shared by the copies like the elements
+    frozen: Shared<bool>,
+}
+
+impl List {
+    pub fn new(items: Vec<Value>) -> Self {
+        Self { items: Shared::new(items), frozen: Shared::new(false) }
+    }
+
+    /// Forbids changing the elements from now on
+    pub fn freeze(&self) {
+        *self.frozen.borrow_mut() = true;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        *self.frozen.borrow()
+    }
+
+    /// Number of elements
+    pub fn len(&self) -> usize {
+        self.items.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Approximate number of bytes a single element occupies
+    pub fn item_size(value: &Value) -> usize {
+        std::mem::size_of::<Value>() + value.heap_size()
+    }
+
+    /// The element at `index`, `None` if it is out of bounds
+    pub fn get(&self, index: usize) -> Option<Value> {
+        self.items.borrow().get(index).cloned()
+    }
+
+    /// Replaces the element at `index` and returns the one it had before,
+    /// `None` if the index is out of bounds
+    pub fn set(&self, index: usize, value: Value) -> Option<Value> {
+        self.items.borrow_mut().get_mut(index).map(|item| std::mem::replace(item, value))
+    }
+
+    /// The elements, written like a list literal. Strings are quoted, with
+    /// `canonical` every element is in its text form (see [Value::to_text]).
+    /// A list that contains itself shows up as `[...]` the second time.
+    pub fn text(&self, canonical: bool) -> String {
+        self.text_within(canonical, &mut Vec::new())
+    }
+
+    /// [List::text], knowing the addresses of the lists and maps it is printed in
+    pub(super) fn text_within(&self, canonical: bool, outer: &mut Vec<usize>) -> String {
+        if outer.contains(&self.items.address()) {
+            return "[...]".to_string();
+        }
+        outer.push(self.items.address());
+        let items: Vec<String> = self.items.borrow().iter().map(|item| item.nested_text(canonical, outer)).collect();
+        outer.pop();
+        format!("[{}]", items.join(", "))
+    }
+}
+
+// Lists are identified by their elements, copies of a list are equal
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        self.items.address() == other.items.address()
+    }
+}
+
+impl Debug for List {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "List({})", self.len())
+    }
+}
+
+impl Display for List {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text(false))
+    }
+}
+
+// A list serializes as the sequence of its elements. One that contains
+// itself would never end, it fails instead.
+#[cfg(feature = "serde")]
+impl serde::Serialize for List {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        if Value::List(self.clone()).contains_itself() {
+            return Err(serde::ser::Error::custom("a list that contains itself can't be serialized"));
+        }
+        serializer.collect_seq(self.items.borrow().iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for List {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(List::new)
+    }
+}
@@ -0,0 +1,222 @@
+// External dependencies
+use indexmap::IndexMap;
+use std::fmt::{Debug, Display};
+
+// Internal dependencies
+use crate::shared::Shared;
+use super::number::Number;
+use super::value::Value;
+
+/// A value that can be the key of a [Map]. Only values that never change
+/// and are compared by their content qualify: strings, numbers, booleans
+/// and nil. NaN isn't equal to itself, so it can't be a key either. Whole
+/// numbers are the same key as the int they are equal to.
+#[derive(Debug, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Key {
+    String(String),
+    Number(Number),
+    Int(i64),
+    Bool(bool),
+    Nil,
+}
+
+// NaN is the only number that isn't equal to itself, and it is never a key
+impl Eq for Key {}
+
+impl Key {
+    /// The key for a value, `None` if the value can't be a key
+    pub fn from_value(value: &Value) -> Option<Key> {
+        match value {
+            Value::String(s) => Some(Key::String(s.clone())),
+            Value::Number(n) => match n.to_i64() {
+                Some(int) => Some(Key::Int(int)),
+                None if n.is_nan() => None,
+                None => Some(Key::Number(*n)),
+            },
+            Value::Int(i) => Some(Key::Int(*i)),
+            Value::Bool(b) => Some(Key::Bool(*b)),
+            Value::Nil => Some(Key::Nil),
+            _ => None,
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            Key::String(s) => Value::String(s.clone()),
+            Key::Number(n) => Value::Number(*n),
+            Key::Int(i) => Value::Int(*i),
+            Key::Bool(b) => Value::Bool(*b),
+            Key::Nil => Value::Nil,
+        }
+    }
+}
+
+/// Entries of values under keys, created by a `{"key": value}` literal.
+/// The entries keep the order they were added in. Copies of a map refer
+/// to the same entries, like with lists.
+#[derive(Clone)]
+pub struct Map {
+    pub entries: Shared<IndexMap<Key, Value>>,
+    /// Set by the `freeze` native, shared by the copies like the entries
+    frozen: Shared<bool>,
+}
+
+impl Map {
+    pub fn new(entries: IndexMap<Key, Value>) -> Self {
+        Self { entries: Shared::new(entries), frozen: Shared::new(false) }
+    }
+
+    /// Forbids adding, changing and removing entries from now on
+    pub fn freeze(&self) {
+        *self.frozen.borrow_mut() = true;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        *self.frozen.borrow()
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Approximate number of bytes a single entry occupies
+    pub fn entry_size(key: &Key, value: &Value) -> usize {
+        let key = match key {
+            Key::String(s) => s.len(),
+            Key::Number(_) | Key::Int(_) | Key::Bool(_) | Key::Nil => 0,
+        };
+        std::mem::size_of::<Key>() + key + std::mem::size_of::<Value>() + value.heap_size()
+    }
+
+    /// The value under `key`, `None` if there is none
+    pub fn get(&self, key: &Key) -> Option<Value> {
+        self.entries.borrow().get(key).cloned()
+    }
+
+    pub fn contains(&self, key: &Key) -> bool {
+        self.entries.borrow().contains_key(key)
+    }
+
+    /// Sets the value under `key` and returns the one it had before. New
+    /// keys go after the existing ones.
+    pub fn insert(&self, key: Key, value: Value) -> Option<Value> {
+        self.entries.borrow_mut().insert(key, value)
+    }
+
+    /// Removes the entry of `key` and returns its value, the other
+    /// entries keep their order
+    pub fn remove(&self, key: &Key) -> Option<Value> {
+        self.entries.borrow_mut().shift_remove(key)
+    }
+
+    /// The keys in the order they were added
+    pub fn keys(&self) -> Vec<Value> {
+        self.entries.borrow().keys().map(Key::to_value).collect()
+    }
+
+    /// The values in the order their keys were added
+    pub fn values(&self) -> Vec<Value> {
+        self.entries.borrow().values().cloned().collect()
+    }
+
+    /// The entries, written like a map literal. See [List::text](super::list::List::text),
+    /// a map that contains itself shows up as `{...}` the second time.
+    pub fn text(&self, canonical: bool) -> String {
+        self.text_within(canonical, &mut Vec::new())
+    }
+
+    /// [Map::text], knowing the addresses of the lists and maps it is printed in
+    pub(super) fn text_within(&self, canonical: bool, outer: &mut Vec<usize>) -> String {
+        if outer.contains(&self.entries.address()) {
+            return "{...}".to_string();
+        }
+        outer.push(self.entries.address());
+        let entries: Vec<String> = self
+            .entries
+            .borrow()
+            .iter()
+            .map(|(key, value)| {
+                let key = key.to_value().nested_text(canonical, outer);
+                format!("{}: {}", key, value.nested_text(canonical, outer))
+            })
+            .collect();
+        outer.pop();
+        format!("{{{}}}", entries.join(", "))
+    }
+}
+
+// Maps are identified by their entries, copies of a map are equal
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries.address() == other.entries.address()
+    }
+}
+
+impl Debug for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Map({})", self.len())
+    }
+}
+
+impl Display for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text(false))
+    }
+}
+
+// A map serializes as the sequence of its key and value pairs, in order.
+// Keys can be more than strings, which formats like JSON only allow as
+// object keys. One that contains itself fails, like a list.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Map {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        if Value::Map(self.clone()).contains_itself() {
+            return Err(serde::ser::Error::custom("a map that contains itself can't be serialized"));
+        }
+        serializer.collect_seq(self.entries.borrow().iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Map {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        Vec::<(Key, Value)>::deserialize(deserializer).map(|entries| Map::new(entries.into_iter().collect()))
+    }
+}
+
+// ---------- Tests for the map module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::list::List;
+
+    fn key(value: Value) -> Option<Key> {
+        Key::from_value(&value)
+    }
+
+    #[test]
+    fn keys_compare_by_content() {
+        let map = Map::new(IndexMap::new());
+        map.insert(key(Value::Number(Number::from(0))).unwrap(), Value::Bool(true));
+        map.insert(key(Value::String("a".to_string())).unwrap(), Value::Nil);
+        assert_eq!(map.get(&key(Value::Number(-Number::from(0))).unwrap()), Some(Value::Bool(true)));
+        assert_eq!(map.get(&key(Value::Int(0)).unwrap()), Some(Value::Bool(true)));
+        assert!(map.contains(&key(Value::String("a".to_string())).unwrap()));
+        assert!(!map.contains(&key(Value::Nil).unwrap()));
+        assert_eq!(key(Value::List(List::new(Vec::new()))), None);
+    }
+
+    #[cfg(not(feature = "decimal"))]
+    #[test]
+    fn nan_is_no_key() {
+        let nan = Number::from(0).checked_div(Number::from(0)).unwrap();
+        assert_eq!(key(Value::Number(nan)), None);
+    }
+}
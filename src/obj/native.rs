@@ -0,0 +1,42 @@
+// External dependencies
+use std::fmt::{Debug, Display};
+
+// Internal dependencies
+use crate::errors::Result;
+use crate::interpreter::Interpreter;
+use super::value::Value;
+
+/// Signature of the Rust functions behind natives. They get the interpreter
+/// (for its configuration and output), the already evaluated arguments and
+/// the line of the call for error messages.
+pub type NativeFn = fn(&mut Interpreter, Vec<Value>, u32) -> Result<Value>;
+
+/// A function that is implemented in Rust but callable from Lox
+#[derive(Clone, Copy)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    /// The most arguments the native takes
+    pub arity: usize,
+    /// How many of the last arguments can be left out
+    pub optional: usize,
+    pub function: NativeFn,
+}
+
+// Natives are identified by their name, there is only one native per name
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+impl Display for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
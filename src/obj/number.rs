@@ -0,0 +1,253 @@
+//! The numeric type of Lox. By default numbers are `f64`, like in the
+//! reference implementation. With the `decimal` feature they are exact
+//! 96 bit decimals instead, so `0.1 + 0.2 == 0.3` holds. The interpreter
+//! only ever uses the API of [Number], so it works with both. Whole number
+//! literals are ints (`i64`) instead, which don't depend on the feature.
+
+// External dependencies
+use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::ops::Neg;
+use std::str::FromStr;
+
+#[cfg(not(feature = "decimal"))]
+type Repr = f64;
+#[cfg(feature = "decimal")]
+type Repr = rust_decimal::Decimal;
+
+/// A Lox number. Arithmetic is checked, because the decimal backend
+/// cannot represent infinities: operations that fail return `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct Number(Repr);
+
+impl Number {
+    pub fn checked_add(self, rhs: Number) -> Option<Number> {
+        #[cfg(not(feature = "decimal"))]
+        return Some(Number(self.0 + rhs.0));
+        #[cfg(feature = "decimal")]
+        return self.0.checked_add(rhs.0).map(Number);
+    }
+
+    pub fn checked_sub(self, rhs: Number) -> Option<Number> {
+        #[cfg(not(feature = "decimal"))]
+        return Some(Number(self.0 - rhs.0));
+        #[cfg(feature = "decimal")]
+        return self.0.checked_sub(rhs.0).map(Number);
+    }
+
+    pub fn checked_mul(self, rhs: Number) -> Option<Number> {
+        #[cfg(not(feature = "decimal"))]
+        return Some(Number(self.0 * rhs.0));
+        #[cfg(feature = "decimal")]
+        return self.0.checked_mul(rhs.0).map(Number);
+    }
+
+    /// Division. With `f64`, dividing by zero results in an infinity
+    /// (or NaN), the decimal backend returns `None` instead.
+    pub fn checked_div(self, rhs: Number) -> Option<Number> {
+        #[cfg(not(feature = "decimal"))]
+        return Some(Number(self.0 / rhs.0));
+        #[cfg(feature = "decimal")]
+        return self.0.checked_div(rhs.0).map(Number);
+    }
+
+    /// Division rounded toward negative infinity, so `-7 ~/ 2` is `-4`
+    pub fn checked_floor_div(self, rhs: Number) -> Option<Number> {
+        #[cfg(not(feature = "decimal"))]
+        return Some(Number((self.0 / rhs.0).floor()));
+        #[cfg(feature = "decimal")]
+        return self.0.checked_div(rhs.0).map(|n| Number(n.floor()));
+    }
+
+    /// The closest `f64`, for APIs that need one (durations, ...)
+    pub fn to_f64(self) -> f64 {
+        #[cfg(not(feature = "decimal"))]
+        return self.0;
+        #[cfg(feature = "decimal")]
+        return rust_decimal::prelude::ToPrimitive::to_f64(&self.0).unwrap_or(f64::NAN);
+    }
+
+    /// Whether the number is NaN, which only exists with `f64`
+    pub fn is_nan(self) -> bool {
+        #[cfg(not(feature = "decimal"))]
+        return self.0.is_nan();
+        #[cfg(feature = "decimal")]
+        return false;
+    }
+
+    /// The number as a count of something, `None` if it is negative or
+    /// has a fractional part
+    pub fn to_count(self) -> Option<usize> {
+        #[cfg(not(feature = "decimal"))]
+        return (self.0 >= 0.0 && self.0.fract() == 0.0 && self.0 <= usize::MAX as f64).then_some(self.0 as usize);
+        #[cfg(feature = "decimal")]
+        return if self.0.is_sign_negative() || !self.0.fract().is_zero() {
+            None
+        } else {
+            rust_decimal::prelude::ToPrimitive::to_usize(&self.0)
+        };
+    }
+
+    /// The number as an int, `None` if it has a fractional part or is out
+    /// of the range of `i64`
+    pub fn to_i64(self) -> Option<i64> {
+        #[cfg(not(feature = "decimal"))]
+        return (self.0.fract() == 0.0 && self.0 >= i64::MIN as f64 && self.0 < i64::MAX as f64).then_some(self.0 as i64);
+        #[cfg(feature = "decimal")]
+        return if self.0.fract().is_zero() { rust_decimal::prelude::ToPrimitive::to_i64(&self.0) } else { None };
+    }
+
+    /// The number closest to an `f64`, `None` if the backend cannot
+    /// represent it (infinities and NaN with `decimal`)
+    pub fn from_f64(value: f64) -> Option<Number> {
+        #[cfg(not(feature = "decimal"))]
+        return Some(Number(value));
+        #[cfg(feature = "decimal")]
+        return rust_decimal::prelude::FromPrimitive::from_f64(value).map(Number);
+    }
+
+    /// Canonical text form that reads back into the exact same number
+    /// through [str::parse]. Whole numbers get a fractional part, so the
+    /// text doesn't read back as an int.
+    pub fn to_text(&self) -> String {
+        #[cfg(not(feature = "decimal"))]
+        return format!("{:?}", self.0); // Debug format round-trips, including inf and NaN
+        #[cfg(feature = "decimal")]
+        return match self.0.to_string() {
+            text if text.contains('.') => text,
+            text => text + ".0",
+        };
+    }
+}
+
+impl Neg for Number {
+    type Output = Number;
+
+    fn neg(self) -> Number {
+        Number(-self.0)
+    }
+}
+
+// Equal numbers hash the same: 0 and -0 are equal, and so are 1.5 and 1.50
+// with decimals. NaN is not equal to itself, so it can't be hashed usefully.
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        #[cfg(not(feature = "decimal"))]
+        return (if self.0 == 0.0 { 0.0 } else { self.0 }).to_bits().hash(state);
+        #[cfg(feature = "decimal")]
+        return self.0.hash(state); // Decimal hashes the normalized number
+    }
+}
+
+impl From<i32> for Number {
+    fn from(value: i32) -> Self {
+        Number(Repr::from(value))
+    }
+}
+
+impl From<i64> for Number {
+    /// Used when an int meets a number. Huge values lose precision with `f64`.
+    fn from(value: i64) -> Self {
+        #[cfg(not(feature = "decimal"))]
+        return Number(value as f64);
+        #[cfg(feature = "decimal")]
+        return Number(Repr::from(value));
+    }
+}
+
+impl From<usize> for Number {
+    /// Used for lengths and indices. Huge values lose precision with `f64`.
+    fn from(value: usize) -> Self {
+        #[cfg(not(feature = "decimal"))]
+        return Number(value as f64);
+        #[cfg(feature = "decimal")]
+        return Number(Repr::from(value));
+    }
+}
+
+impl FromStr for Number {
+    type Err = ();
+
+    /// Parses number literals like `42`, `4.2` or `4.2e-1`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        #[cfg(feature = "decimal")]
+        if s.contains(['e', 'E']) {
+            return Repr::from_scientific(s).map(Number).map_err(|_| ());
+        }
+        s.parse::<Repr>().map(Number).map_err(|_| ())
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        #[cfg(not(feature = "decimal"))]
+        return write!(f, "{}", self.0);
+        // Trailing zeros are dropped, so `1.50` prints as `1.5` and `2.0` as `2`, like f64 does
+        #[cfg(feature = "decimal")]
+        return write!(f, "{}", self.0.normalize());
+    }
+}
+
+// ---------- Tests for the number module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn num(s: &str) -> Number {
+        s.parse().expect("Invalid number!")
+    }
+
+    #[test]
+    fn prints_like_lox_numbers() {
+        assert_eq!(num("2.0").to_string(), "2");
+        assert_eq!(num("1.50").to_string(), "1.5");
+        assert_eq!((-Number::from(3)).to_string(), "-3");
+    }
+
+    #[test]
+    fn text_form_round_trips() {
+        for n in [num("0.1"), num("12345.678"), Number::from(-7)] {
+            assert_eq!(n.to_text().parse::<Number>(), Ok(n));
+        }
+    }
+
+    #[test]
+    fn only_whole_non_negative_numbers_are_counts() {
+        assert_eq!(num("3").to_count(), Some(3));
+        assert_eq!(num("0").to_count(), Some(0));
+        assert_eq!(num("1.5").to_count(), None);
+        assert_eq!(num("-2").to_count(), None);
+    }
+
+    #[test]
+    fn only_whole_numbers_are_ints() {
+        assert_eq!(num("3.0").to_i64(), Some(3));
+        assert_eq!(num("-2").to_i64(), Some(-2));
+        assert_eq!(num("1.5").to_i64(), None);
+        assert_eq!(num("100000000000000000000000").to_i64(), None);
+    }
+
+    #[test]
+    fn floor_division_rounds_down() {
+        let floor_div = |a: i32, b: i32| Number::from(a).checked_floor_div(Number::from(b));
+        assert_eq!(floor_div(7, 2), Some(Number::from(3)));
+        assert_eq!(floor_div(-7, 2), Some(Number::from(-4)));
+        assert_eq!(floor_div(6, -3), Some(Number::from(-2)));
+    }
+
+    #[cfg(not(feature = "decimal"))]
+    #[test]
+    fn float_arithmetic() {
+        assert_ne!(num("0.1").checked_add(num("0.2")), Some(num("0.3")));
+        assert_eq!(Number::from(1).checked_div(Number::from(0)).map(|n| n.to_text()), Some("inf".to_string()));
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn decimal_arithmetic_is_exact() {
+        assert_eq!(num("0.1").checked_add(num("0.2")), Some(num("0.3")));
+        assert_eq!(Number::from(1).checked_div(Number::from(0)), None);
+    }
+}
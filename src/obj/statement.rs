@@ -1,35 +1,186 @@
 use std::fmt::Display;
 
 // Internal dependencies
-use super::expression::Expression;
+use crate::shared::SharedRef;
+use super::annotation::TypeAnnotation;
+use super::expression::{Expression, Pattern};
+use super::function::FunctionDeclaration;
 use super::token::Token;
 
+/// A branch of a `switch`: the values it matches and the statements it runs
+pub type Case = (Vec<Expression>, Vec<Statement>);
+
+/// A `catch` block: the variable of the caught value, the class the value
+/// has to be an instance of (any value if there is none) and the statements
+pub type Catch = (Token, Option<Token>, Vec<Statement>);
+
 #[derive(Debug)]
 pub enum Statement {
     Block(Vec<Statement>),
+    /// 0: `break` keyword (for the line), 1: label of the loop it leaves,
+    /// the innermost loop if there is none
+    Break(Token, Option<Token>),
+    /// 0: name, 1: name of the superclass, 2: names of the classes mixed in
+    /// with `with`, 3: methods, 4: setters
+    Class(Token, Option<Token>, Vec<Token>, Vec<SharedRef<FunctionDeclaration>>, Vec<SharedRef<FunctionDeclaration>>),
+    /// 0: `continue` keyword (for the line), 1: label of the loop it
+    /// continues, the innermost loop if there is none
+    Continue(Token, Option<Token>),
     Expression(Expression),
-    If(Expression, Box<Statement>, Option<Box<Statement>>),
-    Print(Expression),
-    Var(Token, Option<Expression>),
-    While(Expression, Box<Statement>),
+    /// 0: `for` keyword (for the line), 1: loop variable, 2: the iterated
+    /// list, map or string, 3: body, 4: label
+    ForIn(Token, Token, Expression, Box<Statement>, Option<Token>),
+    /// 0: the declaration, shared with the function values created from it
+    Function(SharedRef<FunctionDeclaration>),
+    /// 0: `if` keyword (for the line), 1: condition, 2: then branch, 3: else branch
+    If(Token, Expression, Box<Statement>, Option<Box<Statement>>),
+    /// 0: `print` keyword (for the line), 1: the printed values, joined with spaces
+    Print(Token, Vec<Expression>),
+    /// 0: `return` keyword (for the line), 1: returned value
+    Return(Token, Option<Expression>),
+    /// 0: `throw` keyword (for the line), 1: the thrown value
+    Throw(Token, Expression),
+    /// 0: `try` keyword (for the line), 1: body, 2: the `catch` blocks in
+    /// the order they are tried, 3: body of the `finally` block
+    Try(Token, Vec<Statement>, Vec<Catch>, Option<Vec<Statement>>),
+    /// 0: `switch` keyword (for the line), 1: the value switched on,
+    /// 2: the cases with their values and bodies, 3: body of the default branch
+    Switch(Token, Expression, Vec<Case>, Option<Vec<Statement>>),
+    /// 0: name, 1: type annotation, 2: initializer
+    Var(Token, Option<TypeAnnotation>, Option<Expression>),
+    /// `var` declaring the names of a destructuring pattern.
+    /// 0: the declared names, 1: the destructured value
+    Destructure(Pattern, Expression),
+    /// A variable that cannot be assigned after its declaration.
+    /// 0: name, 1: type annotation, 2: initializer
+    Const(Token, Option<TypeAnnotation>, Expression),
+    /// 0: `while` or `for` keyword (for the line), 1: condition, 2: body,
+    /// 3: increment of a `for` loop, which also runs after a `continue`,
+    /// 4: label, which `break` and `continue` in nested loops can name
+    While(Token, Expression, Box<Statement>, Option<Expression>, Option<Token>),
 }
 
+/// Prints the statement as an S-expression. The output only depends on the
+/// structure of the AST (not on formatting or line numbers), so it doubles
+/// as the serialized form that tooling like `jlox diff` compares.
 impl Display for Statement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Statement::Block(vec) => {
-                write!(f, "(block ")?;
+                write!(f, "(block")?;
                 for stmt in vec {
-                    write!(f, "({}), ", stmt)?;
+                    write!(f, " {}", stmt)?;
                 }
-                write!(f, ")")?;
-                Ok(())
+                write!(f, ")")
             },
             Statement::Expression(expr) => write!(f, "(expr_stmt {})", expr),
-            Statement::If(cond, then, els) => write!(f, "(if {} then {} else {:?})", cond, then, els),
-            Statement::Print(expr) => write!(f, "(print {})", expr),
-            Statement::Var(name, expr) => write!(f, "(var {} = {:?})", name.lexeme(), expr),
-            Statement::While(cond, stmt) => write!(f, "(while {} do {})", cond, stmt),
+            Statement::Class(name, superclass, mixins, methods, setters) => {
+                write!(f, "(class {}", name.lexeme())?;
+                if let Some(superclass) = superclass {
+                    write!(f, " < {}", superclass.lexeme())?;
+                }
+                for mixin in mixins {
+                    write!(f, " with {}", mixin.lexeme())?;
+                }
+                for method in methods {
+                    write!(f, " {}", method)?;
+                }
+                for setter in setters {
+                    write!(f, " (set {})", setter)?;
+                }
+                write!(f, ")")
+            },
+            Statement::ForIn(_, name, iterable, body, label) => {
+                write!(f, "(for{} {} in {} {})", labeled(label, ":"), name.lexeme(), iterable, body)
+            },
+            Statement::Function(declaration) => write!(f, "{}", declaration),
+            Statement::If(_, cond, then, Some(els)) => write!(f, "(if {} {} {})", cond, then, els),
+            Statement::If(_, cond, then, None) => write!(f, "(if {} {})", cond, then),
+            Statement::Print(_, exprs) => {
+                write!(f, "(print")?;
+                for expr in exprs {
+                    write!(f, " {}", expr)?;
+                }
+                write!(f, ")")
+            },
+            Statement::Return(_, Some(value)) => write!(f, "(return {})", value),
+            Statement::Return(_, None) => write!(f, "(return)"),
+            Statement::Throw(_, value) => write!(f, "(throw {})", value),
+            Statement::Try(_, body, catches, finally) => {
+                write!(f, "(try")?;
+                for stmt in body {
+                    write!(f, " {}", stmt)?;
+                }
+                for (name, class, handler) in catches {
+                    write!(f, " (catch {}", name.lexeme())?;
+                    if let Some(class) = class {
+                        write!(f, ": {}", class.lexeme())?;
+                    }
+                    for stmt in handler {
+                        write!(f, " {}", stmt)?;
+                    }
+                    write!(f, ")")?;
+                }
+                if let Some(finally) = finally {
+                    write!(f, " (finally")?;
+                    for stmt in finally {
+                        write!(f, " {}", stmt)?;
+                    }
+                    write!(f, ")")?;
+                }
+                write!(f, ")")
+            },
+            Statement::Switch(_, subject, cases, default) => {
+                write!(f, "(switch {}", subject)?;
+                for (values, body) in cases {
+                    write!(f, " (case")?;
+                    for value in values {
+                        write!(f, " {}", value)?;
+                    }
+                    write!(f, ":")?;
+                    for stmt in body {
+                        write!(f, " {}", stmt)?;
+                    }
+                    write!(f, ")")?;
+                }
+                if let Some(body) = default {
+                    write!(f, " (default:")?;
+                    for stmt in body {
+                        write!(f, " {}", stmt)?;
+                    }
+                    write!(f, ")")?;
+                }
+                write!(f, ")")
+            },
+            Statement::Var(name, annotation, init) => {
+                write!(f, "(var {}", name.lexeme())?;
+                if let Some(annotation) = annotation {
+                    write!(f, ": {}", annotation)?;
+                }
+                if let Some(init) = init {
+                    write!(f, " {}", init)?;
+                }
+                write!(f, ")")
+            },
+            Statement::Destructure(pattern, init) => write!(f, "(var {} {})", pattern, init),
+            Statement::Const(name, annotation, init) => {
+                write!(f, "(const {}", name.lexeme())?;
+                if let Some(annotation) = annotation {
+                    write!(f, ": {}", annotation)?;
+                }
+                write!(f, " {})", init)
+            },
+            Statement::While(_, cond, stmt, None, label) => write!(f, "(while{} {} {})", labeled(label, ":"), cond, stmt),
+            Statement::While(_, cond, stmt, Some(increment), label) => {
+                write!(f, "(while{} {} {} {})", labeled(label, ":"), cond, stmt, increment)
+            },
+            Statement::Break(_, label) => write!(f, "(break{})", labeled(label, "")),
+            Statement::Continue(_, label) => write!(f, "(continue{})", labeled(label, "")),
         }
     }
-}
\ No newline at end of file
+}
+
+/// The label after a space, followed by `suffix`, or nothing without a label
+fn labeled(label: &Option<Token>, suffix: &str) -> String {
+    label.as_ref().map(|label| format!(" {}{}", label.lexeme(), suffix)).unwrap_or_default()
+}
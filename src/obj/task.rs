@@ -0,0 +1,100 @@
+// External dependencies
+use std::any::Any;
+use std::fmt::{Debug, Display};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+// Internal dependencies
+use crate::errors::Result;
+use super::detached::Detached;
+
+/// What a finished task hands back: the result of its function and
+/// everything it printed
+pub type Outcome = (Result<Detached>, Vec<u8>);
+
+/// Source of the task ids, which are unique within the process
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Handle of a function that runs on its own thread, in its own
+/// interpreter. Created by the `spawn` native and consumed by `join`.
+/// Copies of the handle refer to the same task, which can only be
+/// joined once.
+#[derive(Clone)]
+pub struct Task {
+    id: u64,
+    thread: Arc<Mutex<Option<JoinHandle<Outcome>>>>,
+}
+
+impl Task {
+    /// Starts running `work` on a new thread. Fails with the reason if
+    /// the system cannot start another thread.
+    pub fn spawn(work: impl FnOnce() -> Outcome + Send + 'static) -> std::result::Result<Self, String> {
+        let thread = std::thread::Builder::new()
+            .name("jlox-task".to_string())
+            .spawn(work)
+            .map_err(|e| format!("the system cannot start another thread ({})", e))?;
+        Ok(Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            thread: Arc::new(Mutex::new(Some(thread))),
+        })
+    }
+
+    /// Waits for the task to finish. `None` if it was joined before.
+    /// If the task panicked, the message of the panic is the error.
+    pub fn join(&self) -> Option<std::result::Result<Outcome, String>> {
+        let thread = self.thread.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take()?;
+        Some(thread.join().map_err(|panic| panic_message(panic.as_ref())))
+    }
+}
+
+/// The text a panic was raised with, if it was raised with one
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "the task panicked".to_string(),
+    }
+}
+
+// Tasks are identified by their id, copies of a handle are equal
+impl PartialEq for Task {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Debug for Task {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Task({})", self.id)
+    }
+}
+
+impl Display for Task {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<task {}>", self.id)
+    }
+}
+
+// ---------- Tests for the task module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::value::Value;
+
+    #[test]
+    fn joins_once() {
+        let task = Task::spawn(|| (Ok(Detached::new(&Value::Nil)), b"hi".to_vec())).unwrap();
+        let (result, printed) = task.clone().join().expect("Not joined yet").expect("The task panicked");
+        assert!(result.is_ok());
+        assert_eq!(printed, b"hi");
+        assert!(task.join().is_none());
+    }
+
+    #[test]
+    fn panics_become_errors() {
+        let task = Task::spawn(|| panic!("boom")).unwrap();
+        assert_eq!(task.join().expect("Not joined yet").err().as_deref(), Some("boom"));
+    }
+}
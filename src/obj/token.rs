@@ -7,12 +7,23 @@ use super::token_type::TokenType;
 
 /// A Token is a piece of String that is parsed from the source code.
 /// It gives it it's meaning.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Token {
     token_type: TokenType,
     lexeme: String,
     literal: Option<Value>, // Literals can be hold directly inside the Token
     line: u32,
+    column: u32, // 0 if unknown, for example in loaded chunks
+}
+
+/// Where a token is in the source code, used to point at it in error output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: u32,
+    /// Counted in chars, starting at 1
+    pub column: u32,
+    /// Number of chars
+    pub length: u32,
 }
 
 impl Token {
@@ -27,9 +38,16 @@ impl Token {
             lexeme,
             literal,
             line,
+            column: 0,
         }
     }
 
+    /// Sets the column the token starts at
+    pub fn with_column(mut self, column: u32) -> Self {
+        self.column = column;
+        self
+    }
+
     // Field access functions
     
     pub fn token_type(&self) -> TokenType {
@@ -47,6 +65,30 @@ impl Token {
     pub fn line(&self) -> u32 {
         self.line
     }
+
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+
+    /// The position of the token, `None` if its column is unknown
+    pub fn span(&self) -> Option<Span> {
+        (self.column > 0).then(|| Span {
+            line: self.line,
+            column: self.column,
+            length: self.lexeme.chars().count() as u32,
+        })
+    }
+}
+
+// The column is left out, it only serves error output. Two tokens are the
+// same if they have the same text on the same line.
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.token_type == other.token_type
+            && self.lexeme == other.lexeme
+            && self.literal == other.literal
+            && self.line == other.line
+    }
 }
 
 impl Display for Token {
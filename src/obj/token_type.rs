@@ -5,19 +5,20 @@ use std::fmt::Display;
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     // Single-character tokens.
-    LeftParen, RightParen, LeftBrace, RightBrace,
-    Comma, Dot, Minus, Plus, Semicolon, Slash, Star,
+    LeftParen, RightParen, LeftBrace, RightBrace, LeftBracket, RightBracket,
+    Colon, Comma, Dot, Minus, Plus, Semicolon, Slash, Star,
 
     // One or two character tokens.
     Bang, BangEqual, Equal, EqualEqual,
     Greater, GreaterEqual, Less, LessEqual,
+    TildeSlash, QuestionQuestion, QuestionDot,
 
     // Literals
     Identifier, String, Number,
 
     //Keywords
-    And, Class, Else, False, Fun, For, If, Nil, Or,
-    Print, Return, Super, This, True, Var, While,
+    And, Async, Await, Break, Case, Catch, Class, Const, Continue, Default, Else, False, Finally, Fun, For, If, In, Is, Nil, Or,
+    Print, Return, Super, Switch, This, Throw, True, Try, Var, While, With,
 
     // End of file
     Eof,
@@ -1,14 +1,59 @@
 // External dependencies
+use indexmap::IndexMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 
+// Internal dependencies
+use crate::errors::{Result, ValueError};
+use super::channel::Channel;
+use super::class::{Class, Instance};
+use super::coroutine::Coroutine;
+use super::environment::Environment;
+use super::function::Function;
+use super::future::Future;
+use super::list::List;
+use super::map::{Key, Map};
+use super::native::NativeFunction;
+use super::number::Number;
+use super::task::Task;
+
 /// There are two different literal types: String literals and Number literals.
 /// Those can be represented using the Literal enum.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Value {
     String(String),
-    Number(f64),
+    Number(Number),
+    /// Whole numbers without a fractional part, exact over the whole range of `i64`
+    Int(i64),
     Bool(bool),
     Nil,
+    /// Functions can't be serialized, they only exist at runtime
+    #[cfg_attr(feature = "serde", serde(skip))]
+    NativeFunction(NativeFunction),
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Function(Function),
+    /// Classes and instances can't be serialized, they only exist at runtime
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Class(Class),
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Instance(Instance),
+    /// Lists and maps serialize as their elements and entries, one that
+    /// contains itself fails to
+    List(List),
+    Map(Map),
+    /// Tasks can't be serialized, they only exist at runtime
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Task(Task),
+    /// Channels can't be serialized, they only exist at runtime
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Channel(Channel),
+    /// Coroutines can't be serialized, they only exist at runtime
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Coroutine(Coroutine),
+    /// Futures can't be serialized, they only exist at runtime
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Future(Future),
 }
 
 impl Display for Value {
@@ -16,8 +61,477 @@ impl Display for Value {
         match self {
             Self::String(s) => write!(f, "{}", s), // just the string
             Self::Number(n) => write!(f, "{}", n), // just the number
+            Self::Int(i) => write!(f, "{}", i),    // just the int
             Self::Bool(b) => write!(f, "{}", b),   // just the boolean
             Self::Nil => write!(f, "nil"),         // nil
+            Self::NativeFunction(func) => write!(f, "{}", func),
+            Self::Function(func) => write!(f, "{}", func),
+            Self::Class(class) => write!(f, "{}", class),
+            Self::Instance(instance) => write!(f, "{}", instance),
+            Self::List(list) => write!(f, "{}", list),
+            Self::Map(map) => write!(f, "{}", map),
+            Self::Task(task) => write!(f, "{}", task),
+            Self::Channel(channel) => write!(f, "{}", channel),
+            Self::Coroutine(coroutine) => write!(f, "{}", coroutine),
+            Self::Future(future) => write!(f, "{}", future),
+        }
+    }
+}
+
+/// Every type name `is` knows: the ones [Value::type_name] gives, and
+/// "int", which only ints have
+pub const TYPE_NAMES: [&str; 14] = [
+    "string", "number", "int", "bool", "nil", "function", "class", "instance",
+    "list", "map", "task", "channel", "coroutine", "future",
+];
+
+impl Value {
+    /// Name of the runtime type of the value. Ints are numbers here, only
+    /// [Value::has_type] tells them apart.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::String(_) => "string",
+            Self::Number(_) | Self::Int(_) => "number",
+            Self::Bool(_) => "bool",
+            Self::Nil => "nil",
+            Self::NativeFunction(_) | Self::Function(_) => "function",
+            Self::Class(_) => "class",
+            Self::Instance(_) => "instance",
+            Self::List(_) => "list",
+            Self::Map(_) => "map",
+            Self::Task(_) => "task",
+            Self::Channel(_) => "channel",
+            Self::Coroutine(_) => "coroutine",
+            Self::Future(_) => "future",
+        }
+    }
+
+    /// Whether the value has the type named `name` (see [TYPE_NAMES]),
+    /// ints are numbers too. `None` if no type has the name.
+    pub fn has_type(&self, name: &str) -> Option<bool> {
+        if !TYPE_NAMES.contains(&name) {
+            return None;
+        }
+        Some(self.type_name() == name || (name == "int" && matches!(self, Self::Int(_))))
+    }
+
+    /// The value as a number, an int becomes the closest number.
+    /// `None` if the value is neither.
+    pub fn as_number(&self) -> Option<Number> {
+        match self {
+            Self::Number(n) => Some(*n),
+            Self::Int(i) => Some(Number::from(*i)),
+            _ => None,
         }
     }
-}
\ No newline at end of file
+
+    /// The value as a count of something, like the index of a list or the
+    /// repetitions of a string. `None` if it isn't a whole number or is negative.
+    pub fn to_count(&self) -> Option<usize> {
+        match self {
+            Self::Int(i) => usize::try_from(*i).ok(),
+            Self::Number(n) => n.to_count(),
+            _ => None,
+        }
+    }
+
+    /// Copies the value including everything it refers to, so changing the
+    /// copy never affects the original. Strings, numbers, booleans and nil
+    /// are immutable, so for them a plain clone already is a deep copy.
+    /// Instances get new fields with copies of the values, lists and maps
+    /// get copies of their elements, an instance, list or map that refers
+    /// to itself is copied once. Functions, classes, tasks, channels and
+    /// coroutines aren't copied, the copy is another handle of the same one.
+    /// A copied function still shares the variables it captured,
+    /// [Detached](super::detached::Detached) copies those as well.
+    pub fn deep_copy(&self) -> Value {
+        self.copy_with(&mut HashMap::new())
+    }
+
+    /// [Value::deep_copy], remembering the copy of every instance, list
+    /// and map by the address of its fields or elements
+    fn copy_with(&self, copies: &mut HashMap<usize, Value>) -> Value {
+        match self {
+            Self::Instance(instance) => {
+                if let Some(copy) = copies.get(&instance.fields.address()) {
+                    return copy.clone();
+                }
+                let copy = Instance::new(instance.class.clone());
+                copies.insert(instance.fields.address(), Value::Instance(copy.clone()));
+                let fields: Vec<(String, Value)> = instance.fields.borrow().iter().map(|(n, v)| (n.clone(), v.clone())).collect();
+                for (name, value) in fields {
+                    copy.set_field(name, value.copy_with(copies));
+                }
+                Value::Instance(copy)
+            },
+            Self::List(list) => {
+                if let Some(copy) = copies.get(&list.items.address()) {
+                    return copy.clone();
+                }
+                let copy = List::new(Vec::with_capacity(list.len()));
+                copies.insert(list.items.address(), Value::List(copy.clone()));
+                let items: Vec<Value> = list.items.borrow().clone();
+                let items: Vec<Value> = items.iter().map(|item| item.copy_with(copies)).collect();
+                *copy.items.borrow_mut() = items;
+                Value::List(copy)
+            },
+            Self::Map(map) => {
+                if let Some(copy) = copies.get(&map.entries.address()) {
+                    return copy.clone();
+                }
+                let copy = Map::new(Default::default());
+                copies.insert(map.entries.address(), Value::Map(copy.clone()));
+                let entries: Vec<(Key, Value)> = map.entries.borrow().iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+                let entries = entries.into_iter().map(|(key, value)| (key, value.copy_with(copies))).collect();
+                *copy.entries.borrow_mut() = entries;
+                Value::Map(copy)
+            },
+            _ => self.clone(),
+        }
+    }
+
+    /// Approximate number of bytes the instances, lists and maps the value
+    /// refers to occupy, directly or through each other, each one counted
+    /// once. It's what creating all of them one by one would have accounted.
+    pub fn reachable_size(&self) -> usize {
+        self.size_within(&mut HashSet::new())
+    }
+
+    /// [Value::reachable_size], knowing the addresses of the instances,
+    /// lists and maps that are already counted
+    fn size_within(&self, seen: &mut HashSet<usize>) -> usize {
+        match self {
+            Self::Instance(instance) if seen.insert(instance.fields.address()) => {
+                let fields: Vec<(String, Value)> =
+                    instance.fields.borrow().iter().map(|(n, v)| (n.clone(), v.clone())).collect();
+                fields.iter().map(|(name, value)| Environment::binding_size(name, value) + value.size_within(seen)).sum()
+            },
+            Self::List(list) if seen.insert(list.items.address()) => {
+                let items: Vec<Value> = list.items.borrow().clone();
+                items.iter().map(|item| List::item_size(item) + item.size_within(seen)).sum()
+            },
+            Self::Map(map) if seen.insert(map.entries.address()) => {
+                let entries: Vec<(Key, Value)> = map.entries.borrow().iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+                entries.iter().map(|(key, value)| Map::entry_size(key, value) + value.size_within(seen)).sum()
+            },
+            _ => 0,
+        }
+    }
+
+    /// Whether the value is a list or map that contains itself, directly
+    /// or inside the lists and maps in it. Such a value has no text form
+    /// that can be read back and can't be serialized.
+    pub fn contains_itself(&self) -> bool {
+        self.cyclic_within(&mut Vec::new())
+    }
+
+    /// [Value::contains_itself], knowing the addresses of the lists and maps it is in
+    fn cyclic_within(&self, outer: &mut Vec<usize>) -> bool {
+        let (address, children) = match self {
+            Self::List(list) => (list.items.address(), list.items.borrow().clone()),
+            Self::Map(map) => (map.entries.address(), map.values()),
+            _ => return false,
+        };
+        if outer.contains(&address) {
+            return true;
+        }
+        outer.push(address);
+        let cyclic = children.iter().any(|child| child.cyclic_within(outer));
+        outer.pop();
+        cyclic
+    }
+
+    /// Approximate number of bytes the value owns on the heap,
+    /// not counting the value itself
+    pub fn heap_size(&self) -> usize {
+        match self {
+            Self::String(s) => s.len(),
+            Self::Number(_) | Self::Int(_) | Self::Bool(_) | Self::Nil | Self::NativeFunction(_) | Self::Function(_) => 0,
+            // Handles of shared objects, their contents are counted when they are set
+            Self::Class(_) | Self::Instance(_) | Self::List(_) | Self::Map(_) => 0,
+            Self::Task(_) | Self::Channel(_) | Self::Coroutine(_) | Self::Future(_) => 0,
+        }
+    }
+
+    /// Canonical text form of the value. Unlike [Display], it can be read
+    /// back with [Value::from_text] without losing information: strings are
+    /// quoted and escaped, numbers keep every digit (see [Number::to_text]).
+    /// The text never contains a line break. Lists and maps are written like
+    /// their literal with the text form of every element, and can be read
+    /// back unless they contain themselves. Functions, classes, instances, tasks, channels, coroutines and futures have no literal
+    /// form, their text is only informative and can't be read back.
+    pub fn to_text(&self) -> String {
+        match self {
+            Self::String(s) => {
+                let mut text = String::from("\"");
+                for c in s.chars() {
+                    match c {
+                        '"' => text.push_str("\\\""),
+                        '\\' => text.push_str("\\\\"),
+                        '\n' => text.push_str("\\n"),
+                        '\r' => text.push_str("\\r"),
+                        '\t' => text.push_str("\\t"),
+                        _ => text.push(c),
+                    }
+                }
+                text.push('"');
+                text
+            }
+            Self::Number(n) => n.to_text(),
+            Self::Int(i) => i.to_string(),
+            Self::Bool(b) => b.to_string(),
+            Self::Nil => "nil".to_string(),
+            Self::NativeFunction(func) => func.to_string(),
+            Self::Function(func) => func.to_string(),
+            Self::Class(class) => class.to_string(),
+            Self::Instance(instance) => instance.to_string(),
+            Self::List(list) => list.text(true),
+            Self::Map(map) => map.text(true),
+            Self::Task(task) => task.to_string(),
+            Self::Channel(channel) => channel.to_string(),
+            Self::Coroutine(coroutine) => coroutine.to_string(),
+            Self::Future(future) => future.to_string(),
+        }
+    }
+
+    /// The value as an element of a list or map that is printed, see
+    /// [List::text]. `outer` are the addresses of the lists and maps it is in.
+    pub(super) fn nested_text(&self, canonical: bool, outer: &mut Vec<usize>) -> String {
+        match self {
+            Self::List(list) => list.text_within(canonical, outer),
+            Self::Map(map) => map.text_within(canonical, outer),
+            Self::String(_) => self.to_text(),
+            _ if canonical => self.to_text(),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Reads a value from its canonical text form (see [Value::to_text])
+    pub fn from_text(text: &str) -> Result<Self> {
+        let mut reader = TextReader { rest: text };
+        match reader.value() {
+            Some(value) if reader.rest.trim().is_empty() => Ok(value),
+            _ => Err(ValueError::InvalidText(text.to_string()).into()),
+        }
+    }
+}
+
+/// Reads the values of a text form one after the other, see [Value::from_text]
+struct TextReader<'a> {
+    /// The text after the values read so far
+    rest: &'a str,
+}
+
+impl TextReader<'_> {
+    /// The next value, `None` if the text doesn't continue with one
+    fn value(&mut self) -> Option<Value> {
+        self.rest = self.rest.trim_start();
+        if self.eat('[') {
+            let mut items = Vec::new();
+            if !self.eat(']') {
+                loop {
+                    items.push(self.value()?);
+                    if self.eat(']') {
+                        break;
+                    }
+                    if !self.eat(',') {
+                        return None;
+                    }
+                }
+            }
+            return Some(Value::List(List::new(items)));
+        }
+        if self.eat('{') {
+            let mut entries = IndexMap::new();
+            if !self.eat('}') {
+                loop {
+                    let key = Key::from_value(&self.value()?)?;
+                    if !self.eat(':') {
+                        return None;
+                    }
+                    entries.insert(key, self.value()?);
+                    if self.eat('}') {
+                        break;
+                    }
+                    if !self.eat(',') {
+                        return None;
+                    }
+                }
+            }
+            return Some(Value::Map(Map::new(entries)));
+        }
+
+        let end = match self.rest.strip_prefix('"') {
+            Some(inner) => 1 + string_length(inner)?,
+            None => self.rest.find([',', ']', '}', ':']).unwrap_or(self.rest.len()),
+        };
+        let (scalar, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        scalar_from_text(scalar.trim_end())
+    }
+
+    /// Skips the character if the text continues with it, after whitespace
+    fn eat(&mut self, c: char) -> bool {
+        match self.rest.trim_start().strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// The length of the rest of a string in text form up to and including its
+/// closing quote, `None` if it has none
+fn string_length(inner: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in inner.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(index + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Reads a string, number, int, boolean or nil from its text form
+fn scalar_from_text(text: &str) -> Option<Value> {
+    match text {
+        "nil" => return Some(Value::Nil),
+        "true" => return Some(Value::Bool(true)),
+        "false" => return Some(Value::Bool(false)),
+        _ => {}
+    }
+
+    if let Some(inner) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+        let mut s = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => s.push(match chars.next() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    _ => return None,
+                }),
+                '"' => return None, // Unescaped quote inside the string
+                _ => s.push(c),
+            }
+        }
+        return Some(Value::String(s));
+    }
+
+    if let Ok(int) = text.parse::<i64>() {
+        return Some(Value::Int(int));
+    }
+    text.parse::<Number>().ok().map(Value::Number)
+}
+
+// ---------- Tests for the value module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reachable_size_counts_shared_lists_once() {
+        let inner = List::new(vec![Value::String("text".to_string())]);
+        let outer = List::new(vec![Value::List(inner.clone()), Value::List(inner.clone())]);
+        outer.items.borrow_mut().push(Value::List(outer.clone()));
+        let expected = 3 * List::item_size(&Value::Nil) + List::item_size(&Value::String("text".to_string()));
+        assert_eq!(Value::List(outer).reachable_size(), expected);
+    }
+
+    #[test]
+    fn text_form_round_trips() {
+        let values = [
+            Value::Nil,
+            Value::Bool(false),
+            Value::Number(Number::from(1)),
+            Value::Number("-0.1".parse().expect("Invalid number!")),
+            Value::Int(-7),
+            Value::Int(i64::MAX),
+            Value::String("say \"hi\"\\\n\tbye".to_string()),
+        ];
+        for value in values {
+            let text = value.to_text();
+            assert!(!text.contains('\n'));
+            assert_eq!(Value::from_text(&text).expect("Reading text failed!"), value);
+        }
+    }
+
+    #[test]
+    fn lists_round_trip_as_text() {
+        for text in ["[]", "[1, [2.5, \"a, ]\"], [], nil]", "[[[true]]]"] {
+            let value = Value::from_text(text).expect("Reading text failed!");
+            assert!(matches!(value, Value::List(_)));
+            assert_eq!(value.to_text(), text);
+        }
+
+        let list = List::new(vec![Value::Int(1)]);
+        list.items.borrow_mut().push(Value::List(list.clone()));
+        let value = Value::List(list);
+        assert!(value.contains_itself());
+        assert!(Value::from_text(&value.to_text()).is_err());
+    }
+
+    #[test]
+    fn maps_round_trip_as_text() {
+        for text in ["{}", "{\"a: b\": {1: [nil]}, 2.5: true, nil: {}}", "[{false: \"}\"}]"] {
+            let value = Value::from_text(text).expect("Reading text failed!");
+            assert_eq!(value.to_text(), text);
+        }
+        for text in ["{1}", "{1: 2", "{[]: 1}", "{1: 2,}"] {
+            assert!(Value::from_text(text).is_err(), "{} was accepted", text);
+        }
+
+        let map = Map::new(IndexMap::new());
+        map.insert(Key::Int(1), Value::Map(map.clone()));
+        assert!(Value::Map(map.clone()).contains_itself());
+        assert!(Value::from_text(&Value::Map(map).to_text()).is_err());
+    }
+
+    #[test]
+    fn ints_have_the_number_type_too() {
+        assert_eq!(Value::Int(1).has_type("int"), Some(true));
+        assert_eq!(Value::Int(1).has_type("number"), Some(true));
+        assert_eq!(Value::Number(Number::from(1)).has_type("int"), Some(false));
+        assert_eq!(Value::Nil.has_type("nil"), Some(true));
+        assert_eq!(Value::Nil.has_type("null"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_text() {
+        for text in ["", "nul", "\"open", "\"bad \\q escape\"", "\"a\"b\"", "[1, 2", "[1 2]", "[1,]", "[1]]"] {
+            assert!(Value::from_text(text).is_err(), "{} was accepted", text);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let value = Value::String("lox".to_string());
+        let json = serde_json::to_string(&value).expect("Serializing failed!");
+        assert_eq!(serde_json::from_str::<Value>(&json).expect("Deserializing failed!"), value);
+
+        let list = Value::List(List::new(vec![Value::Int(1), Value::List(List::new(Vec::new()))]));
+        let json = serde_json::to_string(&list).expect("Serializing failed!");
+        assert_eq!(serde_json::from_str::<Value>(&json).expect("Deserializing failed!").to_text(), list.to_text());
+
+        let map = Value::from_text("{\"a\": [1], 2: {nil: false}}").expect("Reading text failed!");
+        let json = serde_json::to_string(&map).expect("Serializing failed!");
+        assert_eq!(serde_json::from_str::<Value>(&json).expect("Deserializing failed!").to_text(), map.to_text());
+
+        let Value::List(inner) = &list else { unreachable!() };
+        inner.items.borrow_mut().push(map.clone());
+        let Value::Map(entries) = &map else { unreachable!() };
+        entries.insert(Key::Nil, list.clone());
+        assert!(serde_json::to_string(&list).is_err());
+        assert!(serde_json::to_string(&map).is_err());
+    }
+}
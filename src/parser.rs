@@ -1,28 +1,38 @@
 // External dependencies
-use anyhow::Result;
+use std::collections::HashMap;
 
 // Internal dependencies
-use crate::errors::ParseError;
-use crate::obj::expression::Expression;
+use crate::diagnostic::{Diagnostic, Reporter};
+use crate::errors::{LoxError, ParseError, Result};
+use crate::obj::annotation::TypeAnnotation;
+use crate::obj::expression::{Expression, Pattern};
+use crate::obj::function::{FunctionDeclaration, Parameter};
 use crate::obj::statement::Statement;
 use crate::obj::token::Token;
 use crate::obj::token_type::TokenType;
 use crate::obj::value::Value;
+use crate::shared::SharedRef;
 
 /// The only public function of the parser module that is the interface
 /// between the main module (or some other higher level module) and the
 /// whole parsing process. It takes in a collection of tokens and spits
 /// out an Expression, that represents the AST formed by the tokens.
-pub fn parse(tokens: Vec<Token>) -> Result<Vec<Statement>> {
+/// Every error along the way is handed to the `reporter`.
+pub fn parse(tokens: Vec<Token>, reporter: &mut dyn Reporter) -> Result<Vec<Statement>> {
     let mut had_error = false;
 
     let mut parser = Parser::new(tokens);
     let mut statements: Vec<Statement> = Vec::new();
     while !parser.is_at_end() {
-        match parser.declaration() {
+        let result = parser.declaration();
+        for e in parser.errors.drain(..) {
+            reporter.report(Diagnostic::error(e));
+            had_error = true;
+        }
+        match result {
             Ok(stmt) => statements.push(stmt),
             Err(e) => {
-                eprintln!("{}", e);
+                reporter.report(Diagnostic::error(e));
                 had_error = true;
                 parser.synchronize()?;  // When we had an error, we synchronize so we can
             }                           // report more errors after one occurred
@@ -36,56 +46,289 @@ pub fn parse(tokens: Vec<Token>) -> Result<Vec<Statement>> {
     }
 }
 
+/// Parses a single expression, like `a + 1`, instead of a whole program.
+/// Used by tools that evaluate expressions on their own, for example the
+/// `:type` command of the REPL. Errors are handed to the `reporter`.
+pub fn parse_expression(tokens: Vec<Token>, reporter: &mut dyn Reporter) -> Result<Expression> {
+    let mut parser = Parser::new(tokens);
+    let result = parser.expression().and_then(|expr| {
+        if parser.is_at_end() {
+            Ok(expr)
+        } else {
+            Err(ParseError::TrailingTokens(parser.peek()?.line()).into())
+        }
+    });
+
+    let mut errors: Vec<LoxError> = parser.errors.drain(..).collect();
+    match result {
+        Ok(expr) if errors.is_empty() => Ok(expr),
+        result => {
+            errors.extend(result.err());
+            for e in errors {
+                reporter.report(Diagnostic::error(e));
+            }
+            Err(ParseError::HadError.into())
+        }
+    }
+}
+
+/// How deeply expressions and statements can nest. Every level is a few
+/// recursive calls, the limit keeps hostile input from overflowing the stack.
+pub(crate) const MAX_DEPTH: usize = 128;
+
+/// The methods and the setters of a class body
+type Members = (Vec<SharedRef<FunctionDeclaration>>, Vec<SharedRef<FunctionDeclaration>>);
+
 /// The Parser is a contraption that holds a collection of
 /// Tokens, traverses through them one by one and returns an
 /// AST of expressions.
 struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    depth: usize,     // How many nested expressions and statements are being parsed
+    functions: usize, // How many function bodies enclose the current token, `return` needs one
+    classes: Vec<bool>, // Whether each class body around the current token has a superclass, `this` and `super` need one
+    initializer: bool,  // Whether the innermost function is an `init` method, which cannot return a value
+    loops: Vec<Option<String>>, // Labels of the loop bodies of the innermost function around the current token
+    value_blocks: usize, // How many blocks of `if` expressions in the innermost function enclose the current token
+    /// The names declared in every scope around the current token, innermost
+    /// last, and whether they are constants. Assigning a constant declared in
+    /// the same source is an error right away, other constants are checked at runtime.
+    scopes: Vec<HashMap<String, bool>>,
+    /// Errors that don't stop the parser, because the code around them is
+    /// still well formed. They get reported once the declaration is parsed.
+    errors: Vec<LoxError>,
 }
 /// A recursive descent parser that parses lox tokens
 /// into an AST that can then be walked.
 impl Parser {
     fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+        Parser {
+            tokens,
+            current: 0,
+            depth: 0,
+            functions: 0,
+            classes: Vec::new(),
+            initializer: false,
+            loops: Vec::new(),
+            value_blocks: 0,
+            scopes: vec![HashMap::new()],
+            errors: Vec::new(),
+        }
     }
 
     fn declaration(&mut self) -> Result<Statement> {
         if self.match_token_types([TokenType::Var])? {
             self.var_declaration()
+        } else if self.match_token_types([TokenType::Const])? {
+            self.const_declaration()
+        } else if self.check(TokenType::Fun)? && self.next_is(TokenType::Identifier) {
+            self.advance()?;
+            self.nested(|parser| parser.function_declaration(false)) // Bodies contain declarations, so they count as nesting
+        } else if self.check(TokenType::Async)? && self.tokens.get(self.current + 2).is_some_and(|token| token.token_type() == TokenType::Identifier) {
+            let keyword = self.advance()?;
+            self.consume(TokenType::Fun, ParseError::ExpectedFunAfterAsync(keyword.line()))?;
+            self.nested(|parser| parser.function_declaration(true))
+        } else if self.match_token_types([TokenType::Class])? {
+            self.nested(Self::class_declaration)
         } else {
             self.statement()
         }
     }
 
     fn var_declaration(&mut self) -> Result<Statement> {
+        if self.match_token_types([TokenType::LeftBracket, TokenType::LeftBrace])? {
+            let pattern = self.pattern()?;
+            let line = pattern.token().line();
+            if !self.match_token_types([TokenType::Equal])? {
+                return Err(ParseError::PatternWithoutInitializer(line).into());
+            }
+            let initializer = self.expression()?;
+            self.consume(TokenType::Semicolon, ParseError::UnterminatedVarDeclaration(line))?;
+            for name in pattern.names() {
+                self.declare(name, false);
+            }
+            return Ok(Statement::Destructure(pattern, initializer));
+        }
         let name = self.consume(TokenType::Identifier, ParseError::ExpectedIdentifier(self.previous()?.line()))?;
+        let annotation = self.type_annotation()?;
         let initializer: Option<Expression> = if self.match_token_types([TokenType::Equal])? {
             Some(self.expression()?)
         } else {
             None
         };
         self.consume(TokenType::Semicolon, ParseError::UnterminatedVarDeclaration(name.line()))?;
-        Ok(Statement::Var(name, initializer))
+        self.declare(&name, false); // The initializer still sees the outer variable of the same name
+        Ok(Statement::Var(name, annotation, initializer))
     }
 
-    fn statement(&mut self) -> Result<Statement> {
-        if self.match_token_types([TokenType::Print])? {
-            self.print_statement()
-        } else if self.match_token_types([TokenType::While])? {
-            self.while_statement()
-        } else if self.match_token_types([TokenType::For])? {
-            self.for_statement()
-        } else if self.match_token_types([TokenType::LeftBrace])? {
-            Ok(Statement::Block(self.block()?))
-        } else if self.match_token_types([TokenType::If])? {
-            self.if_statement()
+    fn const_declaration(&mut self) -> Result<Statement> {
+        let name = self.consume(TokenType::Identifier, ParseError::ExpectedIdentifier(self.previous()?.line()))?;
+        let annotation = self.type_annotation()?;
+        if !self.match_token_types([TokenType::Equal])? {
+            return Err(ParseError::ConstWithoutInitializer(name.lexeme(), name.line()).into());
+        }
+        let initializer = self.expression()?;
+        self.consume(TokenType::Semicolon, ParseError::UnterminatedVarDeclaration(name.line()))?;
+        self.declare(&name, true);
+        Ok(Statement::Const(name, annotation, initializer))
+    }
+
+    fn function_declaration(&mut self, is_async: bool) -> Result<Statement> {
+        let name = self.consume(TokenType::Identifier, ParseError::ExpectedIdentifier(self.previous()?.line()))?;
+        self.declare(&name, false); // Before the body, which may refer to the function
+        let mut declaration = self.parameters_and_body(name, false)?;
+        declaration.is_async = is_async;
+        Ok(Statement::Function(SharedRef::new(declaration)))
+    }
+
+    fn class_declaration(&mut self) -> Result<Statement> {
+        let name = self.consume(TokenType::Identifier, ParseError::ExpectedIdentifier(self.previous()?.line()))?;
+        let superclass = if self.match_token_types([TokenType::Less])? {
+            let superclass = self.consume(TokenType::Identifier, ParseError::ExpectedSuperclassName(name.line()))?;
+            if superclass.lexeme() == name.lexeme() {
+                return Err(ParseError::InheritsFromItself(name.lexeme(), name.line()).into());
+            }
+            Some(superclass)
         } else {
-            self.expression_statement()
+            None
+        };
+        let mut mixins = Vec::new();
+        if self.match_token_types([TokenType::With])? {
+            loop {
+                mixins.push(self.consume(TokenType::Identifier, ParseError::ExpectedMixinName(name.line()))?);
+                if !self.match_token_types([TokenType::Comma])? {
+                    break;
+                }
+            }
         }
+        self.consume(TokenType::LeftBrace, ParseError::ExpectedClassBody(name.line()))?;
+        self.declare(&name, false);
+        self.classes.push(superclass.is_some());
+        let members = self.members();
+        self.classes.pop();
+        let (methods, setters) = members?;
+        self.consume(TokenType::RightBrace, ParseError::UnterminatedClassBody(name.line()))?;
+        Ok(Statement::Class(name, superclass, mixins, methods, setters))
+    }
+
+    /// Parses the methods and setters of a class body up to its closing brace
+    fn members(&mut self) -> Result<Members> {
+        let mut methods = Vec::new();
+        let mut setters = Vec::new();
+        while !self.check(TokenType::RightBrace)? && !self.is_at_end() {
+            // `set` is only a keyword in front of a setter name, methods can still be called set
+            if self.check(TokenType::Identifier)? && self.peek()?.lexeme() == "set" && self.next_is(TokenType::Identifier) {
+                self.advance()?;
+                let setter = self.function(false)?;
+                if setter.params.len() != 1 {
+                    return Err(ParseError::SetterArity(setter.name.lexeme(), setter.name.line()).into());
+                }
+                setters.push(SharedRef::new(setter));
+            } else {
+                methods.push(SharedRef::new(self.function(true)?)); // Methods are declared like functions, without `fun`
+            }
+        }
+        Ok((methods, setters))
+    }
+
+    /// Parses a function or method from its name to the end of its body
+    fn function(&mut self, method: bool) -> Result<FunctionDeclaration> {
+        let name = self.consume(TokenType::Identifier, ParseError::ExpectedIdentifier(self.previous()?.line()))?;
+        self.parameters_and_body(name, method)
+    }
+
+    /// Parses an anonymous function after its `fun` keyword, which stands
+    /// in for the name
+    fn lambda(&mut self) -> Result<Expression> {
+        let keyword = self.previous()?;
+        Ok(Expression::Lambda(SharedRef::new(self.parameters_and_body(keyword, false)?)))
+    }
+
+    /// Parses an anonymous function after its `async` keyword
+    fn async_lambda(&mut self) -> Result<Expression> {
+        let line = self.previous()?.line();
+        let keyword = self.consume(TokenType::Fun, ParseError::ExpectedFunAfterAsync(line))?;
+        let mut declaration = self.parameters_and_body(keyword, false)?;
+        declaration.is_async = true;
+        Ok(Expression::Lambda(SharedRef::new(declaration)))
+    }
+
+    /// Parses the parameter list and body of a function named `name`
+    fn parameters_and_body(&mut self, name: Token, method: bool) -> Result<FunctionDeclaration> {
+        self.consume(TokenType::LeftParen, ParseError::ExprectedLeftParen(self.previous()?.line()))?;
+        let mut params = Vec::new();
+        if !self.check(TokenType::RightParen)? {
+            loop {
+                let name = self.consume(TokenType::Identifier, ParseError::ExpectedIdentifier(self.previous()?.line()))?;
+                let annotation = self.type_annotation()?;
+                params.push(Parameter { name, annotation });
+                if !self.match_token_types([TokenType::Comma])? {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, ParseError::ExpectedRightParen(self.previous()?.line()))?;
+        let return_type = self.type_annotation()?;
+        self.consume(TokenType::LeftBrace, ParseError::ExpectedFunctionBody(name.line()))?;
+
+        self.functions += 1;
+        let initializer = std::mem::replace(&mut self.initializer, method && name.lexeme() == "init");
+        let loops = std::mem::take(&mut self.loops); // A function body cannot leave the loop it is declared in
+        let value_blocks = std::mem::take(&mut self.value_blocks);
+        let body = self.scoped(|parser| {
+            params.iter().for_each(|param| parser.declare(&param.name, false));
+            parser.block()
+        });
+        self.value_blocks = value_blocks;
+        self.loops = loops;
+        self.initializer = initializer;
+        self.functions -= 1;
+        Ok(FunctionDeclaration { name, is_async: false, params, return_type, body: body? })
+    }
+
+    /// Parses an optional `: Type` after a name
+    fn type_annotation(&mut self) -> Result<Option<TypeAnnotation>> {
+        if !self.match_token_types([TokenType::Colon])? {
+            return Ok(None);
+        }
+        let name = self.consume(TokenType::Identifier, ParseError::ExpectedTypeName(self.previous()?.line()))?;
+        Ok(Some(TypeAnnotation::new(name)))
+    }
+
+    fn statement(&mut self) -> Result<Statement> {
+        self.nested(|parser| {
+            if parser.match_token_types([TokenType::Print])? {
+                parser.print_statement()
+            } else if parser.match_token_types([TokenType::While])? {
+                parser.while_statement(None)
+            } else if parser.match_token_types([TokenType::For])? {
+                parser.for_statement(None)
+            } else if parser.check(TokenType::Identifier)? && parser.next_is(TokenType::Colon) {
+                parser.labeled_statement()
+            } else if parser.check(TokenType::LeftBrace)? && !parser.map_pattern_ahead() {
+                parser.advance()?;
+                Ok(Statement::Block(parser.block()?))
+            } else if parser.match_token_types([TokenType::If])? {
+                parser.if_statement()
+            } else if parser.match_token_types([TokenType::Return])? {
+                parser.return_statement()
+            } else if parser.match_token_types([TokenType::Break, TokenType::Continue])? {
+                parser.jump_statement()
+            } else if parser.match_token_types([TokenType::Switch])? {
+                parser.switch_statement()
+            } else if parser.match_token_types([TokenType::Throw])? {
+                parser.throw_statement()
+            } else if parser.match_token_types([TokenType::Try])? {
+                parser.try_statement()
+            } else {
+                parser.expression_statement()
+            }
+        })
     }
 
     fn if_statement(&mut self) -> Result<Statement> {
+        let keyword = self.previous()?;
         self.consume(TokenType::LeftParen, ParseError::ExprectedLeftParen(self.previous()?.line()))?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, ParseError::ExpectedRightParen(self.previous()?.line()))?;
@@ -98,16 +341,162 @@ impl Parser {
         };
 
         if let Some(stmt) = else_branch {
-            Ok(Statement::If(condition, Box::new(then_branch), Some(Box::new(stmt))))
+            Ok(Statement::If(keyword, condition, Box::new(then_branch), Some(Box::new(stmt))))
         } else {
-            Ok(Statement::If(condition, Box::new(then_branch), None))
+            Ok(Statement::If(keyword, condition, Box::new(then_branch), None))
         }
     }
 
     fn print_statement(&mut self) -> Result<Statement> {
-        let expr = self.expression()?;
+        let keyword = self.previous()?;
+        let mut exprs = vec![self.expression()?];
+        while self.match_token_types([TokenType::Comma])? { // print a, b, c;
+            exprs.push(self.expression()?);
+        }
         self.consume(TokenType::Semicolon, ParseError::UnterminatedPrintStatement(self.previous()?.line()))?;
-        Ok(Statement::Print(expr))
+        Ok(Statement::Print(keyword, exprs))
+    }
+
+    /// Parses a `break` or `continue` with an optional label after the keyword
+    fn jump_statement(&mut self) -> Result<Statement> {
+        let keyword = self.previous()?;
+        let label = if self.check(TokenType::Identifier)? { Some(self.advance()?) } else { None };
+        self.consume(TokenType::Semicolon, ParseError::ExpectedSemicolon(keyword.line()))?;
+        let breaks = keyword.token_type() == TokenType::Break;
+        let statement = if breaks { "break" } else { "continue" };
+        let unknown = label.as_ref().is_some_and(|label| !self.loops.contains(&Some(label.lexeme())));
+        if (self.loops.is_empty() || unknown) && self.value_blocks > 0 {
+            return Err(ParseError::JumpOutOfValueBlock(statement, keyword.line()).into());
+        }
+        if self.loops.is_empty() {
+            return Err(match breaks {
+                true => ParseError::BreakOutsideLoop(keyword.line()),
+                false => ParseError::ContinueOutsideLoop(keyword.line()),
+            }
+            .into());
+        }
+        if let Some(label) = label.as_ref().filter(|_| unknown) {
+            return Err(ParseError::UndefinedLabel(statement, label.lexeme(), label.line()).into());
+        }
+        Ok(if breaks { Statement::Break(keyword, label) } else { Statement::Continue(keyword, label) })
+    }
+
+    /// Parses a `label: while (...)` or `label: for (...)` loop
+    fn labeled_statement(&mut self) -> Result<Statement> {
+        let label = self.advance()?;
+        self.advance()?; // The `:`, which was looked ahead at
+        if self.loops.contains(&Some(label.lexeme())) {
+            return Err(ParseError::DuplicateLabel(label.lexeme(), label.line()).into());
+        }
+        if self.match_token_types([TokenType::While])? {
+            self.while_statement(Some(label))
+        } else if self.match_token_types([TokenType::For])? {
+            self.for_statement(Some(label))
+        } else {
+            Err(ParseError::LabelWithoutLoop(label.lexeme(), label.line()).into())
+        }
+    }
+
+    fn switch_statement(&mut self) -> Result<Statement> {
+        let keyword = self.previous()?;
+        self.consume(TokenType::LeftParen, ParseError::ExprectedLeftParen(keyword.line()))?;
+        let subject = self.expression()?;
+        self.consume(TokenType::RightParen, ParseError::ExpectedRightParen(self.previous()?.line()))?;
+        self.consume(TokenType::LeftBrace, ParseError::ExpectedSwitchBody(self.previous()?.line()))?;
+
+        let mut cases = Vec::new();
+        let mut default = None;
+        while !self.check(TokenType::RightBrace)? && !self.is_at_end() {
+            if default.is_some() {
+                return Err(ParseError::DefaultNotLast(self.peek()?.line()).into());
+            } else if self.match_token_types([TokenType::Case])? {
+                let mut values = vec![self.expression()?];
+                while self.match_token_types([TokenType::Comma])? {
+                    values.push(self.expression()?);
+                }
+                self.consume(TokenType::Colon, ParseError::ExpectedColonAfterCase(self.previous()?.line()))?;
+                cases.push((values, self.case_body()?));
+            } else if self.match_token_types([TokenType::Default])? {
+                self.consume(TokenType::Colon, ParseError::ExpectedColonAfterCase(self.previous()?.line()))?;
+                default = Some(self.case_body()?);
+            } else {
+                return Err(ParseError::ExpectedCase(self.peek()?.line()).into());
+            }
+        }
+        self.consume(TokenType::RightBrace, ParseError::UnterminatedBlock(self.previous()?.line()))?;
+
+        Ok(Statement::Switch(keyword, subject, cases, default))
+    }
+
+    /// Parses the statements of a switch branch, up to the next branch or
+    /// the end of the switch
+    fn case_body(&mut self) -> Result<Vec<Statement>> {
+        self.scoped(|parser| {
+            let mut statements = Vec::new();
+            while !(parser.check(TokenType::Case)? || parser.check(TokenType::Default)? || parser.check(TokenType::RightBrace)? || parser.is_at_end()) {
+                statements.push(parser.declaration()?);
+            }
+            Ok(statements)
+        })
+    }
+
+    fn throw_statement(&mut self) -> Result<Statement> {
+        let keyword = self.previous()?;
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, ParseError::ExpectedSemicolon(self.previous()?.line()))?;
+        Ok(Statement::Throw(keyword, value))
+    }
+
+    fn try_statement(&mut self) -> Result<Statement> {
+        let keyword = self.previous()?;
+        self.consume(TokenType::LeftBrace, ParseError::ExpectedBlock("try", keyword.line()))?;
+        let body = self.block()?;
+
+        let mut catches = Vec::new();
+        while self.match_token_types([TokenType::Catch])? {
+            let line = self.previous()?.line();
+            self.consume(TokenType::LeftParen, ParseError::ExpectedCatchVariable(line))?;
+            let name = self.consume(TokenType::Identifier, ParseError::ExpectedCatchVariable(line))?;
+            let class = if self.match_token_types([TokenType::Colon])? {
+                Some(self.consume(TokenType::Identifier, ParseError::ExpectedCatchClass(name.line()))?)
+            } else {
+                None
+            };
+            self.consume(TokenType::RightParen, ParseError::ExpectedRightParen(name.line()))?;
+            self.consume(TokenType::LeftBrace, ParseError::ExpectedBlock("catch", line))?;
+            let handler = self.scoped(|parser| {
+                parser.declare(&name, false);
+                parser.block()
+            })?;
+            catches.push((name, class, handler));
+        }
+        let finally = if self.match_token_types([TokenType::Finally])? {
+            self.consume(TokenType::LeftBrace, ParseError::ExpectedBlock("finally", self.previous()?.line()))?;
+            Some(self.block()?)
+        } else {
+            None
+        };
+
+        if catches.is_empty() && finally.is_none() {
+            return Err(ParseError::TryWithoutHandler(keyword.line()).into());
+        }
+        Ok(Statement::Try(keyword, body, catches, finally))
+    }
+
+    fn return_statement(&mut self) -> Result<Statement> {
+        let keyword = self.previous()?;
+        if self.functions == 0 {
+            return Err(ParseError::ReturnOutsideFunction(keyword.line()).into());
+        }
+        if self.value_blocks > 0 {
+            return Err(ParseError::JumpOutOfValueBlock("return", keyword.line()).into());
+        }
+        let value = if self.check(TokenType::Semicolon)? { None } else { Some(self.expression()?) };
+        self.consume(TokenType::Semicolon, ParseError::ExpectedSemicolon(self.previous()?.line()))?;
+        if self.initializer && value.is_some() {
+            return Err(ParseError::ReturnValueFromInitializer(keyword.line()).into());
+        }
+        Ok(Statement::Return(keyword, value))
     }
 
     fn expression_statement(&mut self) -> Result<Statement> {
@@ -119,31 +508,148 @@ impl Parser {
         Ok(Statement::Expression(expr))
     }
 
-    fn block(&mut self) -> Result<Vec<Statement>> {
-        let mut statements: Vec<Statement> = Vec::new();
+    /// Parses an `if` in the place of a value after its keyword. Both branches
+    /// are blocks, an `else` can also be followed by another `if`.
+    fn if_expression(&mut self) -> Result<Expression> {
+        let keyword = self.previous()?;
+        self.consume(TokenType::LeftParen, ParseError::ExprectedLeftParen(self.previous()?.line()))?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, ParseError::ExpectedRightParen(self.previous()?.line()))?;
+        let then_branch = self.value_block("if")?;
+        let else_branch = if !self.match_token_types([TokenType::Else])? {
+            None
+        } else if self.match_token_types([TokenType::If])? {
+            Some(self.nested(Self::if_expression)?)
+        } else {
+            Some(self.value_block("else")?)
+        };
+        Ok(Expression::If(keyword, Box::new(condition), Box::new(then_branch), else_branch.map(Box::new)))
+    }
 
-        while !self.check(TokenType::RightBrace)? && !self.is_at_end() {
-            statements.push(self.declaration()?);
+    /// Parses the block of an `if` expression. An expression at its end
+    /// without a semicolon is its value. Nothing can return, break or continue
+    /// out of it, it has to end with a value.
+    fn value_block(&mut self, keyword: &'static str) -> Result<Expression> {
+        let brace = self.consume(TokenType::LeftBrace, ParseError::ExpectedBlock(keyword, self.previous()?.line()))?;
+        let loops = std::mem::take(&mut self.loops);
+        self.value_blocks += 1;
+        let block = self.scoped(|parser| {
+            let mut statements = Vec::new();
+            let mut value = None;
+            while !parser.check(TokenType::RightBrace)? && !parser.is_at_end() {
+                if !parser.expression_statement_ahead() {
+                    statements.push(parser.declaration()?);
+                    continue;
+                }
+                let expr = parser.expression()?;
+                if parser.check(TokenType::RightBrace)? {
+                    value = Some(Box::new(expr));
+                } else {
+                    parser.consume(
+                        TokenType::Semicolon,
+                        ParseError::UnterminatedExpressionStatement(parser.previous()?.line()),
+                    )?;
+                    statements.push(Statement::Expression(expr));
+                }
+            }
+            parser.consume(TokenType::RightBrace, ParseError::UnterminatedBlock(parser.previous()?.line()))?;
+            Ok((statements, value))
+        });
+        self.value_blocks -= 1;
+        self.loops = loops;
+        let (statements, value) = block?;
+        Ok(Expression::Block(brace, statements, value))
+    }
+
+    /// Whether the statement at the current token is an expression statement,
+    /// see [Parser::statement]. An `if` there starts a statement, not a value.
+    fn expression_statement_ahead(&self) -> bool {
+        let Some(token) = self.tokens.get(self.current) else {
+            return false;
+        };
+        match token.token_type() {
+            TokenType::Var
+            | TokenType::Const
+            | TokenType::Class
+            | TokenType::Print
+            | TokenType::While
+            | TokenType::For
+            | TokenType::If
+            | TokenType::Return
+            | TokenType::Break
+            | TokenType::Continue
+            | TokenType::Switch
+            | TokenType::Throw
+            | TokenType::Try => false,
+            TokenType::Fun => !self.next_is(TokenType::Identifier),
+            TokenType::LeftBrace => self.map_pattern_ahead(),
+            TokenType::Identifier => !self.next_is(TokenType::Colon),
+            _ => true,
         }
+    }
 
-        self.consume(TokenType::RightBrace, ParseError::UnterminatedBlock(self.previous()?.line()))?;
+    /// Parses the statements of a block after its opening brace, the block is a scope of its own
+    fn block(&mut self) -> Result<Vec<Statement>> {
+        self.scoped(|parser| {
+            let mut statements: Vec<Statement> = Vec::new();
+
+            while !parser.check(TokenType::RightBrace)? && !parser.is_at_end() {
+                statements.push(parser.declaration()?);
+            }
 
-        Ok(statements)
+            parser.consume(TokenType::RightBrace, ParseError::UnterminatedBlock(parser.previous()?.line()))?;
+
+            Ok(statements)
+        })
     }
 
-    fn while_statement(&mut self) -> Result<Statement> {
+    fn while_statement(&mut self, label: Option<Token>) -> Result<Statement> {
+        let keyword = self.previous()?;
         self.consume(TokenType::LeftParen, ParseError::ExprectedLeftParen(self.previous()?.line()))?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, ParseError::ExpectedRightParen(self.previous()?.line()))?;
-        let body = self.statement()?;
+        let body = self.loop_body(label.as_ref())?;
+
+        Ok(Statement::While(keyword, condition, Box::new(body), None, label))
+    }
+
+    /// Parses the body of a loop, in which `break` and `continue` can be used
+    fn loop_body(&mut self, label: Option<&Token>) -> Result<Statement> {
+        self.loops.push(label.map(Token::lexeme));
+        let body = self.statement();
+        self.loops.pop();
+        body
+    }
+
+    /// Parses a `for (var x in xs)` loop after the opening parenthesis
+    fn for_in_statement(&mut self, keyword: Token, label: Option<Token>) -> Result<Statement> {
+        self.advance()?; // The `var`
+        let name = self.consume(TokenType::Identifier, ParseError::ExpectedIdentifier(self.previous()?.line()))?;
+        self.advance()?; // The `in`
+        let iterable = self.expression()?;
+        self.consume(TokenType::RightParen, ParseError::ExpectedRightParen(self.previous()?.line()))?;
+        let body = self.scoped(|parser| {
+            parser.declare(&name, false);
+            parser.loop_body(label.as_ref())
+        })?;
+        Ok(Statement::ForIn(keyword, name, iterable, Box::new(body), label))
+    }
 
-        Ok(Statement::While(condition, Box::new(body)))
+    fn for_statement(&mut self, label: Option<Token>) -> Result<Statement> {
+        self.scoped(|parser| parser.for_loop(label)) // The variable of the initializer belongs to the loop
     }
 
-    fn for_statement(&mut self) -> Result<Statement> {
+    fn for_loop(&mut self, label: Option<Token>) -> Result<Statement> {
+        let keyword = self.previous()?;
+
         // Consume left parentheses
         self.consume(TokenType::LeftParen, ParseError::ExprectedLeftParen(self.previous()?.line()))?;
 
+        // `for (var x in xs)` iterates instead
+        if self.check(TokenType::Var)? && self.tokens.get(self.current + 2).is_some_and(|token| token.token_type() == TokenType::In) {
+            return self.for_in_statement(keyword, label);
+        }
+
         // Parse initializer, condition and increment
 
         let initializer = if self.match_token_types([TokenType::Semicolon])? {
@@ -174,15 +680,11 @@ impl Parser {
 
         // Evaluate initializer, condition and increment into a while loop
 
-        let mut body = self.statement()?;
-
-        // Setting the increment expression statement after the body
-        if let Some(expr) = increment {
-            body = Statement::Block(vec![body, Statement::Expression(expr)]);
-        }
+        let body = self.loop_body(label.as_ref())?;
 
-        // Creating the while loop from body and the condition
-        body = Statement::While(condition, Box::new(body));
+        // Creating the while loop from body and the condition. The increment stays
+        // attached to the loop, so it runs after a `continue` too.
+        let mut body = Statement::While(keyword, condition, Box::new(body), increment, label);
 
         // Wrapping into a block that executes the initializer and then the body while loop
         if let Some(stmt) = initializer {
@@ -198,20 +700,70 @@ impl Parser {
 
     // Lowest level of precedence
     fn expression(&mut self) -> Result<Expression> {
-        self.assignment()
+        self.nested(Self::assignment)
     }
 
     fn assignment(&mut self) -> Result<Expression> {
-        let expr = self.or()?;
+        if self.check(TokenType::LeftBrace)? && self.map_pattern_ahead() {
+            self.advance()?;
+            let pattern = self.pattern()?;
+            self.advance()?; // The `=`, which was looked ahead at
+            let value = self.nested(Self::assignment)?;
+            return self.destructuring_assignment(pattern, value);
+        }
 
-        if self.match_token_types([TokenType::Equal])? {
-            let value = self.assignment()?;
+        let expr = self.coalesce()?;
 
-            if let Expression::Variable(name) = expr {
-                return Ok(Expression::Assign(name, Box::new(value)));
+        if self.match_token_types([TokenType::Equal])? {
+            let equals = self.previous()?;
+            let value = self.nested(Self::assignment)?;
+
+            match expr {
+                Expression::Variable(name) if self.is_constant(&name) => {
+                    self.errors.push(ParseError::AssignToConst(name.lexeme(), name.line()).into());
+                    return Ok(Expression::Assign(name, Box::new(value)));
+                }
+                Expression::Variable(name) => return Ok(Expression::Assign(name, Box::new(value))),
+                Expression::Get(object, name) => return Ok(Expression::Set(object, name, Box::new(value))),
+                Expression::Index(list, bracket, index) => return Ok(Expression::IndexSet(list, bracket, index, Box::new(value))),
+                Expression::List(bracket, items) => {
+                    // A list of variables assigns the elements of a list to them
+                    let names: Option<Vec<Token>> = items
+                        .into_iter()
+                        .map(|item| match item {
+                            Expression::Variable(name) => Some(name),
+                            _ => None,
+                        })
+                        .collect();
+                    if let Some(names) = names.filter(|names| !names.is_empty()) {
+                        return self.destructuring_assignment(Pattern::List(bracket, names), value);
+                    }
+                }
+                _ => {}
             }
 
-            return Err(ParseError::InvalidAssignmentTarget.into());
+            return Err(ParseError::InvalidAssignmentTarget(equals.line()).into());
+        }
+
+        Ok(expr)
+    }
+
+    /// Assigns the names of the pattern. Assigning a constant is an error.
+    fn destructuring_assignment(&mut self, pattern: Pattern, value: Expression) -> Result<Expression> {
+        if let Some(name) = pattern.names().iter().find(|name| self.is_constant(name)) {
+            self.errors.push(ParseError::AssignToConst(name.lexeme(), name.line()).into());
+        }
+        Ok(Expression::Destructure(pattern, Box::new(value)))
+    }
+
+    /// `a ?? b`, which is `b` only if `a` is nil
+    fn coalesce(&mut self) -> Result<Expression> {
+        let mut expr = self.or()?;
+
+        while self.match_token_types([TokenType::QuestionQuestion])? {
+            let operator = self.previous()?;
+            let right = self.or()?;
+            expr = Expression::Logical(Box::new(expr), operator, Box::new(right));
         }
 
         Ok(expr)
@@ -262,6 +814,7 @@ impl Parser {
             TokenType::GreaterEqual,
             TokenType::Less,
             TokenType::LessEqual,
+            TokenType::Is,
         ])? {
             let operator = self.previous()?;
             let right = self.term()?;
@@ -290,7 +843,7 @@ impl Parser {
         let mut expr = self.unary()?;
 
         // Match those tokentypes
-        while self.match_token_types([TokenType::Slash, TokenType::Star])? {
+        while self.match_token_types([TokenType::Slash, TokenType::Star, TokenType::TildeSlash])? {
             let operator = self.previous()?;
             let right = self.unary()?;
             expr = Expression::Binary(Box::new(expr), operator, Box::new(right));
@@ -302,13 +855,68 @@ impl Parser {
     fn unary(&mut self) -> Result<Expression> {
         // Match those tokentypes
 
-        if self.match_token_types([TokenType::Bang, TokenType::Minus])? {
+        if self.match_token_types([TokenType::Bang, TokenType::Minus, TokenType::Await])? {
             let operator = self.previous()?;
-            let right = self.unary()?;
+            let right = self.nested(Self::unary)?;
             Ok(Expression::Unary(operator, Box::new(right)))
         } else {
-            self.primary()
+            self.call()
+        }
+    }
+
+    fn call(&mut self) -> Result<Expression> {
+        let mut expr = self.primary()?;
+
+        // Calls, property accesses and indices can be chained: a.b(c)[d]?.e
+        loop {
+            if self.match_token_types([TokenType::LeftParen])? {
+                expr = self.finish_call(expr)?;
+            } else if self.match_token_types([TokenType::Dot])? {
+                let name = self.consume(TokenType::Identifier, ParseError::ExpectedPropertyName(self.previous()?.line()))?;
+                expr = Expression::Get(Box::new(expr), name);
+            } else if self.match_token_types([TokenType::QuestionDot])? {
+                let name = self.consume(TokenType::Identifier, ParseError::ExpectedPropertyName(self.previous()?.line()))?;
+                expr = Expression::OptionalGet(Box::new(expr), name);
+            } else if self.match_token_types([TokenType::LeftBracket])? {
+                expr = self.index(expr)?;
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    /// Parses an index `[i]` or a slice `[a:b]`, after the opening bracket.
+    /// Either bound of a slice can be left out.
+    fn index(&mut self, object: Expression) -> Result<Expression> {
+        let bracket = self.previous()?;
+        let start = if self.check(TokenType::Colon)? { None } else { Some(Box::new(self.expression()?)) };
+        let expr = match (start, self.match_token_types([TokenType::Colon])?) {
+            (Some(index), false) => Expression::Index(Box::new(object), bracket.clone(), index),
+            (start, _) => { // Without a start the colon was checked above
+                let end = if self.check(TokenType::RightBracket)? { None } else { Some(Box::new(self.expression()?)) };
+                Expression::Slice(Box::new(object), bracket.clone(), start, end)
+            }
+        };
+        self.consume(TokenType::RightBracket, ParseError::UnterminatedIndex(bracket.line()))?;
+        Ok(expr)
+    }
+
+    /// Parses the argument list of a call, after the opening parenthesis
+    fn finish_call(&mut self, callee: Expression) -> Result<Expression> {
+        let mut args = Vec::new();
+        if !self.check(TokenType::RightParen)? {
+            loop {
+                args.push(self.expression()?);
+                if !self.match_token_types([TokenType::Comma])? {
+                    break;
+                }
+            }
         }
+        let paren = self.consume(TokenType::RightParen, ParseError::ExpectedRightParen(self.previous()?.line()))?;
+
+        Ok(Expression::Call(Box::new(callee), paren, args))
     }
 
     // Highest level of precedence
@@ -325,6 +933,20 @@ impl Parser {
                     .literal()
                     .ok_or(ParseError::NoLiteralOnToken(self.peek()?.line()))?,
             ));
+        } else if self.match_token_types([TokenType::This])? {
+            let keyword = self.previous()?;
+            if self.classes.is_empty() {
+                return Err(ParseError::ThisOutsideClass(keyword.line()).into());
+            }
+            return Ok(Expression::This(keyword));
+        } else if self.match_token_types([TokenType::Super])? {
+            let keyword = self.previous()?;
+            if self.classes.last() != Some(&true) {
+                return Err(ParseError::SuperWithoutSuperclass(keyword.line()).into());
+            }
+            self.consume(TokenType::Dot, ParseError::ExpectedSuperMethod(keyword.line()))?;
+            let method = self.consume(TokenType::Identifier, ParseError::ExpectedSuperMethod(keyword.line()))?;
+            return Ok(Expression::Super(keyword, method));
         } else if self.match_token_types([TokenType::Identifier])? {
             // If we have an identifier, we return a variable expression
             return Ok(Expression::Variable(self.previous()?));
@@ -332,10 +954,94 @@ impl Parser {
             let expr = self.expression()?; // If we encounter a '(', we start a new expression that is grouped
             self.consume(TokenType::RightParen, ParseError::UnterminatedGrouping(self.previous()?.line()))?; // We consume the ')'
             return Ok(Expression::Grouping(Box::new(expr)));
+        } else if self.match_token_types([TokenType::LeftBracket])? {
+            return self.list();
+        } else if self.match_token_types([TokenType::LeftBrace])? {
+            return self.map(); // At the start of a statement, a brace opens a block instead
+        } else if self.match_token_types([TokenType::Fun])? {
+            return self.nested(Self::lambda);
+        } else if self.match_token_types([TokenType::Async])? {
+            return self.nested(Self::async_lambda);
+        } else if self.match_token_types([TokenType::If])? {
+            return self.nested(Self::if_expression); // At the start of a statement, `if` is a statement instead
+        }
+
+        // If we're at the end or don't match, we error. Otherwise, we return before this line.
+        // The current token is the one that should have started the expression.
+        Err(ParseError::ExpectedExpression(self.peek()?.line()).into())
+    }
+
+    /// Parses the elements of a list literal, after the opening bracket
+    fn list(&mut self) -> Result<Expression> {
+        let bracket = self.previous()?;
+        let mut items = Vec::new();
+        if !self.check(TokenType::RightBracket)? {
+            loop {
+                items.push(self.expression()?);
+                if !self.match_token_types([TokenType::Comma])? {
+                    break;
+                }
+            }
         }
+        self.consume(TokenType::RightBracket, ParseError::UnterminatedList(bracket.line()))?;
 
-        // If we're at the end or don't match, we error. Otherwise, we return before this line
-        Err(ParseError::ExpectedExpression(self.previous()?.line()).into())
+        Ok(Expression::List(bracket, items))
+    }
+
+    /// Parses the entries of a map literal, after the opening brace
+    fn map(&mut self) -> Result<Expression> {
+        let brace = self.previous()?;
+        let mut entries = Vec::new();
+        if !self.check(TokenType::RightBrace)? {
+            loop {
+                let key = self.expression()?;
+                self.consume(TokenType::Colon, ParseError::ExpectedColonAfterKey(self.previous()?.line()))?;
+                entries.push((key, self.expression()?));
+                if !self.match_token_types([TokenType::Comma])? {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightBrace, ParseError::UnterminatedMap(brace.line()))?;
+
+        Ok(Expression::Map(brace, entries))
+    }
+
+    /// Parses the names of a destructuring pattern like `[a, b]` or `{x, y}`,
+    /// after the opening bracket or brace
+    fn pattern(&mut self) -> Result<Pattern> {
+        let open = self.previous()?;
+        let mut names = Vec::new();
+        loop {
+            names.push(self.consume(TokenType::Identifier, ParseError::ExpectedPatternName(self.previous()?.line()))?);
+            if !self.match_token_types([TokenType::Comma])? {
+                break;
+            }
+        }
+        if open.token_type() == TokenType::LeftBracket {
+            self.consume(TokenType::RightBracket, ParseError::UnterminatedList(open.line()))?;
+            Ok(Pattern::List(open, names))
+        } else {
+            self.consume(TokenType::RightBrace, ParseError::UnterminatedMap(open.line()))?;
+            Ok(Pattern::Map(open, names))
+        }
+    }
+
+    /// Whether the brace at the current token starts a `{x, y} = point`
+    /// assignment, which would otherwise be read as a block or a map
+    fn map_pattern_ahead(&self) -> bool {
+        let is = |index: usize, token_type: TokenType| {
+            self.tokens.get(index).is_some_and(|token| token.token_type() == token_type)
+        };
+        let mut index = self.current + 1;
+        while is(index, TokenType::Identifier) {
+            if is(index + 1, TokenType::Comma) {
+                index += 2;
+            } else {
+                return is(index + 1, TokenType::RightBrace) && is(index + 2, TokenType::Equal);
+            }
+        }
+        false
     }
 
     /// When an error is encountered, it ignores any tokens until
@@ -348,12 +1054,19 @@ impl Parser {
             }
 
             match token_type {
-                TokenType::Class
+                TokenType::Async
+                | TokenType::Break
+                | TokenType::Class
+                | TokenType::Const
+                | TokenType::Continue
                 | TokenType::For
                 | TokenType::Fun
                 | TokenType::If
                 | TokenType::Print
                 | TokenType::Return
+                | TokenType::Switch
+                | TokenType::Throw
+                | TokenType::Try
                 | TokenType::Var
                 | TokenType::While => return Ok(()),
                 _ => {}
@@ -366,9 +1079,44 @@ impl Parser {
 
     // Small helper functions
 
-    /// Checks if the current pointer is already at the end
+    /// Runs `parse` one nesting level deeper, or fails if that is
+    /// deeper than `MAX_DEPTH`
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_DEPTH {
+            return Err(ParseError::TooDeeplyNested(self.peek()?.line()).into());
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Runs `parse` in a new scope, which ends with it
+    fn scoped<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.scopes.push(HashMap::new());
+        let result = parse(self);
+        self.scopes.pop();
+        result
+    }
+
+    /// Records a declaration in the innermost scope
+    fn declare(&mut self, name: &Token, constant: bool) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme(), constant);
+        }
+    }
+
+    /// Whether the variable `name` refers to is a constant declared in
+    /// this source. Variables declared elsewhere are looked up at runtime.
+    fn is_constant(&self, name: &Token) -> bool {
+        let lexeme = name.lexeme();
+        self.scopes.iter().rev().find_map(|scope| scope.get(&lexeme).copied()).unwrap_or(false)
+    }
+
+    /// Checks if the current pointer is already at the end. Also true
+    /// for an empty token list, which doesn't even have the EOF token.
     fn is_at_end(&self) -> bool {
-        self.current >= self.tokens.len() - 1 // - 1 because EOF is already the end
+        self.current + 1 >= self.tokens.len() // + 1 because EOF is already the end
     }
 
     /// Tries to get the current Token
@@ -379,9 +1127,12 @@ impl Parser {
             .cloned()
     }
 
+    /// Tries to get the token before the current one. Fails instead of
+    /// underflowing when nothing has been consumed yet.
     fn previous(&self) -> Result<Token> {
-        self.tokens
-            .get(self.current - 1)
+        self.current
+            .checked_sub(1)
+            .and_then(|index| self.tokens.get(index))
             .ok_or(ParseError::TokenAccessError(self.current).into())
             .cloned()
     }
@@ -418,6 +1169,11 @@ impl Parser {
         Ok(false)
     }
 
+    /// Checks whether the token after the current one has the type
+    fn next_is(&self, token_type: TokenType) -> bool {
+        self.tokens.get(self.current + 1).is_some_and(|token| token.token_type() == token_type)
+    }
+
     /// Checks if we're at the end and if not, we check if the current
     /// tokentype is the desired tokentype
     fn check(&self, token_type: TokenType) -> Result<bool> {
@@ -427,3 +1183,231 @@ impl Parser {
         Ok(self.peek()?.token_type() == token_type)
     }
 }
+
+
+// ---------- Tests for the Parser module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::CollectingReporter;
+    use crate::scanner::scan_tokens;
+
+    #[test]
+    fn missing_expression_at_start_is_an_error() {
+        // Used to underflow in `previous()` because no token was consumed yet
+        let mut reporter = CollectingReporter::default();
+        let tokens = scan_tokens(")".to_string(), &mut reporter).expect("Token Scanning failed!");
+        assert!(parse(tokens, &mut reporter).is_err());
+    }
+
+    #[test]
+    fn parses_a_single_expression() {
+        let mut reporter = CollectingReporter::default();
+        let tokens = scan_tokens("1 + 2 * x".to_string(), &mut reporter).expect("Token Scanning failed!");
+        let expr = parse_expression(tokens, &mut reporter).expect("Parsing failed!");
+        assert_eq!(expr.to_string(), "(+ 1 (* 2 (var x)))");
+
+        let tokens = scan_tokens("1 + 2;".to_string(), &mut reporter).expect("Token Scanning failed!");
+        assert!(parse_expression(tokens, &mut reporter).is_err());
+        assert_eq!(reporter.diagnostics()[0].line, Some(1));
+    }
+
+    #[test]
+    fn reports_every_error_after_synchronizing() {
+        let mut reporter = CollectingReporter::default();
+        let tokens = scan_tokens("print ;\nvar = 1;\nprint 1;".to_string(), &mut reporter)
+            .expect("Token Scanning failed!");
+        assert!(parse(tokens, &mut reporter).is_err());
+
+        let lines: Vec<Option<u32>> = reporter.diagnostics().iter().map(|d| d.line).collect();
+        assert_eq!(lines, vec![Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn parses_type_annotations() {
+        let mut reporter = CollectingReporter::default();
+        let tokens = scan_tokens("var a: Number = 1; var b: String;".to_string(), &mut reporter).expect("Token Scanning failed!");
+        let statements = parse(tokens, &mut reporter).expect("Parsing failed!");
+        assert_eq!(statements[0].to_string(), "(var a: Number 1)");
+        assert_eq!(statements[1].to_string(), "(var b: String)");
+
+        let tokens = scan_tokens("var a: = 1;".to_string(), &mut reporter).expect("Token Scanning failed!");
+        assert!(parse(tokens, &mut reporter).is_err());
+        assert!(reporter.diagnostics().last().is_some_and(|d| d.message.contains("[E0216]")));
+    }
+
+    #[test]
+    fn parses_slices_with_optional_bounds() {
+        let mut reporter = CollectingReporter::default();
+        let slices = [
+            ("s[1:2]", "(slice (var s) 1 2)"),
+            ("s[:-1]", "(slice (var s) _ (- 1))"),
+            ("s[a:]", "(slice (var s) (var a) _)"),
+            ("s[:]", "(slice (var s) _ _)"),
+        ];
+        for (source, parsed) in slices {
+            let tokens = scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+            assert_eq!(parse_expression(tokens, &mut reporter).expect("Parsing failed!").to_string(), parsed);
+        }
+
+        let tokens = scan_tokens("s[1:2] = 3;".to_string(), &mut reporter).expect("Token Scanning failed!");
+        assert!(parse(tokens, &mut reporter).is_err());
+    }
+
+    #[test]
+    fn parses_try_with_catch_and_finally() {
+        let mut reporter = CollectingReporter::default();
+        let source = "try { throw 1; } catch (e) { print e; } finally { print 2; }";
+        let tokens = scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+        let statements = parse(tokens, &mut reporter).expect("Parsing failed!");
+        assert_eq!(statements[0].to_string(), "(try (throw 1) (catch e (print (var e))) (finally (print 2)))");
+
+        let source = "try { } catch (e: TypeError) { } catch (e) { }";
+        let tokens = scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+        let statements = parse(tokens, &mut reporter).expect("Parsing failed!");
+        assert_eq!(statements[0].to_string(), "(try (catch e: TypeError) (catch e))");
+
+        for source in ["try { }", "try { } catch { }", "try print 1;", "try { } catch (e:) { }"] {
+            let tokens = scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+            assert!(parse(tokens, &mut reporter).is_err());
+        }
+    }
+
+    #[test]
+    fn parses_destructuring_patterns() {
+        let mut reporter = CollectingReporter::default();
+        let patterns = [
+            ("var [a, b] = l;", "(var [a, b] (var l))"),
+            ("var {x, y} = p;", "(var {x, y} (var p))"),
+            ("[a, b] = [b, a];", "(expr_stmt (= [a, b] (list (var b) (var a))))"),
+            // A brace followed by names and `=` is a pattern, not a block
+            ("{x, y} = p;", "(expr_stmt (= {x, y} (var p)))"),
+            ("{ x; }", "(block (expr_stmt (var x)))"),
+        ];
+        for (source, parsed) in patterns {
+            let tokens = scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+            assert_eq!(parse(tokens, &mut reporter).expect("Parsing failed!")[0].to_string(), parsed);
+        }
+
+        for source in ["var [a, 1] = l;", "var {x};", "[a, b.c] = l;", "[] = l;", "const c = 1; [c] = l;"] {
+            let tokens = scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+            assert!(parse(tokens, &mut reporter).is_err());
+        }
+    }
+
+    #[test]
+    fn labels_name_the_loops_around() {
+        let mut reporter = CollectingReporter::default();
+        let source = "a: while (x) for (var y in z) { break a; continue; }";
+        let tokens = scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+        assert_eq!(
+            parse(tokens, &mut reporter).expect("Parsing failed!")[0].to_string(),
+            "(while a: (var x) (for y in (var z) (block (break a) (continue))))"
+        );
+
+        let errors = [
+            ("a: while (x) break b;", "E0248"),
+            ("a: while (x) a: while (y) {}", "E0249"),
+            ("a: print 1;", "E0250"),
+            ("a: while (x) { fun f() { break a; } }", "E0247"),
+        ];
+        for (source, code) in errors {
+            let mut reporter = CollectingReporter::default();
+            let tokens = scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+            assert!(parse(tokens, &mut reporter).is_err());
+            assert!(reporter.into_diagnostics()[0].message.contains(code), "{}", source);
+        }
+    }
+
+    #[test]
+    fn parses_if_expressions() {
+        let mut reporter = CollectingReporter::default();
+        let expressions = [
+            ("var x = if (a) { 1 } else { 2 };", "(var x (if (var a) (block 1) (block 2)))"),
+            (
+                "var x = if (a) { f(); } else if (b) { 2 };",
+                "(var x (if (var a) (block (expr_stmt (call (var f)))) (if (var b) (block 2))))",
+            ),
+            // At the start of a statement it is an if statement
+            ("if (a) { f(); }", "(if (var a) (block (expr_stmt (call (var f)))))"),
+        ];
+        for (source, parsed) in expressions {
+            let tokens = scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+            assert_eq!(parse(tokens, &mut reporter).expect("Parsing failed!")[0].to_string(), parsed);
+        }
+
+        for source in ["var x = if (a) 1 else 2;", "var x = if (a) { 1 2 };", "fun f() { var x = if (a) { return; }; }"] {
+            let tokens = scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+            assert!(parse(tokens, &mut reporter).is_err());
+        }
+    }
+
+    #[test]
+    fn parses_async_functions_and_await() {
+        let mut reporter = CollectingReporter::default();
+        let source = "async fun f() { return await g(); } var h = async fun (x) { };";
+        let tokens = scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+        let statements = parse(tokens, &mut reporter).expect("Parsing failed!");
+        assert_eq!(statements[0].to_string(), "(async fun f() (return (await (call (var g)))))");
+        assert_eq!(statements[1].to_string(), "(var h (async fun (x)))");
+
+        for source in ["async f() { }", "var h = async (x) { };"] {
+            let tokens = scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+            assert!(parse(tokens, &mut reporter).is_err());
+            assert!(reporter.diagnostics().last().is_some_and(|d| d.message.contains("[E0252]")));
+        }
+    }
+
+    #[test]
+    fn empty_token_lists_do_not_underflow() {
+        let mut reporter = CollectingReporter::default();
+        assert!(parse(Vec::new(), &mut reporter).expect("Parsing failed!").is_empty());
+        assert!(parse_expression(Vec::new(), &mut reporter).is_err());
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        // Scripts are parsed on the main thread, which has a bigger stack
+        // than test threads. Unoptimized builds need most of it at MAX_DEPTH.
+        let parse_deep = || {
+            let mut reporter = CollectingReporter::default();
+            let nested = |depth| format!("print {}1{};", "(".repeat(depth), ")".repeat(depth));
+            let tokens = scan_tokens(nested(100), &mut reporter).expect("Token Scanning failed!");
+            assert!(parse(tokens, &mut reporter).is_ok());
+
+            for source in [nested(100_000), "-".repeat(100_000) + "1;", "a = ".repeat(100_000) + "1;", "{".repeat(100_000)] {
+                let tokens = scan_tokens(source, &mut reporter).expect("Token Scanning failed!");
+                assert!(parse(tokens, &mut reporter).is_err());
+            }
+            assert!(reporter.diagnostics().iter().all(|d| d.message.contains("[E0217]")));
+        };
+        let thread = std::thread::Builder::new().stack_size(8 << 20).spawn(parse_deep).expect("Spawning failed!");
+        thread.join().expect("Parsing deeply nested input panicked");
+    }
+
+    /// Pieces of Lox, so the generated programs get past the first token
+    const FRAGMENTS: &[&str] = &[
+        "var", "print", "if", "else", "while", "for", "(", ")", "{", "}", "[", "]", ";", ",", ".", ":", "=", "==", "!",
+        "-", "+", "*", "/", "~/", "<", "and", "or", "x", "1", "2.5", "\"s\"", "true", "nil", "Number",
+    ];
+
+    proptest::proptest! {
+        #[test]
+        fn parsing_never_panics(pieces in proptest::collection::vec(proptest::sample::select(FRAGMENTS), 0..64)) {
+            let mut reporter = CollectingReporter::default();
+            if let Ok(tokens) = scan_tokens(pieces.join(" "), &mut reporter) {
+                let _ = parse(tokens.clone(), &mut reporter);
+                let _ = parse_expression(tokens, &mut reporter);
+            }
+        }
+
+        #[test]
+        fn parsing_arbitrary_text_never_panics(source in "\\PC*") {
+            let mut reporter = CollectingReporter::default();
+            if let Ok(tokens) = scan_tokens(source, &mut reporter) {
+                let _ = parse(tokens, &mut reporter);
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+// External dependencies
+use anyhow::Result;
+use std::fs::File;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+// Internal dependencies
+use jlox::errors::{LoxError, ParseError, ReplError, ScanError, TypeError};
+use jlox::interpreter::Interpreter;
+use jlox::obj::statement::Statement;
+use jlox::obj::value::Value;
+use jlox::{parser, scanner};
+use crate::reporter::{self, StderrReporter};
+use crate::style;
+
+/// An interactive session. Every line is run in the same interpreter,
+/// so variables defined on one line are visible on the next ones.
+/// Lines starting with `:` are meta-commands (see [Repl::command]).
+pub struct Repl {
+    interpreter: Interpreter,
+    timing: bool, // Print how long parsing and executing took, see :time
+    history: Vec<String>, // Every input that ran without errors, see :save
+    recording: Option<File>, // Where inputs are appended to, see :record
+}
+
+impl Repl {
+    pub fn new(interpreter: Interpreter) -> Self {
+        Self { interpreter, timing: false, history: Vec::new(), recording: None }
+    }
+
+    /// Reads lines from stdin until an empty line (or the end of the input)
+    /// is read. Errors are printed and don't end the session, only `exit()`
+    /// does, its error is returned for main to turn into the exit code.
+    pub fn run(&mut self) -> Result<()> {
+        loop {
+            let line = match read_line("> ")? {
+                Some(line) if !line.trim().is_empty() => line,
+                _ => break Ok(()), // If no input was given, the prompt mode is exited with an Ok
+            };
+
+            let result = match line.trim().strip_prefix(':') {
+                Some(command) => self.command(command),
+                None => self.input(line).and_then(|line| self.remember(line)),
+            };
+            match result {
+                Err(e) if crate::exit_code(&e).is_some() => break Err(e),
+                Err(e) => report(e),
+                Ok(()) => {},
+            }
+        }
+    }
+
+    /// Runs a line of Lox code. If it is a single expression statement,
+    /// its value is echoed in its unambiguous form (nil results are not).
+    /// With `:time on`, the durations are printed afterwards, also when
+    /// the execution failed. Gives the line back if it ran without errors.
+    fn input(&mut self, line: String) -> Result<String> {
+        let start = Instant::now();
+        let statements = crate::parse(line.clone(), self.interpreter.config().typed)?;
+        let parsed = start.elapsed();
+
+        let start = Instant::now();
+        let result = match statements.as_slice() {
+            [Statement::Expression(expr)] => self.interpreter.evaluate(expr).map_err(Into::into).map(|value| {
+                if value != Value::Nil {
+                    println!("{}", value.to_text());
+                }
+            }),
+            _ => crate::execute(&mut self.interpreter, statements),
+        };
+        let executed = start.elapsed();
+
+        if self.timing {
+            println!("parse: {}, execute: {}", format_duration(parsed), format_duration(executed));
+        }
+        result.map_err(|error| reporter::with_excerpt(error, self.interpreter.error_span(), &line))?;
+        Ok(line)
+    }
+
+    /// Adds an input that ran without errors to the history and to the
+    /// recording, if there is one. Inputs always end with a line break,
+    /// so the recorded file is a script that can be run as it is.
+    fn remember(&mut self, mut line: String) -> Result<()> {
+        if !line.ends_with('\n') {
+            line.push('\n');
+        }
+        if let Some(file) = &mut self.recording {
+            file.write_all(line.as_bytes())?;
+            file.flush()?;
+        }
+        self.history.push(line);
+        Ok(())
+    }
+
+    /// Runs a meta-command (the line without its leading colon)
+    ///
+    /// - `:type <expr>` prints the type and the text form of the expression's value
+    /// - `:paste` reads lines until one containing only `.` and runs them as one input
+    /// - `:time on|off` toggles printing the parse and execute duration of every input
+    /// - `:record <file>` appends every following input that runs without errors to the file
+    /// - `:stop` ends the recording
+    /// - `:save <file>` writes every input of the session that ran without errors to the file
+    fn command(&mut self, command: &str) -> Result<()> {
+        let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+        let argument = argument.trim();
+
+        match name {
+            "type" => {
+                if argument.is_empty() {
+                    return Err(ReplError::MissingArgument("type", "an expression").into());
+                }
+                let mut reporter = StderrReporter::new();
+                let expr = scanner::scan_tokens(argument.to_string(), &mut reporter)
+                    .and_then(|tokens| parser::parse_expression(tokens, &mut reporter));
+                let denied = reporter.finish();
+                let expr = expr?;
+                denied?;
+                let value = self.interpreter.inspect(&expr)?;
+                println!("{}: {}", value.type_name(), value.to_text());
+                Ok(())
+            }
+            "paste" => {
+                println!("(paste mode, finish with a line containing only \".\")");
+                let mut source = String::new();
+                while let Some(line) = read_line("... ")? {
+                    if line.trim() == "." {
+                        break;
+                    }
+                    source.push_str(&line);
+                }
+                let source = self.input(source)?;
+                self.remember(source)
+            }
+            "time" => {
+                self.timing = match argument {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err(ReplError::MissingArgument("time", "on or off").into()),
+                };
+                Ok(())
+            }
+            "record" => {
+                if argument.is_empty() {
+                    return Err(ReplError::MissingArgument("record", "a file").into());
+                }
+                self.recording = Some(File::options().create(true).append(true).open(argument)?);
+                println!("(recording to {}, stop with :stop)", argument);
+                Ok(())
+            }
+            "stop" => match self.recording.take() {
+                Some(_) => Ok(()),
+                None => Err(ReplError::NotRecording.into()),
+            },
+            "save" => {
+                if argument.is_empty() {
+                    return Err(ReplError::MissingArgument("save", "a file").into());
+                }
+                std::fs::write(argument, self.history.concat())?;
+                println!("({} inputs saved to {})", self.history.len(), argument);
+                Ok(())
+            }
+            _ => Err(ReplError::UnknownCommand(name.to_string()).into()),
+        }
+    }
+}
+
+/// Prints the prompt and reads one line from stdin, including its line
+/// break. Returns `None` at the end of the input.
+fn read_line(prompt: &str) -> Result<Option<String>> {
+    print!("{}", prompt);
+    std::io::stdout().flush()?; // Print the prompt to the cli
+
+    let mut line = String::new();
+    let read = std::io::stdin().read_line(&mut line)?; // Read cli input into a String
+    Ok((read > 0).then_some(line))
+}
+
+/// Formats a duration in milliseconds with microsecond precision
+fn format_duration(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Prints an error of a single input. Scan, parse and type errors were already
+/// printed by the reporter, so their summary is left out.
+fn report(error: anyhow::Error) {
+    match error.downcast_ref::<LoxError>() {
+        Some(LoxError::Scan(ScanError::HadError))
+        | Some(LoxError::Parse(ParseError::HadError))
+        | Some(LoxError::Type(TypeError::HadError)) => {}
+        _ => eprintln!("{}", style::paint(&error.to_string(), style::theme().error)),
+    }
+}
@@ -0,0 +1,156 @@
+// External dependencies
+use anyhow::anyhow;
+use std::sync::OnceLock;
+
+// Internal dependencies
+use jlox::diagnostic::{Diagnostic, Reporter, Severity};
+use jlox::errors::{DiagnosticError, LoxError};
+use jlox::obj::token::Span;
+use crate::style;
+
+/// Number of errors printed per run when `--max-errors` is not given
+pub const DEFAULT_MAX_ERRORS: usize = 20;
+
+/// How every [StderrReporter] behaves, set through the command line
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    /// `--max-errors`, `0` means no limit
+    max_errors: usize,
+    /// `--deny-warnings`
+    deny_warnings: bool,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Sets how many errors every [StderrReporter] prints before it goes
+/// quiet (`0` means no limit) and whether warnings count as errors.
+/// Only the first call has an effect.
+pub fn init(max_errors: usize, deny_warnings: bool) {
+    let _ = SETTINGS.set(Settings { max_errors, deny_warnings });
+}
+
+/// The reporter the CLI installs: prints every diagnostic to stderr.
+/// After the configured number of errors, further errors are only
+/// counted, and [StderrReporter::finish] prints how many were left out.
+pub struct StderrReporter {
+    settings: Settings,
+    errors: usize,
+    denied_warnings: usize,
+}
+
+impl StderrReporter {
+    pub fn new() -> Self {
+        let settings = SETTINGS.get().copied().unwrap_or(Settings {
+            max_errors: DEFAULT_MAX_ERRORS,
+            deny_warnings: false,
+        });
+        Self { settings, errors: 0, denied_warnings: 0 }
+    }
+
+    /// Prints the number of errors that were left out, if there were any.
+    /// Fails if warnings were reported while they are denied.
+    pub fn finish(self) -> Result<(), DiagnosticError> {
+        let max_errors = self.settings.max_errors;
+        let hidden = self.errors.saturating_sub(max_errors);
+        if max_errors > 0 && hidden > 0 {
+            let ellipsis = style::symbol("…", "...");
+            eprintln!("{} and {} more error{}", ellipsis, hidden, if hidden == 1 { "" } else { "s" });
+        }
+
+        match self.denied_warnings {
+            0 => Ok(()),
+            denied => Err(DiagnosticError::DeniedWarnings(denied)),
+        }
+    }
+
+    /// Prints an error, unless the error limit is reached
+    fn print_error(&mut self, message: &str) {
+        self.errors += 1;
+        if self.settings.max_errors == 0 || self.errors <= self.settings.max_errors {
+            eprintln!("{}", style::paint(message, style::theme().error));
+        }
+    }
+}
+
+impl Reporter for StderrReporter {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        match diagnostic.severity {
+            Severity::Error => self.print_error(&diagnostic.to_string()),
+            Severity::Warning if self.settings.deny_warnings => {
+                self.denied_warnings += 1;
+                self.print_error(&format!("Denied warning: {}", diagnostic));
+            }
+            Severity::Warning => {
+                eprintln!("{}", style::paint(&format!("Warning: {}", diagnostic), style::theme().warning))
+            }
+        }
+    }
+}
+
+/// Appends the source line of a runtime error to it, with a caret under
+/// the part of the line the error came from. Other errors, and errors
+/// whose span does not belong to them, are returned unchanged.
+pub fn with_excerpt(error: anyhow::Error, span: Option<Span>, source: &str) -> anyhow::Error {
+    let Some(span) = span else {
+        return error;
+    };
+    match error.downcast_ref::<LoxError>() {
+        Some(lox @ LoxError::Runtime(_)) if lox.line() == Some(span.line) => match excerpt(source, span) {
+            Some(excerpt) => anyhow!("{}\n{}", error, excerpt),
+            None => error,
+        },
+        _ => error,
+    }
+}
+
+/// The line of the span, prefixed by its number, and a line of carets under it
+fn excerpt(source: &str, span: Span) -> Option<String> {
+    let text = source.lines().nth(span.line.checked_sub(1)? as usize)?;
+    let start = span.column.checked_sub(1)? as usize;
+    if start >= text.chars().count() {
+        return None;
+    }
+
+    // Tabs are kept, so the carets line up however wide the terminal draws them
+    let indent: String = text.chars().take(start).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+    let carets = "^".repeat(span.length.max(1) as usize);
+
+    let number = span.line.to_string();
+    let bar = style::symbol("│", "|");
+    Some(format!(
+        "{} {} {}\n{} {} {}{}",
+        number,
+        bar,
+        text,
+        " ".repeat(number.len()),
+        bar,
+        indent,
+        style::paint(&carets, style::theme().error)
+    ))
+}
+
+// ---------- Tests for the reporter module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reporter(deny_warnings: bool) -> StderrReporter {
+        StderrReporter {
+            settings: Settings { max_errors: DEFAULT_MAX_ERRORS, deny_warnings },
+            errors: 0,
+            denied_warnings: 0,
+        }
+    }
+
+    #[test]
+    fn denied_warnings_fail_the_run() {
+        let mut allowed = reporter(false);
+        allowed.report(Diagnostic::warning("unused variable".to_string(), Some(1)));
+        assert!(allowed.finish().is_ok());
+
+        let mut denied = reporter(true);
+        denied.report(Diagnostic::warning("unused variable".to_string(), Some(1)));
+        assert!(matches!(denied.finish(), Err(DiagnosticError::DeniedWarnings(1))));
+    }
+}
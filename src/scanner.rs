@@ -1,40 +1,73 @@
-// External dependencies
-use anyhow::Result;
-
 // Internal dependencies
+use crate::obj::number::Number;
 use crate::obj::value::Value;
 use crate::obj::token::Token;
 use crate::obj::token_type::TokenType;
-use crate::errors::ScanError;
+use crate::diagnostic::{Diagnostic, Origin, Reporter};
+use crate::errors::{LoxError, Result, ScanError};
 
 /// Only public function of the scanner module. It takes in a raw source code String
 /// and spits out a Vector of freshly baked Tokens. It is the *blackbox interface* of the
-/// scanner module.
-pub fn scan_tokens(source: String) -> Result<Vec<Token>> {
-    let scanner = Scanner::new(source);
-    scanner.scan_tokens() // No propagation needed because it returns a Result
+/// scanner module. Every error along the way is handed to the `reporter`.
+pub fn scan_tokens(source: String, reporter: &mut dyn Reporter) -> Result<Vec<Token>> {
+    scan_tokens_at(source, &Origin::default(), reporter)
+}
+
+/// Like [scan_tokens], for source code that starts at `origin` of some
+/// outer file. Lines and columns of the tokens, and with them the lines
+/// of every diagnostic, are those of the outer file.
+pub fn scan_tokens_at(source: String, origin: &Origin, reporter: &mut dyn Reporter) -> Result<Vec<Token>> {
+    let scanner = Scanner::new(source, origin);
+    scanner.scan_tokens(reporter).map(|(tokens, _)| tokens) // No propagation needed because it returns a Result
+}
+
+/// Like [scan_tokens], but also hands out the `//` comments of the source
+/// code in order. Tools that print the code again, like the formatter, use
+/// them, the parser never sees them.
+pub fn scan_with_comments(source: String, reporter: &mut dyn Reporter) -> Result<(Vec<Token>, Vec<Comment>)> {
+    Scanner::new(source, &Origin::default()).scan_tokens(reporter)
+}
+
+/// A `//` comment and where it was
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub line: u32,
+    /// The comment from its slashes to the end of the line, without trailing whitespace
+    pub text: String,
+    /// Whether there is code before it on its line
+    pub trailing: bool,
 }
 
 /// Contraption that holds the necessary data for the scanning process.
 struct Scanner {
-    source: String,
+    source: Vec<char>, // Indexed by char, so any UTF-8 input can be sliced safely
     tokens: Vec<Token>,
+    comments: Vec<Comment>,
     start: usize,   // First char of lexeme being scanned
     current: usize, // Current considered char
     line: usize,    // What line 'current' is on
+    line_start: usize,   // Index of the first char of that line
+    start_column: u32,   // Column of 'start', counted in chars from 1
+    first_line: usize,   // Line the source starts on
+    column_offset: u32,  // Column the source starts at minus 1, only applies to the first line
 }
 
 impl Scanner {
     /// Creates a new Scanner by passing in the source code as a `String`.
     /// It also sets counters to default values and initializes the tokens
     /// Vector.
-    fn new(source: String) -> Self {
+    fn new(source: String, origin: &Origin) -> Self {
         Self {
-            source,
+            source: source.chars().collect(),
             tokens: Vec::new(),
+            comments: Vec::new(),
             start: 0,
             current: 0,
-            line: 1,
+            line: origin.line as usize,
+            line_start: 0,
+            start_column: 1,
+            first_line: origin.line as usize,
+            column_offset: origin.column.saturating_sub(1),
         }
     }
 
@@ -43,15 +76,17 @@ impl Scanner {
     /// When there are any errors while a token gets scanned, the **had_error**
     /// is set to true and after scanning, the program will exit with an error.
     /// # Move occurence
-    /// When `scan_tokens` is called, the scanner gets consumed and only the Vector
-    /// of Tokens remains. Scanner cannot be used again (it probably doesn't need to)
-    fn scan_tokens(mut self) -> Result<Vec<Token>> {
+    /// When `scan_tokens` is called, the scanner gets consumed and only the Vectors
+    /// of Tokens and Comments remain. Scanner cannot be used again (it probably doesn't need to)
+    fn scan_tokens(mut self, reporter: &mut dyn Reporter) -> Result<(Vec<Token>, Vec<Comment>)> {
         let mut had_error = false;
 
         while !self.is_at_end() {
             self.start = self.current;
+            let offset = if self.line == self.first_line { self.column_offset } else { 0 };
+            self.start_column = (self.start - self.line_start) as u32 + 1 + offset;
             if let Err(e) = self.scan_token() {
-                eprintln!("{}", e);
+                reporter.report(Diagnostic::error(e));
                 had_error = true;
             }
         }
@@ -63,7 +98,7 @@ impl Scanner {
         if had_error {
             Err(ScanError::HadError.into())
         } else {
-            Ok(self.tokens) // Return the reference to the tokens, not the cloned tokens itself
+            Ok((self.tokens, self.comments)) // Return the reference to the tokens, not the cloned tokens itself
         }
     }
 
@@ -77,6 +112,7 @@ impl Scanner {
             ' ' | '\r' | '\t' => Ok(()), // do nothing, just advance forward
             '\n' => {
                 self.line += 1; // Increment line counter
+                self.line_start = self.current;
                 Ok(())
             }
 
@@ -85,7 +121,10 @@ impl Scanner {
             ')' => self.add_token(TokenType::RightParen),
             '{' => self.add_token(TokenType::LeftBrace),
             '}' => self.add_token(TokenType::RightBrace),
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
             ',' => self.add_token(TokenType::Comma),
+            ':' => self.add_token(TokenType::Colon),
             '.' => self.add_token(TokenType::Dot),
             '-' => self.add_token(TokenType::Minus),
             '+' => self.add_token(TokenType::Plus),
@@ -122,12 +161,32 @@ impl Scanner {
                 }
             }
 
+            '~' => {
+                if self.match_advance('/')? {
+                    self.add_token(TokenType::TildeSlash) // Floor division
+                } else {
+                    Err(ScanError::UnexpectedCharacter(c, self.line).into())
+                }
+            }
+            '?' => {
+                if self.match_advance('?')? {
+                    self.add_token(TokenType::QuestionQuestion) // Nil coalescing
+                } else if self.match_advance('.')? {
+                    self.add_token(TokenType::QuestionDot) // Optional chaining
+                } else {
+                    Err(ScanError::UnexpectedCharacter(c, self.line).into())
+                }
+            }
+
             // Slash
             '/' => {
                 if self.match_advance('/')? {
                     while !self.is_at_end() && self.peek()? != '\n' {
                         self.advance()?;
                     }
+                    let text: String = self.source[self.start..self.current].iter().collect();
+                    let trailing = self.tokens.last().is_some_and(|token| token.line() as usize == self.line);
+                    self.comments.push(Comment { line: self.line as u32, text: text.trim_end().to_string(), trailing });
                     Ok(())
                 } else {
                     self.add_token(TokenType::Slash)
@@ -140,10 +199,10 @@ impl Scanner {
             _ => {
                 if c.is_numeric() {
                     self.handle_number() // We don't want to match every digit, so we just handle this in the default case
-                } else if c.is_alphabetic() {
-                    self.handle_identifier() // Same here with a random alphabetic character
+                } else if c.is_alphabetic() || c == '_' {
+                    self.handle_identifier() // Same here with a random alphabetic character or underscore
                 } else {
-                    return Err(ScanError::UnexpectedCharacter(c, self.line).into());
+                    Err(ScanError::UnexpectedCharacter(c, self.line).into())
                 }
             }
         }
@@ -174,23 +233,23 @@ impl Scanner {
     /// Gets the current char without stepping
     fn peek(&self) -> Result<char> {
         self.source
-            .chars()
-            .nth(self.current)
+            .get(self.current)
+            .copied()
             .ok_or(ScanError::CharacterAccessError(self.line).into())
     }
 
     /// Gets the next char without stepping
     fn peek_next(&self) -> Result<char> {
         self.source
-            .chars()
-            .nth(self.current + 1)
+            .get(self.current + 1)
+            .copied()
             .ok_or(ScanError::CharacterAccessError(self.line).into())
     }
 
     /// Adds a `Token` to the token vector without any literal
     fn add_token(&mut self, token_type: TokenType) -> Result<()> {
         let lexeme_text = self.get_lexeme_text()?;
-        let token = Token::new(token_type, lexeme_text, None, self.line as u32);
+        let token = Token::new(token_type, lexeme_text, None, self.line as u32).with_column(self.start_column);
         self.tokens.push(token);
         Ok(())
     }
@@ -198,7 +257,7 @@ impl Scanner {
     /// Adds a `Token` to the token vector with a literal
     fn add_token_with_literal(&mut self, token_type: TokenType, literal: Value) -> Result<()> {
         let lexeme_text = self.get_lexeme_text()?;
-        let token = Token::new(token_type, lexeme_text, Some(literal), self.line as u32);
+        let token = Token::new(token_type, lexeme_text, Some(literal), self.line as u32).with_column(self.start_column);
         self.tokens.push(token);
         Ok(())
     }
@@ -209,7 +268,7 @@ impl Scanner {
             .source
             .get(self.start..self.current)
             .ok_or(ScanError::CharacterAccessError(self.line))?;
-        Ok(text.to_string())
+        Ok(text.iter().collect())
     }
 
     /// Checks if the `current` pointer is at the end or above of the source String
@@ -219,18 +278,25 @@ impl Scanner {
 
     /// Checks if the current pointer could advance one and then peek
     fn can_peek_next(&self) -> bool {
-        self.current < (self.source.len() - 1)
+        self.current + 1 < self.source.len()
     }
 
     /// Gets called when scan_token encounters a " character, so the
     /// String can be correctly saved as a literal token.
     fn handle_string(&mut self) -> Result<()> {
+        if self.match_advance('"')? {
+            if self.match_advance('"')? {
+                return self.handle_text_block();
+            }
+            return self.add_token_with_literal(TokenType::String, Value::String(String::new())); // ""
+        }
+
         let string_start_line = self.line;
         while !self.is_at_end() && self.peek()? != '"' {
-            if self.peek()? == '\n' {
+            if self.advance()? == '\n' {
                 self.line += 1;
+                self.line_start = self.current;
             }
-            self.advance()?;
         }
 
         if self.is_at_end() {
@@ -243,37 +309,141 @@ impl Scanner {
             .source
             .get((self.start + 1)..(self.current - 1))
             .ok_or(ScanError::CharacterAccessError(self.line))?
-            .to_string(); // Text between ""
+            .iter()
+            .collect(); // Text between ""
         self.add_token_with_literal(TokenType::String, Value::String(value))
     }
 
+    /// Gets called after an opening `"""`. The text block ends at the next
+    /// `"""`, quotes right before it belong to the text. See `strip_indentation`
+    /// for how the text becomes the value.
+    fn handle_text_block(&mut self) -> Result<()> {
+        let string_start_line = self.line;
+        let text_start = self.current;
+        while self.source.get(self.current..self.current + 3) != Some(&['"'; 3][..])
+            || self.source.get(self.current + 3) == Some(&'"')
+        {
+            if self.is_at_end() {
+                return Err(ScanError::UnterminatedString(string_start_line).into());
+            }
+            if self.advance()? == '\n' {
+                self.line += 1;
+                self.line_start = self.current;
+            }
+        }
+
+        let text: String = self.source[text_start..self.current].iter().collect();
+        self.current += 3; // The closing """
+        self.add_token_with_literal(TokenType::String, Value::String(strip_indentation(&text)))
+    }
+
     /// Gets called when scan_tokens encounters a digit character, so the
     /// Number that the characters represent can be parsed and correctly
-    /// saved as a literal token.
+    /// saved as a literal token. Besides decimals like `4.2` there are
+    /// hexadecimal (`0xFF`) and binary (`0b1010`) ints and exponents
+    /// (`1.5e-3`), and underscores can separate digits (`1_000`).
     fn handle_number(&mut self) -> Result<()> {
-        while !self.is_at_end() && self.peek()?.is_numeric() {
-            self.advance()?;
+        if self.get_lexeme_text()? == "0" && !self.is_at_end() && matches!(self.peek()?, 'x' | 'X' | 'b' | 'B') {
+            let radix = if self.advance()?.eq_ignore_ascii_case(&'x') { 16 } else { 2 };
+            return self.handle_radix_number(radix);
         }
 
+        self.digits(10)?;
+        let mut fractional = false;
         if !self.is_at_end()
             && self.peek()? == '.'
             && self.can_peek_next()
-            && self.peek_next()?.is_numeric()
+            && self.peek_next()?.is_ascii_digit()
         {
             self.advance()?; // Consume the .
-            while !self.is_at_end() && self.peek()?.is_numeric() {
+            self.digits(10)?;
+            fractional = true;
+        }
+        if !self.is_at_end() && matches!(self.peek()?, 'e' | 'E') {
+            self.advance()?;
+            if !self.is_at_end() && matches!(self.peek()?, '+' | '-') {
                 self.advance()?;
             }
+            if !self.digits(10)? {
+                return Err(self.malformed_number("expected digits in the exponent"));
+            }
+            fractional = true;
         }
+        self.end_of_number("expected only digits")?;
 
-        let lexeme = self.get_lexeme_text()?;
-        let value = lexeme.parse::<f64>()?;
+        // Whole numbers are ints, a lossy number would silently change one that is too large
+        let lexeme: String = self.get_lexeme_text()?.chars().filter(|c| *c != '_').collect();
+        if !fractional {
+            let int = lexeme.parse::<i64>().map_err(|_| self.malformed_number("too large for an int"))?;
+            return self.add_token_with_literal(TokenType::Number, Value::Int(int));
+        }
+        let value = lexeme
+            .parse::<Number>()
+            .ok()
+            .filter(|value| value.to_f64().is_finite()) // 1e999 is no literal for infinity
+            .ok_or_else(|| ScanError::InvalidNumber(lexeme.clone(), self.line))?;
 
         self.add_token_with_literal(TokenType::Number, Value::Number(value))
     }
 
+    /// Scans the digits of a hexadecimal or binary int, after its prefix
+    fn handle_radix_number(&mut self, radix: u32) -> Result<()> {
+        let (missing, invalid) = match radix {
+            16 => ("expected hexadecimal digits after 0x", "expected only hexadecimal digits"),
+            _ => ("expected binary digits after 0b", "expected only binary digits"),
+        };
+        if !self.digits(radix)? {
+            return Err(self.malformed_number(missing));
+        }
+        self.end_of_number(invalid)?;
+
+        let digits: String = self.get_lexeme_text()?.chars().skip(2).filter(|c| *c != '_').collect();
+        let int = i64::from_str_radix(&digits, radix).map_err(|_| self.malformed_number("too large for an int"))?;
+        self.add_token_with_literal(TokenType::Number, Value::Int(int))
+    }
+
+    /// Consumes digits of the radix, which single underscores may separate.
+    /// Returns whether there were any digits.
+    fn digits(&mut self, radix: u32) -> Result<bool> {
+        let mut any = false;
+        while !self.is_at_end() {
+            let c = self.peek()?;
+            if c.is_digit(radix) {
+                any = true;
+            } else if c == '_' {
+                let after_digit = self.current > self.start && self.source[self.current - 1].is_digit(radix);
+                if !after_digit || !self.can_peek_next() || !self.peek_next()?.is_digit(radix) {
+                    return Err(self.malformed_number("underscores can only separate digits"));
+                }
+            } else {
+                break;
+            }
+            self.advance()?;
+        }
+        Ok(any)
+    }
+
+    /// Fails if a letter or digit directly follows a number, which is most
+    /// likely a typo in the number
+    fn end_of_number(&mut self, reason: &'static str) -> Result<()> {
+        if !self.is_at_end() && (self.peek()?.is_alphanumeric() || self.peek()? == '_') {
+            return Err(self.malformed_number(reason));
+        }
+        Ok(())
+    }
+
+    /// Skips the rest of a malformed number, so it isn't scanned as other
+    /// tokens, and returns the error for it
+    fn malformed_number(&mut self, reason: &'static str) -> LoxError {
+        while self.source.get(self.current).is_some_and(|c| c.is_alphanumeric() || *c == '_') {
+            self.current += 1;
+        }
+        let lexeme: String = self.source[self.start..self.current].iter().collect();
+        ScanError::MalformedNumber(lexeme, reason, self.line).into()
+    }
+
     fn handle_identifier(&mut self) -> Result<()> {
-        while !self.is_at_end() && self.peek()?.is_alphanumeric() {
+        while !self.is_at_end() && (self.peek()?.is_alphanumeric() || self.peek()? == '_') {
             self.advance()?;
         }
 
@@ -293,42 +463,98 @@ impl Scanner {
     }
 }
 
+/// Turns the text between the triple quotes into the string value. If the
+/// opening quotes end their line, the text is a block: that first line
+/// break is dropped, as is the line of the closing quotes if nothing else
+/// is on it, and the indentation all lines have in common is stripped:
+///
+/// ```text
+/// var help = """
+///     usage: greet <name>
+///       prints a greeting
+///     """;
+/// ```
+///
+/// is `"usage: greet <name>\n  prints a greeting"`. Text that starts on
+/// the line of the opening quotes is taken as it is.
+fn strip_indentation(text: &str) -> String {
+    let body = match text.split_once('\n') {
+        Some((first, body)) if first.trim().is_empty() => body,
+        _ => return text.to_string(),
+    };
+    let indentation = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+    let mut lines: Vec<&str> = body.split('\n').collect();
+    let closing = lines.last().filter(|line| line.trim().is_empty()).map(|line| indentation(line));
+    if closing.is_some() {
+        lines.pop();
+    }
+
+    let common = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| indentation(line))
+        .chain(closing)
+        .min()
+        .unwrap_or(0);
+    let lines: Vec<&str> = lines
+        .iter()
+        .map(|line| if line.trim().is_empty() { "" } else { &line[common..] })
+        .collect();
+    lines.join("\n")
+}
+
 /// Matches a keyword to a TokenType. If the keyword is not found, it returns None.
-fn match_keyword(lexeme: &str) -> Option<TokenType> {
+pub fn match_keyword(lexeme: &str) -> Option<TokenType> {
     match lexeme {
         "and" => Some(TokenType::And),
+        "async" => Some(TokenType::Async),
+        "await" => Some(TokenType::Await),
+        "break" => Some(TokenType::Break),
+        "case" => Some(TokenType::Case),
+        "catch" => Some(TokenType::Catch),
         "class" => Some(TokenType::Class),
+        "const" => Some(TokenType::Const),
+        "continue" => Some(TokenType::Continue),
+        "default" => Some(TokenType::Default),
         "else" => Some(TokenType::Else),
         "false" => Some(TokenType::False),
+        "finally" => Some(TokenType::Finally),
         "for" => Some(TokenType::For),
         "fun" => Some(TokenType::Fun),
         "if" => Some(TokenType::If),
+        "in" => Some(TokenType::In),
+        "is" => Some(TokenType::Is),
         "nil" => Some(TokenType::Nil),
         "or" => Some(TokenType::Or),
         "print" => Some(TokenType::Print),
         "return" => Some(TokenType::Return),
         "super" => Some(TokenType::Super),
+        "switch" => Some(TokenType::Switch),
         "this" => Some(TokenType::This),
+        "throw" => Some(TokenType::Throw),
         "true" => Some(TokenType::True),
+        "try" => Some(TokenType::Try),
         "var" => Some(TokenType::Var),
         "while" => Some(TokenType::While),
+        "with" => Some(TokenType::With),
         _ => None,
     }
 }
 
-/// ---------- Tests for the Scanner module ----------
+// ---------- Tests for the Scanner module ----------
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::diagnostic::CollectingReporter;
 
     #[test]
     fn hello_world_scan() {
         let source = "print \"Hello, World!\";".to_string();
-        let tokens = scan_tokens(source).expect("Token Scanning failed!");
+        let tokens = scan_tokens(source, &mut CollectingReporter::default()).expect("Token Scanning failed!");
 
         let cmp_token = Token::new(TokenType::Print, "print".to_string(), None, 1);
-        assert_eq!(*tokens.get(0).unwrap(), cmp_token);
+        assert_eq!(*tokens.first().unwrap(), cmp_token);
 
         let cmp_token = Token::new(
             TokenType::String,
@@ -348,10 +574,10 @@ mod tests {
     #[test]
     fn keyword_scan() {
         let source = "var x = true;\r\nclass TestClass {\r\n    testMethod(s) {\r\n        print s;\r\n    }\r\n}".to_string();
-        let tokens = scan_tokens(source).expect("Token Scanning failed!");
+        let tokens = scan_tokens(source, &mut CollectingReporter::default()).expect("Token Scanning failed!");
 
         let cmp_token = Token::new(TokenType::Var, "var".to_string(), None, 1);
-        assert_eq!(*tokens.get(0).unwrap(), cmp_token);
+        assert_eq!(*tokens.first().unwrap(), cmp_token);
 
         let cmp_token = Token::new(TokenType::Identifier, "x".to_string(), None, 1);
         assert_eq!(*tokens.get(1).unwrap(), cmp_token);
@@ -411,20 +637,20 @@ mod tests {
     #[test]
     fn number_scan() {
         let source = "123 45.67".to_string();
-        let tokens = scan_tokens(source).expect("Token Scanning failed!");
+        let tokens = scan_tokens(source, &mut CollectingReporter::default()).expect("Token Scanning failed!");
 
         let cmp_token = Token::new(
             TokenType::Number,
             "123".to_string(),
-            Some(Value::Number(123.0)),
+            Some(Value::Int(123)),
             1,
         );
-        assert_eq!(*tokens.get(0).unwrap(), cmp_token);
+        assert_eq!(*tokens.first().unwrap(), cmp_token);
 
         let cmp_token = Token::new(
             TokenType::Number,
             "45.67".to_string(),
-            Some(Value::Number(45.67)),
+            Some(Value::Number("45.67".parse().expect("Invalid number!"))),
             1,
         );
         assert_eq!(*tokens.get(1).unwrap(), cmp_token);
@@ -433,10 +659,36 @@ mod tests {
         assert_eq!(*tokens.get(2).unwrap(), cmp_token);
     }
 
+    #[test]
+    fn number_literal_forms() {
+        let literal = |source: &str| {
+            let tokens = scan_tokens(source.to_string(), &mut CollectingReporter::default()).expect("Token Scanning failed!");
+            tokens.first().and_then(Token::literal)
+        };
+        let number = |text: &str| Some(Value::Number(text.parse().expect("Invalid number!")));
+        assert_eq!(literal("0xFF"), Some(Value::Int(255)));
+        assert_eq!(literal("0b1010"), Some(Value::Int(10)));
+        assert_eq!(literal("1_000_000"), Some(Value::Int(1_000_000)));
+        assert_eq!(literal("1e9"), number("1000000000"));
+        assert_eq!(literal("1.5e-3"), number("0.0015"));
+        assert_eq!(literal("2_5.0_1E+0_2"), number("2501"));
+    }
+
+    #[test]
+    fn malformed_numbers_are_scan_errors() {
+        for source in ["0x", "0xFG", "0b102", "1e", "1e+", "1__0", "1_", "12ab", "0x_1", "0x8000000000000000", "9223372036854775808"] {
+            let mut reporter = CollectingReporter::default();
+            assert!(scan_tokens(source.to_string(), &mut reporter).is_err(), "{} was accepted", source);
+            let messages: Vec<&str> = reporter.diagnostics().iter().map(|d| d.message.as_str()).collect();
+            assert_eq!(messages.len(), 1, "{} gave {:?}", source, messages);
+            assert!(messages[0].contains("E0105"), "{} gave {:?}", source, messages);
+        }
+    }
+
     #[test]
     fn string_scan() {
         let source = "\"Hello, World!\"".to_string();
-        let tokens = scan_tokens(source).expect("Token Scanning failed!");
+        let tokens = scan_tokens(source, &mut CollectingReporter::default()).expect("Token Scanning failed!");
 
         let cmp_token = Token::new(
             TokenType::String,
@@ -444,19 +696,31 @@ mod tests {
             Some(Value::String("Hello, World!".to_string())),
             1,
         );
-        assert_eq!(*tokens.get(0).unwrap(), cmp_token);
+        assert_eq!(*tokens.first().unwrap(), cmp_token);
 
         let cmp_token = Token::new(TokenType::Eof, String::new(), None, 1);
         assert_eq!(*tokens.get(1).unwrap(), cmp_token);
     }
 
+    #[test]
+    fn identifier_scan_with_underscores() {
+        let source = "_tmp snake_case __init__ _ a1_b2 _while".to_string();
+        let tokens = scan_tokens(source, &mut CollectingReporter::default()).expect("Token Scanning failed!");
+
+        let names = ["_tmp", "snake_case", "__init__", "_", "a1_b2", "_while"];
+        assert_eq!(tokens.len(), names.len() + 1);
+        for (token, name) in tokens.iter().zip(names) {
+            assert_eq!(*token, Token::new(TokenType::Identifier, name.to_string(), None, 1));
+        }
+    }
+
     #[test]
     fn comment_scan() {
         let source = "// This is a comment\nvar x = 42;".to_string();
-        let tokens = scan_tokens(source).expect("Token Scanning failed!");
+        let tokens = scan_tokens(source, &mut CollectingReporter::default()).expect("Token Scanning failed!");
 
         let cmp_token = Token::new(TokenType::Var, "var".to_string(), None, 2);
-        assert_eq!(*tokens.get(0).unwrap(), cmp_token);
+        assert_eq!(*tokens.first().unwrap(), cmp_token);
 
         let cmp_token = Token::new(TokenType::Identifier, "x".to_string(), None, 2);
         assert_eq!(*tokens.get(1).unwrap(), cmp_token);
@@ -467,7 +731,7 @@ mod tests {
         let cmp_token = Token::new(
             TokenType::Number,
             "42".to_string(),
-            Some(Value::Number(42.0)),
+            Some(Value::Int(42)),
             2,
         );
         assert_eq!(*tokens.get(3).unwrap(), cmp_token);
@@ -478,4 +742,71 @@ mod tests {
         let cmp_token = Token::new(TokenType::Eof, String::new(), None, 2);
         assert_eq!(*tokens.get(5).unwrap(), cmp_token);
     }
+
+    #[test]
+    fn comments_are_kept_aside() {
+        let source = "// First  \nvar x = \"//\"; // second\n".to_string();
+        let (tokens, comments) = scan_with_comments(source, &mut CollectingReporter::default()).expect("Token Scanning failed!");
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(
+            comments,
+            vec![
+                Comment { line: 1, text: "// First".to_string(), trailing: false },
+                Comment { line: 2, text: "// second".to_string(), trailing: true },
+            ]
+        );
+    }
+
+    #[test]
+    fn tokens_know_their_column() {
+        let source = "var s = \"a\nb\";\n\tprint s;".to_string();
+        let tokens = scan_tokens(source, &mut CollectingReporter::default()).expect("Token Scanning failed!");
+        let columns: Vec<u32> = tokens.iter().map(Token::column).collect();
+        assert_eq!(columns, vec![1, 5, 7, 9, 3, 2, 8, 9, 0]);
+    }
+
+    #[test]
+    fn snippets_are_shifted_to_their_origin() {
+        let origin = Origin::new("page.md", 10, 7);
+        let tokens = scan_tokens_at("var a\n  = 1;".to_string(), &origin, &mut CollectingReporter::default())
+            .expect("Token Scanning failed!");
+        let positions: Vec<(u32, u32)> = tokens.iter().map(|t| (t.line(), t.column())).collect();
+        assert_eq!(positions, vec![(10, 7), (10, 11), (11, 3), (11, 5), (11, 6), (11, 0)]);
+    }
+
+    #[test]
+    fn non_ascii_input_is_scanned_by_char() {
+        let source = "var é = \"ünï\"; @".to_string();
+        let mut reporter = CollectingReporter::default();
+        assert!(scan_tokens(source, &mut reporter).is_err());
+        assert!(reporter.diagnostics()[0].message.contains("[E0102]"));
+
+        let tokens = scan_tokens("\"ünï\" é".to_string(), &mut reporter).expect("Token Scanning failed!");
+        assert_eq!(tokens[0].literal(), Some(Value::String("ünï".to_string())));
+        assert_eq!(tokens[1].lexeme(), "é");
+        assert_eq!(tokens[1].column(), 7);
+    }
+
+    #[test]
+    fn text_blocks_strip_common_indentation() {
+        let source = "var help = \"\"\"\n    usage: greet\n\n      <name>\n    \"\"\";\nprint \"\"\"say \"hi\"\"\"\";".to_string();
+        let tokens = scan_tokens(source, &mut CollectingReporter::default()).expect("Token Scanning failed!");
+        assert_eq!(tokens[3].literal(), Some(Value::String("usage: greet\n\n  <name>".to_string())));
+        assert_eq!(tokens[3].line(), 5);
+        assert_eq!(tokens[6].literal(), Some(Value::String("say \"hi\"".to_string())));
+
+        assert_eq!(strip_indentation("\n\t\ta\n\t\t\tb"), "a\n\tb");
+        assert_eq!(strip_indentation("  kept  \n  as is"), "  kept  \n  as is");
+
+        let mut reporter = CollectingReporter::default();
+        assert!(scan_tokens("\"\"\"\nnever closed\"\"".to_string(), &mut reporter).is_err());
+        assert!(reporter.diagnostics()[0].message.contains("[E0101]"));
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn scanning_never_panics(source in "\\PC*") {
+            let _ = scan_tokens(source, &mut CollectingReporter::default());
+        }
+    }
 }
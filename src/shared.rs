@@ -0,0 +1,99 @@
+//! Shared mutable state of the interpreter. By default it is built on
+//! `Rc<RefCell<T>>`, which is cheap but keeps the interpreter on one thread.
+//! With the `sync` feature it is built on `Arc<RwLock<T>>` instead, which
+//! makes [Interpreter](crate::interpreter::Interpreter) `Send`, so it can be
+//! moved to worker threads (e.g. inside an async server).
+
+// External dependencies
+use std::ops::{Deref, DerefMut};
+#[cfg(not(feature = "sync"))]
+use std::{cell::RefCell, rc::Rc};
+#[cfg(feature = "sync")]
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Reference counted, mutable value. The same API is available with and
+/// without the `sync` feature, so the interpreter doesn't care which one it gets.
+#[derive(Debug, Default)]
+pub struct Shared<T> {
+    #[cfg(not(feature = "sync"))]
+    inner: Rc<RefCell<T>>,
+    #[cfg(feature = "sync")]
+    inner: Arc<RwLock<T>>,
+}
+
+impl<T> Clone for Shared<T> {
+    // Derive would require T: Clone, but only the pointer gets cloned
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone() }
+    }
+}
+
+#[cfg(not(feature = "sync"))]
+impl<T> Shared<T> {
+    pub fn new(value: T) -> Self {
+        Self { inner: Rc::new(RefCell::new(value)) }
+    }
+
+    /// Immutable access to the value. Panics if it is currently borrowed mutably.
+    pub fn borrow(&self) -> impl Deref<Target = T> + '_ {
+        self.inner.borrow()
+    }
+
+    /// Mutable access to the value. Panics if it is currently borrowed.
+    pub fn borrow_mut(&self) -> impl DerefMut<Target = T> + '_ {
+        self.inner.borrow_mut()
+    }
+
+    /// Address of the value, which identifies it as long as it is alive
+    pub fn address(&self) -> usize {
+        Rc::as_ptr(&self.inner) as *const () as usize
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<T> Shared<T> {
+    pub fn new(value: T) -> Self {
+        Self { inner: Arc::new(RwLock::new(value)) }
+    }
+
+    /// Immutable access to the value. Blocks while another thread writes to it.
+    pub fn borrow(&self) -> impl Deref<Target = T> + '_ {
+        // A poisoned lock only means another thread panicked, the value itself is still usable
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Mutable access to the value. Blocks while another thread accesses it.
+    pub fn borrow_mut(&self) -> impl DerefMut<Target = T> + '_ {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Address of the value, which identifies it as long as it is alive
+    pub fn address(&self) -> usize {
+        Arc::as_ptr(&self.inner) as *const () as usize
+    }
+}
+
+/// Reference counted, immutable value, like the declaration of a function.
+/// `Rc<T>` by default and `Arc<T>` with the `sync` feature, for the same
+/// reason as [Shared].
+#[cfg(not(feature = "sync"))]
+pub type SharedRef<T> = Rc<T>;
+/// Reference counted, immutable value, like the declaration of a function.
+/// `Rc<T>` by default and `Arc<T>` with the `sync` feature, for the same
+/// reason as [Shared].
+#[cfg(feature = "sync")]
+pub type SharedRef<T> = Arc<T>;
+
+/// Marker for things the interpreter owns, like [ExecutionHooks](crate::interpreter::ExecutionHooks).
+/// With the `sync` feature they have to be `Send`, otherwise anything goes.
+#[cfg(feature = "sync")]
+pub trait MaybeSend: Send {}
+#[cfg(feature = "sync")]
+impl<T: Send> MaybeSend for T {}
+
+/// Marker for things the interpreter owns, like [ExecutionHooks](crate::interpreter::ExecutionHooks).
+/// With the `sync` feature they have to be `Send`, otherwise anything goes.
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSend {}
+#[cfg(not(feature = "sync"))]
+impl<T> MaybeSend for T {}
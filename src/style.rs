@@ -0,0 +1,217 @@
+// External dependencies
+use std::ffi::OsString;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// When jlox colors its output, set through `--color`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Always color, even when stderr is not a terminal or `NO_COLOR` is set
+    Always,
+    /// Color if stderr is a terminal and `NO_COLOR` is not set
+    Auto,
+    /// Never color
+    Never,
+}
+
+/// The colors a theme can use, written as in the config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    None,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+impl Color {
+    fn from_name(name: &str) -> Option<Color> {
+        match name {
+            "none" => Some(Color::None),
+            "red" => Some(Color::Red),
+            "green" => Some(Color::Green),
+            "yellow" => Some(Color::Yellow),
+            "blue" => Some(Color::Blue),
+            "magenta" => Some(Color::Magenta),
+            "cyan" => Some(Color::Cyan),
+            _ => None,
+        }
+    }
+
+    /// ANSI foreground color code
+    fn code(self) -> Option<u8> {
+        match self {
+            Color::None => None,
+            Color::Red => Some(31),
+            Color::Green => Some(32),
+            Color::Yellow => Some(33),
+            Color::Blue => Some(34),
+            Color::Magenta => Some(35),
+            Color::Cyan => Some(36),
+        }
+    }
+}
+
+/// Colors and characters of the terminal output. The defaults can be
+/// overridden in the config file (see [config_path]), one `key = value`
+/// per line, `#` starts a comment:
+///
+/// ```text
+/// error = magenta
+/// warning = none
+/// unicode = false
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    /// Errors, failed test cases and removed statements
+    pub error: Color,
+    /// Warnings
+    pub warning: Color,
+    /// Passed test cases and added statements
+    pub success: Color,
+    /// Whether Unicode symbols may be used, or only ASCII
+    pub unicode: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            error: Color::Red,
+            warning: Color::Yellow,
+            success: Color::Green,
+            unicode: true,
+        }
+    }
+}
+
+impl Theme {
+    /// Reads the theme from the config file. Unknown keys and invalid
+    /// values are logged and skipped, a missing file means the defaults.
+    pub fn load() -> Theme {
+        let mut theme = Theme::default();
+        let Some(path) = config_path() else {
+            return theme;
+        };
+        let Ok(config) = std::fs::read_to_string(&path) else {
+            return theme;
+        };
+
+        for (index, line) in config.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let applied = line.split_once('=').and_then(|(key, value)| theme.set(key.trim(), value.trim()));
+            if applied.is_none() {
+                warn!("Ignoring invalid line {} of {}: {}", index + 1, path.display(), line);
+            }
+        }
+        theme
+    }
+
+    /// Sets a single setting, `None` if the key or value is invalid
+    fn set(&mut self, key: &str, value: &str) -> Option<()> {
+        match key {
+            "error" => self.error = Color::from_name(value)?,
+            "warning" => self.warning = Color::from_name(value)?,
+            "success" => self.success = Color::from_name(value)?,
+            "unicode" => self.unicode = value.parse().ok()?,
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+/// Location of the config file: `$JLOX_CONFIG` if it is set (an empty value
+/// disables the config file), otherwise `jlox/config` inside the
+/// `$XDG_CONFIG_HOME` or `~/.config` directory.
+fn config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("JLOX_CONFIG") {
+        return (!path.is_empty()).then(|| PathBuf::from(path));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("jlox").join("config"))
+}
+
+/// The settings every output function uses, see [init]
+struct Style {
+    color: bool,
+    theme: Theme,
+}
+
+static STYLE: OnceLock<Style> = OnceLock::new();
+
+/// Whether output should be colored with the given choice
+pub fn use_color(choice: ColorChoice) -> bool {
+    colored(choice, std::io::stderr().is_terminal(), std::env::var_os("NO_COLOR"))
+}
+
+/// The decision of [use_color], given whether stderr is a terminal
+/// and the value of `NO_COLOR`
+fn colored(choice: ColorChoice, terminal: bool, no_color: Option<OsString>) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => terminal && no_color.is_none_or(|v| v.is_empty()),
+    }
+}
+
+/// Enables or disables colors and loads the theme. Only the first call
+/// has an effect. Logging should be set up before, so problems in the
+/// config file show up.
+pub fn init(color: bool) {
+    STYLE.get_or_init(|| Style { color, theme: Theme::load() });
+}
+
+fn style() -> &'static Style {
+    STYLE.get_or_init(|| Style { color: false, theme: Theme::default() })
+}
+
+/// The active theme
+pub fn theme() -> Theme {
+    style().theme
+}
+
+/// Wraps the text in the escape codes of the color, if colors are enabled
+pub fn paint(text: &str, color: Color) -> String {
+    match color.code() {
+        Some(code) if style().color => format!("\x1b[{}m{}\x1b[0m", code, text),
+        _ => text.to_string(),
+    }
+}
+
+/// The symbol if the theme allows Unicode, otherwise its ASCII fallback
+pub fn symbol(unicode: &'static str, ascii: &'static str) -> &'static str {
+    if theme().unicode {
+        unicode
+    } else {
+        ascii
+    }
+}
+
+// ---------- Tests for the style module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_color_turns_off_automatic_colors() {
+        assert!(colored(ColorChoice::Auto, true, None));
+        assert!(!colored(ColorChoice::Auto, true, Some("1".into())));
+        assert!(colored(ColorChoice::Auto, true, Some("".into()))); // An empty NO_COLOR counts as unset
+        assert!(!colored(ColorChoice::Auto, false, None));
+    }
+
+    #[test]
+    fn the_flag_wins_over_the_environment() {
+        assert!(!colored(ColorChoice::Never, true, None));
+        assert!(colored(ColorChoice::Always, false, Some("1".into())));
+    }
+}
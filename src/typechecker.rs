@@ -0,0 +1,704 @@
+// External dependencies
+use std::collections::HashMap;
+use std::fmt::Display;
+
+// Internal dependencies
+use crate::diagnostic::{Diagnostic, Reporter};
+use crate::errors::{Result, TypeError};
+use crate::obj::annotation::TypeAnnotation;
+use crate::obj::expression::{Expression, Pattern};
+use crate::obj::function::FunctionDeclaration;
+use crate::obj::statement::Statement;
+use crate::obj::token::Token;
+use crate::obj::token_type::TokenType;
+use crate::obj::value::Value;
+
+/// The static type of an expression. Checking is gradual: everything the
+/// checker cannot know, like the value of a variable without annotation,
+/// is [Type::Any] and accepted everywhere.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Any,
+    Nil,
+    Bool,
+    /// Ints and numbers, which mix freely in arithmetic
+    Number,
+    String,
+    /// Lists and maps can hold elements of any type, so their elements are [Type::Any]
+    List,
+    Map,
+    /// 0: parameter types, `None` if they are unknown, 1: return type
+    Function(Option<Vec<Type>>, Box<Type>),
+}
+
+impl Type {
+    /// The type an annotation names, `None` for unknown names
+    pub fn from_annotation(annotation: &TypeAnnotation) -> Option<Type> {
+        match annotation.name.lexeme().as_str() {
+            "Any" => Some(Type::Any),
+            "Nil" => Some(Type::Nil),
+            "Bool" => Some(Type::Bool),
+            "Number" => Some(Type::Number),
+            "String" => Some(Type::String),
+            "List" => Some(Type::List),
+            "Map" => Some(Type::Map),
+            "Function" => Some(Type::Function(None, Box::new(Type::Any))),
+            _ => None,
+        }
+    }
+
+    /// Whether a value of type `other` may be used where `self` is expected
+    pub fn accepts(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Any, _) | (_, Type::Any) => true,
+            (Type::Function(..), Type::Function(..)) => true,
+            _ => self == other,
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Any => write!(f, "Any"),
+            Type::Nil => write!(f, "Nil"),
+            Type::Bool => write!(f, "Bool"),
+            Type::Number => write!(f, "Number"),
+            Type::String => write!(f, "String"),
+            Type::List => write!(f, "List"),
+            Type::Map => write!(f, "Map"),
+            Type::Function(..) => write!(f, "Function"),
+        }
+    }
+}
+
+/// Signatures of the native functions: name, parameter types, return type
+const NATIVE_SIGNATURES: &[(&str, &[Type], Type)] = &[
+    #[cfg(feature = "ffi")]
+    ("callNative", &[Type::String, Type::String, Type::String, Type::List], Type::Any),
+    ("callstack", &[], Type::List),
+    ("channel", &[], Type::Any),
+    ("clock", &[], Type::Number),
+    ("clone", &[Type::Any], Type::Any),
+    ("contains", &[Type::String, Type::String], Type::Bool),
+    ("coroutine", &[Type::Any], Type::Any),
+    ("endsWith", &[Type::String, Type::String], Type::Bool),
+    ("eval", &[Type::String], Type::Any),
+    ("evalIn", &[Type::String, Type::Map], Type::Any),
+    ("exit", &[Type::Number], Type::Nil),
+    ("fields", &[Type::Any], Type::List),
+    ("filter", &[Type::List, Type::Any], Type::List),
+    ("freeze", &[Type::Any], Type::Any),
+    ("gc", &[], Type::Nil),
+    ("getEnv", &[Type::String], Type::Any),
+    ("getattr", &[Type::Any, Type::String], Type::Any),
+    ("has", &[Type::Map, Type::Any], Type::Bool),
+    ("indexOf", &[Type::String, Type::String], Type::Number),
+    ("input", &[Type::Any], Type::Any),
+    ("insert", &[Type::List, Type::Number, Type::Any], Type::Nil),
+    ("join", &[Type::Any], Type::Any),
+    ("keys", &[Type::Map], Type::List),
+    ("len", &[Type::Any], Type::Number),
+    ("length", &[Type::Any], Type::Number),
+    #[cfg(feature = "ffi")]
+    ("loadLibrary", &[Type::String], Type::String),
+    ("lookupHost", &[Type::String], Type::List),
+    ("map", &[Type::List, Type::Any], Type::List),
+    ("memory", &[], Type::Number),
+    ("methods", &[Type::Any], Type::List),
+    ("pop", &[Type::List], Type::Any),
+    ("push", &[Type::List, Type::Any], Type::Nil),
+    ("random", &[], Type::Number),
+    ("randomInt", &[Type::Number, Type::Number], Type::Number),
+    ("readFile", &[Type::String], Type::String),
+    ("readLine", &[], Type::Any),
+    ("receive", &[Type::Any], Type::Any),
+    ("reduce", &[Type::List, Type::Any, Type::Any], Type::Any),
+    ("remove", &[Type::Any, Type::Any], Type::Any),
+    ("replace", &[Type::String, Type::String, Type::String], Type::String),
+    ("repr", &[Type::Any], Type::String),
+    ("resume", &[Type::Any, Type::Any], Type::Any),
+    ("seed", &[Type::Number], Type::Nil),
+    ("send", &[Type::Any, Type::Any], Type::Nil),
+    ("setattr", &[Type::Any, Type::String, Type::Any], Type::Nil),
+    ("sleep", &[Type::Number], Type::Nil),
+    ("sleepAsync", &[Type::Number], Type::Any),
+    ("spawn", &[Type::Any], Type::Any),
+    ("split", &[Type::String, Type::String], Type::List),
+    ("startsWith", &[Type::String, Type::String], Type::Bool),
+    ("status", &[Type::Any], Type::String),
+    ("substring", &[Type::String, Type::Number, Type::Number], Type::String),
+    ("timeMillis", &[], Type::Number),
+    ("toLower", &[Type::String], Type::String),
+    ("toUpper", &[Type::String], Type::String),
+    ("trim", &[Type::String], Type::String),
+    ("type", &[Type::Any], Type::String),
+    ("values", &[Type::Map], Type::List),
+    ("write", &[Type::Any], Type::Nil),
+    ("writeFile", &[Type::String, Type::String], Type::Nil),
+    ("yield", &[Type::Any], Type::Any),
+];
+
+/// Return types of the natives that take a varying number of arguments.
+/// Calls to them aren't checked here, the interpreter checks the count.
+const VARIADIC_NATIVES: &[(&str, Type)] = &[("joinWith", Type::String)];
+
+/// Only public function of the typechecker module. It infers the types of
+/// literals and operators and checks them against the type annotations
+/// before anything runs. Every mismatch is handed to the `reporter`, the
+/// result only tells whether there was any.
+pub fn check(statements: &[Statement], reporter: &mut dyn Reporter) -> Result<()> {
+    let globals = NATIVE_SIGNATURES
+        .iter()
+        .map(|(name, params, ret)| (name.to_string(), Type::Function(Some(params.to_vec()), Box::new(ret.clone()))))
+        .chain(VARIADIC_NATIVES.iter().map(|(name, ret)| (name.to_string(), Type::Function(None, Box::new(ret.clone())))))
+        .collect();
+    let mut checker = TypeChecker { scopes: vec![globals], returns: Vec::new(), reporter, had_error: false };
+    for stmt in statements {
+        checker.statement(stmt);
+    }
+
+    if checker.had_error {
+        Err(TypeError::HadError.into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Contraption that walks the syntax tree with the declared type of every
+/// variable in scope. Variables without annotation are [Type::Any].
+struct TypeChecker<'a> {
+    scopes: Vec<HashMap<String, Type>>,
+    /// Declared return types of the functions being checked, innermost last
+    returns: Vec<Type>,
+    reporter: &'a mut dyn Reporter,
+    had_error: bool,
+}
+
+impl TypeChecker<'_> {
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Block(stmts) => self.block(stmts),
+            Statement::ForIn(keyword, name, iterable, body, _) => {
+                let element = match self.expression(iterable) {
+                    Type::String => Type::String,
+                    Type::Any | Type::List | Type::Map => Type::Any,
+                    other => {
+                        self.error(TypeError::NotIterable(other.to_string(), keyword.line()));
+                        Type::Any
+                    }
+                };
+                self.scopes.push(HashMap::from([(name.lexeme(), element)]));
+                self.statement(body);
+                self.scopes.pop();
+            }
+            Statement::Switch(_, subject, cases, default) => {
+                self.expression(subject);
+                for (values, body) in cases {
+                    for value in values {
+                        self.expression(value);
+                    }
+                    self.block(body);
+                }
+                if let Some(body) = default {
+                    self.block(body);
+                }
+            }
+            Statement::Expression(expr) => {
+                self.expression(expr);
+            }
+            Statement::Class(name, _, _, methods, setters) => {
+                // Instances have no declared fields, so everything about them is Any
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.insert(name.lexeme(), Type::Any);
+                }
+                for method in methods.iter().chain(setters) {
+                    let (params, ret) = self.signature(method);
+                    self.function_body(method, params, ret);
+                }
+            }
+            Statement::Function(declaration) => {
+                let (params, ret) = self.signature(declaration);
+                // Declared before the body is checked, so recursive calls know the signature
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.insert(declaration.name.lexeme(), function_type(declaration, params.clone(), &ret));
+                }
+                self.function_body(declaration, params, ret);
+            }
+            Statement::If(_, cond, then, els) => {
+                self.expression(cond);
+                self.statement(then);
+                if let Some(els) = els {
+                    self.statement(els);
+                }
+            }
+            Statement::Print(_, exprs) => {
+                for expr in exprs {
+                    self.expression(expr);
+                }
+            }
+            Statement::Return(keyword, value) => {
+                let found = value.as_ref().map_or(Type::Nil, |value| self.expression(value));
+                let declared = self.returns.last().cloned().unwrap_or(Type::Any);
+                self.expect(&declared, &found, keyword.line());
+            }
+            Statement::Var(name, annotation, init) => {
+                let init_type = init.as_ref().map(|init| self.expression(init));
+                let declared = self.annotated(annotation.as_ref());
+                // A variable without initializer starts out as nil, which is fine until it gets read
+                if let Some(init_type) = init_type {
+                    self.expect(&declared, &init_type, name.line());
+                }
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.insert(name.lexeme(), declared);
+                }
+            }
+            Statement::Const(name, annotation, init) => {
+                let init_type = self.expression(init);
+                // The value never changes, so without annotation the constant has the type of its initializer
+                let declared = match annotation {
+                    Some(annotation) => {
+                        let declared = self.annotated(Some(annotation));
+                        self.expect(&declared, &init_type, name.line());
+                        declared
+                    }
+                    None => init_type,
+                };
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.insert(name.lexeme(), declared);
+                }
+            }
+            Statement::Destructure(pattern, init) => {
+                let init_type = self.expression(init);
+                self.destructured(pattern, &init_type);
+                // Elements, entries and properties are not typed, so neither are the variables
+                if let Some(scope) = self.scopes.last_mut() {
+                    for name in pattern.names() {
+                        scope.insert(name.lexeme(), Type::Any);
+                    }
+                }
+            }
+            Statement::While(_, cond, body, increment, _) => {
+                self.expression(cond);
+                self.statement(body);
+                if let Some(increment) = increment {
+                    self.expression(increment);
+                }
+            }
+            Statement::Throw(_, value) => {
+                self.expression(value);
+            }
+            Statement::Try(_, body, catches, finally) => {
+                self.block(body);
+                for (name, _, handler) in catches {
+                    // Anything can be thrown, and instances have no declared fields
+                    self.scopes.push(HashMap::from([(name.lexeme(), Type::Any)]));
+                    self.block(handler);
+                    self.scopes.pop();
+                }
+                if let Some(finally) = finally {
+                    self.block(finally);
+                }
+            }
+            Statement::Break(..) | Statement::Continue(..) => {}
+        }
+    }
+
+    /// Checks statements in a scope of their own
+    fn block(&mut self, stmts: &[Statement]) {
+        self.scopes.push(HashMap::new());
+        for stmt in stmts {
+            self.statement(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    /// The declared parameter and return types of a function
+    fn signature(&mut self, declaration: &FunctionDeclaration) -> (Vec<Type>, Type) {
+        let params = declaration.params.iter().map(|p| self.annotated(p.annotation.as_ref())).collect();
+        (params, self.annotated(declaration.return_type.as_ref()))
+    }
+
+    /// Checks the body of a function with its parameters in scope
+    fn function_body(&mut self, declaration: &FunctionDeclaration, params: Vec<Type>, ret: Type) {
+        let scope = declaration.params.iter().map(|p| p.name.lexeme()).zip(params).collect();
+        self.scopes.push(scope);
+        self.returns.push(ret);
+        for stmt in &declaration.body {
+            self.statement(stmt);
+        }
+        self.returns.pop();
+        self.scopes.pop();
+    }
+
+    /// Infers the type of an expression, reporting every mismatch inside it
+    fn expression(&mut self, expr: &Expression) -> Type {
+        match expr {
+            Expression::Assign(name, value) => {
+                let value = self.expression(value);
+                let declared = self.lookup(name);
+                self.expect(&declared, &value, name.line());
+                value
+            }
+            Expression::Destructure(pattern, value) => {
+                let value = self.expression(value);
+                self.destructured(pattern, &value);
+                value
+            }
+            Expression::Binary(left, op, right) => {
+                let left = self.expression(left);
+                let right = self.expression(right);
+                self.binary(&left, op, &right)
+            }
+            Expression::Call(callee, paren, args) => {
+                let callee = self.expression(callee);
+                let args: Vec<Type> = args.iter().map(|arg| self.expression(arg)).collect();
+                self.call(&callee, &args, paren.line())
+            }
+            Expression::Get(object, name) | Expression::OptionalGet(object, name) => {
+                let object = self.expression(object);
+                match (&object, name.lexeme().as_str()) {
+                    (Type::Any, _) => Type::Any,
+                    (Type::Nil, _) if in_optional_chain(expr) => Type::Nil,
+                    (Type::String | Type::List | Type::Map, "length") | (Type::Function(..), "arity") => Type::Number,
+                    _ => {
+                        self.error(TypeError::UndefinedProperty(object.to_string(), name.lexeme(), name.line()));
+                        Type::Any
+                    }
+                }
+            }
+            Expression::Set(object, name, value) => {
+                let object = self.expression(object);
+                let value = self.expression(value);
+                if object != Type::Any {
+                    self.error(TypeError::UndefinedProperty(object.to_string(), name.lexeme(), name.line()));
+                }
+                value
+            }
+            Expression::Grouping(inner) => self.expression(inner),
+            Expression::Index(list, bracket, index) => match self.index(list, bracket, index) {
+                Type::String => Type::String, // A single character
+                _ => Type::Any,
+            },
+            Expression::IndexSet(list, bracket, index, value) => {
+                self.index(list, bracket, index);
+                self.expression(value)
+            }
+            Expression::Slice(object, bracket, start, end) => {
+                let object = self.expression(object);
+                for bound in [start, end].into_iter().flatten() {
+                    let bound = self.expression(bound);
+                    self.expect(&Type::Number, &bound, bracket.line());
+                }
+                match object {
+                    Type::Any | Type::List | Type::String => object,
+                    _ => {
+                        self.error(TypeError::NotSliceable(object.to_string(), bracket.line()));
+                        Type::Any
+                    }
+                }
+            }
+            Expression::Block(_, stmts, value) => {
+                self.scopes.push(HashMap::new());
+                stmts.iter().for_each(|stmt| self.statement(stmt));
+                let value = value.as_ref().map_or(Type::Nil, |value| self.expression(value));
+                self.scopes.pop();
+                value
+            }
+            Expression::If(_, cond, then, els) => {
+                self.expression(cond);
+                let then = self.expression(then);
+                let els = els.as_ref().map_or(Type::Nil, |els| self.expression(els));
+                // Only known if both branches agree
+                if then == els { then } else { Type::Any }
+            }
+            Expression::Lambda(declaration) => {
+                let (params, ret) = self.signature(declaration);
+                self.function_body(declaration, params.clone(), ret.clone());
+                function_type(declaration, params, &ret)
+            }
+            Expression::List(_, items) => {
+                for item in items {
+                    self.expression(item);
+                }
+                Type::List
+            }
+            Expression::Map(_, entries) => {
+                for (key, value) in entries {
+                    self.expression(key);
+                    self.expression(value);
+                }
+                Type::Map
+            }
+            Expression::Logical(left, op, right) => {
+                // The result is one of the operands, `nil ?? b` is always the right one
+                let left = self.expression(left);
+                let right = self.expression(right);
+                if left == right {
+                    left
+                } else if left == Type::Nil && op.token_type() == TokenType::QuestionQuestion {
+                    right
+                } else {
+                    Type::Any
+                }
+            }
+            Expression::Unary(op, right) => {
+                let right = self.expression(right);
+                match op.token_type() {
+                    TokenType::Minus => {
+                        if !Type::Number.accepts(&right) {
+                            self.error(TypeError::OperandType(op.lexeme(), right.to_string(), op.line()));
+                        }
+                        Type::Number
+                    }
+                    TokenType::Await => Type::Any, // Whatever the future completes with
+                    _ => Type::Bool,
+                }
+            }
+            Expression::Literal(value) => match value {
+                Value::String(_) => Type::String,
+                Value::Number(_) | Value::Int(_) => Type::Number,
+                Value::Bool(_) => Type::Bool,
+                Value::Nil => Type::Nil,
+                Value::NativeFunction(_) | Value::Function(_) => Type::Function(None, Box::new(Type::Any)),
+                Value::List(_) => Type::List,
+                Value::Map(_) => Type::Map,
+                Value::Class(_) | Value::Instance(_) | Value::Task(_) | Value::Channel(_) => Type::Any,
+                Value::Coroutine(_) | Value::Future(_) => Type::Any,
+            },
+            Expression::Super(..) | Expression::This(_) => Type::Any,
+            Expression::Variable(name) => self.lookup(name),
+        }
+    }
+
+    /// Checks that an indexed value is a list, string or map, and that lists
+    /// and strings get a number. Returns the type of the indexed value.
+    fn index(&mut self, object: &Expression, bracket: &Token, index: &Expression) -> Type {
+        let object = self.expression(object);
+        let index = self.expression(index);
+        match object {
+            Type::Any | Type::Map => {}
+            Type::List | Type::String => self.expect(&Type::Number, &index, bracket.line()),
+            _ => self.error(TypeError::NotIndexable(object.to_string(), bracket.line())),
+        }
+        object
+    }
+
+    /// Type of a binary operation, following the rules of the interpreter
+    fn binary(&mut self, left: &Type, op: &Token, right: &Type) -> Type {
+        let mismatch = |checker: &mut Self| {
+            checker.error(TypeError::OperandTypes(op.lexeme(), left.to_string(), right.to_string(), op.line()))
+        };
+        let numbers = Type::Number.accepts(left) && Type::Number.accepts(right);
+        match op.token_type() {
+            TokenType::Plus => match (left, right) {
+                (Type::Any, _) | (_, Type::Any) => Type::Any,
+                (Type::Number, Type::Number) => Type::Number,
+                (Type::String, Type::String) | (Type::String, Type::Number) | (Type::Number, Type::String) => Type::String,
+                _ => {
+                    mismatch(self);
+                    Type::Any
+                }
+            },
+            TokenType::Star => match (left, right) {
+                (Type::Any, _) | (_, Type::Any) => Type::Any,
+                (Type::String, Type::Number) | (Type::Number, Type::String) => Type::String, // Repetition
+                _ => {
+                    if !numbers {
+                        mismatch(self);
+                    }
+                    Type::Number
+                }
+            },
+            TokenType::Minus | TokenType::Slash | TokenType::TildeSlash => {
+                if !numbers {
+                    mismatch(self);
+                }
+                Type::Number
+            }
+            TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual => {
+                if !numbers {
+                    mismatch(self);
+                }
+                Type::Bool
+            }
+            _ => Type::Bool, // Equality works on any two values, and `is` tests any value
+        }
+    }
+
+    /// Type of the result of a call, checking the callee and the arguments
+    fn call(&mut self, callee: &Type, args: &[Type], line: u32) -> Type {
+        match callee {
+            Type::Any => Type::Any,
+            Type::Function(None, ret) => *ret.clone(),
+            Type::Function(Some(params), ret) => {
+                if params.len() != args.len() {
+                    self.error(TypeError::WrongArgumentCount(params.len(), args.len(), line));
+                } else {
+                    for (index, (param, arg)) in params.iter().zip(args).enumerate() {
+                        if !param.accepts(arg) {
+                            self.error(TypeError::ArgumentType(index + 1, param.to_string(), arg.to_string(), line));
+                        }
+                    }
+                }
+                *ret.clone()
+            }
+            _ => {
+                self.error(TypeError::NotCallable(callee.to_string(), line));
+                Type::Any
+            }
+        }
+    }
+
+    /// The type an optional annotation declares. Without annotation, or
+    /// with an unknown type name, it is [Type::Any].
+    fn annotated(&mut self, annotation: Option<&TypeAnnotation>) -> Type {
+        match annotation {
+            Some(annotation) => match Type::from_annotation(annotation) {
+                Some(declared) => declared,
+                None => {
+                    self.error(TypeError::UnknownType(annotation.name.lexeme(), annotation.name.line()));
+                    Type::Any
+                }
+            },
+            None => Type::Any,
+        }
+    }
+
+    /// The declared type of a variable. Unknown variables are left to the
+    /// interpreter, which reports them when they get used.
+    fn lookup(&self, name: &Token) -> Type {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme()))
+            .cloned()
+            .unwrap_or(Type::Any)
+    }
+
+    /// Reports a mismatch if a value of type `found` cannot be destructured by the pattern.
+    /// Instances are `Any`, so a map pattern accepts them.
+    fn destructured(&mut self, pattern: &Pattern, found: &Type) {
+        let expected = match pattern {
+            Pattern::List(..) => Type::List,
+            Pattern::Map(..) => Type::Map,
+        };
+        self.expect(&expected, found, pattern.token().line());
+    }
+
+    /// Reports a mismatch if a value of type `found` cannot be stored where `expected` is declared
+    fn expect(&mut self, expected: &Type, found: &Type, line: u32) {
+        if !expected.accepts(found) {
+            self.error(TypeError::Mismatch(expected.to_string(), found.to_string(), line));
+        }
+    }
+
+    fn error(&mut self, error: TypeError) {
+        self.had_error = true;
+        self.reporter.report(Diagnostic::error(error));
+    }
+}
+
+/// Whether the property access is part of a chain with a `?.`, which skips
+/// the rest of the chain when it finds nil
+fn in_optional_chain(expr: &Expression) -> bool {
+    match expr {
+        Expression::OptionalGet(..) => true,
+        Expression::Get(object, _) | Expression::Call(object, ..) | Expression::Index(object, ..) | Expression::Slice(object, ..) => {
+            in_optional_chain(object)
+        }
+        _ => false,
+    }
+}
+
+/// The type of a function value. Calling an async function gives a future,
+/// its return annotation only applies to the value the future completes with.
+fn function_type(declaration: &FunctionDeclaration, params: Vec<Type>, ret: &Type) -> Type {
+    let ret = if declaration.is_async { Type::Any } else { ret.clone() };
+    Type::Function(Some(params), Box::new(ret))
+}
+
+// ---------- Tests for the Typechecker module ----------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::CollectingReporter;
+    use crate::parser::parse;
+    use crate::scanner::scan_tokens;
+
+    fn check_source(source: &str) -> Vec<String> {
+        let mut reporter = CollectingReporter::default();
+        let tokens = scan_tokens(source.to_string(), &mut reporter).expect("Token Scanning failed!");
+        let statements = parse(tokens, &mut reporter).expect("Parsing failed!");
+        let result = check(&statements, &mut reporter);
+        let messages: Vec<String> = reporter.into_diagnostics().into_iter().map(|d| d.message).collect();
+        assert_eq!(result.is_err(), !messages.is_empty());
+        messages
+    }
+
+    #[test]
+    fn accepts_well_typed_code() {
+        let source = "var a: Number = 1; var s: String = \"x\" + repr(a); a = len(s) * 2; var b = a; b = \"y\"; print -a, s.length;";
+        assert!(check_source(source).is_empty());
+    }
+
+    #[test]
+    fn checks_annotations() {
+        let source = "var a: Number = \"one\";\n{ var s: String; s = 1 < 2; }\nvar c: Colour;";
+        assert_eq!(
+            check_source(source),
+            [
+                "Type Error [E0401]: Expected a value of type Number, found String on line 1",
+                "Type Error [E0401]: Expected a value of type String, found Bool on line 2",
+                "Type Error [E0406]: Unknown type Colour on line 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_operators_and_calls() {
+        let source = "print \"a\" + true;\nprint 1();\nprint len(\"a\", \"b\");\nprint sleep(\"2\");\nprint -nil;\nprint true.size;";
+        assert_eq!(
+            check_source(source),
+            [
+                "Type Error [E0403]: Operator + cannot be applied to String and Bool on line 1",
+                "Type Error [E0402]: Cannot call a value of type Number on line 2",
+                "Type Error [E0404]: Expected 1 arguments but got 2 on line 3",
+                "Type Error [E0405]: Argument 1 expects a value of type Number, found String on line 4",
+                "Type Error [E0407]: Operator - cannot be applied to Nil on line 5",
+                "Type Error [E0408]: Bool has no property size on line 6",
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_lists() {
+        let source = "var l: List = [1, \"two\"];\nl[0] = l.length;\nprint l[\"0\"];\nvar n = 1;\nvar m: Number = 2;\nprint n[0], m[0];";
+        assert_eq!(
+            check_source(source),
+            [
+                "Type Error [E0401]: Expected a value of type Number, found String on line 3",
+                "Type Error [E0409]: Cannot index a value of type Number on line 6",
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_functions_against_their_signatures() {
+        let source = "fun half(n: Number): Number {\n  if (n < 0) return \"negative\";\n  return n / 2;\n}\nprint half(\"4\") + half(4);\nfun any(x) { return x; }\nvar s: String = any(1);";
+        assert_eq!(
+            check_source(source),
+            [
+                "Type Error [E0401]: Expected a value of type Number, found String on line 2",
+                "Type Error [E0405]: Argument 1 expects a value of type Number, found String on line 5",
+            ]
+        );
+    }
+
+    #[test]
+    fn unannotated_variables_are_not_checked() {
+        // A shadowed native is no longer known to the checker
+        assert!(check_source("var x = 1; x = \"one\"; var len = x; len(1, 2);").is_empty());
+    }
+}
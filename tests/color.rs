@@ -0,0 +1,18 @@
+// External dependencies
+use std::process::Command;
+
+/// Runs a script with a parse error and returns what jlox printed to stderr
+fn errors(args: &[&str]) -> String {
+    let path = std::env::temp_dir().join(format!("jlox-color-{}-{}.lox", std::process::id(), args.join("")));
+    std::fs::write(&path, "print 1 +;\n").expect("Writing the script failed");
+    let output = Command::new(env!("CARGO_BIN_EXE_jlox")).args(args).arg(&path).output().expect("Failed to run jlox");
+    std::fs::remove_file(&path).expect("Removing the script failed");
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn color_never_leaves_out_escape_codes() {
+    assert!(errors(&["--color=always"]).contains("\x1b[31m"));
+    assert!(!errors(&["--color=never"]).contains('\x1b'));
+    assert!(!errors(&["--color", "never"]).contains('\x1b'));
+}
@@ -0,0 +1,39 @@
+// Internal dependencies
+use jlox::diagnostic::CollectingReporter;
+use jlox::errors::{LoxError, ParseError, RuntimeError};
+use jlox::interpreter::Interpreter;
+
+/// Runs the source the way a library caller would, collecting the diagnostics
+fn run(source: &str) -> (LoxError, CollectingReporter) {
+    let mut reporter = CollectingReporter::default();
+    let mut interpreter = Interpreter::builder().output(Vec::new()).build();
+    let error = interpreter.run(source.to_string(), &mut reporter).expect_err("The source ran without errors");
+    (error, reporter)
+}
+
+#[test]
+fn parse_errors_can_be_matched() {
+    let (error, reporter) = run("var a = 1;\nprint a +;");
+    assert!(matches!(error, LoxError::Parse(ParseError::HadError)));
+    assert_eq!(error.code(), Some("E0200"));
+    // What went wrong is in the diagnostics, with the line it happened on
+    let diagnostic = &reporter.diagnostics()[0];
+    assert!(diagnostic.message.contains("[E0201]"));
+    assert_eq!(diagnostic.line, Some(2));
+}
+
+#[test]
+fn runtime_errors_can_be_matched() {
+    let (error, _) = run("var count = 1;\nprint cout;");
+    match &error {
+        LoxError::Runtime(RuntimeError::UndefinedVariable(name, suggestion, line)) => {
+            assert_eq!((name.as_str(), suggestion.as_deref(), *line), ("cout", Some("count"), 2));
+        }
+        other => panic!("Expected an undefined variable, got {:?}", other),
+    }
+    assert_eq!((error.code(), error.line()), (Some("R0301"), Some(2)));
+
+    // It is a regular error, so it works with `?` and error libraries
+    let error: Box<dyn std::error::Error> = Box::new(error);
+    assert!(error.to_string().starts_with("Runtime Error [R0301]: Undefined variable cout"));
+}
@@ -0,0 +1,20 @@
+// External dependencies
+use std::process::Command;
+
+/// Runs `jlox test` over every case in `tests/cases` and fails if any
+/// output differs from its committed `.expected` file.
+#[test]
+fn golden_cases() {
+    let output = Command::new(env!("CARGO_BIN_EXE_jlox"))
+        .args(["test", "tests/cases"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("Failed to run jlox");
+
+    assert!(
+        output.status.success(),
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr),
+    );
+}
@@ -0,0 +1,61 @@
+// External dependencies
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Runs a prompt session with the given input and returns what it printed
+/// to stdout, without the prompts
+fn session(input: &str) -> String {
+    let mut repl = Command::new(env!("CARGO_BIN_EXE_jlox"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run jlox");
+    repl.stdin.take().expect("No stdin").write_all(input.as_bytes()).expect("Writing the input failed");
+    let output = repl.wait_with_output().expect("The session failed");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).replace("... ", "").replace("> ", "")
+}
+
+#[test]
+fn paste_runs_the_lines_as_one_input() {
+    // Neither line would parse on its own
+    let output = session(":paste\nfun twice(n) {\n  return n * 2;\n}\n.\nprint twice(21);\n");
+    assert_eq!(output, "(paste mode, finish with a line containing only \".\")\n42\n");
+}
+
+#[test]
+fn time_prints_durations_until_turned_off() {
+    let output = session(":time on\nprint 1;\n:time off\nprint 2;\n");
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!((lines[0], lines[2]), ("1", "2"));
+    let (parse, execute) = lines[1].split_once(", ").expect("No durations printed");
+    assert!(parse.starts_with("parse: ") && parse.ends_with("ms"));
+    assert!(execute.starts_with("execute: ") && execute.ends_with("ms"));
+}
+
+#[test]
+fn record_appends_inputs_until_stopped() {
+    let path = std::env::temp_dir().join(format!("jlox-repl-record-{}.lox", std::process::id()));
+    let path = path.to_str().expect("Temporary path is not UTF-8");
+    // The failing input and the ones around the recording stay out of it
+    session(&format!("var a = 1;\n:record {}\nvar b = a + 1;\nprint nope;\nprint b;\n:stop\nprint a;\n", path));
+    let recorded = std::fs::read_to_string(path).expect("Nothing was recorded");
+    std::fs::remove_file(path).expect("Removing the recording failed");
+    assert_eq!(recorded, "var b = a + 1;\nprint b;\n");
+}
+
+#[test]
+fn saved_sessions_run_as_scripts() {
+    let path = std::env::temp_dir().join(format!("jlox-repl-save-{}.lox", std::process::id()));
+    let path = path.to_str().expect("Temporary path is not UTF-8");
+    let input = ":paste\nfun greet(name) {\n  return \"Hi \" + name;\n}\n.\nvar who = \"Ada\";\nprint greet(who);\nwho = nil + 1;\n";
+    let output = session(&format!("{}:save {}\n", input, path));
+    assert!(output.ends_with(&format!("Hi Ada\n(3 inputs saved to {})\n", path)));
+
+    let script = Command::new(env!("CARGO_BIN_EXE_jlox")).arg(path).output().expect("Failed to run jlox");
+    std::fs::remove_file(path).expect("Removing the saved session failed");
+    assert!(script.status.success(), "{}", String::from_utf8_lossy(&script.stderr));
+    assert_eq!(String::from_utf8_lossy(&script.stdout), "Hi Ada\n");
+}